@@ -0,0 +1,218 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::Result;
+use crate::error::Error;
+use crate::cli::{
+	ExceptionClause, FlowControl, Handler, Instruction, Instructions, MethodBody, Operand, flow_control,
+};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EdgeKind {
+	FallThrough,
+	/// Branch, `switch` case or `leave` target.
+	Branch,
+	/// From a protected block to the filter or handler of its clause.
+	Exception,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Edge {
+	/// Index of the block at the other end.
+	pub block: usize,
+	pub kind: EdgeKind,
+}
+
+/// A straight run of instructions entered only at the first one and left
+/// only after the last one, exceptions aside.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BasicBlock {
+	/// Offset of the first instruction.
+	pub start: u32,
+	/// Offset past the last instruction.
+	pub end: u32,
+	/// Indices in `Cfg::instructions`.
+	pub instructions: Range<usize>,
+	pub successors: Vec<Edge>,
+	pub predecessors: Vec<Edge>,
+}
+
+/// Control-flow graph of a method body. Block 0 is the entry.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cfg {
+	pub instructions: Box<[Instruction]>,
+	/// Sorted by offset.
+	pub blocks: Box<[BasicBlock]>,
+	pub clauses: Box<[ExceptionClause]>,
+}
+
+impl Cfg {
+	/// Splits the body into basic blocks at branch targets, after control
+	/// transfers and at protected block and handler boundaries.
+	pub fn build(body: &MethodBody) -> Result<Cfg> {
+		let instructions = Instructions::new(body.code).collect::<Result<Vec<_>>>()?;
+		if instructions.is_empty() {
+			Err("Method body has no instructions.")?;
+		}
+		let code_size = body.code.len() as u32;
+
+		let mut boundaries: BTreeSet<u32> = instructions.iter().map(|i| i.offset).collect();
+		boundaries.insert(code_size);
+
+		let mut leaders = BTreeSet::new();
+		leaders.insert(0);
+		for ins in instructions.iter() {
+			for target in targets(ins) {
+				if target == code_size || !boundaries.contains(&target) {
+					Err(Error::Verification(ins.offset, "Branch target is not at an instruction boundary."))?;
+				}
+				leaders.insert(target);
+			}
+			if flow_control(ins.opcode) != FlowControl::Next {
+				leaders.insert(ins.next());
+			}
+		}
+		for c in body.clauses.iter() {
+			let mut bounds = vec![c.try_offset, c.try_end(), c.handler_offset, c.handler_end()];
+			if let Handler::Filter(filter) = c.handler {
+				bounds.push(filter);
+			}
+			for b in bounds {
+				if !boundaries.contains(&b) {
+					Err(Error::Verification(b, "Exception clause is not at an instruction boundary."))?;
+				}
+				leaders.insert(b);
+			}
+		}
+		// The end of the code only closes the last block.
+		leaders.remove(&code_size);
+
+		let mut blocks = Vec::with_capacity(leaders.len());
+		let mut leaders = leaders.into_iter().peekable();
+		let mut first = 0;
+		for (i, ins) in instructions.iter().enumerate() {
+			if leaders.peek() == Some(&ins.offset) {
+				leaders.next();
+				if i != 0 {
+					blocks.push(block(&instructions, first..i));
+				}
+				first = i;
+			}
+		}
+		blocks.push(block(&instructions, first..instructions.len()));
+
+		let mut cfg = Cfg {
+			instructions: instructions.into_boxed_slice(),
+			blocks: blocks.into_boxed_slice(),
+			clauses: body.clauses.clone(),
+		};
+		cfg.link()?;
+		Ok(cfg)
+	}
+
+	fn link(&mut self) -> Result<()> {
+		let mut edges = Vec::new();
+
+		for (i, b) in self.blocks.iter().enumerate() {
+			let last = &self.instructions[b.instructions.end - 1];
+			let flow = flow_control(last.opcode);
+
+			for target in targets(last) {
+				let to = self.block_at(target).ok_or("Branch target is out of the method body.")?;
+				edges.push((i, to, EdgeKind::Branch));
+			}
+			if matches!(flow, FlowControl::Next | FlowControl::CondBranch) {
+				if i + 1 == self.blocks.len() {
+					Err(Error::Verification(last.offset, "Control falls through the end of the method body."))?;
+				}
+				edges.push((i, i + 1, EdgeKind::FallThrough));
+			}
+		}
+
+		for c in self.clauses.iter() {
+			let mut handlers = vec![c.handler_offset];
+			if let Handler::Filter(filter) = c.handler {
+				handlers.push(filter);
+			}
+			for handler in handlers {
+				let to = self.block_at(handler).ok_or("Handler is out of the method body.")?;
+				for (i, b) in self.blocks.iter().enumerate() {
+					if b.start >= c.try_offset && b.start < c.try_end() {
+						edges.push((i, to, EdgeKind::Exception));
+					}
+				}
+			}
+		}
+
+		for (from, to, kind) in edges {
+			let edge = Edge { block: to, kind };
+			// `switch` cases may repeat.
+			if !self.blocks[from].successors.contains(&edge) {
+				self.blocks[from].successors.push(edge);
+				self.blocks[to].predecessors.push(Edge { block: from, kind });
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Index of the block starting exactly at `offset`.
+	pub fn block_at(&self, offset: u32) -> Option<usize> {
+		self.blocks.binary_search_by_key(&offset, |b| b.start).ok()
+	}
+
+	/// Index of the block containing the instruction at `offset`.
+	pub fn block_of(&self, offset: u32) -> Option<usize> {
+		let i = self.blocks.partition_point(|b| b.start <= offset).checked_sub(1)?;
+		if offset < self.blocks[i].end { Some(i) } else { None }
+	}
+
+	pub fn block_instructions(&self, block: usize) -> &[Instruction] {
+		&self.instructions[self.blocks[block].instructions.clone()]
+	}
+
+	/// Blocks in reverse post-order from the entry, unreachable ones are left
+	/// out. Dataflow passes converge faster visiting blocks in this order.
+	pub fn reverse_post_order(&self) -> Vec<usize> {
+		let mut order = Vec::with_capacity(self.blocks.len());
+		let mut visited = vec![false; self.blocks.len()];
+		// Each entry is a block and the number of its successors visited.
+		let mut stack = vec![(0, 0)];
+		visited[0] = true;
+
+		while let Some((block, n)) = stack.pop() {
+			match self.blocks[block].successors.get(n) {
+				Some(edge) => {
+					stack.push((block, n + 1));
+					if !visited[edge.block] {
+						visited[edge.block] = true;
+						stack.push((edge.block, 0));
+					}
+				},
+				None => order.push(block),
+			}
+		}
+
+		order.reverse();
+		order
+	}
+}
+
+fn block(instructions: &[Instruction], range: Range<usize>) -> BasicBlock {
+	BasicBlock {
+		start: instructions[range.start].offset,
+		end: instructions[range.end - 1].next(),
+		instructions: range,
+		successors: Vec::new(),
+		predecessors: Vec::new(),
+	}
+}
+
+/// Offsets the instruction may transfer control to, besides the next one.
+fn targets(ins: &Instruction) -> Vec<u32> {
+	match &ins.operand {
+		Operand::Branch(target) => vec![*target],
+		Operand::Switch(targets) => targets.to_vec(),
+		_ => Vec::new(),
+	}
+}
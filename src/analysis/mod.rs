@@ -0,0 +1,8 @@
+mod cfg;
+pub use self::cfg::*;
+
+mod stack;
+pub use self::stack::*;
+
+mod verifier;
+pub use self::verifier::*;
@@ -0,0 +1,136 @@
+use crate::Result;
+use crate::error::Error;
+use crate::assembly::Assembly;
+use crate::cli::{
+	CALL, CALLI, CALLVIRT, ENDFILTER, ENDFINALLY, FlowControl, Handler, Instruction, JMP, MethodBody,
+	MethodSig, NEWOBJ, Operand, RET, TypeSig, blob_at, flow_control, stack_effect,
+};
+use crate::analysis::{Cfg, EdgeKind};
+
+/// Evaluation stack depths of a method body, III.1.7.4.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StackDepths {
+	/// Depth before each instruction of the CFG, None for unreachable ones.
+	pub before: Box<[Option<u16>]>,
+	/// The deepest the stack gets, never above MaxStack.
+	pub max: u16,
+}
+
+/// Computes stack depths of the 0-based method's body, checking that the
+/// stack never underflows nor exceeds MaxStack, has the same depth whichever
+/// way an instruction is reached and is empty where the rules require it.
+pub fn stack_depths(asm: &Assembly, method: usize, body: &MethodBody, cfg: &Cfg) -> Result<StackDepths> {
+	let m = asm.rows.method_defs.get(method).ok_or("Method row is out of the table bounds.")?;
+	let own_sig = MethodSig::parse(blob_at(asm.blobs(), m.sig.into_index())?)?;
+
+	let mut entry: Vec<Option<usize>> = vec![None; cfg.blocks.len()];
+	entry[0] = Some(0);
+
+	// Handlers start with the exception object on the stack, if any.
+	for c in cfg.clauses.iter() {
+		let depth = match c.handler {
+			Handler::Catch(_) | Handler::Filter(_) => 1,
+			Handler::Finally | Handler::Fault => 0,
+		};
+		merge(cfg, &mut entry, c.handler_offset, depth)?;
+		if let Handler::Filter(filter) = c.handler {
+			merge(cfg, &mut entry, filter, 1)?;
+		}
+	}
+
+	let mut before = vec![None; cfg.instructions.len()];
+	let mut max = 0;
+
+	// Handlers are unreachable through normal edges, so the walk starts from
+	// their entries too.
+	let mut worklist: Vec<usize> = (0..cfg.blocks.len()).filter(|b| entry[*b].is_some()).rev().collect();
+	while let Some(b) = worklist.pop() {
+		let block = &cfg.blocks[b];
+		let mut depth = entry[b].unwrap_or(0);
+
+		for (i, ins) in cfg.block_instructions(b).iter().enumerate() {
+			// Depths never exceed MaxStack, which is 16-bit.
+			before[block.instructions.start + i] = Some(depth as u16);
+			let (pop, push) = effect(asm, &own_sig, ins)?;
+			depth = depth.checked_sub(pop).ok_or(Error::Verification(ins.offset, "Stack underflow."))?;
+			depth = depth.checked_add(push).ok_or(Error::Verification(ins.offset, "Stack depth exceeds MaxStack."))?;
+			if depth > body.max_stack as usize {
+				Err(Error::Verification(ins.offset, "Stack depth exceeds MaxStack."))?;
+			}
+			max = max.max(depth as u16);
+
+			let must_be_empty = match ins.opcode {
+				RET | JMP | ENDFINALLY | ENDFILTER => true,
+				// `leave` empties the stack itself.
+				_ => false,
+			};
+			if must_be_empty && depth != 0 {
+				Err(Error::Verification(ins.offset, "Stack is not empty on return from the method or handler."))?;
+			}
+		}
+
+		let last = &cfg.instructions[block.instructions.end - 1];
+		if flow_control(last.opcode) == FlowControl::Leave {
+			depth = 0;
+		}
+
+		for edge in block.successors.iter().filter(|e| e.kind != EdgeKind::Exception) {
+			let target = cfg.blocks[edge.block].start;
+			// Protected blocks are entered only with the empty stack, III.1.7.5.
+			let enters_try = cfg.clauses.iter()
+				.any(|c| c.try_offset == target && (block.start < c.try_offset || block.start >= c.try_end()));
+			if enters_try && depth != 0 {
+				Err(Error::Verification(target, "Stack is not empty on entry to a protected block."))?;
+			}
+			if merge(cfg, &mut entry, target, depth)? {
+				worklist.push(edge.block);
+			}
+		}
+	}
+
+	Ok(StackDepths { before: before.into_boxed_slice(), max })
+}
+
+/// Records the depth on entry to the block at `offset`, telling whether it
+/// is new.
+fn merge(cfg: &Cfg, entry: &mut [Option<usize>], offset: u32, depth: usize) -> Result<bool> {
+	let block = cfg.block_at(offset).ok_or(Error::Verification(offset, "Handler is not at an instruction boundary."))?;
+	match entry[block] {
+		None => {
+			entry[block] = Some(depth);
+			Ok(true)
+		},
+		Some(d) if d == depth => Ok(false),
+		Some(_) => Err(Error::Verification(offset, "Stack depths differ where control flow merges."))?,
+	}
+}
+
+/// Values an instruction pops and pushes. Counts of call arguments are as
+/// large as signatures make them, only checking against MaxStack bounds them.
+fn effect(asm: &Assembly, own_sig: &MethodSig, ins: &Instruction) -> Result<(usize, usize)> {
+	if let Some((pop, push)) = stack_effect(ins.opcode) {
+		return Ok((pop as usize, push as usize));
+	}
+
+	let returns = |sig: &MethodSig| if sig.ret == TypeSig::Void { 0 } else { 1 };
+	let this = |sig: &MethodSig| if sig.has_this && !sig.explicit_this { 1 } else { 0 };
+
+	let effect = match (ins.opcode, &ins.operand) {
+		(RET, _) => (returns(own_sig), 0),
+		(CALL, Operand::Token(token)) | (CALLVIRT, Operand::Token(token)) => {
+			let sig = asm.rows.method_sig(asm.blobs(), *token)?;
+			(sig.params.len() + this(&sig), returns(&sig))
+		},
+		(NEWOBJ, Operand::Token(token)) => {
+			let sig = asm.rows.method_sig(asm.blobs(), *token)?;
+			(sig.params.len(), 1)
+		},
+		(CALLI, Operand::Token(token)) => {
+			let sig = asm.rows.standalone_method_sig(asm.blobs(), token.value())?;
+			// The function pointer comes last.
+			(sig.params.len() + this(&sig) + 1, returns(&sig))
+		},
+		_ => Err(Error::Verification(ins.offset, "Unknown opcode."))?,
+	};
+	Ok(effect)
+}
@@ -0,0 +1,863 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::Result;
+use crate::error::Error;
+use crate::assembly::Assembly;
+use crate::cli::*;
+use crate::analysis::{Cfg, EdgeKind, stack_depths};
+
+/// Types of evaluation stack slots the verifier tracks, III.1.8.1.1.
+#[derive(Debug, PartialEq, Clone)]
+pub enum VerType {
+	Int32,
+	Int64,
+	NativeInt,
+	/// F, both float32 and float64.
+	Float,
+	/// Object reference of the type, `object` when it is not known.
+	Object(TypeSig),
+	/// The null reference, assignable to any object reference.
+	Null,
+	/// Managed pointer to a location of the type.
+	ByRef(TypeSig),
+	Value(TypeSig),
+	/// Runtime handle pushed by `ldtoken`.
+	Handle,
+}
+
+impl VerType {
+	/// Type of a value of the signature type once it is on the stack.
+	pub fn of(sig: &TypeSig) -> VerType {
+		match sig {
+			TypeSig::Boolean | TypeSig::Char | TypeSig::I1 | TypeSig::U1 |
+			TypeSig::I2 | TypeSig::U2 | TypeSig::I4 | TypeSig::U4 => VerType::Int32,
+			TypeSig::I8 | TypeSig::U8 => VerType::Int64,
+			TypeSig::I | TypeSig::U | TypeSig::Ptr(_) | TypeSig::FnPtr(_) => VerType::NativeInt,
+			TypeSig::R4 | TypeSig::R8 => VerType::Float,
+			TypeSig::String | TypeSig::Object | TypeSig::Class(_) | TypeSig::SzArray(_) |
+			TypeSig::Array(..) => VerType::Object(strip(sig).clone()),
+			TypeSig::GenericInst { is_value_type: false, .. } => VerType::Object(sig.clone()),
+			TypeSig::ByRef(t) => VerType::ByRef(strip(t).clone()),
+			TypeSig::CustomMod { ty, .. } | TypeSig::Pinned(ty) => VerType::of(ty),
+			_ => VerType::Value(strip(sig).clone()),
+		}
+	}
+
+	fn is_integer(&self) -> bool {
+		matches!(self, VerType::Int32 | VerType::Int64 | VerType::NativeInt)
+	}
+
+	fn is_numeric(&self) -> bool {
+		self.is_integer() || *self == VerType::Float
+	}
+
+	fn is_reference(&self) -> bool {
+		matches!(self, VerType::Object(_) | VerType::Null)
+	}
+
+	/// Whether a value of this type may be stored where `to` is expected,
+	/// III.1.8.1.2.3. Class hierarchies are not checked, as referenced
+	/// assemblies are not loaded.
+	pub fn is_assignable_to(&self, to: &TypeSig) -> bool {
+		match (self, VerType::of(to)) {
+			(VerType::Int32, VerType::Int32) | (VerType::Int32, VerType::NativeInt) |
+			(VerType::NativeInt, VerType::Int32) | (VerType::NativeInt, VerType::NativeInt) |
+			(VerType::Int64, VerType::Int64) | (VerType::Float, VerType::Float) => true,
+			(VerType::Object(_), VerType::Object(_)) | (VerType::Null, VerType::Object(_)) => true,
+			(VerType::ByRef(a), VerType::ByRef(b)) => same_location(a, &b),
+			(VerType::Value(a), VerType::Value(b)) => *a == b,
+			(VerType::Handle, VerType::Value(_)) => true,
+			_ => false,
+		}
+	}
+
+	/// The type both may be treated as where control flow merges.
+	fn merge(&self, other: &VerType) -> Option<VerType> {
+		let merged = match (self, other) {
+			(a, b) if a == b => a.clone(),
+			(VerType::Null, VerType::Object(t)) | (VerType::Object(t), VerType::Null) => VerType::Object(t.clone()),
+			(VerType::Object(_), VerType::Object(_)) => VerType::Object(TypeSig::Object),
+			_ => return None,
+		};
+		Some(merged)
+	}
+}
+
+impl fmt::Display for VerType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			VerType::Int32     => write!(f, "int32"),
+			VerType::Int64     => write!(f, "int64"),
+			VerType::NativeInt => write!(f, "native int"),
+			VerType::Float     => write!(f, "F"),
+			VerType::Object(_) => write!(f, "O"),
+			VerType::Null      => write!(f, "null"),
+			VerType::ByRef(_)  => write!(f, "&"),
+			VerType::Value(_)  => write!(f, "value type"),
+			VerType::Handle    => write!(f, "runtime handle"),
+		}
+	}
+}
+
+/// Drops custom modifiers, which do not affect verification.
+fn strip(sig: &TypeSig) -> &TypeSig {
+	match sig {
+		TypeSig::CustomMod { ty, .. } => strip(ty),
+		_ => sig,
+	}
+}
+
+/// Managed pointers are compatible if their targets are of the same size
+/// and kind, e.g. `int32&` and `uint32&`.
+fn same_location(a: &TypeSig, b: &TypeSig) -> bool {
+	let size = |t: &TypeSig| match strip(t) {
+		TypeSig::Boolean | TypeSig::I1 | TypeSig::U1 => Some(1),
+		TypeSig::Char | TypeSig::I2 | TypeSig::U2 => Some(2),
+		TypeSig::I4 | TypeSig::U4 => Some(4),
+		TypeSig::I8 | TypeSig::U8 => Some(8),
+		TypeSig::I | TypeSig::U => Some(0),
+		_ => None,
+	};
+	match (size(a), size(b)) {
+		(Some(x), Some(y)) => x == y,
+		_ => VerType::of(a) == VerType::of(b) || VerType::of(a).is_reference() && VerType::of(b).is_reference(),
+	}
+}
+
+/// Checks the body of the 0-based method for type safety, III.1.8: stack
+/// depths, operand types of every instruction and what flows into calls,
+/// fields, locals and `ret`. Methods without bodies pass.
+pub fn verify(asm: &Assembly, method: usize) -> Result<()> {
+	let body = match asm.method_body(method)? {
+		Some(body) => body,
+		None => return Ok(()),
+	};
+	let cfg = Cfg::build(&body)?;
+	stack_depths(asm, method, &body, &cfg)?;
+
+	let m = &asm.rows.method_defs[method];
+	let sig = MethodSig::parse(blob_at(asm.blobs(), m.sig.into_index())?)?;
+	// The variable arguments are only reached through `arglist`, the fixed
+	// ones are typed by the signature as usual.
+	let vararg = sig.call_conv == CallingConvention::VarArg;
+
+	let mut args = Vec::with_capacity(sig.params.len() + 1);
+	if sig.has_this && !sig.explicit_this {
+		let owner = asm.rows.method_owner(method).ok_or("Method has no owner type.")?;
+		let owner = TypeDefOrRef::TypeDef(owner as u32 + 1);
+		args.push(if is_value_type(asm, owner)? {
+			TypeSig::ByRef(Box::new(TypeSig::ValueType(owner)))
+		} else {
+			TypeSig::Class(owner)
+		});
+	}
+	args.extend(sig.params.iter().cloned());
+
+	let locals = asm.rows.local_var_sig(asm.blobs(), body.local_var_sig_tok)?;
+	if !locals.locals.is_empty() && !body.init_locals {
+		Err(Error::Verification(0, "Local variables are not zero-initialized."))?;
+	}
+	let locals = locals.locals.iter()
+		.map(|l| if l.by_ref { TypeSig::ByRef(Box::new(l.ty.clone())) } else { l.ty.clone() })
+		.collect();
+
+	let verifier = Verifier { asm, ret: sig.ret, args, locals, vararg };
+	verifier.run(&cfg)
+}
+
+struct Verifier<'a, 'b> {
+	asm: &'b Assembly<'a>,
+	ret: TypeSig,
+	/// Including `this`.
+	args: Vec<TypeSig>,
+	locals: Vec<TypeSig>,
+	vararg: bool,
+}
+
+impl Verifier<'_, '_> {
+	fn run(&self, cfg: &Cfg) -> Result<()> {
+		let mut entry: Vec<Option<Vec<VerType>>> = vec![None; cfg.blocks.len()];
+		entry[0] = Some(Vec::new());
+
+		for c in cfg.clauses.iter() {
+			let stack = match c.handler {
+				Handler::Catch(token) => vec![VerType::of(&self.token_type(token)?)],
+				Handler::Filter(_) => vec![VerType::Object(TypeSig::Object)],
+				Handler::Finally | Handler::Fault => Vec::new(),
+			};
+			if !matches!(stack.first(), None | Some(VerType::Object(_))) {
+				Err(Error::Verification(c.handler_offset, "Caught type is not a reference type."))?;
+			}
+			self.merge(cfg, &mut entry, c.handler_offset, &stack)?;
+			if let Handler::Filter(filter) = c.handler {
+				self.merge(cfg, &mut entry, filter, &stack)?;
+			}
+		}
+
+		let mut worklist: Vec<usize> = (0..cfg.blocks.len()).filter(|b| entry[*b].is_some()).rev().collect();
+		while let Some(b) = worklist.pop() {
+			let mut stack = entry[b].clone().unwrap_or_default();
+			for ins in cfg.block_instructions(b) {
+				self.step(&mut stack, ins)?;
+			}
+
+			for edge in cfg.blocks[b].successors.iter().filter(|e| e.kind != EdgeKind::Exception) {
+				if self.merge(cfg, &mut entry, cfg.blocks[edge.block].start, &stack)? && !worklist.contains(&edge.block) {
+					worklist.push(edge.block);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Merges the stack into the state on entry to the block at `offset`,
+	/// telling whether the state changed.
+	fn merge(&self, cfg: &Cfg, entry: &mut [Option<Vec<VerType>>], offset: u32, stack: &[VerType]) -> Result<bool> {
+		let block = cfg.block_at(offset).ok_or(Error::Verification(offset, "Handler is not at an instruction boundary."))?;
+		let merged = match &entry[block] {
+			None => stack.to_vec(),
+			Some(known) => {
+				if known.len() != stack.len() {
+					Err(Error::Verification(offset, "Stack depths differ where control flow merges."))?;
+				}
+				known.iter().zip(stack.iter())
+					.map(|(a, b)| a.merge(b))
+					.collect::<Option<Vec<_>>>()
+					.ok_or(Error::Verification(offset, "Stack types differ where control flow merges."))?
+			},
+		};
+		let changed = entry[block].as_ref() != Some(&merged);
+		entry[block] = Some(merged);
+		Ok(changed)
+	}
+
+	fn step(&self, stack: &mut Vec<VerType>, ins: &Instruction) -> Result<()> {
+		let error = |message| Error::Verification(ins.offset, message);
+		let mut pop = || stack.pop().ok_or(error("Stack underflow."));
+
+		let push = match ins.opcode {
+			NOP | BREAK => None,
+
+			LDARG_0 | LDARG_1 | LDARG_2 | LDARG_3 | LDARG_S | LDARG =>
+				Some(VerType::of(self.arg(ins)?)),
+			LDARGA_S | LDARGA => Some(VerType::ByRef(strip(self.arg(ins)?).clone())),
+			STARG_S | STARG => {
+				let value = pop()?;
+				expect_assignable(&value, self.arg(ins)?, ins)?;
+				None
+			},
+			LDLOC_0 | LDLOC_1 | LDLOC_2 | LDLOC_3 | LDLOC_S | LDLOC =>
+				Some(VerType::of(self.local(ins)?)),
+			LDLOCA_S | LDLOCA => Some(VerType::ByRef(strip(self.local(ins)?).clone())),
+			STLOC_0 | STLOC_1 | STLOC_2 | STLOC_3 | STLOC_S | STLOC => {
+				let value = pop()?;
+				expect_assignable(&value, self.local(ins)?, ins)?;
+				None
+			},
+
+			LDNULL => Some(VerType::Null),
+			LDC_I4_M1 | LDC_I4_0 | LDC_I4_1 | LDC_I4_2 | LDC_I4_3 | LDC_I4_4 | LDC_I4_5 |
+			LDC_I4_6 | LDC_I4_7 | LDC_I4_8 | LDC_I4_S | LDC_I4 => Some(VerType::Int32),
+			LDC_I8 => Some(VerType::Int64),
+			LDC_R4 | LDC_R8 => Some(VerType::Float),
+			LDSTR => Some(VerType::Object(TypeSig::String)),
+
+			DUP => {
+				let value = pop()?;
+				stack.push(value.clone());
+				Some(value)
+			},
+			POP => {
+				pop()?;
+				None
+			},
+
+			CALL | CALLVIRT | NEWOBJ => self.call(stack, ins)?,
+			RET => {
+				if self.ret != TypeSig::Void {
+					let value = pop()?;
+					expect_assignable(&value, &self.ret, ins)?;
+				}
+				None
+			},
+
+			BR | BR_S | ENDFINALLY | RETHROW => None,
+			LEAVE | LEAVE_S => {
+				stack.clear();
+				None
+			},
+			BRFALSE | BRFALSE_S | BRTRUE | BRTRUE_S => {
+				let value = pop()?;
+				if !value.is_integer() && !value.is_reference() && !matches!(value, VerType::ByRef(_)) {
+					Err(error("Branch condition is not an integer or a reference."))?;
+				}
+				None
+			},
+			BEQ | BEQ_S | BGE | BGE_S | BGT | BGT_S | BLE | BLE_S | BLT | BLT_S |
+			BNE_UN | BNE_UN_S | BGE_UN | BGE_UN_S | BGT_UN | BGT_UN_S | BLE_UN | BLE_UN_S |
+			BLT_UN | BLT_UN_S => {
+				let b = pop()?;
+				let a = pop()?;
+				let equality = matches!(ins.opcode, BEQ | BEQ_S | BNE_UN | BNE_UN_S);
+				if !comparable(&a, &b, equality) {
+					Err(error("Operands of the comparison are incompatible."))?;
+				}
+				None
+			},
+			CEQ | CGT | CGT_UN | CLT | CLT_UN => {
+				let b = pop()?;
+				let a = pop()?;
+				// `cgt.un` against null is the idiomatic null check.
+				let equality = matches!(ins.opcode, CEQ | CGT_UN);
+				if !comparable(&a, &b, equality) {
+					Err(error("Operands of the comparison are incompatible."))?;
+				}
+				Some(VerType::Int32)
+			},
+			SWITCH => {
+				if !matches!(pop()?, VerType::Int32 | VerType::NativeInt) {
+					Err(error("Switch value is not an integer."))?;
+				}
+				None
+			},
+			ENDFILTER => {
+				if pop()? != VerType::Int32 {
+					Err(error("Filter result is not int32."))?;
+				}
+				None
+			},
+			THROW => {
+				if !pop()?.is_reference() {
+					Err(error("Thrown value is not an object reference."))?;
+				}
+				None
+			},
+
+			ADD | SUB | MUL | DIV | REM => {
+				let b = pop()?;
+				let a = pop()?;
+				Some(binary_numeric(&a, &b, true).ok_or(error("Operands of the arithmetic are incompatible."))?)
+			},
+			AND | OR | XOR | DIV_UN | REM_UN | ADD_OVF | ADD_OVF_UN | MUL_OVF | MUL_OVF_UN |
+			SUB_OVF | SUB_OVF_UN => {
+				let b = pop()?;
+				let a = pop()?;
+				Some(binary_numeric(&a, &b, false).ok_or(error("Operands of the integer operation are incompatible."))?)
+			},
+			SHL | SHR | SHR_UN => {
+				let amount = pop()?;
+				let value = pop()?;
+				if !value.is_integer() || !matches!(amount, VerType::Int32 | VerType::NativeInt) {
+					Err(error("Operands of the shift are incompatible."))?;
+				}
+				Some(value)
+			},
+			NEG => {
+				let value = pop()?;
+				if !value.is_numeric() {
+					Err(error("Operand of the negation is not a number."))?;
+				}
+				Some(value)
+			},
+			NOT => {
+				let value = pop()?;
+				if !value.is_integer() {
+					Err(error("Operand of the bitwise not is not an integer."))?;
+				}
+				Some(value)
+			},
+			CKFINITE => {
+				if pop()? != VerType::Float {
+					Err(error("Operand of ckfinite is not a float."))?;
+				}
+				Some(VerType::Float)
+			},
+			CONV_R_UN => {
+				if !pop()?.is_integer() {
+					Err(error("Operand of the conversion is not an integer."))?;
+				}
+				Some(VerType::Float)
+			},
+			CONV_I1 | CONV_I2 | CONV_I4 | CONV_U1 | CONV_U2 | CONV_U4 |
+			CONV_OVF_I1 | CONV_OVF_I2 | CONV_OVF_I4 | CONV_OVF_U1 | CONV_OVF_U2 | CONV_OVF_U4 |
+			CONV_OVF_I1_UN | CONV_OVF_I2_UN | CONV_OVF_I4_UN | CONV_OVF_U1_UN | CONV_OVF_U2_UN |
+			CONV_OVF_U4_UN => Some(convert(pop()?, VerType::Int32, ins)?),
+			CONV_I8 | CONV_U8 | CONV_OVF_I8 | CONV_OVF_U8 | CONV_OVF_I8_UN | CONV_OVF_U8_UN =>
+				Some(convert(pop()?, VerType::Int64, ins)?),
+			CONV_I | CONV_U | CONV_OVF_I | CONV_OVF_U | CONV_OVF_I_UN | CONV_OVF_U_UN =>
+				Some(convert(pop()?, VerType::NativeInt, ins)?),
+			CONV_R4 | CONV_R8 => Some(convert(pop()?, VerType::Float, ins)?),
+
+			LDIND_I1 | LDIND_U1 | LDIND_I2 | LDIND_U2 | LDIND_I4 | LDIND_U4 | LDIND_I8 | LDIND_I |
+			LDIND_R4 | LDIND_R8 | LDIND_REF => {
+				let target = pointee(&pop()?, ins)?;
+				let value = VerType::of(&target);
+				let expected = indirect_type(ins.opcode);
+				let fits = match expected {
+					// The type is that of the location.
+					None => value.is_reference(),
+					Some(t) => value == t,
+				};
+				if !fits {
+					Err(error("Indirect load does not match the location type."))?;
+				}
+				Some(value)
+			},
+			STIND_I1 | STIND_I2 | STIND_I4 | STIND_I8 | STIND_I | STIND_R4 | STIND_R8 | STIND_REF => {
+				let value = pop()?;
+				let target = pointee(&pop()?, ins)?;
+				let fits = match indirect_type(ins.opcode) {
+					None => value.is_reference() && VerType::of(&target).is_reference(),
+					Some(t) => VerType::of(&target) == t && value.is_assignable_to(&target),
+				};
+				if !fits {
+					Err(error("Indirect store does not match the location type."))?;
+				}
+				None
+			},
+			LDOBJ => {
+				let ty = self.operand_type(ins)?;
+				let target = pointee(&pop()?, ins)?;
+				if !same_location(&target, &ty) {
+					Err(error("Location does not hold the loaded type."))?;
+				}
+				Some(VerType::of(&ty))
+			},
+			STOBJ => {
+				let ty = self.operand_type(ins)?;
+				let value = pop()?;
+				let target = pointee(&pop()?, ins)?;
+				expect_assignable(&value, &ty, ins)?;
+				if !same_location(&target, &ty) {
+					Err(error("Location does not hold the stored type."))?;
+				}
+				None
+			},
+			CPOBJ => {
+				let ty = self.operand_type(ins)?;
+				let src = pointee(&pop()?, ins)?;
+				let dst = pointee(&pop()?, ins)?;
+				if !same_location(&src, &ty) || !same_location(&dst, &ty) {
+					Err(error("Locations do not hold the copied type."))?;
+				}
+				None
+			},
+			INITOBJ => {
+				let ty = self.operand_type(ins)?;
+				let target = pointee(&pop()?, ins)?;
+				if !same_location(&target, &ty) {
+					Err(error("Location does not hold the initialized type."))?;
+				}
+				None
+			},
+
+			LDFLD | LDFLDA => {
+				let ty = self.field(ins, false)?;
+				let object = pop()?;
+				if !object.is_reference() && !matches!(object, VerType::ByRef(_) | VerType::Value(_)) {
+					Err(error("Field owner is not an object, a pointer or a value."))?;
+				}
+				if ins.opcode == LDFLDA && matches!(object, VerType::Value(_)) {
+					Err(error("Address of a field of a value on the stack."))?;
+				}
+				Some(if ins.opcode == LDFLD { VerType::of(&ty) } else { VerType::ByRef(strip(&ty).clone()) })
+			},
+			STFLD => {
+				let ty = self.field(ins, false)?;
+				let value = pop()?;
+				let object = pop()?;
+				if !object.is_reference() && !matches!(object, VerType::ByRef(_)) {
+					Err(error("Field owner is not an object or a pointer."))?;
+				}
+				expect_assignable(&value, &ty, ins)?;
+				None
+			},
+			LDSFLD => Some(VerType::of(&self.field(ins, true)?)),
+			LDSFLDA => Some(VerType::ByRef(strip(&self.field(ins, true)?).clone())),
+			STSFLD => {
+				let ty = self.field(ins, true)?;
+				expect_assignable(&pop()?, &ty, ins)?;
+				None
+			},
+
+			NEWARR => {
+				let ty = self.operand_type(ins)?;
+				if !matches!(pop()?, VerType::Int32 | VerType::NativeInt) {
+					Err(error("Array length is not an integer."))?;
+				}
+				Some(VerType::Object(TypeSig::SzArray(Box::new(ty))))
+			},
+			LDLEN => {
+				array_element(&pop()?, ins)?;
+				Some(VerType::NativeInt)
+			},
+			LDELEM_I1 | LDELEM_U1 | LDELEM_I2 | LDELEM_U2 | LDELEM_I4 | LDELEM_U4 | LDELEM_I8 |
+			LDELEM_I | LDELEM_R4 | LDELEM_R8 | LDELEM_REF | LDELEM | LDELEMA => {
+				index(pop()?, ins)?;
+				let element = array_element(&pop()?, ins)?;
+				let element = match (ins.opcode, element) {
+					(_, None) => match ins.opcode {
+						LDELEM | LDELEMA => self.operand_type(ins)?,
+						LDELEM_REF => TypeSig::Object,
+						op => element_sig(op),
+					},
+					(LDELEM | LDELEMA, Some(e)) => {
+						if !same_location(&e, &self.operand_type(ins)?) {
+							Err(error("Array does not hold the element type."))?;
+						}
+						e
+					},
+					(LDELEM_REF, Some(e)) => {
+						if !VerType::of(&e).is_reference() {
+							Err(error("Array does not hold object references."))?;
+						}
+						e
+					},
+					(op, Some(e)) => {
+						if !same_location(&e, &element_sig(op)) {
+							Err(error("Array does not hold the element type."))?;
+						}
+						e
+					},
+				};
+				Some(if ins.opcode == LDELEMA { VerType::ByRef(element) } else { VerType::of(&element) })
+			},
+			STELEM_I | STELEM_I1 | STELEM_I2 | STELEM_I4 | STELEM_I8 | STELEM_R4 | STELEM_R8 |
+			STELEM_REF | STELEM => {
+				let value = pop()?;
+				index(pop()?, ins)?;
+				let element = match (ins.opcode, array_element(&pop()?, ins)?) {
+					(STELEM, _) => self.operand_type(ins)?,
+					(STELEM_REF, e) => {
+						let e = e.unwrap_or(TypeSig::Object);
+						if !VerType::of(&e).is_reference() {
+							Err(error("Array does not hold object references."))?;
+						}
+						e
+					},
+					(op, _) => element_sig(op),
+				};
+				expect_assignable(&value, &element, ins)?;
+				None
+			},
+
+			BOX => {
+				let ty = self.operand_type(ins)?;
+				expect_assignable(&pop()?, &ty, ins)?;
+				Some(VerType::Object(TypeSig::Object))
+			},
+			UNBOX | UNBOX_ANY | CASTCLASS | ISINST => {
+				let ty = self.operand_type(ins)?;
+				if !pop()?.is_reference() {
+					Err(error("Operand is not an object reference."))?;
+				}
+				Some(match ins.opcode {
+					UNBOX     => VerType::ByRef(ty),
+					UNBOX_ANY => VerType::of(&ty),
+					// A value type token stands for its boxed form.
+					_ => match VerType::of(&ty) {
+						VerType::Object(t) => VerType::Object(t),
+						_ => VerType::Object(TypeSig::Object),
+					},
+				})
+			},
+
+			LDTOKEN => Some(VerType::Handle),
+			ARGLIST if self.vararg => Some(VerType::Handle),
+			ARGLIST => Err(error("Method does not take variable arguments."))?,
+			LDFTN => Some(VerType::NativeInt),
+			LDVIRTFTN => {
+				if !pop()?.is_reference() {
+					Err(error("Operand is not an object reference."))?;
+				}
+				Some(VerType::NativeInt)
+			},
+			SIZEOF => Some(VerType::Int32),
+
+			JMP | CALLI | LOCALLOC | CPBLK | INITBLK | MKREFANY | REFANYVAL | REFANYTYPE =>
+				Err(error("Instruction is not verifiable."))?,
+			_ => Err(error("Unknown opcode."))?,
+		};
+
+		if let Some(value) = push {
+			stack.push(value);
+		}
+		Ok(())
+	}
+
+	fn call(&self, stack: &mut Vec<VerType>, ins: &Instruction) -> Result<Option<VerType>> {
+		let error = |message| Error::Verification(ins.offset, message);
+		let token = match ins.operand {
+			Operand::Token(token) => token,
+			_ => Err(error("Call has no method token."))?,
+		};
+		let sig = self.asm.rows.method_sig(self.asm.blobs(), token)?;
+		if sig.params.len() > stack.len() {
+			Err(error("Stack underflow."))?;
+		}
+
+		for param in sig.params.iter().rev() {
+			let arg = stack.pop().ok_or(error("Stack underflow."))?;
+			expect_assignable(&arg, param, ins)?;
+		}
+
+		if ins.opcode == NEWOBJ {
+			let owner = self.method_owner(token)?;
+			return Ok(Some(VerType::of(&owner)));
+		}
+
+		if sig.has_this && !sig.explicit_this {
+			let this = stack.pop().ok_or(error("Stack underflow."))?;
+			let constrained = ins.prefixes.iter().any(|p| matches!(p, Prefix::Constrained(_)));
+			let fits = match this {
+				VerType::Object(_) | VerType::Null => true,
+				// Value types are passed by reference.
+				VerType::ByRef(_) => ins.opcode == CALL || constrained,
+				_ => false,
+			};
+			if !fits {
+				Err(error("Call target is not an object reference."))?;
+			}
+		} else if ins.opcode == CALLVIRT {
+			Err(error("Virtual call to a static method."))?;
+		}
+
+		Ok(if sig.ret == TypeSig::Void { None } else { Some(VerType::of(&sig.ret)) })
+	}
+
+	fn arg(&self, ins: &Instruction) -> Result<&TypeSig> {
+		let i = match (ins.opcode, &ins.operand) {
+			(LDARG_0, _) => 0,
+			(LDARG_1, _) => 1,
+			(LDARG_2, _) => 2,
+			(LDARG_3, _) => 3,
+			(_, Operand::U8(i)) => *i as usize,
+			(_, Operand::U16(i)) => *i as usize,
+			_ => Err(Error::Verification(ins.offset, "Argument has no index."))?,
+		};
+		self.args.get(i).ok_or(Error::Verification(ins.offset, "Argument index is out of range."))
+	}
+
+	fn local(&self, ins: &Instruction) -> Result<&TypeSig> {
+		let i = match (ins.opcode, &ins.operand) {
+			(LDLOC_0, _) | (STLOC_0, _) => 0,
+			(LDLOC_1, _) | (STLOC_1, _) => 1,
+			(LDLOC_2, _) | (STLOC_2, _) => 2,
+			(LDLOC_3, _) | (STLOC_3, _) => 3,
+			(_, Operand::U8(i)) => *i as usize,
+			(_, Operand::U16(i)) => *i as usize,
+			_ => Err(Error::Verification(ins.offset, "Local variable has no index."))?,
+		};
+		self.locals.get(i).ok_or(Error::Verification(ins.offset, "Local variable index is out of range."))
+	}
+
+	fn operand_type(&self, ins: &Instruction) -> Result<TypeSig> {
+		match ins.operand {
+			Operand::Token(token) => self.token_type(token),
+			_ => Err(Error::Verification(ins.offset, "Instruction has no type token.")),
+		}
+	}
+
+	/// Type of the field the instruction accesses, checking it is static
+	/// or not as the instruction expects, when that is known.
+	fn field(&self, ins: &Instruction, is_static: bool) -> Result<TypeSig> {
+		let token = match ins.operand {
+			Operand::Token(token) => token,
+			_ => Err(Error::Verification(ins.offset, "Instruction has no field token."))?,
+		};
+		if token.table_index() == METADATA_FIELD {
+			let field = self.asm.rows.fields.get(token.row_index()).ok_or("Field row is out of the table bounds.")?;
+			if (field.flags & FIELD_STATIC != 0) != is_static {
+				Err(Error::Verification(ins.offset, "Field is static where an instance one is expected or vice versa."))?;
+			}
+		}
+		self.asm.rows.field_sig(self.asm.blobs(), token)
+	}
+
+	/// Type a TypeDef, TypeRef or TypeSpec token stands for.
+	fn token_type(&self, token: MetadataToken) -> Result<TypeSig> {
+		let row = token.row_index() as u32 + 1;
+		let ty = match token.table_index() {
+			METADATA_TYPE_DEF => TypeDefOrRef::TypeDef(row),
+			METADATA_TYPE_REF => TypeDefOrRef::TypeRef(row),
+			METADATA_TYPE_SPEC => {
+				let spec = self.asm.rows.type_specs.get((row as usize).wrapping_sub(1)).ok_or("TypeSpec row is out of the table bounds.")?;
+				return TypeSig::parse_type_spec(blob_at(self.asm.blobs(), spec.sig.into_index())?);
+			},
+			_ => Err("Token does not reference a type.")?,
+		};
+		type_sig(self.asm, ty)
+	}
+
+	/// Type whose constructor the token references.
+	fn method_owner(&self, token: MetadataToken) -> Result<TypeSig> {
+		let rows = &self.asm.rows;
+		let owner = match token.table_index() {
+			METADATA_METHOD_DEF => {
+				let owner = rows.method_owner(token.row_index()).ok_or("Method has no owner type.")?;
+				TypeDefOrRef::TypeDef(owner as u32 + 1)
+			},
+			METADATA_MEMBER_REF => {
+				let member = rows.member_refs.get(token.row_index()).ok_or("MemberRef row is out of the table bounds.")?;
+				match member.class {
+					MemberRefParent::TypeDef(r) => TypeDefOrRef::TypeDef(r),
+					MemberRefParent::TypeRef(r) => TypeDefOrRef::TypeRef(r),
+					MemberRefParent::TypeSpec(r) => TypeDefOrRef::TypeSpec(r),
+					_ => Err("Constructor is not a member of a type.")?,
+				}
+			},
+			_ => Err("Token does not reference a constructor.")?,
+		};
+		match owner {
+			TypeDefOrRef::TypeSpec(r) => {
+				let token = MetadataToken::try_from((METADATA_TYPE_SPEC as u32) << 24 | r)?;
+				self.token_type(token)
+			},
+			_ => type_sig(self.asm, owner),
+		}
+	}
+}
+
+/// Signature type of a TypeDef or TypeRef, telling classes from value types.
+fn type_sig(asm: &Assembly, ty: TypeDefOrRef) -> Result<TypeSig> {
+	let name = asm.rows.type_name(asm.strings(), ty)?;
+	// Primitive types referenced by their names, II.23.2.16.
+	let primitive = match name.as_str() {
+		"System.Boolean" => Some(TypeSig::Boolean),
+		"System.Char"    => Some(TypeSig::Char),
+		"System.SByte"   => Some(TypeSig::I1),
+		"System.Byte"    => Some(TypeSig::U1),
+		"System.Int16"   => Some(TypeSig::I2),
+		"System.UInt16"  => Some(TypeSig::U2),
+		"System.Int32"   => Some(TypeSig::I4),
+		"System.UInt32"  => Some(TypeSig::U4),
+		"System.Int64"   => Some(TypeSig::I8),
+		"System.UInt64"  => Some(TypeSig::U8),
+		"System.Single"  => Some(TypeSig::R4),
+		"System.Double"  => Some(TypeSig::R8),
+		"System.IntPtr"  => Some(TypeSig::I),
+		"System.UIntPtr" => Some(TypeSig::U),
+		"System.String"  => Some(TypeSig::String),
+		"System.Object"  => Some(TypeSig::Object),
+		_ => None,
+	};
+	if let (Some(sig), TypeDefOrRef::TypeRef(_)) = (primitive, ty) {
+		return Ok(sig);
+	}
+
+	Ok(if is_value_type(asm, ty)? { TypeSig::ValueType(ty) } else { TypeSig::Class(ty) })
+}
+
+/// Types defined here are value types if they derive from System.ValueType
+/// or System.Enum, referenced ones if signatures tag them `valuetype`.
+pub fn is_value_type(asm: &Assembly, ty: TypeDefOrRef) -> Result<bool> {
+	let def = match ty {
+		TypeDefOrRef::TypeDef(r) => {
+			asm.rows.type_defs.get((r as usize).wrapping_sub(1)).ok_or("TypeDef row is out of the table bounds.")?
+		},
+		TypeDefOrRef::TypeRef(r) => return Ok(asm.value_type_refs().contains(&r)),
+		TypeDefOrRef::TypeSpec(_) => return Ok(false),
+	};
+	if def.extends.into_index() == 0 || matches!(def.extends, TypeDefOrRef::TypeSpec(_)) {
+		return Ok(false);
+	}
+	let base = asm.rows.type_name(asm.strings(), def.extends)?;
+	let name = asm.rows.type_name(asm.strings(), ty)?;
+	Ok((base == "System.ValueType" || base == "System.Enum") && name != "System.Enum")
+}
+
+fn expect_assignable(value: &VerType, to: &TypeSig, ins: &Instruction) -> Result<()> {
+	if !value.is_assignable_to(to) {
+		Err(Error::Verification(ins.offset, "Value is not assignable to the destination type."))?;
+	}
+	Ok(())
+}
+
+/// Operand types of comparisons and conditional branches, III.1.5 table 4.
+fn comparable(a: &VerType, b: &VerType, equality: bool) -> bool {
+	match (a, b) {
+		(VerType::Int32, VerType::Int32) | (VerType::Int32, VerType::NativeInt) |
+		(VerType::NativeInt, VerType::Int32) | (VerType::NativeInt, VerType::NativeInt) |
+		(VerType::Int64, VerType::Int64) | (VerType::Float, VerType::Float) |
+		(VerType::ByRef(_), VerType::ByRef(_)) => true,
+		(a, b) if a.is_reference() && b.is_reference() => equality,
+		_ => false,
+	}
+}
+
+/// Result type of binary arithmetic, III.1.5 table 2, and of integer
+/// operations, table 5, if floats are not allowed.
+fn binary_numeric(a: &VerType, b: &VerType, allow_float: bool) -> Option<VerType> {
+	let result = match (a, b) {
+		(VerType::Int32, VerType::Int32) => VerType::Int32,
+		(VerType::Int32, VerType::NativeInt) | (VerType::NativeInt, VerType::Int32) |
+		(VerType::NativeInt, VerType::NativeInt) => VerType::NativeInt,
+		(VerType::Int64, VerType::Int64) => VerType::Int64,
+		(VerType::Float, VerType::Float) if allow_float => VerType::Float,
+		_ => return None,
+	};
+	Some(result)
+}
+
+fn convert(value: VerType, to: VerType, ins: &Instruction) -> Result<VerType> {
+	if !value.is_numeric() {
+		Err(Error::Verification(ins.offset, "Operand of the conversion is not a number."))?;
+	}
+	Ok(to)
+}
+
+/// Target type of a managed pointer. Unmanaged ones are not verifiable.
+fn pointee(address: &VerType, ins: &Instruction) -> Result<TypeSig> {
+	match address {
+		VerType::ByRef(t) => Ok(t.clone()),
+		_ => Err(Error::Verification(ins.offset, "Address is not a managed pointer.")),
+	}
+}
+
+/// Stack type of `ldind.*` and `stind.*`, None for `.ref` ones.
+fn indirect_type(op: u16) -> Option<VerType> {
+	match op {
+		LDIND_I1 | LDIND_U1 | LDIND_I2 | LDIND_U2 | LDIND_I4 | LDIND_U4 |
+		STIND_I1 | STIND_I2 | STIND_I4 => Some(VerType::Int32),
+		LDIND_I8 | STIND_I8 => Some(VerType::Int64),
+		LDIND_I | STIND_I => Some(VerType::NativeInt),
+		LDIND_R4 | LDIND_R8 | STIND_R4 | STIND_R8 => Some(VerType::Float),
+		_ => None,
+	}
+}
+
+/// Element type named by typed `ldelem.*` and `stelem.*` opcodes.
+fn element_sig(op: u16) -> TypeSig {
+	match op {
+		LDELEM_I1 | STELEM_I1 => TypeSig::I1,
+		LDELEM_U1 => TypeSig::U1,
+		LDELEM_I2 | STELEM_I2 => TypeSig::I2,
+		LDELEM_U2 => TypeSig::U2,
+		LDELEM_I4 | STELEM_I4 => TypeSig::I4,
+		LDELEM_U4 => TypeSig::U4,
+		LDELEM_I8 | STELEM_I8 => TypeSig::I8,
+		LDELEM_I | STELEM_I => TypeSig::I,
+		LDELEM_R4 | STELEM_R4 => TypeSig::R4,
+		LDELEM_R8 | STELEM_R8 => TypeSig::R8,
+		_ => TypeSig::Object,
+	}
+}
+
+fn index(value: VerType, ins: &Instruction) -> Result<()> {
+	if !matches!(value, VerType::Int32 | VerType::NativeInt) {
+		Err(Error::Verification(ins.offset, "Array index is not an integer."))?;
+	}
+	Ok(())
+}
+
+/// Element type of a single-dimensional array, None for the null
+/// reference, whose type is not known.
+fn array_element(array: &VerType, ins: &Instruction) -> Result<Option<TypeSig>> {
+	match array {
+		VerType::Object(TypeSig::SzArray(e)) => Ok(Some(strip(e).clone())),
+		VerType::Null => Ok(None),
+		_ => Err(Error::Verification(ins.offset, "Operand is not a single-dimensional array.")),
+	}
+}
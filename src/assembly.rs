@@ -0,0 +1,182 @@
+use std::cell::OnceCell;
+use std::collections::HashSet;
+
+use log::{error, trace, warn};
+
+use crate::Result;
+use crate::cli;
+use crate::pe;
+
+/// A CLI image with its headers and metadata tables parsed.
+#[derive(Clone)]
+pub struct Assembly<'a> {
+	pub image: &'a [u8],
+	pub pe: pe::Header,
+	pub cli: cli::Header,
+	pub metadata: cli::Metadata<'a>,
+	pub tables: cli::Tables,
+	pub rows: cli::TableRows,
+	/// See `value_type_refs`.
+	value_type_refs: OnceCell<HashSet<u32>>,
+}
+
+impl<'a> Assembly<'a> {
+	pub fn parse(image: &'a [u8]) -> Result<Assembly<'a>> {
+		let pe = pe::Header::parse(image)?;
+
+		let cli_offset = pe.rva2offset(pe.cli_rva as usize).ok_or("Failed to convert CLI header RVA.")?;
+		let cli = image.get(cli_offset..cli_offset + pe.cli_size as usize).ok_or("CLI header RVA is wrong.")?;
+		let cli = cli::Header::parse(cli, &pe)?;
+
+		let metadata_offset = pe.rva2offset(cli.metadata_rva as usize).ok_or("Failed to convert CLI metadata RVA.")?;
+		let metadata = image.get(metadata_offset..metadata_offset + cli.metadata_size as usize)
+			.ok_or("CLI metadata RVA is wrong.")?;
+		let metadata = cli::Metadata::parse(metadata)?;
+
+		trace!("Parsing logical tables...");
+		let logical_tables = metadata.logical_tables.ok_or("Metadata has no logical tables.")?;
+		let tables = cli::Tables::parse(logical_tables)?;
+		let rows = cli::TableRows::parse(&tables, &logical_tables[tables.size..])?;
+
+		Ok(Assembly { image, pe, cli, metadata, tables, rows, value_type_refs: OnceCell::new() })
+	}
+
+	/// Runs the II.22 checks, logging the findings. Fails if any is an
+	/// error, as the rest of the crate indexes tables and heaps trusting
+	/// them to be valid.
+	pub fn validate(&self) -> Result<()> {
+		let findings = cli::validate(&self.tables, &self.rows, &self.metadata);
+		for finding in findings.iter() {
+			match finding.severity {
+				cli::Severity::Warning => warn!("{}", finding),
+				cli::Severity::Error   => error!("{}", finding),
+			}
+		}
+		if findings.iter().any(|f| f.severity == cli::Severity::Error) {
+			Err("Metadata is invalid.")?;
+		}
+		Ok(())
+	}
+
+	/// The #Strings heap, heaps missing from the image are treated as empty.
+	pub fn strings(&self) -> &'a [u8] {
+		self.metadata.strings.unwrap_or(&[0])
+	}
+
+	pub fn user_strings(&self) -> &'a [u8] {
+		self.metadata.user_strings.unwrap_or(&[0])
+	}
+
+	pub fn blobs(&self) -> &'a [u8] {
+		self.metadata.blobs.unwrap_or(&[0])
+	}
+
+	pub fn guids(&self) -> &'a [u8] {
+		self.metadata.guids.unwrap_or(&[])
+	}
+
+	/// TypeRef rows known to be value types, collected from all signatures
+	/// once they are first asked for.
+	pub fn value_type_refs(&self) -> &HashSet<u32> {
+		self.value_type_refs.get_or_init(|| self.rows.value_type_refs(self.blobs()))
+	}
+
+	/// Parses the body of the 0-based method row, methods without RVA
+	/// (abstract, runtime-implemented or P/Invoke ones) have none.
+	pub fn method_body(&self, method: usize) -> Result<Option<cli::MethodBody<'a>>> {
+		let m = self.rows.method_defs.get(method).ok_or("Method row is out of the table bounds.")?;
+		if m.rva == 0 {
+			return Ok(None);
+		}
+
+		let offset = self.pe.rva2offset(m.rva as usize).ok_or("Failed to convert method RVA.")?;
+		let data = self.image.get(offset..).ok_or("Method RVA is wrong.")?;
+		Ok(Some(cli::MethodBody::parse(data)?))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SOURCE: &str = r#"
+.assembly extern mscorlib { .publickeytoken = (B7 7A 5C 56 19 34 E0 89 ) .ver 4:0:0:0 }
+.assembly test { .ver 1:2:3:4 }
+.module test.exe
+.data Table = bytearray (01 02 03 04)
+.class public auto ansi sealed Test extends [mscorlib]System.Object
+{
+	.custom instance void [mscorlib]System.ObsoleteAttribute::.ctor() = (01 00 00 00)
+	.field public static literal int32 Answer = int32(42)
+	.field public static int32 Data at Table
+	.field private string name
+	.method public static void Main() cil managed
+	{
+		.entrypoint
+		.maxstack 2
+		.locals init (int32 x, string s)
+		ldstr "Hello"
+		stloc.1
+		ldloc.1
+		call void [mscorlib]System.Console::WriteLine(string)
+		ret
+	}
+	.class nested private auto ansi Inner extends [mscorlib]System.Object
+	{
+		.method public instance int64 Twice(int64 x) cil managed
+		{
+			.maxstack 2
+			ldarg.1
+			ldarg.1
+			add
+			ret
+		}
+	}
+}
+"#;
+
+	/// Rebuilds the metadata root of the image with the #~ stream written
+	/// from its parsed rows.
+	fn rewrite(asm: &Assembly) -> Vec<u8> {
+		let logical_tables = asm.rows.write_stream(asm.strings().len(), asm.guids().len(), asm.blobs().len());
+		let metadata = cli::Metadata { logical_tables: Some(&logical_tables), ..asm.metadata.clone() };
+		let mut out = Vec::new();
+		metadata.write(&mut out);
+		out
+	}
+
+	#[test]
+	fn metadata_round_trips() {
+		let image = crate::ilasm::assemble(SOURCE).expect("Failed to assemble.");
+		let asm = Assembly::parse(&image).expect("Failed to parse.");
+		let logical_tables = asm.rows.write_stream(asm.strings().len(), asm.guids().len(), asm.blobs().len());
+		assert!(Some(logical_tables.as_slice()) == asm.metadata.logical_tables, "#~ is not written back as read.");
+
+		let data = rewrite(&asm);
+		let metadata = cli::Metadata::parse(&data).expect("Failed to parse rewritten metadata.");
+
+		assert_eq!(metadata.version, asm.metadata.version);
+		assert_eq!(metadata.logical_tables, asm.metadata.logical_tables, "#~");
+		assert_eq!(metadata.strings, asm.metadata.strings, "#Strings");
+		assert_eq!(metadata.user_strings, asm.metadata.user_strings, "#US");
+		assert_eq!(metadata.blobs, asm.metadata.blobs, "#Blob");
+		assert_eq!(metadata.guids, asm.metadata.guids, "#GUID");
+	}
+
+	#[test]
+	fn module_reserved_fields_round_trip() {
+		let image = crate::ilasm::assemble(SOURCE).expect("Failed to assemble.");
+		let mut asm = Assembly::parse(&image).expect("Failed to parse.");
+		let module = &mut asm.rows.modules[0];
+		module.generation = 3;
+		module.enc_id = cli::GuidIndex(1);
+		module.enc_base_id = cli::GuidIndex(1);
+
+		let data = rewrite(&asm);
+		let metadata = cli::Metadata::parse(&data).expect("Failed to parse rewritten metadata.");
+		let logical_tables = metadata.logical_tables.expect("Rewritten metadata has no #~ stream.");
+		let tables = cli::Tables::parse(logical_tables).expect("Failed to parse the #~ header.");
+		let rows = cli::TableRows::parse(&tables, &logical_tables[tables.size..]).expect("Failed to parse rows.");
+		assert_eq!(rows.modules, asm.rows.modules);
+	}
+}
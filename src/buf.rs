@@ -97,9 +97,11 @@ read_impl!(u64,  8);
 read_impl!(i64,  8);
 read_impl!(u128, 16);
 read_impl!(i128, 16);
+read_impl!(f32,  4);
+read_impl!(f64,  8);
 
 pub trait Reading : Index<usize> + Index<RangeFrom<usize>> {
-	fn read_at<'a, T>(self: &'a Self, offset: usize) -> Result<T>
+	fn read_at<'a, T>(&'a self, offset: usize) -> Result<T>
 	where
 		<Self as Index<RangeFrom<usize>>>::Output: 'a,
 		T: TryPart<'a, <Self as Index<RangeFrom<usize>>>::Output>
@@ -108,18 +110,54 @@ pub trait Reading : Index<usize> + Index<RangeFrom<usize>> {
 		T::try_read(&self[offset..])
 	}
 
-	fn read<'a, T>(self: &'a Self, offset: &mut usize) -> Result<T>
+	fn read<'a, T>(&'a self, offset: &mut usize) -> Result<T>
 	where
 		<Self as Index<RangeFrom<usize>>>::Output: 'a,
 		T: TryPart<'a, <Self as Index<RangeFrom<usize>>>::Output> + PartSize
 	{
 		// TODO(dmi): @robustness Any offset checks?
 		let o = *offset;
-		T::try_read(&self[o..]).and_then(|x| {
-			*offset += T::size();
-			Ok(x)
-		})
+		T::try_read(&self[o..]).inspect(|_| *offset += T::size())
 	}
 }
 
 impl<T> Reading for T where T: ?Sized + Index<usize> + Index<RangeFrom<usize>> {}
+
+pub trait WritePart {
+	fn write(self, dst: &mut Vec<u8>);
+}
+
+macro_rules! write_impl {
+	($ty:tt) => {
+		impl WritePart for $ty {
+			#[inline]
+			fn write(self, dst: &mut Vec<u8>) {
+				dst.extend_from_slice(&self.to_le_bytes());
+			}
+		}
+	}
+}
+
+write_impl!(u8);
+write_impl!(i8);
+write_impl!(u16);
+write_impl!(i16);
+write_impl!(u32);
+write_impl!(i32);
+write_impl!(u64);
+write_impl!(i64);
+write_impl!(u128);
+write_impl!(i128);
+write_impl!(f32);
+write_impl!(f64);
+
+pub trait Writing {
+	fn write<T: WritePart>(&mut self, x: T);
+}
+
+impl Writing for Vec<u8> {
+	#[inline]
+	fn write<T: WritePart>(&mut self, x: T) {
+		x.write(self);
+	}
+}
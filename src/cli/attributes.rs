@@ -0,0 +1,330 @@
+use crate::Result;
+use crate::error::Error;
+use crate::buf::Reading;
+use crate::cli::constants::*;
+use crate::cli::{
+	TableRows, CustomAttribute, CustomAttributeType, HasCustomAttribute, MemberRefParent,
+	TypeDefOrRef, TypeSig, MethodSig, FIELD_STATIC, blob_at, read_compressed_u32,
+};
+
+// Taken from ECMA II.23.3
+const ATTRIBUTE_PROLOG: u16 = 0x0001;
+
+/// A decoded argument of a custom attribute, II.23.3.
+#[derive(Debug, PartialEq, Clone)]
+pub enum AttributeValue {
+	Bool(bool),
+	/// UTF-16 code unit.
+	Char(u16),
+	I1(i8),
+	U1(u8),
+	I2(i16),
+	U2(u16),
+	I4(i32),
+	U4(u32),
+	I8(i64),
+	U8(u64),
+	R4(f32),
+	R8(f64),
+	/// None stands for a null string.
+	String(Option<String>),
+	/// Serialized (reflection) type name, e.g. `System.Int32, mscorlib`.
+	Type(Option<String>),
+	Enum {
+		ty: String,
+		value: Box<AttributeValue>,
+	},
+	/// None stands for a null array.
+	Array(Option<Box<[AttributeValue]>>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NamedArg {
+	/// Whether it sets a field or a property.
+	pub is_field: bool,
+	pub name: String,
+	pub value: AttributeValue,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AttributeArgs {
+	pub fixed: Box<[AttributeValue]>,
+	pub named: Box<[NamedArg]>,
+}
+
+/// The type of an argument as both constructor signatures and
+/// FieldOrPropType (II.23.3) describe it.
+#[derive(Debug, PartialEq, Clone)]
+enum ArgType {
+	/// One of the primitive element types.
+	Primitive(u8),
+	String,
+	Type,
+	/// System.Object, the value is prefixed by its type.
+	Boxed,
+	/// Carries the enum name and its underlying element type.
+	Enum(String, u8),
+	SzArray(Box<ArgType>),
+}
+
+impl TableRows {
+	/// All attributes attached to the `parent`.
+	pub fn attributes_of(&self, parent: HasCustomAttribute) -> impl Iterator<Item = &CustomAttribute> {
+		self.custom_attributes.iter().filter(move |a| a.parent == parent)
+	}
+
+	/// Full name of the attribute type, e.g. `System.ObsoleteAttribute`.
+	pub fn attribute_name(&self, strings: &[u8], attribute: &CustomAttribute) -> Result<String> {
+		let ty = match attribute.ty {
+			CustomAttributeType::MethodDef(m) => {
+				let owner = self.method_owner((m as usize).wrapping_sub(1))
+					.ok_or("Attribute constructor has no owner type.")?;
+				TypeDefOrRef::TypeDef(owner as u32 + 1)
+			},
+			CustomAttributeType::MemberRef(m) => {
+				let m = self.member_refs.get((m as usize).wrapping_sub(1))
+					.ok_or("MemberRef row is out of the table bounds.")?;
+				match m.class {
+					MemberRefParent::TypeDef(t) => TypeDefOrRef::TypeDef(t),
+					MemberRefParent::TypeRef(t) => TypeDefOrRef::TypeRef(t),
+					_ => Err("Unsupported attribute constructor parent.")?,
+				}
+			},
+		};
+		self.type_name(strings, ty)
+	}
+
+	/// Decodes the attribute value blob against its constructor signature.
+	pub fn attribute_args(&self, strings: &[u8], blobs: &[u8], attribute: &CustomAttribute) -> Result<AttributeArgs> {
+		let sig = match attribute.ty {
+			CustomAttributeType::MethodDef(m) => self.method_defs.get((m as usize).wrapping_sub(1))
+				.ok_or("MethodDef row is out of the table bounds.")?
+				.sig,
+			CustomAttributeType::MemberRef(m) => self.member_refs.get((m as usize).wrapping_sub(1))
+				.ok_or("MemberRef row is out of the table bounds.")?
+				.sig,
+		};
+		let ctor = MethodSig::parse(blob_at(blobs, sig.into_index())?)?;
+
+		let decoder = AttributeDecoder::new(self, strings, blobs);
+		let data = blob_at(blobs, attribute.value.into_index())?;
+		decoder.args(&ctor, data)
+	}
+}
+
+/// Decodes attribute arguments, resolving enum and System.Type arguments
+/// through the metadata.
+pub struct AttributeDecoder<'a> {
+	rows: &'a TableRows,
+	strings: &'a [u8],
+	blobs: &'a [u8],
+}
+
+impl<'a> AttributeDecoder<'a> {
+	pub fn new(rows: &'a TableRows, strings: &'a [u8], blobs: &'a [u8]) -> Self {
+		AttributeDecoder { rows, strings, blobs }
+	}
+
+	fn args(&self, ctor: &MethodSig, data: &[u8]) -> Result<AttributeArgs> {
+		let offset = &mut 0usize;
+
+		// An empty blob is a valid value of a parameterless attribute.
+		if data.is_empty() && ctor.params.is_empty() {
+			return Ok(AttributeArgs { fixed: Box::new([]), named: Box::new([]) });
+		}
+
+		let prolog: u16 = data.read(offset)?;
+		if prolog != ATTRIBUTE_PROLOG {
+			Err("Custom attribute has wrong prolog.")?;
+		}
+
+		let mut fixed = Vec::with_capacity(ctor.params.len());
+		for param in ctor.params.iter() {
+			let ty = self.arg_type(param)?;
+			fixed.push(self.value(data, offset, &ty)?);
+		}
+
+		let n: u16 = data.read(offset)?;
+		let named = self.named_args(data, offset, n as usize)?;
+
+		Ok(AttributeArgs {
+			fixed: fixed.into_boxed_slice(),
+			named,
+		})
+	}
+
+	/// Reads `n` named field and property arguments.
+	pub fn named_args(&self, data: &[u8], offset: &mut usize, n: usize) -> Result<Box<[NamedArg]>> {
+		let mut named = Vec::new();
+		for _ in 0..n {
+			let is_field = match data.read::<u8>(offset)? {
+				ELEMENT_TYPE_FIELD    => true,
+				ELEMENT_TYPE_PROPERTY => false,
+				_ => Err("Named argument is neither field nor property.")?,
+			};
+			let ty = self.field_or_prop_type(data, offset)?;
+			let name = read_ser_string(data, offset)?.ok_or("Named argument has null name.")?;
+			let value = self.value(data, offset, &ty)?;
+			named.push(NamedArg { is_field, name, value });
+		}
+		Ok(named.into_boxed_slice())
+	}
+
+	fn arg_type(&self, ty: &TypeSig) -> Result<ArgType> {
+		let ty = match ty {
+			TypeSig::Boolean => ArgType::Primitive(ELEMENT_TYPE_BOOLEAN),
+			TypeSig::Char    => ArgType::Primitive(ELEMENT_TYPE_CHAR),
+			TypeSig::I1      => ArgType::Primitive(ELEMENT_TYPE_I1),
+			TypeSig::U1      => ArgType::Primitive(ELEMENT_TYPE_U1),
+			TypeSig::I2      => ArgType::Primitive(ELEMENT_TYPE_I2),
+			TypeSig::U2      => ArgType::Primitive(ELEMENT_TYPE_U2),
+			TypeSig::I4      => ArgType::Primitive(ELEMENT_TYPE_I4),
+			TypeSig::U4      => ArgType::Primitive(ELEMENT_TYPE_U4),
+			TypeSig::I8      => ArgType::Primitive(ELEMENT_TYPE_I8),
+			TypeSig::U8      => ArgType::Primitive(ELEMENT_TYPE_U8),
+			TypeSig::R4      => ArgType::Primitive(ELEMENT_TYPE_R4),
+			TypeSig::R8      => ArgType::Primitive(ELEMENT_TYPE_R8),
+			TypeSig::String  => ArgType::String,
+			TypeSig::Object  => ArgType::Boxed,
+			TypeSig::SzArray(t) => ArgType::SzArray(Box::new(self.arg_type(t)?)),
+			TypeSig::Class(t) => {
+				if self.rows.type_name(self.strings, *t)? != "System.Type" {
+					Err("Unsupported class type of attribute argument.")?;
+				}
+				ArgType::Type
+			},
+			// Attributes accept only enums as value types. The size of the
+			// value depends on the underlying type, enums of other assemblies
+			// cannot be read without loading them.
+			TypeSig::ValueType(t) => {
+				let name = self.rows.type_name(self.strings, *t)?;
+				let underlying = match t {
+					TypeDefOrRef::TypeDef(row) => self.enum_underlying_type((*row as usize).wrapping_sub(1))?,
+					_ => Err("Underlying type of an enum of another assembly is unknown.")?,
+				};
+				ArgType::Enum(name, underlying)
+			},
+			TypeSig::CustomMod { ty, .. } => self.arg_type(ty)?,
+			_ => Err("Unsupported type of attribute argument.")?,
+		};
+		Ok(ty)
+	}
+
+	/// Reads FieldOrPropType, II.23.3.
+	fn field_or_prop_type(&self, data: &[u8], offset: &mut usize) -> Result<ArgType> {
+		let b: u8 = data.read(offset)?;
+		let ty = match b {
+			ELEMENT_TYPE_BOOLEAN..=ELEMENT_TYPE_R8 => ArgType::Primitive(b),
+			ELEMENT_TYPE_STRING      => ArgType::String,
+			ELEMENT_TYPE_SYSTEM_TYPE => ArgType::Type,
+			ELEMENT_TYPE_BOXED       => ArgType::Boxed,
+			ELEMENT_TYPE_SZARRAY     => ArgType::SzArray(Box::new(self.field_or_prop_type(data, offset)?)),
+			ELEMENT_TYPE_ENUM        => {
+				let name = read_ser_string(data, offset)?.ok_or("Enum argument has null type name.")?;
+				let t = self.enum_by_name(&name).ok_or("Underlying type of an enum of another assembly is unknown.")?;
+				let underlying = self.enum_underlying_type(t)?;
+				ArgType::Enum(name, underlying)
+			},
+			_ => Err("Unknown type of named attribute argument.")?,
+		};
+		Ok(ty)
+	}
+
+	fn value(&self, data: &[u8], offset: &mut usize, ty: &ArgType) -> Result<AttributeValue> {
+		let value = match ty {
+			ArgType::Primitive(t) => read_primitive(data, offset, *t)?,
+			ArgType::String => AttributeValue::String(read_ser_string(data, offset)?),
+			ArgType::Type   => AttributeValue::Type(read_ser_string(data, offset)?),
+			ArgType::Boxed  => {
+				let ty = self.field_or_prop_type(data, offset)?;
+				self.value(data, offset, &ty)?
+			},
+			ArgType::Enum(name, underlying) => AttributeValue::Enum {
+				ty: name.clone(),
+				value: Box::new(read_primitive(data, offset, *underlying)?),
+			},
+			ArgType::SzArray(elem) => {
+				let n: u32 = data.read(offset)?;
+				if n == 0xFFFF_FFFF {
+					AttributeValue::Array(None)
+				} else {
+					let items = (0..n)
+						.map(|_| self.value(data, offset, elem))
+						.collect::<Result<Vec<_>>>()?;
+					AttributeValue::Array(Some(items.into_boxed_slice()))
+				}
+			},
+		};
+		Ok(value)
+	}
+
+	/// Finds a 0-based type_def row by its serialized name. Nested types
+	/// are separated by `+` there.
+	fn enum_by_name(&self, name: &str) -> Option<usize> {
+		let name = name.split(',').next().unwrap_or(name).trim().replace('+', "/");
+		(0..self.rows.type_defs.len()).find(|t| {
+			let ty = TypeDefOrRef::TypeDef(*t as u32 + 1);
+			self.rows.type_name(self.strings, ty).is_ok_and(|n| n == name)
+		})
+	}
+
+	/// Enums have exactly one instance field, which holds the value.
+	fn enum_underlying_type(&self, type_def: usize) -> Result<u8> {
+		self.rows.type_defs.get(type_def).ok_or("TypeDef row is out of the table bounds.")?;
+		let field = self.rows.fields_of(type_def)
+			.map(|f| &self.rows.fields[f])
+			.find(|f| f.flags & FIELD_STATIC == 0)
+			.ok_or("Enum has no instance field.")?;
+
+		let ty = match TypeSig::parse_field_sig(blob_at(self.blobs, field.sig.into_index())?)? {
+			TypeSig::Boolean => ELEMENT_TYPE_BOOLEAN,
+			TypeSig::Char    => ELEMENT_TYPE_CHAR,
+			TypeSig::I1      => ELEMENT_TYPE_I1,
+			TypeSig::U1      => ELEMENT_TYPE_U1,
+			TypeSig::I2      => ELEMENT_TYPE_I2,
+			TypeSig::U2      => ELEMENT_TYPE_U2,
+			TypeSig::I4      => ELEMENT_TYPE_I4,
+			TypeSig::U4      => ELEMENT_TYPE_U4,
+			TypeSig::I8      => ELEMENT_TYPE_I8,
+			TypeSig::U8      => ELEMENT_TYPE_U8,
+			_ => Err("Enum has invalid underlying type.")?,
+		};
+		Ok(ty)
+	}
+}
+
+fn read_primitive(data: &[u8], offset: &mut usize, ty: u8) -> Result<AttributeValue> {
+	let value = match ty {
+		ELEMENT_TYPE_BOOLEAN => AttributeValue::Bool(data.read::<u8>(offset)? != 0),
+		ELEMENT_TYPE_CHAR    => AttributeValue::Char(data.read(offset)?),
+		ELEMENT_TYPE_I1      => AttributeValue::I1(data.read(offset)?),
+		ELEMENT_TYPE_U1      => AttributeValue::U1(data.read(offset)?),
+		ELEMENT_TYPE_I2      => AttributeValue::I2(data.read(offset)?),
+		ELEMENT_TYPE_U2      => AttributeValue::U2(data.read(offset)?),
+		ELEMENT_TYPE_I4      => AttributeValue::I4(data.read(offset)?),
+		ELEMENT_TYPE_U4      => AttributeValue::U4(data.read(offset)?),
+		ELEMENT_TYPE_I8      => AttributeValue::I8(data.read(offset)?),
+		ELEMENT_TYPE_U8      => AttributeValue::U8(data.read(offset)?),
+		ELEMENT_TYPE_R4      => AttributeValue::R4(data.read(offset)?),
+		ELEMENT_TYPE_R8      => AttributeValue::R8(data.read(offset)?),
+		_ => Err("Unknown primitive type of attribute argument.")?,
+	};
+	Ok(value)
+}
+
+/// Reads SerString: a compressed length followed by UTF-8 bytes, or a single
+/// 0xFF byte for a null string.
+pub fn read_ser_string(data: &[u8], offset: &mut usize) -> Result<Option<String>> {
+	if data.read_at::<u8>(*offset)? == 0xFF {
+		*offset += 1;
+		return Ok(None);
+	}
+
+	let len = read_compressed_u32(data, offset)? as usize;
+	let bytes = data.get(*offset..*offset + len).ok_or("Attribute string exceeds the blob.")?;
+	*offset += len;
+
+	let s = std::str::from_utf8(bytes)
+		.map_err(|_| Error::General("Attribute string is not a valid utf-8 string."))?;
+	Ok(Some(s.to_owned()))
+}
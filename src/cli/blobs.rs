@@ -1,8 +1,12 @@
+use std::collections::HashMap;
+
 use log::{debug};
 
 use crate::Result;
 use crate::error::Error;
 use crate::buf::Reading;
+use crate::cli::BlobIndex;
+use crate::utils::align_up;
 
 pub fn parse_blobs(data: &[u8]) -> Result<Box<[&[u8]]>> {
 	debug!("Parsing blobs...");
@@ -39,7 +43,7 @@ pub fn parse_user_strings(data: &[u8]) -> Result<Box<[String]>> {
 	let mut i: usize = 0;
 	while i < data.len() - 1 {
 		let (blob, len) = parse_blob(&data[i..])?;
-		if blob.len() > 0 {
+		if !blob.is_empty() {
 			let len = blob.len() - 1;
 			let wide: &[u16] = unsafe {
 				std::slice::from_raw_parts(
@@ -59,27 +63,203 @@ pub fn parse_user_strings(data: &[u8]) -> Result<Box<[String]>> {
 	Ok(strings.into_boxed_slice())
 }
 
+/// Reads a blob starting at `index` byte offset in the #Blob heap.
+pub fn blob_at(data: &[u8], index: usize) -> Result<&[u8]> {
+	if index >= data.len() {
+		Err("Blob index is out of the heap bounds.")?;
+	}
+	let (blob, _) = parse_blob(&data[index..])?;
+	Ok(blob)
+}
+
 // TODO(dmi): @check Add few large strings to subject.
 fn parse_blob(data: &[u8]) -> Result<(&[u8], usize)> {
-	let b0 = data[0];
+	let mut start = 0;
+	let n = read_compressed_u32(data, &mut start)
+		.map_err(|_| Error::General("Incorrect blob length."))? as usize;
+
+	if start + n > data.len() {
+		Err("Blob length exceeds the heap.")?;
+	}
+
+	Ok((&data[start..start + n], start + n))
+}
+
+/// Reads a user string starting at `index` byte offset in the #US heap, as
+/// referenced by `ldstr` tokens.
+pub fn user_string_at(data: &[u8], index: usize) -> Result<String> {
+	let blob = blob_at(data, index)?;
+	if blob.is_empty() {
+		return Ok(String::new());
+	}
+
+	// The terminal byte is not a part of the string.
+	let wide: Vec<u16> = blob[..blob.len() - 1].chunks_exact(2)
+		.map(|c| u16::from_le_bytes([c[0], c[1]]))
+		.collect();
+	String::from_utf16(&wide)
+		.map_err(|_| Error::General("User string is not a valid utf-16 string."))
+}
+
+/// Reads an unsigned integer compressed the way blob lengths and most of
+/// signature items are (II.23.2).
+pub fn read_compressed_u32(data: &[u8], offset: &mut usize) -> Result<u32> {
+	let b0: u8 = data.read(offset)?;
+
 	if b0 & 0b1000_0000 == 0 {
-		let n = (b0 & 0b0111_1111) as usize;
-		return Ok((&data[1..n + 1], n + 1));
+		Ok(b0 as u32)
+	} else if b0 & 0b1100_0000 == 0b1000_0000 {
+		let x: u8 = data.read(offset)?;
+		Ok(((b0 & 0b0011_1111) as u32) << 8 | x as u32)
+	} else if b0 & 0b1110_0000 == 0b1100_0000 {
+		let x: u8 = data.read(offset)?;
+		let y: u8 = data.read(offset)?;
+		let z: u8 = data.read(offset)?;
+		Ok(((b0 & 0b0001_1111) as u32) << 24 | (x as u32) << 16 | (y as u32) << 8 | z as u32)
+	} else {
+		Err("Incorrect compressed integer.")?
+	}
+}
+
+/// Reads a signed integer, which is compressed as its unsigned counterpart
+/// with the sign bit rotated into the least significant bit (II.23.2).
+pub fn read_compressed_i32(data: &[u8], offset: &mut usize) -> Result<i32> {
+	let start = *offset;
+	let x = read_compressed_u32(data, offset)?;
+
+	let sign_bias = match *offset - start {
+		1 => 0x40,
+		2 => 0x2000,
+		_ => 0x1000_0000,
+	};
+
+	let value = (x >> 1) as i32;
+	if x & 1 == 0 {
+		Ok(value)
+	} else {
+		Ok(value - sign_bias)
+	}
+}
+
+/// Writes `x` the way blob lengths are encoded (II.24.2.4). Values larger
+/// than 0x1FFFFFFF cannot be encoded.
+pub fn compress_u32(x: u32, out: &mut Vec<u8>) {
+	debug_assert!(x <= 0x1FFF_FFFF);
+	if x <= 0x7F {
+		out.push(x as u8);
+	} else if x <= 0x3FFF {
+		out.push((x >> 8) as u8 | 0b1000_0000);
+		out.push(x as u8);
+	} else {
+		out.push((x >> 24) as u8 | 0b1100_0000);
+		out.push((x >> 16) as u8);
+		out.push((x >> 8) as u8);
+		out.push(x as u8);
+	}
+}
+
+/// Writes `x` as a signed compressed integer (II.23.2). Values outside of
+/// [-2^28, 2^28 - 1] cannot be encoded.
+pub fn compress_i32(x: i32, out: &mut Vec<u8>) {
+	debug_assert!((-0x1000_0000..=0x0FFF_FFFF).contains(&x));
+	let (bits, mask) = if (-0x40..=0x3F).contains(&x) {
+		(7, 0x7F)
+	} else if (-0x2000..=0x1FFF).contains(&x) {
+		(14, 0x3FFF)
+	} else {
+		(29, 0x1FFF_FFFF)
+	};
+	// The sign bit is rotated into the least significant bit.
+	let rotated = ((x as u32) << 1 | (x as u32) >> (bits - 1) & 1) & mask;
+	compress_u32(rotated, out);
+}
+
+/// Builds a #Blob heap, identical blobs are stored only once.
+#[derive(Debug, Clone)]
+pub struct BlobsBuilder {
+	data: Vec<u8>,
+	known: HashMap<Vec<u8>, u32>,
+}
+
+impl Default for BlobsBuilder {
+	fn default() -> Self {
+		// The first entry is always the empty blob.
+		BlobsBuilder { data: vec![0], known: HashMap::new() }
+	}
+}
+
+impl BlobsBuilder {
+	pub fn add(&mut self, blob: &[u8]) -> BlobIndex {
+		if blob.is_empty() {
+			return BlobIndex(0);
+		}
+		if let Some(i) = self.known.get(blob) {
+			return BlobIndex(*i);
+		}
+
+		let i = self.data.len() as u32;
+		compress_u32(blob.len() as u32, &mut self.data);
+		self.data.extend_from_slice(blob);
+		self.known.insert(blob.to_vec(), i);
+		BlobIndex(i)
+	}
+
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.data.len() == 1
 	}
 
-	if b0 & 0b1100_0000 == 0b1000_0000 {
-		let x = data[1] as usize;
-		let n = ((b0 & 0b0011_1111) as usize) << 8 + x;
-		return Ok((&data[2..n + 2], n + 2));
+	pub fn finish(mut self) -> Vec<u8> {
+		self.data.resize(align_up(self.data.len(), 4), 0);
+		self.data
 	}
+}
+
+/// Builds a #US heap. Strings are referenced by their byte offset, which is
+/// what `ldstr` tokens carry.
+#[derive(Debug, Clone)]
+pub struct UserStringsBuilder {
+	data: Vec<u8>,
+	known: HashMap<String, u32>,
+}
 
-	if b0 & 0b1110_0000 == 0b1100_0000 {
-		let x = data[1] as usize;
-		let y = data[2] as usize;
-		let z = data[3] as usize;
-		let n = ((b0 & 0b0001_1111) as usize) << 24 + (x << 16) + (y << 8) + z;
-		return Ok((&data[4..n + 4], n + 4));
+impl Default for UserStringsBuilder {
+	fn default() -> Self {
+		UserStringsBuilder { data: vec![0], known: HashMap::new() }
 	}
+}
+
+impl UserStringsBuilder {
+	pub fn add(&mut self, s: &str) -> u32 {
+		if let Some(i) = self.known.get(s) {
+			return *i;
+		}
+
+		let wide: Vec<u16> = s.encode_utf16().collect();
+		// See `parse_user_strings` for the meaning of the terminal byte.
+		let special = wide.iter().any(|c| {
+			*c > 0xFF || matches!(*c, 0x01..=0x08 | 0x0E..=0x1F | 0x27 | 0x2D | 0x7F)
+		});
 
-	Err("Incorrect blob length.")?
+		let i = self.data.len() as u32;
+		compress_u32(wide.len() as u32 * 2 + 1, &mut self.data);
+		for c in wide {
+			self.data.extend_from_slice(&c.to_le_bytes());
+		}
+		self.data.push(special as u8);
+		self.known.insert(s.to_owned(), i);
+		i
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.data.len() == 1
+	}
+
+	pub fn finish(mut self) -> Vec<u8> {
+		self.data.resize(align_up(self.data.len(), 4), 0);
+		self.data
+	}
 }
@@ -37,3 +37,146 @@ pub const METADATA_NESTED_CLASS:             usize = 0x29;
 pub const METADATA_GENERIC_PARAM:            usize = 0x2A;
 pub const METADATA_METHOD_SPEC:              usize = 0x2B;
 pub const METADATA_GENERIC_PARAM_CONSTRAINT: usize = 0x2C;
+
+pub fn table_name(id: usize) -> &'static str {
+	match id {
+		METADATA_MODULE                   => "Module",
+		METADATA_TYPE_REF                 => "TypeRef",
+		METADATA_TYPE_DEF                 => "TypeDef",
+		METADATA_FIELD                    => "Field",
+		METADATA_METHOD_DEF               => "MethodDef",
+		METADATA_PARAM                    => "Param",
+		METADATA_INTERFACE_IMPL           => "InterfaceImpl",
+		METADATA_MEMBER_REF               => "MemberRef",
+		METADATA_CONSTANT                 => "Constant",
+		METADATA_CUSTOM_ATTRIBUTE         => "CustomAttribute",
+		METADATA_FIELD_MARSHAL            => "FieldMarshal",
+		METADATA_DECL_SECURITY            => "DeclSecurity",
+		METADATA_CLASS_LAYOUT             => "ClassLayout",
+		METADATA_FIELD_LAYOUT             => "FieldLayout",
+		METADATA_STANDALONE_SIG           => "StandAloneSig",
+		METADATA_EVENT_MAP                => "EventMap",
+		METADATA_EVENT                    => "Event",
+		METADATA_PROPERTY_MAP             => "PropertyMap",
+		METADATA_PROPERTY                 => "Property",
+		METADATA_METHOD_SEMANTICS         => "MethodSemantics",
+		METADATA_METHOD_IMPL              => "MethodImpl",
+		METADATA_MODULE_REF               => "ModuleRef",
+		METADATA_TYPE_SPEC                => "TypeSpec",
+		METADATA_IMPL_MAP                 => "ImplMap",
+		METADATA_FIELD_RVA                => "FieldRVA",
+		METADATA_ASSEMBLY                 => "Assembly",
+		METADATA_ASSEMBLY_PROCESSOR       => "AssemblyProcessor",
+		METADATA_ASSEMBLY_OS              => "AssemblyOS",
+		METADATA_ASSEMBLY_REF             => "AssemblyRef",
+		METADATA_ASSEMBLY_REF_PROCESSOR   => "AssemblyRefProcessor",
+		METADATA_ASSEMBLY_REF_OS          => "AssemblyRefOS",
+		METADATA_FILE                     => "File",
+		METADATA_EXPORTED_TYPE            => "ExportedType",
+		METADATA_MANIFEST_RESOURCE        => "ManifestResource",
+		METADATA_NESTED_CLASS             => "NestedClass",
+		METADATA_GENERIC_PARAM            => "GenericParam",
+		METADATA_METHOD_SPEC              => "MethodSpec",
+		METADATA_GENERIC_PARAM_CONSTRAINT => "GenericParamConstraint",
+		_                                 => "Unknown",
+	}
+}
+
+// Taken from ECMA II.23.1.16
+pub const ELEMENT_TYPE_END:         u8 = 0x00;
+pub const ELEMENT_TYPE_VOID:        u8 = 0x01;
+pub const ELEMENT_TYPE_BOOLEAN:     u8 = 0x02;
+pub const ELEMENT_TYPE_CHAR:        u8 = 0x03;
+pub const ELEMENT_TYPE_I1:          u8 = 0x04;
+pub const ELEMENT_TYPE_U1:          u8 = 0x05;
+pub const ELEMENT_TYPE_I2:          u8 = 0x06;
+pub const ELEMENT_TYPE_U2:          u8 = 0x07;
+pub const ELEMENT_TYPE_I4:          u8 = 0x08;
+pub const ELEMENT_TYPE_U4:          u8 = 0x09;
+pub const ELEMENT_TYPE_I8:          u8 = 0x0A;
+pub const ELEMENT_TYPE_U8:          u8 = 0x0B;
+pub const ELEMENT_TYPE_R4:          u8 = 0x0C;
+pub const ELEMENT_TYPE_R8:          u8 = 0x0D;
+pub const ELEMENT_TYPE_STRING:      u8 = 0x0E;
+pub const ELEMENT_TYPE_PTR:         u8 = 0x0F;
+pub const ELEMENT_TYPE_BYREF:       u8 = 0x10;
+pub const ELEMENT_TYPE_VALUETYPE:   u8 = 0x11;
+pub const ELEMENT_TYPE_CLASS:       u8 = 0x12;
+pub const ELEMENT_TYPE_VAR:         u8 = 0x13;
+pub const ELEMENT_TYPE_ARRAY:       u8 = 0x14;
+pub const ELEMENT_TYPE_GENERICINST: u8 = 0x15;
+pub const ELEMENT_TYPE_TYPEDBYREF:  u8 = 0x16;
+pub const ELEMENT_TYPE_I:           u8 = 0x18;
+pub const ELEMENT_TYPE_U:           u8 = 0x19;
+pub const ELEMENT_TYPE_FNPTR:       u8 = 0x1B;
+pub const ELEMENT_TYPE_OBJECT:      u8 = 0x1C;
+pub const ELEMENT_TYPE_SZARRAY:     u8 = 0x1D;
+pub const ELEMENT_TYPE_MVAR:        u8 = 0x1E;
+pub const ELEMENT_TYPE_CMOD_REQD:   u8 = 0x1F;
+pub const ELEMENT_TYPE_CMOD_OPT:    u8 = 0x20;
+pub const ELEMENT_TYPE_INTERNAL:    u8 = 0x21;
+pub const ELEMENT_TYPE_MODIFIER:    u8 = 0x40;
+pub const ELEMENT_TYPE_SENTINEL:    u8 = 0x41;
+pub const ELEMENT_TYPE_PINNED:      u8 = 0x45;
+// Used only in custom attribute blobs.
+pub const ELEMENT_TYPE_SYSTEM_TYPE: u8 = 0x50;
+pub const ELEMENT_TYPE_BOXED:       u8 = 0x51;
+pub const ELEMENT_TYPE_FIELD:       u8 = 0x53;
+pub const ELEMENT_TYPE_PROPERTY:    u8 = 0x54;
+pub const ELEMENT_TYPE_ENUM:        u8 = 0x55;
+
+// Taken from ECMA II.23.2.1, II.23.2.3
+pub const SIG_DEFAULT:       u8 = 0x00;
+pub const SIG_C:             u8 = 0x01;
+pub const SIG_STDCALL:       u8 = 0x02;
+pub const SIG_THISCALL:      u8 = 0x03;
+pub const SIG_FASTCALL:      u8 = 0x04;
+pub const SIG_VARARG:        u8 = 0x05;
+pub const SIG_FIELD:         u8 = 0x06;
+pub const SIG_LOCAL:         u8 = 0x07;
+pub const SIG_PROPERTY:      u8 = 0x08;
+pub const SIG_GENERICINST:   u8 = 0x0A;
+pub const SIG_KIND_MASK:     u8 = 0x0F;
+pub const SIG_GENERIC:       u8 = 0x10;
+pub const SIG_HAS_THIS:      u8 = 0x20;
+pub const SIG_EXPLICIT_THIS: u8 = 0x40;
+
+// Taken from ECMA II.23.4, extended with the values ILAsm knows about.
+pub const NATIVE_TYPE_BOOLEAN:         u8 = 0x02;
+pub const NATIVE_TYPE_I1:              u8 = 0x03;
+pub const NATIVE_TYPE_U1:              u8 = 0x04;
+pub const NATIVE_TYPE_I2:              u8 = 0x05;
+pub const NATIVE_TYPE_U2:              u8 = 0x06;
+pub const NATIVE_TYPE_I4:              u8 = 0x07;
+pub const NATIVE_TYPE_U4:              u8 = 0x08;
+pub const NATIVE_TYPE_I8:              u8 = 0x09;
+pub const NATIVE_TYPE_U8:              u8 = 0x0A;
+pub const NATIVE_TYPE_R4:              u8 = 0x0B;
+pub const NATIVE_TYPE_R8:              u8 = 0x0C;
+pub const NATIVE_TYPE_CURRENCY:        u8 = 0x0F;
+pub const NATIVE_TYPE_BSTR:            u8 = 0x13;
+pub const NATIVE_TYPE_LPSTR:           u8 = 0x14;
+pub const NATIVE_TYPE_LPWSTR:          u8 = 0x15;
+pub const NATIVE_TYPE_LPTSTR:          u8 = 0x16;
+pub const NATIVE_TYPE_FIXEDSYSSTRING:  u8 = 0x17;
+pub const NATIVE_TYPE_IUNKNOWN:        u8 = 0x19;
+pub const NATIVE_TYPE_IDISPATCH:       u8 = 0x1A;
+pub const NATIVE_TYPE_STRUCT:          u8 = 0x1B;
+pub const NATIVE_TYPE_INTERFACE:       u8 = 0x1C;
+pub const NATIVE_TYPE_SAFEARRAY:       u8 = 0x1D;
+pub const NATIVE_TYPE_FIXEDARRAY:      u8 = 0x1E;
+pub const NATIVE_TYPE_INT:             u8 = 0x1F;
+pub const NATIVE_TYPE_UINT:            u8 = 0x20;
+pub const NATIVE_TYPE_BYVALSTR:        u8 = 0x22;
+pub const NATIVE_TYPE_ANSIBSTR:        u8 = 0x23;
+pub const NATIVE_TYPE_TBSTR:           u8 = 0x24;
+pub const NATIVE_TYPE_VARIANTBOOL:     u8 = 0x25;
+pub const NATIVE_TYPE_FUNC:            u8 = 0x26;
+pub const NATIVE_TYPE_ASANY:           u8 = 0x28;
+pub const NATIVE_TYPE_ARRAY:           u8 = 0x2A;
+pub const NATIVE_TYPE_LPSTRUCT:        u8 = 0x2B;
+pub const NATIVE_TYPE_CUSTOMMARSHALER: u8 = 0x2C;
+pub const NATIVE_TYPE_ERROR:           u8 = 0x2D;
+pub const NATIVE_TYPE_LPUTF8STR:       u8 = 0x30;
+// Stands for an unspecified array element type.
+pub const NATIVE_TYPE_MAX:             u8 = 0x50;
@@ -0,0 +1,137 @@
+// Taken from ECMA II.23.1
+
+// TypeAttributes, II.23.1.15
+
+pub const TYPE_VISIBILITY_MASK:           u32 = 0x0000_0007;
+pub const TYPE_NOT_PUBLIC:                u32 = 0x0000_0000;
+pub const TYPE_PUBLIC:                    u32 = 0x0000_0001;
+pub const TYPE_NESTED_PUBLIC:             u32 = 0x0000_0002;
+pub const TYPE_NESTED_PRIVATE:            u32 = 0x0000_0003;
+pub const TYPE_NESTED_FAMILY:             u32 = 0x0000_0004;
+pub const TYPE_NESTED_ASSEMBLY:           u32 = 0x0000_0005;
+pub const TYPE_NESTED_FAM_AND_ASSEM:      u32 = 0x0000_0006;
+pub const TYPE_NESTED_FAM_OR_ASSEM:       u32 = 0x0000_0007;
+
+pub const TYPE_LAYOUT_MASK:               u32 = 0x0000_0018;
+pub const TYPE_AUTO_LAYOUT:               u32 = 0x0000_0000;
+pub const TYPE_SEQUENTIAL_LAYOUT:         u32 = 0x0000_0008;
+pub const TYPE_EXPLICIT_LAYOUT:           u32 = 0x0000_0010;
+
+pub const TYPE_CLASS_SEMANTICS_MASK:      u32 = 0x0000_0020;
+pub const TYPE_CLASS:                     u32 = 0x0000_0000;
+pub const TYPE_INTERFACE:                 u32 = 0x0000_0020;
+
+pub const TYPE_ABSTRACT:                  u32 = 0x0000_0080;
+pub const TYPE_SEALED:                    u32 = 0x0000_0100;
+pub const TYPE_SPECIAL_NAME:              u32 = 0x0000_0400;
+pub const TYPE_IMPORT:                    u32 = 0x0000_1000;
+pub const TYPE_SERIALIZABLE:              u32 = 0x0000_2000;
+
+pub const TYPE_STRING_FORMAT_MASK:        u32 = 0x0003_0000;
+pub const TYPE_ANSI_CLASS:                u32 = 0x0000_0000;
+pub const TYPE_UNICODE_CLASS:             u32 = 0x0001_0000;
+pub const TYPE_AUTO_CLASS:                u32 = 0x0002_0000;
+pub const TYPE_CUSTOM_FORMAT_CLASS:       u32 = 0x0003_0000;
+pub const TYPE_CUSTOM_STRING_FORMAT_MASK: u32 = 0x00C0_0000;
+
+pub const TYPE_BEFORE_FIELD_INIT:         u32 = 0x0010_0000;
+pub const TYPE_RT_SPECIAL_NAME:           u32 = 0x0000_0800;
+pub const TYPE_HAS_SECURITY:              u32 = 0x0004_0000;
+pub const TYPE_IS_TYPE_FORWARDER:         u32 = 0x0020_0000;
+
+// FieldAttributes, II.23.1.5
+
+pub const FIELD_ACCESS_MASK:              u16 = 0x0007;
+pub const FIELD_COMPILER_CONTROLLED:      u16 = 0x0000;
+pub const FIELD_PRIVATE:                  u16 = 0x0001;
+pub const FIELD_FAM_AND_ASSEM:            u16 = 0x0002;
+pub const FIELD_ASSEMBLY:                 u16 = 0x0003;
+pub const FIELD_FAMILY:                   u16 = 0x0004;
+pub const FIELD_FAM_OR_ASSEM:             u16 = 0x0005;
+pub const FIELD_PUBLIC:                   u16 = 0x0006;
+
+pub const FIELD_STATIC:                   u16 = 0x0010;
+pub const FIELD_INIT_ONLY:                u16 = 0x0020;
+pub const FIELD_LITERAL:                  u16 = 0x0040;
+pub const FIELD_NOT_SERIALIZED:           u16 = 0x0080;
+pub const FIELD_SPECIAL_NAME:             u16 = 0x0200;
+pub const FIELD_PINVOKE_IMPL:             u16 = 0x2000;
+pub const FIELD_RT_SPECIAL_NAME:          u16 = 0x0400;
+pub const FIELD_HAS_FIELD_MARSHAL:        u16 = 0x1000;
+pub const FIELD_HAS_DEFAULT:              u16 = 0x8000;
+pub const FIELD_HAS_FIELD_RVA:            u16 = 0x0100;
+
+// MethodAttributes, II.23.1.10
+
+pub const METHOD_MEMBER_ACCESS_MASK:      u16 = 0x0007;
+pub const METHOD_COMPILER_CONTROLLED:     u16 = 0x0000;
+pub const METHOD_PRIVATE:                 u16 = 0x0001;
+pub const METHOD_FAM_AND_ASSEM:           u16 = 0x0002;
+pub const METHOD_ASSEM:                   u16 = 0x0003;
+pub const METHOD_FAMILY:                  u16 = 0x0004;
+pub const METHOD_FAM_OR_ASSEM:            u16 = 0x0005;
+pub const METHOD_PUBLIC:                  u16 = 0x0006;
+
+pub const METHOD_STATIC:                  u16 = 0x0010;
+pub const METHOD_FINAL:                   u16 = 0x0020;
+pub const METHOD_VIRTUAL:                 u16 = 0x0040;
+pub const METHOD_HIDE_BY_SIG:             u16 = 0x0080;
+
+pub const METHOD_VTABLE_LAYOUT_MASK:      u16 = 0x0100;
+pub const METHOD_REUSE_SLOT:              u16 = 0x0000;
+pub const METHOD_NEW_SLOT:                u16 = 0x0100;
+
+pub const METHOD_STRICT:                  u16 = 0x0200;
+pub const METHOD_ABSTRACT:                u16 = 0x0400;
+pub const METHOD_SPECIAL_NAME:            u16 = 0x0800;
+pub const METHOD_PINVOKE_IMPL:            u16 = 0x2000;
+pub const METHOD_UNMANAGED_EXPORT:        u16 = 0x0008;
+pub const METHOD_RT_SPECIAL_NAME:         u16 = 0x1000;
+pub const METHOD_HAS_SECURITY:            u16 = 0x4000;
+pub const METHOD_REQUIRE_SEC_OBJECT:      u16 = 0x8000;
+
+// MethodImplAttributes, II.23.1.11
+
+pub const METHOD_IMPL_CODE_TYPE_MASK:     u16 = 0x0003;
+pub const METHOD_IMPL_IL:                 u16 = 0x0000;
+pub const METHOD_IMPL_NATIVE:             u16 = 0x0001;
+pub const METHOD_IMPL_OPTIL:              u16 = 0x0002;
+pub const METHOD_IMPL_RUNTIME:            u16 = 0x0003;
+
+pub const METHOD_IMPL_MANAGED_MASK:       u16 = 0x0004;
+pub const METHOD_IMPL_UNMANAGED:          u16 = 0x0004;
+pub const METHOD_IMPL_MANAGED:            u16 = 0x0000;
+
+pub const METHOD_IMPL_FORWARD_REF:        u16 = 0x0010;
+pub const METHOD_IMPL_PRESERVE_SIG:       u16 = 0x0080;
+pub const METHOD_IMPL_INTERNAL_CALL:      u16 = 0x1000;
+pub const METHOD_IMPL_SYNCHRONIZED:       u16 = 0x0020;
+pub const METHOD_IMPL_NO_INLINING:        u16 = 0x0008;
+pub const METHOD_IMPL_NO_OPTIMIZATION:    u16 = 0x0040;
+
+// ParamAttributes, II.23.1.13
+
+pub const PARAM_IN:                       u16 = 0x0001;
+pub const PARAM_OUT:                      u16 = 0x0002;
+pub const PARAM_OPTIONAL:                 u16 = 0x0010;
+pub const PARAM_HAS_DEFAULT:              u16 = 0x1000;
+pub const PARAM_HAS_FIELD_MARSHAL:        u16 = 0x2000;
+pub const PARAM_UNUSED:                   u16 = 0xCFE0;
+
+// SecurityAction, II.22.11
+
+pub const SECURITY_ACTION_REQUEST:              u16 = 0x0001;
+pub const SECURITY_ACTION_DEMAND:               u16 = 0x0002;
+pub const SECURITY_ACTION_ASSERT:               u16 = 0x0003;
+pub const SECURITY_ACTION_DENY:                 u16 = 0x0004;
+pub const SECURITY_ACTION_PERMIT_ONLY:          u16 = 0x0005;
+pub const SECURITY_ACTION_LINK_DEMAND:          u16 = 0x0006;
+pub const SECURITY_ACTION_INHERITANCE_DEMAND:   u16 = 0x0007;
+pub const SECURITY_ACTION_REQUEST_MINIMUM:      u16 = 0x0008;
+pub const SECURITY_ACTION_REQUEST_OPTIONAL:     u16 = 0x0009;
+pub const SECURITY_ACTION_REQUEST_REFUSE:       u16 = 0x000A;
+pub const SECURITY_ACTION_PREJIT_GRANT:         u16 = 0x000B;
+pub const SECURITY_ACTION_PREJIT_DENIED:        u16 = 0x000C;
+pub const SECURITY_ACTION_NON_CAS_DEMAND:       u16 = 0x000D;
+pub const SECURITY_ACTION_NON_CAS_LINK_DEMAND:  u16 = 0x000E;
+pub const SECURITY_ACTION_NON_CAS_INHERITANCE:  u16 = 0x000F;
@@ -4,7 +4,8 @@ use std::fmt;
 
 use crate::Result;
 use crate::error::Error;
-use crate::buf::Reading;
+use crate::buf::{Reading, Writing};
+use crate::cli::GuidIndex;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
 pub struct Guid {
@@ -15,14 +16,27 @@ pub struct Guid {
 }
 
 impl Guid {
+	pub fn from_bytes(b: [u8; 16]) -> Guid {
+		let mut offset = 0;
+		// Cannot fail, there are always enough bytes.
+		Guid::parse(&b, &mut offset).unwrap_or_default()
+	}
+
+	pub fn write(&self, out: &mut Vec<u8>) {
+		out.write(self.data0);
+		out.write(self.data1);
+		out.write(self.data2);
+		out.extend_from_slice(&self.data3);
+	}
+
 	fn parse(data: &[u8], offset: &mut usize) -> Result<Guid> {
 		let data0: u32 = data.read(offset)?;
 		let data1: u16 = data.read(offset)?;
 		let data2: u16 = data.read(offset)?;
 
 		let mut data3 = [0u8; 8];
-		for i in 0..8 {
-			data3[i] = data.read(offset)?;
+		for x in &mut data3 {
+			*x = data.read(offset)?;
 		}
 
 		Ok(Guid { data0, data1, data2, data3 })
@@ -32,7 +46,8 @@ impl Guid {
 impl fmt::Display for Guid {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(f, "{{{:08X}-{:04X}-{:04X}-", self.data0, self.data1, self.data2)?;
-		for x in &self.data3 {
+		write!(f, "{:02X}{:02X}-", self.data3[0], self.data3[1])?;
+		for x in &self.data3[2..] {
 			write!(f, "{:02X}", x)?;
 		}
 		write!(f, "}}")
@@ -58,3 +73,31 @@ pub fn parse_guids(data: &[u8]) -> Result<Box<[Guid]>> {
 
 	Ok(guids.into_boxed_slice())
 }
+
+/// Builds a #GUID heap, guids are referenced by their 1-based index.
+#[derive(Debug, Clone, Default)]
+pub struct GuidsBuilder {
+	guids: Vec<Guid>,
+}
+
+impl GuidsBuilder {
+	pub fn add(&mut self, guid: Guid) -> GuidIndex {
+		if let Some(i) = self.guids.iter().position(|g| *g == guid) {
+			return GuidIndex(i as u32 + 1);
+		}
+		self.guids.push(guid);
+		GuidIndex(self.guids.len() as u32)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.guids.is_empty()
+	}
+
+	pub fn finish(self) -> Vec<u8> {
+		let mut out = Vec::with_capacity(self.guids.len() << 4);
+		for g in &self.guids {
+			g.write(&mut out);
+		}
+		out
+	}
+}
@@ -4,7 +4,7 @@ use log::{debug};
 
 use crate::Result;
 use crate::error::Error;
-use crate::buf::Reading;
+use crate::buf::{Reading, Writing};
 use crate::utils::{align_up, dump, os_is_64};
 
 // Taken from ECMA II.25.3.3.1
@@ -31,6 +31,9 @@ const METADATA_MAGIC: u32 = 0x424A5342;
 
 const METADATA_STREAM_NAME_MAX_LEN: usize = 32;
 
+/// Size of the CLI header, II.25.3.3.
+pub const CLI_HEADER_SIZE: usize = 72;
+
 #[derive(Debug, PartialEq, Clone, Default)]
 pub struct Header {
 	pub ep_token: u32,
@@ -65,6 +68,24 @@ impl Header {
 		Ok(Header { ep_token, metadata_rva, metadata_size })
 	}
 
+	/// Writes the header of an IL-only image without resources, strong name
+	/// signature and the rest of optional parts.
+	pub fn write(&self, out: &mut Vec<u8>) {
+		out.write(CLI_HEADER_SIZE as u32);
+		// Runtime version.
+		out.write(2u16);
+		out.write(5u16);
+		out.write(self.metadata_rva);
+		out.write(self.metadata_size);
+		out.write(COMIMAGE_FLAGS_ILONLY);
+		out.write(self.ep_token);
+		// Resources, StrongNameSignature, CodeManagerTable, VTableFixups,
+		// ExportAddressTableJumps and ManagedNativeHeader directories.
+		for _ in 0..6 {
+			out.write(0u64);
+		}
+	}
+
 	fn check_flags(data: &[u8], offset: &mut usize) -> Result<()> {
 		let flags: u32 = data.read(offset)?;
 		if flags & COMIMAGE_FLAGS_ILONLY == 0 {
@@ -110,8 +131,17 @@ impl Header {
 	}
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Metadata<'a> {
+	pub major_version:  u16,
+	pub minor_version:  u16,
+	pub version:        &'a str,
+	/// Reserved, always 0.
+	pub flags:          u16,
+	/// Names of the streams in the order their headers are listed, which
+	/// is also the order of their data. Empty stands for the order ILAsm
+	/// uses.
+	pub stream_order:   Box<[&'a str]>,
 	pub logical_tables: Option<&'a [u8]>,
 	pub strings:        Option<&'a [u8]>,
 	pub user_strings:   Option<&'a [u8]>,
@@ -119,6 +149,23 @@ pub struct Metadata<'a> {
 	pub guids:          Option<&'a [u8]>,
 }
 
+impl Default for Metadata<'_> {
+	fn default() -> Self {
+		Metadata {
+			major_version: 1,
+			minor_version: 1,
+			version: "",
+			flags: 0,
+			stream_order: Box::new([]),
+			logical_tables: None,
+			strings: None,
+			user_strings: None,
+			blobs: None,
+			guids: None,
+		}
+	}
+}
+
 impl<'a> Metadata<'a> {
 	pub fn parse(data: &'a [u8]) -> Result<Metadata<'a>> {
 		let mut offset = &mut 0usize;
@@ -128,7 +175,10 @@ impl<'a> Metadata<'a> {
 			Err("Metadata signature is wrong.")?;
 		}
 
-		*offset += 8;
+		let major_version: u16 = data.read(offset)?;
+		let minor_version: u16 = data.read(offset)?;
+		// Reserved.
+		*offset += 4;
 
 		let len_version: u32 = data.read(offset)?;
 		if len_version > 255 {
@@ -136,10 +186,12 @@ impl<'a> Metadata<'a> {
 		}
 		
 		let version = str::from_utf8(&data[16..(16 + len_version as usize)])
-			.map_err(|_| Error::General("Version string is not a valid utf-8 string."))?;
+			.map_err(|_| Error::General("Version string is not a valid utf-8 string."))?
+			.trim_end_matches('\0');
 		debug!("Version: {}", version);
 
-		*offset += align_up(len_version as usize, 4) + 2;
+		*offset += align_up(len_version as usize, 4);
+		let flags: u16 = data.read(offset)?;
 
 		let n_streams: u16 = data.read(offset)?;
 		debug!("Metadata streams: {}", n_streams);
@@ -149,6 +201,7 @@ impl<'a> Metadata<'a> {
 		let mut user_strings =   None;
 		let mut blobs =          None;
 		let mut guids =          None;
+		let mut stream_order =   Vec::new();
 
 		for i in 0..n_streams {
 			let s_offset = data.read::<u32>(offset)? as usize;
@@ -157,9 +210,9 @@ impl<'a> Metadata<'a> {
 			let name = &data[*offset..];
 			let mut len = 0;
 			
-			for j in 0..METADATA_STREAM_NAME_MAX_LEN {
+			for c in name.iter().take(METADATA_STREAM_NAME_MAX_LEN) {
 				len += 1;
-				if name[j] == 0 {
+				if *c == 0 {
 					break;
 				}
 			}
@@ -183,10 +236,16 @@ impl<'a> Metadata<'a> {
 				_ => Err("Unknown section name.")?,
 			};
 			
+			stream_order.push(name);
 			*offset += align_up(len, 4);
 		}
 		
 		Ok(Metadata {
+			major_version,
+			minor_version,
+			version,
+			flags,
+			stream_order: stream_order.into_boxed_slice(),
 			logical_tables,
 			strings,
 			user_strings,
@@ -194,4 +253,110 @@ impl<'a> Metadata<'a> {
 			guids,
 		})
 	}
+
+	/// Writes the metadata root (II.24.2.1), followed by the streams in
+	/// their order. Streams shall be already padded to 4 bytes. Padding of
+	/// the version string and stream offsets are recomputed, so a root is
+	/// only written back byte for byte if it had no gaps between streams.
+	pub fn write(&self, out: &mut Vec<u8>) {
+		let stream = |name: &str| match name {
+			"#~"       => self.logical_tables,
+			"#Strings" => self.strings,
+			"#US"      => self.user_strings,
+			"#GUID"    => self.guids,
+			"#Blob"    => self.blobs,
+			_ => None,
+		};
+		let order: &[&str] = if self.stream_order.is_empty() {
+			&["#~", "#Strings", "#US", "#GUID", "#Blob"]
+		} else {
+			&self.stream_order
+		};
+		let streams: Vec<_> = order.iter().map(|name| (*name, stream(name))).collect();
+
+		let start = out.len();
+		let len_version = align_up(self.version.len() + 1, 4);
+
+		out.write(METADATA_MAGIC);
+		out.write(self.major_version);
+		out.write(self.minor_version);
+		// Reserved.
+		out.write(0u32);
+		out.write(len_version as u32);
+		out.extend_from_slice(self.version.as_bytes());
+		out.resize(out.len() + len_version - self.version.len(), 0);
+		out.write(self.flags);
+
+		let present = || streams.iter().filter_map(|(name, s)| s.map(|s| (*name, s)));
+
+		out.write(present().count() as u16);
+
+		let headers_size: usize = present().map(|(name, _)| 8 + align_up(name.len() + 1, 4)).sum();
+		let mut s_offset = out.len() - start + headers_size;
+
+		for (name, s) in present() {
+			debug_assert!(s.len() & 3 == 0);
+			out.write(s_offset as u32);
+			out.write(s.len() as u32);
+			out.extend_from_slice(name.as_bytes());
+			out.resize(out.len() + align_up(name.len() + 1, 4) - name.len(), 0);
+			s_offset += s.len();
+		}
+
+		for (_, s) in present() {
+			out.extend_from_slice(s);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A root laid out by hand rather than by `Metadata::write`: version
+	/// 1.1 and the usual version string, but a non-zero flags field and
+	/// streams listed in an order other than ILAsm's.
+	fn root() -> Vec<u8> {
+		let streams: [(&str, &[u8]); 5] = [
+			("#~",       &[1, 1, 1, 1]),
+			("#Blob",    &[0, 2, 2, 2]),
+			("#GUID",    &[3; 16]),
+			("#Strings", &[0, 4, 4, 0]),
+			("#US",      &[0, 5, 5, 5]),
+		];
+		let mut root = Vec::new();
+		root.extend_from_slice(b"BSJB");
+		root.extend_from_slice(&[1, 0, 1, 0]);
+		root.extend_from_slice(&[0; 4]);
+		root.extend_from_slice(&[12, 0, 0, 0]);
+		root.extend_from_slice(b"v4.0.30319\0\0");
+		root.extend_from_slice(&[0x34, 0x12]);
+		root.extend_from_slice(&[5, 0]);
+
+		let headers: usize = streams.iter().map(|(name, _)| 8 + align_up(name.len() + 1, 4)).sum();
+		let mut offset = root.len() + headers;
+		for (name, data) in streams.iter() {
+			root.extend_from_slice(&(offset as u32).to_le_bytes());
+			root.extend_from_slice(&(data.len() as u32).to_le_bytes());
+			root.extend_from_slice(name.as_bytes());
+			root.resize(root.len() + align_up(name.len() + 1, 4) - name.len(), 0);
+			offset += data.len();
+		}
+		for (_, data) in streams.iter() {
+			root.extend_from_slice(data);
+		}
+		root
+	}
+
+	#[test]
+	fn root_round_trips() {
+		let root = root();
+		let metadata = Metadata::parse(&root).expect("Failed to parse.");
+		assert_eq!((metadata.major_version, metadata.minor_version, metadata.flags), (1, 1, 0x1234));
+		assert_eq!(&*metadata.stream_order, ["#~", "#Blob", "#GUID", "#Strings", "#US"]);
+
+		let mut out = Vec::new();
+		metadata.write(&mut out);
+		assert_eq!(out, root);
+	}
 }
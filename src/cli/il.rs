@@ -1,10 +1,13 @@
 use crate::Result;
 use crate::error::Error;
+use crate::buf::Reading;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum OperandType {
 	None,
 	U8,
+	/// Signed byte, as `ldc.i4.s` has.
+	I8,
 	U16,
 	I32,
 	I64,
@@ -16,13 +19,19 @@ pub enum OperandType {
 	BranchTarget,
 	/// III.1.9 Contains MetadataToken as u32.
 	Token,
+	/// Contains #US heap offset with the high byte set to 0x70.
+	String,
+	/// III.3.66 Contains u32 number of targets followed by i32 offsets from
+	/// the next instruction offset.
+	Switch,
 }
 
 impl OperandType {
-	fn measure(&self) -> usize {
+	pub fn measure(&self) -> usize {
 		match self {
 			OperandType::None => 0,
 			OperandType::U8 => 1,
+			OperandType::I8 => 1,
 			OperandType::U16 => 2,
 			OperandType::I32 => 4,
 			OperandType::I64 => 8,
@@ -31,6 +40,9 @@ impl OperandType {
 			OperandType::ShortBranchTarget => 1,
 			OperandType::BranchTarget => 4,
 			OperandType::Token => 4,
+			OperandType::String => 4,
+			// Only the number of targets, targets follow it.
+			OperandType::Switch => 4,
 		}
 	}
 }
@@ -70,7 +82,7 @@ macro_rules! for_opcodes1 {
 			(LDC_I4_6,       0x1C, "ldc.i4.6",       OperandType::None)
 			(LDC_I4_7,       0x1D, "ldc.i4.7",       OperandType::None)
 			(LDC_I4_8,       0x1E, "ldc.i4.8",       OperandType::None)
-			(LDC_I4_S,       0x1F, "ldc.i4.s",       OperandType::I32)
+			(LDC_I4_S,       0x1F, "ldc.i4.s",       OperandType::I8)
 			(LDC_I4,         0x20, "ldc.i4",         OperandType::I32)
 			(LDC_I8,         0x21, "ldc.i8",         OperandType::I64)
 			(LDC_R4,         0x22, "ldc.r4",         OperandType::F32)
@@ -107,7 +119,7 @@ macro_rules! for_opcodes1 {
 			(BGT_UN,         0x42, "bgt.un",         OperandType::BranchTarget)
 			(BLE_UN,         0x43, "ble.un",         OperandType::BranchTarget)
 			(BLT_UN,         0x44, "blt.un",         OperandType::BranchTarget)
-			(SWITCH,         0x45, "switch",         OperandType::None)
+			(SWITCH,         0x45, "switch",         OperandType::Switch)
 			(LDIND_I1,       0x46, "ldind.i1",       OperandType::None)
 			(LDIND_U1,       0x47, "ldind.u1",       OperandType::None)
 			(LDIND_I2,       0x48, "ldind.i2",       OperandType::None)
@@ -152,7 +164,7 @@ macro_rules! for_opcodes1 {
 			(CALLVIRT,       0x6F, "callvirt",       OperandType::Token)
 			(CPOBJ,          0x70, "cpobj",          OperandType::Token)
 			(LDOBJ,          0x71, "ldobj",          OperandType::Token)
-			(LDSTR,          0x72, "ldstr",          OperandType::Token)
+			(LDSTR,          0x72, "ldstr",          OperandType::String)
 			(NEWOBJ,         0x73, "newobj",         OperandType::Token)
 			(CASTCLASS,      0x74, "castclass",      OperandType::Token)
 			(ISINST,         0x75, "isinst",         OperandType::Token)
@@ -226,66 +238,80 @@ macro_rules! for_opcodes1 {
 			(SUB_OVF,        0xDA, "sub.ovf",        OperandType::None)
 			(SUB_OVF_UN,     0xDB, "sub.ovf.un",     OperandType::None)
 			(ENDFINALLY,     0xDC, "endfinally",     OperandType::None)
-			(LEAVE,          0xDD, "leave",          OperandType::I32)
-			(LEAVE_S,        0xDE, "leave.s",        OperandType::U8)
+			(LEAVE,          0xDD, "leave",          OperandType::BranchTarget)
+			(LEAVE_S,        0xDE, "leave.s",        OperandType::ShortBranchTarget)
 			(STIND_I,        0xDF, "stind.i",        OperandType::None)
 			(CONV_U,         0xE0, "conv.u",         OperandType::None)}
 	};
 }
 
-// One-byte opcodes
-macro_rules! opcode2 {
-	($name:ident, $op:literal, $str:literal) => {
-		const $name: u8 = $op;
-	};
-}
-
 const TWO_BYTE_OPCODE_PREFIX: u8 = 0xFE;
 
-// Two-byte opcodes.
-opcode2!(ARGLIST,     0x00, "arglist");
-opcode2!(CEQ,         0x01, "ceq");
-opcode2!(CGT,         0x02, "cgt");
-opcode2!(CGT_UN,      0x03, "cgt.un");
-opcode2!(CLT,         0x04, "clt");
-opcode2!(CLT_UN,      0x05, "clt.un");
-opcode2!(LDFTN,       0x06, "ldftn");
-opcode2!(LDVIRTFTN,   0x07, "ldvirtftn");
-opcode2!(LDARG,       0x09, "ldarg");
-opcode2!(LDARGA,      0x0A, "ldarga");
-opcode2!(STARG,       0x0B, "starg");
-opcode2!(LDLOC,       0x0C, "ldloc");
-opcode2!(LDLOCA,      0x0D, "ldloca");
-opcode2!(STLOC,       0x0E, "stloc");
-opcode2!(LOCALLOC,    0x0F, "localloc");
-opcode2!(ENDFILTER,   0x11, "endfilter");
-opcode2!(UNALIGNED,   0x12, "unaligned.");
-opcode2!(VOLATILE,    0x13, "volatile.");
-opcode2!(TAIL,        0x14, "tail.");
-opcode2!(INITOBJ,     0x15, "Initobj");
-opcode2!(CONSTRAINED, 0x16, "constrained.");
-opcode2!(CPBLK,       0x17, "cpblk");
-opcode2!(INITBLK,     0x18, "initblk");
-opcode2!(NO,          0x19, "no.");
-opcode2!(RETHROW,     0x1A, "rethrow");
-opcode2!(SIZEOF,      0x1C, "sizeof");
-opcode2!(REFANYTYPE,  0x1D, "Refanytype");
-opcode2!(READONLY,    0x1E, "readonly.");
+// Two-byte opcodes are stored as 0xFE00 | second byte.
+
+macro_rules! for_opcodes2 {
+	($x:ident) => {
+		$x!{(ARGLIST,       0xFE00, "arglist",      OperandType::None)
+			(CEQ,           0xFE01, "ceq",          OperandType::None)
+			(CGT,           0xFE02, "cgt",          OperandType::None)
+			(CGT_UN,        0xFE03, "cgt.un",       OperandType::None)
+			(CLT,           0xFE04, "clt",          OperandType::None)
+			(CLT_UN,        0xFE05, "clt.un",       OperandType::None)
+			(LDFTN,         0xFE06, "ldftn",        OperandType::Token)
+			(LDVIRTFTN,     0xFE07, "ldvirtftn",    OperandType::Token)
+			(LDARG,         0xFE09, "ldarg",        OperandType::U16)
+			(LDARGA,        0xFE0A, "ldarga",       OperandType::U16)
+			(STARG,         0xFE0B, "starg",        OperandType::U16)
+			(LDLOC,         0xFE0C, "ldloc",        OperandType::U16)
+			(LDLOCA,        0xFE0D, "ldloca",       OperandType::U16)
+			(STLOC,         0xFE0E, "stloc",        OperandType::U16)
+			(LOCALLOC,      0xFE0F, "localloc",     OperandType::None)
+			(ENDFILTER,     0xFE11, "endfilter",    OperandType::None)
+			(UNALIGNED,     0xFE12, "unaligned.",   OperandType::U8)
+			(VOLATILE,      0xFE13, "volatile.",    OperandType::None)
+			(TAIL,          0xFE14, "tail.",        OperandType::None)
+			(INITOBJ,       0xFE15, "initobj",      OperandType::Token)
+			(CONSTRAINED,   0xFE16, "constrained.", OperandType::Token)
+			(CPBLK,         0xFE17, "cpblk",        OperandType::None)
+			(INITBLK,       0xFE18, "initblk",      OperandType::None)
+			(NO,            0xFE19, "no.",          OperandType::U8)
+			(RETHROW,       0xFE1A, "rethrow",      OperandType::None)
+			(SIZEOF,        0xFE1C, "sizeof",       OperandType::Token)
+			(REFANYTYPE,    0xFE1D, "refanytype",   OperandType::None)
+			(READONLY,      0xFE1E, "readonly.",    OperandType::None)}
+	};
+}
 
 // Generating stuff:
 
 macro_rules! gen_constants {
 	($(($name:ident, $op:literal, $str:literal, $operand:path))+) => {
-		$( const $name: u8 = $op; )+
+		$( pub const $name: u16 = $op; )+
 	};
 }
 
 for_opcodes1!(gen_constants);
+for_opcodes2!(gen_constants);
+
+fn is_two_byte(op: u16) -> bool {
+	op >> 8 == TWO_BYTE_OPCODE_PREFIX as u16
+}
+
+/// Reads either one-byte or two-byte opcode.
+pub fn read_opcode(il: &[u8], offset: &mut usize) -> Result<u16> {
+	let b: u8 = il.read(offset)?;
+	if b == TWO_BYTE_OPCODE_PREFIX {
+		let b: u8 = il.read(offset)?;
+		Ok((TWO_BYTE_OPCODE_PREFIX as u16) << 8 | b as u16)
+	} else {
+		Ok(b as u16)
+	}
+}
 
-pub fn dump_opcode(x: u8) -> &'static str {
+pub fn dump_opcode(op: u16) -> &'static str {
 	macro_rules! gen_match {
 		($(($name:ident, $op:literal, $str:literal, $operand:path))+) => {
-			match x {
+			match op {
 				$(
 					$op => $str,
 				)+
@@ -294,15 +320,39 @@ pub fn dump_opcode(x: u8) -> &'static str {
 		};
 	}
 
-	for_opcodes1!(gen_match)
+	if is_two_byte(op) {
+		for_opcodes2!(gen_match)
+	} else {
+		for_opcodes1!(gen_match)
+	}
 }
 
-// TODO(dmi): @incomplete It should handle two-byte opcodes.
-pub fn ins_size(op: u8) -> Result<usize> {
-	Ok(1 + operand_type(op)?.measure())
+/// Looks up an opcode by its ILAsm name, e.g. `ldc.i4.s`.
+pub fn opcode_by_name(name: &str) -> Option<u16> {
+	macro_rules! gen_match {
+		($(($name:ident, $op:literal, $str:literal, $operand:path))+) => {
+			match name {
+				$(
+					$str => Some($op),
+				)+
+				_ => None,
+			}
+		};
+	}
+
+	for_opcodes1!(gen_match).or_else(|| for_opcodes2!(gen_match))
 }
 
-fn operand_type(op: u8) -> Result<OperandType> {
+pub fn opcode_size(op: u16) -> usize {
+	if is_two_byte(op) { 2 } else { 1 }
+}
+
+/// Size of the instruction, excluding `switch` targets.
+pub fn ins_size(op: u16) -> Result<usize> {
+	Ok(opcode_size(op) + operand_type(op)?.measure())
+}
+
+pub fn operand_type(op: u16) -> Result<OperandType> {
 	macro_rules! gen_match {
 		($(($name:ident, $op:literal, $str:literal, $operand:path))+) => {
 			match op {
@@ -314,5 +364,94 @@ fn operand_type(op: u8) -> Result<OperandType> {
 		};
 	}
 
-	for_opcodes1!(gen_match)
+	if is_two_byte(op) {
+		for_opcodes2!(gen_match)
+	} else {
+		for_opcodes1!(gen_match)
+	}
+}
+
+/// How an instruction passes control on, III.1.7.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FlowControl {
+	/// Continues with the following instruction.
+	Next,
+	Branch,
+	/// Either branches or continues, `switch` included.
+	CondBranch,
+	/// Exits a protected block or a handler, emptying the stack.
+	Leave,
+	/// `ret` and `jmp`.
+	Return,
+	/// `throw` and `rethrow`.
+	Throw,
+	/// `endfinally` and `endfilter`.
+	EndHandler,
+}
+
+pub fn flow_control(op: u16) -> FlowControl {
+	match op {
+		BR | BR_S => FlowControl::Branch,
+		BRFALSE | BRFALSE_S | BRTRUE | BRTRUE_S |
+		BEQ | BEQ_S | BGE | BGE_S | BGT | BGT_S | BLE | BLE_S | BLT | BLT_S |
+		BNE_UN | BNE_UN_S | BGE_UN | BGE_UN_S | BGT_UN | BGT_UN_S |
+		BLE_UN | BLE_UN_S | BLT_UN | BLT_UN_S | SWITCH => FlowControl::CondBranch,
+		LEAVE | LEAVE_S => FlowControl::Leave,
+		RET | JMP => FlowControl::Return,
+		THROW | RETHROW => FlowControl::Throw,
+		ENDFINALLY | ENDFILTER => FlowControl::EndHandler,
+		_ => FlowControl::Next,
+	}
+}
+
+/// Numbers of values the instruction pops and pushes, III.1.2.1. None for
+/// calls and `ret`, which depend on signatures, and for unknown opcodes.
+pub fn stack_effect(op: u16) -> Option<(u16, u16)> {
+	let effect = match op {
+		CALL | CALLVIRT | CALLI | NEWOBJ | RET => return None,
+
+		NOP | BREAK | BR | BR_S | LEAVE | LEAVE_S | ENDFINALLY | JMP | RETHROW |
+		UNALIGNED | VOLATILE | TAIL | CONSTRAINED | NO | READONLY => (0, 0),
+
+		LDARG_0 | LDARG_1 | LDARG_2 | LDARG_3 | LDARG_S | LDARG | LDARGA_S | LDARGA |
+		LDLOC_0 | LDLOC_1 | LDLOC_2 | LDLOC_3 | LDLOC_S | LDLOC | LDLOCA_S | LDLOCA |
+		LDNULL | LDC_I4_M1 | LDC_I4_0 | LDC_I4_1 | LDC_I4_2 | LDC_I4_3 | LDC_I4_4 |
+		LDC_I4_5 | LDC_I4_6 | LDC_I4_7 | LDC_I4_8 | LDC_I4_S | LDC_I4 | LDC_I8 |
+		LDC_R4 | LDC_R8 | LDSTR | LDSFLD | LDSFLDA | LDTOKEN | LDFTN | SIZEOF | ARGLIST => (0, 1),
+
+		STLOC_0 | STLOC_1 | STLOC_2 | STLOC_3 | STLOC_S | STLOC | STARG_S | STARG |
+		POP | BRFALSE | BRFALSE_S | BRTRUE | BRTRUE_S | SWITCH | THROW | STSFLD |
+		ENDFILTER | INITOBJ => (1, 0),
+
+		DUP => (1, 2),
+
+		LDIND_I1 | LDIND_U1 | LDIND_I2 | LDIND_U2 | LDIND_I4 | LDIND_U4 | LDIND_I8 |
+		LDIND_I | LDIND_R4 | LDIND_R8 | LDIND_REF | NEG | NOT |
+		CONV_I1 | CONV_I2 | CONV_I4 | CONV_I8 | CONV_R4 | CONV_R8 | CONV_U4 | CONV_U8 |
+		CONV_R_UN | CONV_U2 | CONV_U1 | CONV_I | CONV_U |
+		CONV_OVF_I1_UN | CONV_OVF_I2_UN | CONV_OVF_I4_UN | CONV_OVF_I8_UN |
+		CONV_OVF_U1_UN | CONV_OVF_U2_UN | CONV_OVF_U4_UN | CONV_OVF_U8_UN |
+		CONV_OVF_I_UN | CONV_OVF_U_UN | CONV_OVF_I1 | CONV_OVF_U1 | CONV_OVF_I2 |
+		CONV_OVF_U2 | CONV_OVF_I4 | CONV_OVF_U4 | CONV_OVF_I8 | CONV_OVF_U8 |
+		CONV_OVF_I | CONV_OVF_U | CKFINITE | LDOBJ | CASTCLASS | ISINST | UNBOX |
+		UNBOX_ANY | BOX | LDFLD | LDFLDA | NEWARR | LDLEN | REFANYVAL | MKREFANY |
+		REFANYTYPE | LDVIRTFTN | LOCALLOC => (1, 1),
+
+		BEQ | BEQ_S | BGE | BGE_S | BGT | BGT_S | BLE | BLE_S | BLT | BLT_S |
+		BNE_UN | BNE_UN_S | BGE_UN | BGE_UN_S | BGT_UN | BGT_UN_S | BLE_UN | BLE_UN_S |
+		BLT_UN | BLT_UN_S | STIND_REF | STIND_I1 | STIND_I2 | STIND_I4 | STIND_I8 |
+		STIND_R4 | STIND_R8 | STIND_I | STFLD | STOBJ | CPOBJ => (2, 0),
+
+		ADD | SUB | MUL | DIV | DIV_UN | REM | REM_UN | AND | OR | XOR | SHL | SHR |
+		SHR_UN | ADD_OVF | ADD_OVF_UN | MUL_OVF | MUL_OVF_UN | SUB_OVF | SUB_OVF_UN |
+		CEQ | CGT | CGT_UN | CLT | CLT_UN | LDELEMA | LDELEM_I1 | LDELEM_U1 |
+		LDELEM_I2 | LDELEM_U2 | LDELEM_I4 | LDELEM_U4 | LDELEM_I8 | LDELEM_I |
+		LDELEM_R4 | LDELEM_R8 | LDELEM_REF | LDELEM => (2, 1),
+
+		STELEM_I | STELEM_I1 | STELEM_I2 | STELEM_I4 | STELEM_I8 | STELEM_R4 |
+		STELEM_R8 | STELEM_REF | STELEM | CPBLK | INITBLK => (3, 0),
+
+		_ => return None,
+	};
+	Some(effect)
 }
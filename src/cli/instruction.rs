@@ -0,0 +1,164 @@
+use std::convert::TryFrom;
+
+use crate::Result;
+use crate::buf::Reading;
+use crate::cli::{
+	MetadataToken, OperandType, operand_type, read_opcode,
+	UNALIGNED, VOLATILE, TAIL, CONSTRAINED, NO, READONLY,
+};
+
+/// A prefix modifying the instruction it precedes, III.2.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Prefix {
+	/// Carries the alignment: 1, 2 or 4.
+	Unaligned(u8),
+	Volatile,
+	Tail,
+	Constrained(MetadataToken),
+	/// Carries the mask of checks to skip.
+	No(u8),
+	ReadOnly,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand {
+	None,
+	U8(u8),
+	I8(i8),
+	U16(u16),
+	I32(i32),
+	I64(i64),
+	F32(f32),
+	F64(f64),
+	/// Absolute offset of the branch target in the method body.
+	Branch(u32),
+	/// Absolute offsets of the switch targets in the method body.
+	Switch(Box<[u32]>),
+	Token(MetadataToken),
+	/// Offset in the #US heap.
+	String(u32),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Instruction {
+	/// Offset of the first prefix or of the opcode itself.
+	pub offset: u32,
+	/// Size including prefixes and the operand.
+	pub size: u32,
+	pub prefixes: Box<[Prefix]>,
+	pub opcode: u16,
+	pub operand: Operand,
+}
+
+impl Instruction {
+	/// Offset of the instruction following this one.
+	pub fn next(&self) -> u32 {
+		self.offset + self.size
+	}
+
+	/// Decodes the instruction at `offset` with its prefixes.
+	pub fn parse(il: &[u8], offset: &mut usize) -> Result<Instruction> {
+		let start = *offset;
+
+		let mut prefixes = Vec::new();
+		let opcode = loop {
+			let opcode = read_opcode(il, offset)?;
+			let prefix = match opcode {
+				UNALIGNED   => Prefix::Unaligned(il.read(offset)?),
+				VOLATILE    => Prefix::Volatile,
+				TAIL        => Prefix::Tail,
+				CONSTRAINED => Prefix::Constrained(MetadataToken::try_from(il.read::<u32>(offset)?)?),
+				NO          => Prefix::No(il.read(offset)?),
+				READONLY    => Prefix::ReadOnly,
+				_ => break opcode,
+			};
+			prefixes.push(prefix);
+		};
+
+		let operand = match operand_type(opcode)? {
+			OperandType::None  => Operand::None,
+			OperandType::U8    => Operand::U8(il.read(offset)?),
+			OperandType::I8    => Operand::I8(il.read(offset)?),
+			OperandType::U16   => Operand::U16(il.read(offset)?),
+			OperandType::I32   => Operand::I32(il.read(offset)?),
+			OperandType::I64   => Operand::I64(il.read(offset)?),
+			OperandType::F32   => Operand::F32(il.read(offset)?),
+			OperandType::F64   => Operand::F64(il.read(offset)?),
+			OperandType::Token => Operand::Token(MetadataToken::try_from(il.read::<u32>(offset)?)?),
+			OperandType::String => {
+				let token: u32 = il.read(offset)?;
+				if token >> 24 != 0x70 {
+					Err("String token does not reference the #US heap.")?;
+				}
+				Operand::String(token & 0xFF_FFFF)
+			},
+			OperandType::ShortBranchTarget => {
+				let delta = il.read::<i8>(offset)? as i64;
+				Operand::Branch(branch_target(il, *offset, delta)?)
+			},
+			OperandType::BranchTarget => {
+				let delta = il.read::<i32>(offset)? as i64;
+				Operand::Branch(branch_target(il, *offset, delta)?)
+			},
+			OperandType::Switch => {
+				let n: u32 = il.read(offset)?;
+				if n as usize > il.len() / 4 {
+					Err("Switch table exceeds the method body.")?;
+				}
+				let deltas = (0..n)
+					.map(|_| il.read::<i32>(offset))
+					.collect::<std::result::Result<Vec<_>, _>>()?;
+				// Targets are relative to the end of the whole table.
+				let targets = deltas.into_iter()
+					.map(|d| branch_target(il, *offset, d as i64))
+					.collect::<Result<Vec<_>>>()?;
+				Operand::Switch(targets.into_boxed_slice())
+			},
+		};
+
+		Ok(Instruction {
+			offset: start as u32,
+			size: (*offset - start) as u32,
+			prefixes: prefixes.into_boxed_slice(),
+			opcode,
+			operand,
+		})
+	}
+}
+
+fn branch_target(il: &[u8], next: usize, delta: i64) -> Result<u32> {
+	let target = next as i64 + delta;
+	if target < 0 || target > il.len() as i64 {
+		Err("Branch target is out of the method body.")?;
+	}
+	Ok(target as u32)
+}
+
+/// Iterates over instructions of a method body IL stream. Stops after the
+/// first malformed instruction.
+pub struct Instructions<'a> {
+	il: &'a [u8],
+	offset: usize,
+}
+
+impl<'a> Instructions<'a> {
+	pub fn new(il: &'a [u8]) -> Self {
+		Instructions { il, offset: 0 }
+	}
+}
+
+impl Iterator for Instructions<'_> {
+	type Item = Result<Instruction>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.offset >= self.il.len() {
+			return None;
+		}
+
+		let r = Instruction::parse(self.il, &mut self.offset);
+		if r.is_err() {
+			self.offset = self.il.len();
+		}
+		Some(r)
+	}
+}
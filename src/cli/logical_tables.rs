@@ -1,10 +1,14 @@
+use std::ops::Range;
+
 use log::{debug};
 
 use crate::Result;
 use crate::error::Error;
-use crate::buf::Reading;
+use crate::buf::{Reading, Writing};
 
 use crate::cli::constants::*;
+use crate::cli::string_at;
+use crate::utils::align_up;
 
 // II.24.2.6: The physical representation of a row cell e at a
 // column with type C is defined as follows: 
@@ -40,22 +44,112 @@ pub enum IndexSize {
 	U32,
 }
 
+/// Bits of the tables, which II.22 requires to be sorted by their primary key.
+pub const SORTED_TABLES: u64 = 1 << METADATA_INTERFACE_IMPL
+	| 1 << METADATA_CONSTANT
+	| 1 << METADATA_CUSTOM_ATTRIBUTE
+	| 1 << METADATA_FIELD_MARSHAL
+	| 1 << METADATA_DECL_SECURITY
+	| 1 << METADATA_CLASS_LAYOUT
+	| 1 << METADATA_FIELD_LAYOUT
+	| 1 << METADATA_METHOD_SEMANTICS
+	| 1 << METADATA_METHOD_IMPL
+	| 1 << METADATA_IMPL_MAP
+	| 1 << METADATA_FIELD_RVA
+	| 1 << METADATA_NESTED_CLASS
+	| 1 << METADATA_GENERIC_PARAM
+	| 1 << METADATA_GENERIC_PARAM_CONSTRAINT;
+
 #[derive(Copy, Clone)]
 pub struct Tables {
+	pub major_version: u8,
+	pub minor_version: u8,
 	pub string_index_size: IndexSize,
 	pub guid_index_size:   IndexSize,
 	pub blob_index_size:   IndexSize,
 	pub lens: [u32; 64],
 	pub size: usize,
+	pub sorted_mask: u64,
 	valid_mask: u64,
 }
 
 impl Tables {
+	/// Describes the tables of `rows`, choosing index sizes by the row
+	/// counts and the sizes (in bytes) of the heaps.
+	pub fn new(rows: &TableRows, strings_size: usize, guids_size: usize, blobs_size: usize) -> Tables {
+		let index_size = |n: usize| if n > 0xFFFF { IndexSize::U32 } else { IndexSize::U16 };
+
+		let mut lens = [0u32; 64];
+
+		macro_rules! table {
+			($table:ident, $id:ident) => {
+				lens[$id] = rows.$table.len() as u32;
+			};
+		}
+
+		table!(modules,                   METADATA_MODULE);
+		table!(type_refs,                 METADATA_TYPE_REF);
+		table!(type_defs,                 METADATA_TYPE_DEF);
+		table!(fields,                    METADATA_FIELD);
+		table!(method_defs,               METADATA_METHOD_DEF);
+		table!(params,                    METADATA_PARAM);
+		table!(interface_impls,           METADATA_INTERFACE_IMPL);
+		table!(member_refs,               METADATA_MEMBER_REF);
+		table!(constants,                 METADATA_CONSTANT);
+		table!(custom_attributes,         METADATA_CUSTOM_ATTRIBUTE);
+		table!(field_marshals,            METADATA_FIELD_MARSHAL);
+		table!(security_attributes,       METADATA_DECL_SECURITY);
+		table!(class_layouts,             METADATA_CLASS_LAYOUT);
+		table!(field_layouts,             METADATA_FIELD_LAYOUT);
+		table!(standalone_signatures,     METADATA_STANDALONE_SIG);
+		table!(event_maps,                METADATA_EVENT_MAP);
+		table!(events,                    METADATA_EVENT);
+		table!(property_maps,             METADATA_PROPERTY_MAP);
+		table!(properties,                METADATA_PROPERTY);
+		table!(method_semantics,          METADATA_METHOD_SEMANTICS);
+		table!(method_impls,              METADATA_METHOD_IMPL);
+		table!(module_refs,               METADATA_MODULE_REF);
+		table!(type_specs,                METADATA_TYPE_SPEC);
+		table!(impl_maps,                 METADATA_IMPL_MAP);
+		table!(field_rvas,                METADATA_FIELD_RVA);
+		table!(assemblies,                METADATA_ASSEMBLY);
+		table!(assembly_refs,             METADATA_ASSEMBLY_REF);
+		table!(files,                     METADATA_FILE);
+		table!(exported_types,            METADATA_EXPORTED_TYPE);
+		table!(manifest_resources,        METADATA_MANIFEST_RESOURCE);
+		table!(nested_classes,            METADATA_NESTED_CLASS);
+		table!(generic_params,            METADATA_GENERIC_PARAM);
+		table!(method_specs,              METADATA_METHOD_SPEC);
+		table!(generic_param_constraints, METADATA_GENERIC_PARAM_CONSTRAINT);
+
+		let mut valid_mask = 0u64;
+		for (i, len) in lens.iter().enumerate() {
+			if *len != 0 {
+				valid_mask |= 1 << i;
+			}
+		}
+
+		Tables {
+			major_version: 2,
+			minor_version: 0,
+			string_index_size: index_size(strings_size),
+			guid_index_size: index_size(guids_size >> 4),
+			blob_index_size: index_size(blobs_size),
+			lens,
+			size: 24 + 4 * valid_mask.count_ones() as usize,
+			sorted_mask: SORTED_TABLES,
+			valid_mask,
+		}
+	}
+
 	pub fn parse(data: &[u8]) -> Result<Tables> {
 		let mut offset = &mut 0usize;
 
-		// Reserverd1, major version, minor version.
-		*offset += 6;
+		// Reserverd1.
+		*offset += 4;
+
+		let major_version: u8 = data.read(offset)?;
+		let minor_version: u8 = data.read(offset)?;
 		
 		// The HeapSizes field is a bitvector that encodes the width of
 		// indexes into the various heaps. If bit 0 is set, indexes into
@@ -81,30 +175,61 @@ impl Tables {
 		let n = valid_mask.count_ones() as usize;
 		debug!("Valid mask: {:#066b} -> {} table(s).", valid_mask, n);
 
-		// Sorted.
-		*offset += 8;
+		let sorted_mask: u64 = data.read(offset)?;
 		
 		let mut lens = [0u32; 64];
-		for i in 0..lens.len() {
+		for (i, len) in lens.iter_mut().enumerate() {
 			if (valid_mask >> i) & 1 == 1 {
-				lens[i] = data.read(offset)?;
-				debug!("Table #{} has {:#0x} item(s).", i, lens[i]);
+				*len = data.read(offset)?;
+				debug!("Table #{} has {:#0x} item(s).", i, len);
 			}
 		}
 
 		let size = *offset;
 		
 		Ok(Tables {
+			major_version,
+			minor_version,
 			string_index_size,
 			guid_index_size,
 			blob_index_size,
 			lens,
 			size,
+			sorted_mask,
 			valid_mask,
 		})
 	}
 
-	fn has_table(&self, id: usize) -> bool {
+	pub fn write(&self, out: &mut Vec<u8>) {
+		let mut heap_sizes = 0u8;
+		if self.string_index_size == IndexSize::U32 {
+			heap_sizes |= 0x01;
+		}
+		if self.guid_index_size == IndexSize::U32 {
+			heap_sizes |= 0x02;
+		}
+		if self.blob_index_size == IndexSize::U32 {
+			heap_sizes |= 0x04;
+		}
+
+		// Reserved1.
+		out.write(0u32);
+		out.write(self.major_version);
+		out.write(self.minor_version);
+		out.write(heap_sizes);
+		// Reserved2, shall be one.
+		out.write(1u8);
+		out.write(self.valid_mask);
+		out.write(self.sorted_mask);
+
+		for (i, len) in self.lens.iter().enumerate() {
+			if self.has_table(i) {
+				out.write(*len);
+			}
+		}
+	}
+
+	pub fn has_table(&self, id: usize) -> bool {
 		(self.valid_mask >> id) & 1 == 1
 	}
 }
@@ -172,12 +297,12 @@ pub struct TableRows {
 	/// need.
 	/// The signature shall describe either:
 	/// - a method - code generators create a row in the StandAloneSig
-	/// table for each occurrence of a calli CIL instruction. That row indexes
-	/// the call-site signature for the function pointer operand of the calli
-	/// instruction
+	///   table for each occurrence of a calli CIL instruction. That row indexes
+	///   the call-site signature for the function pointer operand of the calli
+	///   instruction
 	/// - local variables - code generators create one row in the
-	/// standalone_signatures for each method, to describe all of its local
-	/// variables.
+	///   standalone_signatures for each method, to describe all of its local
+	///   variables.
 	pub standalone_signatures: Box<[StandAloneSig]>,
 	/// EventMap info does not directly influence runtime behavior;
 	/// what counts is the information stored for each method that the
@@ -223,17 +348,17 @@ pub struct TableRows {
 	pub files: Box<[File]>,
 	/// It holds a row for each type:
 	/// - Defined within other modules of this Assembly; that is exported out of
-	/// this Assembly.  In essence, it stores TypeDef row numbers of all types
-	/// that are marked public in other modules that this Assembly comprises.
-	/// The actual target row in a TypeDef table is given by the combination
-	/// of TypeDefId (in effect, row number) and Implementation (in effect,
-	/// the module that holds the target TypeDef table). Note that this is the
-	/// only occurrence in metadata of foreign tokens; that is, token values
-	/// that have a meaning in another module. (A regular token value is an
-	/// index into a table in the current module); OR
+	///   this Assembly.  In essence, it stores TypeDef row numbers of all types
+	///   that are marked public in other modules that this Assembly comprises.
+	///   The actual target row in a TypeDef table is given by the combination
+	///   of TypeDefId (in effect, row number) and Implementation (in effect,
+	///   the module that holds the target TypeDef table). Note that this is the
+	///   only occurrence in metadata of foreign tokens; that is, token values
+	///   that have a meaning in another module. (A regular token value is an
+	///   index into a table in the current module); OR
 	/// - Originally defined in this Assembly but now moved to another
-	/// Assembly. Flags must have IsTypeForwarder set and Implementation is an
-	/// AssemblyRef indicating the Assembly the type may now be found in.
+	///   Assembly. Flags must have IsTypeForwarder set and Implementation is an
+	///   AssemblyRef indicating the Assembly the type may now be found in.
 	pub exported_types: Box<[ExportedType]>,
 	pub manifest_resources: Box<[ManifestResource]>,
 	/// NestedClass is defined as lexically "inside" the text of its enclosing Type.
@@ -279,7 +404,7 @@ impl TableRows {
 		table!(constants,                 METADATA_CONSTANT,                 Constant);
 		table!(custom_attributes,         METADATA_CUSTOM_ATTRIBUTE,         CustomAttribute);
 		table!(field_marshals,            METADATA_FIELD_MARSHAL,            FieldMarshal);
-		table!(security_attributes,       METADATA_FIELD_MARSHAL,            DeclSecutity);
+		table!(security_attributes,       METADATA_DECL_SECURITY,            DeclSecutity);
 		table!(class_layouts,             METADATA_CLASS_LAYOUT,             ClassLayout);
 		table!(field_layouts,             METADATA_FIELD_LAYOUT,             FieldLayout);
 		table!(standalone_signatures,     METADATA_STANDALONE_SIG,           StandAloneSig);
@@ -357,62 +482,247 @@ impl TableRows {
 			generic_param_constraints,
 		})
 	}
+
+	/// Writes the rows of all tables present in `header`, which shall be
+	/// describing these rows (see `Tables::new`).
+	pub fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		macro_rules! table {
+			($table:ident, $id:ident) => {
+				if header.has_table($id) {
+					for row in self.$table.iter() {
+						row.write(header, out);
+					}
+				}
+			};
+		}
+
+		table!(modules,                   METADATA_MODULE);
+		table!(type_refs,                 METADATA_TYPE_REF);
+		table!(type_defs,                 METADATA_TYPE_DEF);
+		table!(fields,                    METADATA_FIELD);
+		table!(method_defs,               METADATA_METHOD_DEF);
+		table!(params,                    METADATA_PARAM);
+		table!(interface_impls,           METADATA_INTERFACE_IMPL);
+		table!(member_refs,               METADATA_MEMBER_REF);
+		table!(constants,                 METADATA_CONSTANT);
+		table!(custom_attributes,         METADATA_CUSTOM_ATTRIBUTE);
+		table!(field_marshals,            METADATA_FIELD_MARSHAL);
+		table!(security_attributes,       METADATA_DECL_SECURITY);
+		table!(class_layouts,             METADATA_CLASS_LAYOUT);
+		table!(field_layouts,             METADATA_FIELD_LAYOUT);
+		table!(standalone_signatures,     METADATA_STANDALONE_SIG);
+		table!(event_maps,                METADATA_EVENT_MAP);
+		table!(events,                    METADATA_EVENT);
+		table!(property_maps,             METADATA_PROPERTY_MAP);
+		table!(properties,                METADATA_PROPERTY);
+		table!(method_semantics,          METADATA_METHOD_SEMANTICS);
+		table!(method_impls,              METADATA_METHOD_IMPL);
+		table!(module_refs,               METADATA_MODULE_REF);
+		table!(type_specs,                METADATA_TYPE_SPEC);
+		table!(impl_maps,                 METADATA_IMPL_MAP);
+		table!(field_rvas,                METADATA_FIELD_RVA);
+		table!(assemblies,                METADATA_ASSEMBLY);
+		table!(assembly_refs,             METADATA_ASSEMBLY_REF);
+		table!(files,                     METADATA_FILE);
+		table!(exported_types,            METADATA_EXPORTED_TYPE);
+		table!(manifest_resources,        METADATA_MANIFEST_RESOURCE);
+		table!(nested_classes,            METADATA_NESTED_CLASS);
+		table!(generic_params,            METADATA_GENERIC_PARAM);
+		table!(method_specs,              METADATA_METHOD_SPEC);
+		table!(generic_param_constraints, METADATA_GENERIC_PARAM_CONSTRAINT);
+	}
+
+	/// Produces a complete #~ stream for these rows. Sizes of the heaps
+	/// (in bytes) are required to pick the heap index sizes.
+	pub fn write_stream(&self, strings_size: usize, guids_size: usize, blobs_size: usize) -> Vec<u8> {
+		let header = Tables::new(self, strings_size, guids_size, blobs_size);
+
+		let mut out = Vec::new();
+		header.write(&mut out);
+		self.write(&header, &mut out);
+
+		// Streams are 4-byte aligned.
+		out.resize(align_up(out.len(), 4), 0);
+		out
+	}
+
+	/// Fields owned by the 0-based type_def row, as 0-based rows.
+	pub fn fields_of(&self, type_def: usize) -> Range<usize> {
+		run(self.type_defs.len(), self.fields.len(), |i| self.type_defs[i].field_list.into_index(), type_def)
+	}
+
+	/// Methods owned by the 0-based type_def row, as 0-based rows.
+	pub fn methods_of(&self, type_def: usize) -> Range<usize> {
+		run(self.type_defs.len(), self.method_defs.len(), |i| self.type_defs[i].method_list.into_index(), type_def)
+	}
+
+	/// Params owned by the 0-based method_def row, as 0-based rows.
+	pub fn params_of(&self, method_def: usize) -> Range<usize> {
+		run(self.method_defs.len(), self.params.len(), |i| self.method_defs[i].param_list.into_index(), method_def)
+	}
+
+	/// 0-based type_def row owning the 0-based field row.
+	pub fn field_owner(&self, field: usize) -> Option<usize> {
+		(0..self.type_defs.len()).find(|t| self.fields_of(*t).contains(&field))
+	}
+
+	/// 0-based type_def row owning the 0-based method_def row.
+	pub fn method_owner(&self, method_def: usize) -> Option<usize> {
+		(0..self.type_defs.len()).find(|t| self.methods_of(*t).contains(&method_def))
+	}
+
+	/// ClassLayout of the 0-based type_def row if it has one.
+	pub fn class_layout_of(&self, type_def: usize) -> Option<&ClassLayout> {
+		self.class_layouts.iter().find(|l| l.parent.into_index() == type_def + 1)
+	}
+
+	/// Full name of a type as ILAsm writes it: namespace dot-separated and
+	/// nested types slash-separated, e.g. `System.Environment/SpecialFolder`.
+	pub fn type_name(&self, strings: &[u8], ty: TypeDefOrRef) -> Result<String> {
+		let row = ty.into_index().wrapping_sub(1);
+		let (namespace, name, enclosing) = match ty {
+			TypeDefOrRef::TypeDef(_) => {
+				let t = self.type_defs.get(row).ok_or("TypeDef row is out of the table bounds.")?;
+				let enclosing = self.nested_classes.iter()
+					.find(|n| n.nested.into_index() == row + 1)
+					.map(|n| TypeDefOrRef::TypeDef(n.enclosing.into_index() as u32));
+				(t.namespace, t.name, enclosing)
+			},
+			TypeDefOrRef::TypeRef(_) => {
+				let t = self.type_refs.get(row).ok_or("TypeRef row is out of the table bounds.")?;
+				let enclosing = match t.scope {
+					ResolutionScope::TypeRef(r) => Some(TypeDefOrRef::TypeRef(r)),
+					_ => None,
+				};
+				(t.namespace, t.name, enclosing)
+			},
+			TypeDefOrRef::TypeSpec(_) => Err("TypeSpec has no name.")?,
+		};
+
+		let mut full = match enclosing {
+			Some(e) => self.type_name(strings, e)? + "/",
+			None => String::new(),
+		};
+		let namespace = string_at(strings, namespace.into_index())?;
+		if !namespace.is_empty() {
+			full.push_str(namespace);
+			full.push('.');
+		}
+		full.push_str(string_at(strings, name.into_index())?);
+		Ok(full)
+	}
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
-pub struct StringIndex(u32);
+/// Returns the run owned by `i`-th row, where `start_of` gives the 1-based
+/// list start of a row.
+fn run(n_owners: usize, n: usize, start_of: impl Fn(usize) -> usize, i: usize) -> Range<usize> {
+	let start = start_of(i).saturating_sub(1).min(n);
+	let end = if i + 1 < n_owners {
+		start_of(i + 1).saturating_sub(1).min(n)
+	} else {
+		n
+	};
+	start..end.max(start)
+}
+
+fn write_index(size: IndexSize, i: u32, out: &mut Vec<u8>) {
+	match size {
+		IndexSize::U16 => out.write(i as u16),
+		IndexSize::U32 => out.write(i),
+	}
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct StringIndex(pub u32);
 
 impl StringIndex {
 	fn parse(header: &Tables, data: &[u8], offset: &mut usize) -> Result<Self> {
 		let i = match header.string_index_size {
 			IndexSize::U16 => StringIndex(data.read::<u16>(offset)? as u32),
-			IndexSize::U32 => StringIndex(data.read::<u32>(offset)? as u32),
+			IndexSize::U32 => StringIndex(data.read::<u32>(offset)?),
 		};
 		Ok(i)
 	}
 
-	pub fn into_index(&self) -> usize {
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		write_index(header.string_index_size, self.0, out);
+	}
+
+	pub fn into_index(self) -> usize {
 		self.0 as usize
 	}
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
-pub struct GuidIndex(u32);
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct GuidIndex(pub u32);
 
 impl GuidIndex {
 	fn parse(header: &Tables, data: &[u8], offset: &mut usize) -> Result<Self> {
 		let i = match header.guid_index_size {
 			IndexSize::U16 => GuidIndex(data.read::<u16>(offset)? as u32),
-			IndexSize::U32 => GuidIndex(data.read::<u32>(offset)? as u32),
+			IndexSize::U32 => GuidIndex(data.read::<u32>(offset)?),
 		};
 		Ok(i)
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		write_index(header.guid_index_size, self.0, out);
+	}
+
+	/// 1-based index into the #GUID heap, zero stands for no guid.
+	pub fn into_index(self) -> usize {
+		self.0 as usize
+	}
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
-pub struct BlobIndex(u32);
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct BlobIndex(pub u32);
 
-// TODO(dmi): @incomplete Not sure if blob index can have different size.
 impl BlobIndex {
 	fn parse(header: &Tables, data: &[u8], offset: &mut usize) -> Result<Self> {
-		Ok(BlobIndex(data.read::<u16>(offset)? as u32))
+		let i = match header.blob_index_size {
+			IndexSize::U16 => BlobIndex(data.read::<u16>(offset)? as u32),
+			IndexSize::U32 => BlobIndex(data.read::<u32>(offset)?),
+		};
+		Ok(i)
+	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		write_index(header.blob_index_size, self.0, out);
+	}
+
+	pub fn into_index(self) -> usize {
+		self.0 as usize
 	}
 }
 
 macro_rules! simple_index {
 	($name:ident, $id:ident) => {
-		#[derive(Debug, PartialEq, Clone, Default)]
-		pub struct $name(u32);
+		#[derive(Debug, PartialEq, Copy, Clone, Default)]
+		pub struct $name(pub u32);
 
 		impl $name {
 			fn parse(header: &Tables, data: &[u8], offset: &mut usize) -> Result<Self> {
 				let i = if header.lens[$id] <= 0xFFFF {
 					$name(data.read::<u16>(offset)? as u32)
 				} else {
-					$name(data.read::<u32>(offset)? as u32)
+					$name(data.read::<u32>(offset)?)
 				};
 				Ok(i)
 			}
+
+			fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+				if header.lens[$id] <= 0xFFFF {
+					out.write(self.0 as u16);
+				} else {
+					out.write(self.0);
+				}
+			}
+
+			/// 1-based row number, zero stands for a null reference.
+			pub fn into_index(self) -> usize {
+				self.0 as usize
+			}
 		}
 	};
 }
@@ -436,34 +746,37 @@ macro_rules! max {
 	};
 }
 
-macro_rules! count_tts {
-    () => {0usize};
-    ($_head:tt $($tail:tt)*) => {1usize + count_tts!($($tail)*)};
-}
-
-const fn size_for_big_index(n: usize) -> usize { 1 << (16 - log2(n)) }
-const fn log2(x: usize) -> usize { 64usize - x.leading_zeros() as usize }
-
 macro_rules! coded_index {
 	($name:ident, $bits:expr, $(($v:ident $t:expr, $id:ident))+) => {
+		#[allow(clippy::enum_variant_names)]
 		#[derive(Debug, PartialEq, Copy, Clone)]
 		pub enum $name {
 			$($v(u32),)+
 		}
 
 		impl $name {
-			fn parse(header: &Tables, data: &[u8], offset: &mut usize) -> Result<$name> {
-				const COUNT: usize = count_tts!($($v),+);
+			/// Coded index is 2 bytes wide only if all its tables have less
+			/// than 2^(16 - tag bits) rows.
+			fn is_small(header: &Tables) -> bool {
 				let max_len = max!($(header.lens[$id]),+) as usize;
-				let value = if max_len < size_for_big_index(COUNT) {
+				max_len < 1 << (16 - $bits)
+			}
+
+			fn parse(header: &Tables, data: &[u8], offset: &mut usize) -> Result<$name> {
+				let value = if $name::is_small(header) {
 					data.read::<u16>(offset)? as u32
 				} else {
 					data.read::<u32>(offset)?
 				};
+				$name::decode(value)
+			}
 
+			/// Splits the value as it is physically stored in a row (or in
+			/// a signature) into the table tag and the row number.
+			pub fn decode(value: u32) -> Result<$name> {
 				let tag = value & (1 << $bits) - 1;
 				let idx = value >> $bits;
-				
+
 				let r = match tag {
 					$(
 						$t => $name::$v(idx),
@@ -473,6 +786,45 @@ macro_rules! coded_index {
 
 				Ok(r)
 			}
+
+			pub fn table_index(&self) -> usize {
+				match self {
+					$(
+						$name::$v(_) => $id,
+					)+
+				}
+			}
+
+			/// 1-based row number, zero stands for a null reference.
+			pub fn into_index(self) -> usize {
+				match self {
+					$(
+						$name::$v(idx) => idx as usize,
+					)+
+				}
+			}
+
+			/// Metadata token of the referenced row.
+			pub fn token(self) -> u32 {
+				(self.table_index() as u32) << 24 | self.into_index() as u32
+			}
+
+			/// The value as it is physically stored in a row.
+			pub fn encode(self) -> u32 {
+				match self {
+					$(
+						$name::$v(idx) => idx << $bits | $t,
+					)+
+				}
+			}
+
+			fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+				if $name::is_small(header) {
+					out.write(self.encode() as u16);
+				} else {
+					out.write(self.encode());
+				}
+			}
 		}
 	};
 }
@@ -563,33 +915,35 @@ coded_index!(TypeOrMethodDef, 1,
 /// II.22.30
 #[derive(Debug, PartialEq, Clone)]
 pub struct Module {
+	/// Reserved, shall be zero.
+	pub generation: u16,
 	/// Module name.
 	pub name: StringIndex,
 	/// Simply a Guid used to distinguish between two
 	/// versions of the same module.
 	pub mvid: GuidIndex,
+	/// Reserved, shall be zero. Set by edit-and-continue builds.
+	pub enc_id: GuidIndex,
+	/// Reserved, shall be zero. Set by edit-and-continue builds.
+	pub enc_base_id: GuidIndex,
 }
 
 impl Module {
 	fn parse(header: &Tables, data: &[u8], offset: &mut usize) -> Result<Module> {
 		let generation: u16 = data.read(offset)?;
-		if generation != 0 {
-			Err("Module has invalid generation.")?;
-		}
-
 		let name = StringIndex::parse(header, data, offset)?;
 		let mvid = GuidIndex::parse(header, data, offset)?;
+		let enc_id = GuidIndex::parse(header, data, offset)?;
+		let enc_base_id = GuidIndex::parse(header, data, offset)?;
+		Ok(Module { generation, name, mvid, enc_id, enc_base_id })
+	}
 
-		let enc_id: u16 = data.read(offset)?;
-		if enc_id != 0 {
-			Err("Module.EncId is not zero.")?;
-		}
-		let enc_base_id: u16 = data.read(offset)?;
-		if enc_base_id != 0 {
-			Err("Module.EncBaseId is not zero.")?;
-		}
-
-		Ok(Module { name, mvid })
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.generation);
+		self.name.write(header, out);
+		self.mvid.write(header, out);
+		self.enc_id.write(header, out);
+		self.enc_base_id.write(header, out);
 	}
 }
 
@@ -608,13 +962,19 @@ impl TypeRef {
 		let namespace = StringIndex::parse(header, data, offset)?;
 		Ok(TypeRef { scope, name, namespace })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.scope.write(header, out);
+		self.name.write(header, out);
+		self.namespace.write(header, out);
+	}
 }
 
 /// II.22.37
 #[derive(Debug, PartialEq, Clone)]
 pub struct TypeDef {
-	// TODO(dmi): @incomplete See TypeAttributes II.23.1.15
-	flags: u32,
+	/// See TypeAttributes II.23.1.15.
+	pub flags: u32,
 	pub name: StringIndex,
 	pub namespace: StringIndex,
 	pub extends: TypeDefOrRef,
@@ -623,13 +983,13 @@ pub struct TypeDef {
 	// - the last row of the Field table
 	// - the next run of Fields, found by inspecting the field_list of
 	// the next row in TypeDef table.
-	field_list: FieldIndex,
+	pub field_list: FieldIndex,
 	// It marks the first of a continguous run of
 	// Methods owned bu this Type. The run continues to the smaller of:
 	// - the last row of the MethodDef table
 	// - the next run of Methods, found by inspecting the method_list of
 	// the next row in TypeDef table.
-	method_list: MethodDefIndex,
+	pub method_list: MethodDefIndex,
 }
 
 impl TypeDef {
@@ -650,13 +1010,22 @@ impl TypeDef {
 			method_list,
 		})
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		self.name.write(header, out);
+		self.namespace.write(header, out);
+		self.extends.write(header, out);
+		self.field_list.write(header, out);
+		self.method_list.write(header, out);
+	}
 }
 
 /// II.22.15
 #[derive(Debug, PartialEq, Clone)]
 pub struct Field {
-	// TODO(dmi): @incomplete See FieldAttributes II.23.1.5
-	flags: u16,
+	/// See FieldAttributes II.23.1.5.
+	pub flags: u16,
 	pub name: StringIndex,
 	pub sig: BlobIndex,
 }
@@ -668,16 +1037,22 @@ impl Field {
 		let sig = BlobIndex::parse(header, data, offset)?;
 		Ok(Field { flags, name, sig })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		self.name.write(header, out);
+		self.sig.write(header, out);
+	}
 }
 
 /// II.22.26
 #[derive(Debug, PartialEq, Clone)]
 pub struct MethodDef {
 	pub rva: u32,
-	// TODO(dmi): @incomplete See MethodImplAttributes II.23.1.10
-	impl_flags: u16,
-	// TODO(dmi): @incomplete See MethodAttributes II.23.1.10
-	flags: u16,
+	/// See MethodImplAttributes II.23.1.11.
+	pub impl_flags: u16,
+	/// See MethodAttributes II.23.1.10.
+	pub flags: u16,
 	pub name: StringIndex,
 	pub sig: BlobIndex,
 	// TODO(dmi): @incomplete It marks the first of a contiguous run of
@@ -709,13 +1084,22 @@ impl MethodDef {
 			param_list,
 		})
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.rva);
+		out.write(self.impl_flags);
+		out.write(self.flags);
+		self.name.write(header, out);
+		self.sig.write(header, out);
+		self.param_list.write(header, out);
+	}
 }
 
 /// II.22.33
 #[derive(Debug, PartialEq, Clone)]
 pub struct Param {
-	// TODO(dmi): @incomplete See ParamAttributes II.23.1.13
-	flags: u16,
+	/// See ParamAttributes II.23.1.13.
+	pub flags: u16,
 	pub seq: u16,
 	pub name: StringIndex,
 }
@@ -727,6 +1111,12 @@ impl Param {
 		let name = StringIndex::parse(header, data, offset)?;
 		Ok(Param { flags, seq, name })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		out.write(self.seq);
+		self.name.write(header, out);
+	}
 }
 
 /// II.22.23
@@ -742,6 +1132,11 @@ impl InterfaceImpl {
 		let iface = TypeDefOrRef::parse(header, data, offset)?;
 		Ok(InterfaceImpl { class, iface })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.class.write(header, out);
+		self.iface.write(header, out);
+	}
 }
 
 /// II.22.25
@@ -759,13 +1154,19 @@ impl MemberRef {
 		let sig = BlobIndex::parse(header, data, offset)?;
 		Ok(MemberRef { class, name, sig })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.class.write(header, out);
+		self.name.write(header, out);
+		self.sig.write(header, out);
+	}
 }
 
 /// II.22.9
 #[derive(Debug, PartialEq, Clone)]
 pub struct Constant {
 	// TODO(dmi) @incomplete See II.23.1.6
-	ty: u8,
+	pub ty: u8,
 	pub parent: HasConstant,
 	pub value: BlobIndex,
 }
@@ -779,6 +1180,14 @@ impl Constant {
 		let value = BlobIndex::parse(header, data, offset)?;
 		Ok(Constant { ty, parent, value })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.ty);
+		// Padding.
+		out.write(0u8);
+		self.parent.write(header, out);
+		self.value.write(header, out);
+	}
 }
 
 /// II.22.10
@@ -796,6 +1205,12 @@ impl CustomAttribute {
 		let value = BlobIndex::parse(header, data, offset)?;
 		Ok(CustomAttribute { parent, ty, value })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.parent.write(header, out);
+		self.ty.write(header, out);
+		self.value.write(header, out);
+	}
 }
 
 /// II.22.17
@@ -811,13 +1226,18 @@ impl FieldMarshal {
 		let native_ty = BlobIndex::parse(header, data, offset)?;
 		Ok(FieldMarshal { parent, native_ty })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.parent.write(header, out);
+		self.native_ty.write(header, out);
+	}
 }
 
 /// II.22.11
 #[derive(Debug, PartialEq, Clone)]
 pub struct DeclSecutity {
-	// TODO(dmi): @incomplete See table in II.22.11
-	action: u16,
+	/// See SECURITY_ACTION_* and II.22.11.
+	pub action: u16,
 	pub parent: HasDeclSecurity,
 	pub permission_set: BlobIndex,
 }
@@ -829,6 +1249,12 @@ impl DeclSecutity {
 		let permission_set = BlobIndex::parse(header, data, offset)?;
 		Ok(DeclSecutity { action, parent, permission_set })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.action);
+		self.parent.write(header, out);
+		self.permission_set.write(header, out);
+	}
 }
 
 /// II.22.8
@@ -846,6 +1272,12 @@ impl ClassLayout {
 		let parent = TypeDefIndex::parse(header, data, offset)?;
 		Ok(ClassLayout { packing_size, class_size, parent })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.packing_size);
+		out.write(self.class_size);
+		self.parent.write(header, out);
+	}
 }
 
 /// II.22.16
@@ -861,6 +1293,11 @@ impl FieldLayout {
 		let field = FieldIndex::parse(header, data, offset)?;
 		Ok(FieldLayout { offset: f_offset, field })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.offset);
+		self.field.write(header, out);
+	}
 }
 
 /// II.22.36
@@ -874,6 +1311,10 @@ impl StandAloneSig {
 		let sig = BlobIndex::parse(header, data, offset)?;
 		Ok(StandAloneSig { sig })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.sig.write(header, out);
+	}
 }
 
 /// II.22.12
@@ -884,7 +1325,7 @@ pub struct EventMap {
 	/// type. That run continues to the smaller of:
 	/// - the last row othe events
 	/// - the next run of Events, found by inspecting the event_list of
-	/// the next row in event_maps
+	///   the next row in event_maps
 	pub event_list: EventIndex,
 }
 
@@ -894,13 +1335,18 @@ impl EventMap {
 		let event_list = EventIndex::parse(header, data, offset)?;
 		Ok(EventMap { parent, event_list })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.parent.write(header, out);
+		self.event_list.write(header, out);
+	}
 }
 
 /// II.22.13
 #[derive(Debug, PartialEq, Clone)]
 pub struct Event {
 	// TODO(dmi): @incomplete See EventAttributes II.23.1.4
-	flags: u16,
+	pub flags: u16,
 	pub name: StringIndex,
 	pub ty: TypeDefOrRef,
 }
@@ -912,6 +1358,12 @@ impl Event {
 		let ty = TypeDefOrRef::parse(header, data, offset)?;
 		Ok(Event { flags, name, ty })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		self.name.write(header, out);
+		self.ty.write(header, out);
+	}
 }
 
 /// II.22.35
@@ -922,7 +1374,7 @@ pub struct PropertyMap {
 	/// Parent. The run continues to the smaller of:
 	/// - the last row of the Property table
 	/// - the next run of Properties, found by inspecting the
-	/// property_list of the next row in property_maps
+	///   property_list of the next row in property_maps
 	pub property_list: PropertyIndex,
 }
 
@@ -932,13 +1384,18 @@ impl PropertyMap {
 		let property_list = PropertyIndex::parse(header, data, offset)?;
 		Ok(PropertyMap { parent, property_list })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.parent.write(header, out);
+		self.property_list.write(header, out);
+	}
 }
 
 /// II.22.34
 #[derive(Debug, PartialEq, Clone)]
 pub struct Property {
 	// TODO(dmi): @incomplete See PropertyAttributes II.23.1.14
-	flags: u16,
+	pub flags: u16,
 	pub name: StringIndex,
 	/// The name of this column is misleading. It does not index a TypeDef or
 	/// TypeRef table - instead it indexes the signature in the Blob heap of
@@ -953,13 +1410,19 @@ impl Property {
 		let ty = BlobIndex::parse(header, data, offset)?;
 		Ok(Property { flags, name, ty })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		self.name.write(header, out);
+		self.ty.write(header, out);
+	}
 }
 
 /// II.22.28
 #[derive(Debug, PartialEq, Clone)]
 pub struct MethodSemantics {
 	// TODO(dmi): @incomplete See MethodSemanticsAttributes II.23.1.12
-	semantics: u16,
+	pub semantics: u16,
 	pub method: MethodDefIndex,
 	pub assoc: HasSemantics,
 }
@@ -971,6 +1434,12 @@ impl MethodSemantics {
 		let assoc = HasSemantics::parse(header, data, offset)?;
 		Ok(MethodSemantics { semantics, method, assoc })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.semantics);
+		self.method.write(header, out);
+		self.assoc.write(header, out);
+	}
 }
 
 /// II.22.27
@@ -988,6 +1457,12 @@ impl MethodImpl {
 		let decl = MethodDefOrRef::parse(header, data, offset)?;
 		Ok(MethodImpl { class, body, decl })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.class.write(header, out);
+		self.body.write(header, out);
+		self.decl.write(header, out);
+	}
 }
 
 /// II.22.31
@@ -1001,6 +1476,10 @@ impl ModuleRef {
 		let name = StringIndex::parse(header, data, offset)?;
 		Ok(ModuleRef { name })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.name.write(header, out);
+	}
 }
 
 /// II.22.39
@@ -1019,13 +1498,17 @@ impl TypeSpec {
 		let sig = BlobIndex::parse(header, data, offset)?;
 		Ok(TypeSpec { sig })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.sig.write(header, out);
+	}
 }
 
 /// II.22.22
 #[derive(Debug, PartialEq, Clone)]
 pub struct ImplMap {
 	// TODO(dmi): @incomplete See PInvoke.Attributes II.23.18
-	flags: u16,
+	pub flags: u16,
 	pub member_fwd: MemberForwarded,
 	pub name: StringIndex,
 	pub scope: ModuleRefIndex,
@@ -1039,6 +1522,13 @@ impl ImplMap {
 		let scope = ModuleRefIndex::parse(header, data, offset)?;
 		Ok(ImplMap { flags, member_fwd, name, scope })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		self.member_fwd.write(header, out);
+		self.name.write(header, out);
+		self.scope.write(header, out);
+	}
 }
 
 /// II.22.18
@@ -1054,6 +1544,11 @@ impl FieldRVA {
 		let field = FieldIndex::parse(header, data, offset)?;
 		Ok(FieldRVA { rva, field })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.rva);
+		self.field.write(header, out);
+	}
 }
 
 /// II.22.2
@@ -1073,6 +1568,13 @@ impl HashAlgo {
 			_ => Err("Unknown hash algorithm.")?,
 		}
 	}
+
+	fn write(&self, out: &mut Vec<u8>) {
+		match self {
+			HashAlgo::MD5  => out.write(0x8003u32),
+			HashAlgo::SHA1 => out.write(0x8004u32),
+		}
+	}
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -1083,7 +1585,7 @@ pub struct Assembly {
 	pub build_number: u16,
 	pub revision_number: u16,
 	// TODO(dmi): @incomplete See AssemblyFlags II.23.1.2
-	flags: u32,
+	pub flags: u32,
 	pub pub_key: BlobIndex,
 	pub name: StringIndex,
 	pub culture: StringIndex,
@@ -1113,6 +1615,18 @@ impl Assembly {
 			culture,
 		})
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.hash_algo.write(out);
+		out.write(self.major_version);
+		out.write(self.minor_version);
+		out.write(self.build_number);
+		out.write(self.revision_number);
+		out.write(self.flags);
+		self.pub_key.write(header, out);
+		self.name.write(header, out);
+		self.culture.write(header, out);
+	}
 }
 
 /// II.22.5
@@ -1123,7 +1637,7 @@ pub struct AssemblyRef {
 	pub build_number: u16,
 	pub revision_number: u16,
 	// TODO(dmi): @incomplete See AssemblyFlags II.23.1.2
-	flags: u32,
+	pub flags: u32,
 	/// Indicating the public key or token that identifies the author
 	/// of this Assembly.
 	pub pub_key_or_token: BlobIndex,
@@ -1156,13 +1670,25 @@ impl AssemblyRef {
 			hash,
 		})
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.major_version);
+		out.write(self.minor_version);
+		out.write(self.build_number);
+		out.write(self.revision_number);
+		out.write(self.flags);
+		self.pub_key_or_token.write(header, out);
+		self.name.write(header, out);
+		self.culture.write(header, out);
+		self.hash.write(header, out);
+	}
 }
 
 /// II.22.19
 #[derive(Debug, PartialEq, Clone)]
 pub struct File {
 	// TODO(dmi): @incomplete See FileAttributes II.23.1.6
-	flags: u32,
+	pub flags: u32,
 	pub name: StringIndex,
 	pub hash: BlobIndex,
 }
@@ -1174,6 +1700,12 @@ impl File {
 		let hash = BlobIndex::parse(header, data, offset)?;
 		Ok(File { flags, name, hash  })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		self.name.write(header, out);
+		self.hash.write(header, out);
+	}
 }
 
 /// II.22.14
@@ -1186,7 +1718,7 @@ impl File {
 #[derive(Debug, PartialEq, Clone)]
 pub struct ExportedType {
 	// TODO(dmi): @incomplete See TypeAttributes II.23.1.15
-	flags: u32,
+	pub flags: u32,
 	/// This column is used as a hint only. If the entry in the target TypeDef
 	/// table matches the TypeName and TypeNamespace entries in this table,
 	/// resolution has succeeded.  But if there is a mismatch, the CLI shall
@@ -1213,6 +1745,14 @@ impl ExportedType {
 			implementation,
 		})
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.flags);
+		self.type_def_id.write(header, out);
+		self.name.write(header, out);
+		self.namespace.write(header, out);
+		self.implementation.write(header, out);
+	}
 }
 
 /// II.22.24
@@ -1222,7 +1762,7 @@ pub struct ManifestResource {
 	/// this resource record begins.
 	pub offset: u32,
 	// TODO(dmi): @incomplete See ManifestResourceAttributes II23.1.9
-	flags: u32,
+	pub flags: u32,
 	pub name: StringIndex,
 	/// Specifies which file holds this resource.
 	pub implementation: Implementation,
@@ -1236,6 +1776,13 @@ impl ManifestResource {
 		let implementation = Implementation::parse(header, data, offset)?;
 		Ok(ManifestResource { offset: r_offset, flags, name, implementation })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.offset);
+		out.write(self.flags);
+		self.name.write(header, out);
+		self.implementation.write(header, out);
+	}
 }
 
 /// II.22.32
@@ -1251,6 +1798,11 @@ impl NestedClass {
 		let enclosing = TypeDefIndex::parse(header, data, offset)?;
 		Ok(NestedClass { nested, enclosing })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.nested.write(header, out);
+		self.enclosing.write(header, out);
+	}
 }
 
 /// II.22.20
@@ -1260,7 +1812,7 @@ pub struct GenericParam {
 	/// zero.
 	pub number: u16,
 	// TODO(dmi): @incomplete See GenericParamAttributes II.23.1.7
-	flags: u16,
+	pub flags: u16,
 	pub owner: TypeOrMethodDef,
 	/// This is purely descriptive and is used only by source language
 	/// compilers and by Reflection.
@@ -1275,6 +1827,13 @@ impl GenericParam {
 		let name = StringIndex::parse(header, data, offset)?;
 		Ok(GenericParam { number, flags, owner, name })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		out.write(self.number);
+		out.write(self.flags);
+		self.owner.write(header, out);
+		self.name.write(header, out);
+	}
 }
 
 /// II.22.29
@@ -1291,6 +1850,11 @@ impl MethodSpec {
 		let inst = BlobIndex::parse(header, data, offset)?;
 		Ok(MethodSpec { method, inst })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.method.write(header, out);
+		self.inst.write(header, out);
+	}
 }
 
 /// II.22.21
@@ -1310,6 +1874,11 @@ impl GenericParamConstraint {
 		let constraint = TypeDefOrRef::parse(header, data, offset)?;
 		Ok(GenericParamConstraint { owner, constraint })
 	}
+
+	fn write(&self, header: &Tables, out: &mut Vec<u8>) {
+		self.owner.write(header, out);
+		self.constraint.write(header, out);
+	}
 }
 
 fn empty<T>() -> Box<[T]> {
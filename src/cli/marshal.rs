@@ -0,0 +1,264 @@
+use std::fmt;
+
+use crate::Result;
+use crate::error::Error;
+use crate::buf::Reading;
+use crate::cli::constants::*;
+use crate::cli::{TableRows, HasFieldMarshall, blob_at, read_compressed_u32};
+
+/// A marshalling descriptor of a field or parameter, II.23.4.
+#[derive(Debug, PartialEq, Clone)]
+pub enum NativeType {
+	Boolean,
+	I1,
+	U1,
+	I2,
+	U2,
+	I4,
+	U4,
+	I8,
+	U8,
+	R4,
+	R8,
+	Int,
+	UInt,
+	Currency,
+	VariantBool,
+	Error,
+	/// Pointer to an ANSI null-terminated string.
+	LpStr,
+	/// Pointer to an UTF-16 null-terminated string.
+	LpWStr,
+	LpTStr,
+	LpUtf8Str,
+	BStr,
+	AnsiBStr,
+	TBStr,
+	ByValStr,
+	/// String stored inline, of fixed number of characters.
+	FixedSysString(u32),
+	/// Function pointer.
+	Func,
+	AsAny,
+	Struct,
+	LpStruct,
+	Interface,
+	IUnknown,
+	IDispatch,
+	/// Pointer to the first element of a native array.
+	Array {
+		/// None stands for the element type derived from the managed one.
+		elem: Option<Box<NativeType>>,
+		/// 0-based index of the parameter holding the number of elements.
+		size_param: Option<u32>,
+		/// Number of elements, or additional ones if `size_param` is set.
+		size: Option<u32>,
+	},
+	/// Array stored inline, of fixed number of elements.
+	FixedArray {
+		size: u32,
+		elem: Option<Box<NativeType>>,
+	},
+	SafeArray {
+		/// VARENUM of elements.
+		var_type: Option<u32>,
+		user_type: Option<String>,
+	},
+	CustomMarshaler {
+		guid: String,
+		native_type: String,
+		/// Name of the type implementing ICustomMarshaler.
+		marshaler: String,
+		cookie: String,
+	},
+}
+
+impl NativeType {
+	pub fn parse(data: &[u8]) -> Result<NativeType> {
+		NativeType::parse_at(data, &mut 0)
+	}
+
+	fn parse_at(data: &[u8], offset: &mut usize) -> Result<NativeType> {
+		let ty = match data.read::<u8>(offset)? {
+			NATIVE_TYPE_BOOLEAN     => NativeType::Boolean,
+			NATIVE_TYPE_I1          => NativeType::I1,
+			NATIVE_TYPE_U1          => NativeType::U1,
+			NATIVE_TYPE_I2          => NativeType::I2,
+			NATIVE_TYPE_U2          => NativeType::U2,
+			NATIVE_TYPE_I4          => NativeType::I4,
+			NATIVE_TYPE_U4          => NativeType::U4,
+			NATIVE_TYPE_I8          => NativeType::I8,
+			NATIVE_TYPE_U8          => NativeType::U8,
+			NATIVE_TYPE_R4          => NativeType::R4,
+			NATIVE_TYPE_R8          => NativeType::R8,
+			NATIVE_TYPE_INT         => NativeType::Int,
+			NATIVE_TYPE_UINT        => NativeType::UInt,
+			NATIVE_TYPE_CURRENCY    => NativeType::Currency,
+			NATIVE_TYPE_VARIANTBOOL => NativeType::VariantBool,
+			NATIVE_TYPE_ERROR       => NativeType::Error,
+			NATIVE_TYPE_LPSTR       => NativeType::LpStr,
+			NATIVE_TYPE_LPWSTR      => NativeType::LpWStr,
+			NATIVE_TYPE_LPTSTR      => NativeType::LpTStr,
+			NATIVE_TYPE_LPUTF8STR   => NativeType::LpUtf8Str,
+			NATIVE_TYPE_BSTR        => NativeType::BStr,
+			NATIVE_TYPE_ANSIBSTR    => NativeType::AnsiBStr,
+			NATIVE_TYPE_TBSTR       => NativeType::TBStr,
+			NATIVE_TYPE_BYVALSTR    => NativeType::ByValStr,
+			NATIVE_TYPE_FUNC        => NativeType::Func,
+			NATIVE_TYPE_ASANY       => NativeType::AsAny,
+			NATIVE_TYPE_STRUCT      => NativeType::Struct,
+			NATIVE_TYPE_LPSTRUCT    => NativeType::LpStruct,
+			NATIVE_TYPE_INTERFACE   => NativeType::Interface,
+			NATIVE_TYPE_IUNKNOWN    => NativeType::IUnknown,
+			NATIVE_TYPE_IDISPATCH   => NativeType::IDispatch,
+			NATIVE_TYPE_FIXEDSYSSTRING => NativeType::FixedSysString(read_compressed_u32(data, offset)?),
+			NATIVE_TYPE_ARRAY => {
+				let elem = NativeType::parse_elem(data, offset)?;
+				let size_param = read_optional(data, offset)?;
+				let size = read_optional(data, offset)?;
+				NativeType::Array { elem, size_param, size }
+			},
+			NATIVE_TYPE_FIXEDARRAY => {
+				let size = read_compressed_u32(data, offset)?;
+				let elem = if *offset < data.len() {
+					NativeType::parse_elem(data, offset)?
+				} else {
+					None
+				};
+				NativeType::FixedArray { size, elem }
+			},
+			NATIVE_TYPE_SAFEARRAY => {
+				let var_type = read_optional(data, offset)?;
+				let user_type = if *offset < data.len() {
+					Some(read_string(data, offset)?)
+				} else {
+					None
+				};
+				NativeType::SafeArray { var_type, user_type }
+			},
+			NATIVE_TYPE_CUSTOMMARSHALER => NativeType::CustomMarshaler {
+				guid: read_string(data, offset)?,
+				native_type: read_string(data, offset)?,
+				marshaler: read_string(data, offset)?,
+				cookie: read_string(data, offset)?,
+			},
+			_ => Err("Unknown native type.")?,
+		};
+
+		Ok(ty)
+	}
+
+	fn parse_elem(data: &[u8], offset: &mut usize) -> Result<Option<Box<NativeType>>> {
+		match data.read_at::<u8>(*offset) {
+			Ok(NATIVE_TYPE_MAX) => {
+				*offset += 1;
+				Ok(None)
+			},
+			Ok(_) => Ok(Some(Box::new(NativeType::parse_at(data, offset)?))),
+			Err(_) => Ok(None),
+		}
+	}
+}
+
+/// Trailing items of a descriptor may be omitted.
+fn read_optional(data: &[u8], offset: &mut usize) -> Result<Option<u32>> {
+	if *offset < data.len() {
+		Ok(Some(read_compressed_u32(data, offset)?))
+	} else {
+		Ok(None)
+	}
+}
+
+/// Reads a string prefixed by its compressed length.
+fn read_string(data: &[u8], offset: &mut usize) -> Result<String> {
+	let len = read_compressed_u32(data, offset)? as usize;
+	let bytes = data.get(*offset..*offset + len).ok_or("Marshalling string exceeds the blob.")?;
+	*offset += len;
+
+	let s = std::str::from_utf8(bytes)
+		.map_err(|_| Error::General("Marshalling string is not a valid utf-8 string."))?;
+	Ok(s.to_owned())
+}
+
+/// Displays the descriptor as ILAsm writes it inside `marshal(...)`.
+impl fmt::Display for NativeType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			NativeType::Boolean        => write!(f, "bool"),
+			NativeType::I1             => write!(f, "int8"),
+			NativeType::U1             => write!(f, "unsigned int8"),
+			NativeType::I2             => write!(f, "int16"),
+			NativeType::U2             => write!(f, "unsigned int16"),
+			NativeType::I4             => write!(f, "int32"),
+			NativeType::U4             => write!(f, "unsigned int32"),
+			NativeType::I8             => write!(f, "int64"),
+			NativeType::U8             => write!(f, "unsigned int64"),
+			NativeType::R4             => write!(f, "float32"),
+			NativeType::R8             => write!(f, "float64"),
+			NativeType::Int            => write!(f, "int"),
+			NativeType::UInt           => write!(f, "unsigned int"),
+			NativeType::Currency       => write!(f, "currency"),
+			NativeType::VariantBool    => write!(f, "variant bool"),
+			NativeType::Error          => write!(f, "error"),
+			NativeType::LpStr          => write!(f, "lpstr"),
+			NativeType::LpWStr         => write!(f, "lpwstr"),
+			NativeType::LpTStr         => write!(f, "lptstr"),
+			NativeType::LpUtf8Str      => write!(f, "lpstr /* utf-8 */"),
+			NativeType::BStr           => write!(f, "bstr"),
+			NativeType::AnsiBStr       => write!(f, "ansi bstr"),
+			NativeType::TBStr          => write!(f, "tbstr"),
+			NativeType::ByValStr       => write!(f, "byvalstr"),
+			NativeType::FixedSysString(n) => write!(f, "fixed sysstring [{}]", n),
+			NativeType::Func           => write!(f, "method"),
+			NativeType::AsAny          => write!(f, "as any"),
+			NativeType::Struct         => write!(f, "struct"),
+			NativeType::LpStruct       => write!(f, "lpstruct"),
+			NativeType::Interface      => write!(f, "interface"),
+			NativeType::IUnknown       => write!(f, "iunknown"),
+			NativeType::IDispatch      => write!(f, "idispatch"),
+			NativeType::Array { elem, size_param, size } => {
+				if let Some(elem) = elem {
+					write!(f, "{}", elem)?;
+				}
+				write!(f, "[")?;
+				if let Some(size) = size {
+					write!(f, "{}", size)?;
+				}
+				if let Some(p) = size_param {
+					write!(f, "+{}", p)?;
+				}
+				write!(f, "]")
+			},
+			NativeType::FixedArray { size, elem } => {
+				write!(f, "fixed array [{}]", size)?;
+				if let Some(elem) = elem {
+					write!(f, " {}", elem)?;
+				}
+				Ok(())
+			},
+			NativeType::SafeArray { var_type, user_type } => {
+				write!(f, "safearray")?;
+				if let Some(t) = var_type {
+					write!(f, " /* vt {} */", t)?;
+				}
+				if let Some(t) = user_type {
+					write!(f, ", {:?}", t)?;
+				}
+				Ok(())
+			},
+			NativeType::CustomMarshaler { marshaler, cookie, .. } => {
+				write!(f, "custom({:?}, {:?})", marshaler, cookie)
+			},
+		}
+	}
+}
+
+impl TableRows {
+	/// Returns the marshalling descriptor of a field or parameter if it has one.
+	pub fn marshal_of(&self, blobs: &[u8], parent: HasFieldMarshall) -> Result<Option<NativeType>> {
+		match self.field_marshals.iter().find(|m| m.parent == parent) {
+			Some(m) => Ok(Some(NativeType::parse(blob_at(blobs, m.native_ty.into_index())?)?)),
+			None => Ok(None),
+		}
+	}
+}
@@ -0,0 +1,330 @@
+use std::convert::TryFrom;
+
+use log::{debug};
+
+use crate::Result;
+use crate::buf::{Reading, Writing};
+use crate::cli::MetadataToken;
+use crate::utils::align_up;
+
+// Taken from ECMA II.25.4
+
+const METHOD_TINY_FORMAT: u8    = 0x2;
+const METHOD_FAT_FORMAT: u16    = 0x3;
+const METHOD_FORMAT_MASK: u16   = 0x3;
+const METHOD_MORE_SECTS: u16    = 0x8;
+const METHOD_INIT_LOCALS: u16   = 0x10;
+
+// MaxStack of tiny headers.
+const TINY_MAX_STACK: u16 = 8;
+const TINY_MAX_SIZE: usize = 63;
+const FAT_HEADER_SIZE: u16 = 12;
+
+const SECTION_EH_TABLE: u8      = 0x1;
+const SECTION_OPT_IL_TABLE: u8  = 0x2;
+const SECTION_FAT_FORMAT: u8    = 0x40;
+const SECTION_MORE_SECTS: u8    = 0x80;
+
+const CLAUSE_EXCEPTION: u32     = 0x0;
+const CLAUSE_FILTER: u32        = 0x1;
+const CLAUSE_FINALLY: u32       = 0x2;
+const CLAUSE_FAULT: u32         = 0x4;
+
+const SMALL_CLAUSE_SIZE: usize  = 12;
+const FAT_CLAUSE_SIZE: usize    = 24;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Handler {
+	/// Catches exceptions of the class.
+	Catch(MetadataToken),
+	/// Offset of the filter code, which decides whether the handler runs.
+	Filter(u32),
+	Finally,
+	Fault,
+}
+
+/// II.25.4.6
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ExceptionClause {
+	pub handler: Handler,
+	pub try_offset: u32,
+	pub try_length: u32,
+	pub handler_offset: u32,
+	pub handler_length: u32,
+}
+
+impl ExceptionClause {
+	/// Ends are checked against the code size when the clause is parsed.
+	pub fn try_end(&self) -> u32 {
+		self.try_offset + self.try_length
+	}
+
+	pub fn handler_end(&self) -> u32 {
+		self.handler_offset + self.handler_length
+	}
+}
+
+/// II.25.4
+#[derive(Debug, PartialEq, Clone)]
+pub struct MethodBody<'a> {
+	pub max_stack: u16,
+	/// Locals shall be zero-initialized.
+	pub init_locals: bool,
+	/// StandAloneSig token of locals, zero if there are none.
+	pub local_var_sig_tok: u32,
+	pub code: &'a [u8],
+	pub clauses: Box<[ExceptionClause]>,
+}
+
+impl<'a> MethodBody<'a> {
+	/// Parses a body starting at `data`, which may extend beyond the body.
+	pub fn parse(data: &'a [u8]) -> Result<MethodBody<'a>> {
+		let b: u8 = data.read_at(0)?;
+
+		if b & METHOD_FORMAT_MASK as u8 == METHOD_TINY_FORMAT {
+			let size = (b >> 2) as usize;
+			debug!("Method is CorILMethod_TinyFormat: {} byte(s).", size);
+			let code = data.get(1..1 + size).ok_or("Method code exceeds the image.")?;
+			return Ok(MethodBody {
+				max_stack: TINY_MAX_STACK,
+				init_locals: false,
+				local_var_sig_tok: 0,
+				code,
+				clauses: Box::new([]),
+			});
+		}
+
+		let offset = &mut 0usize;
+		let flags_and_size: u16 = data.read(offset)?;
+		if flags_and_size & METHOD_FORMAT_MASK != METHOD_FAT_FORMAT {
+			Err("Invalid method header.")?;
+		}
+
+		let flags = flags_and_size & 0x0FFF;
+		let header_size = (flags_and_size >> 12) as usize * 4;
+		let max_stack: u16 = data.read(offset)?;
+		let code_size = data.read::<u32>(offset)? as usize;
+		let local_var_sig_tok: u32 = data.read(offset)?;
+		debug!("Method is CorILMethod_FatFormat: {} byte(s), max stack {}.", code_size, max_stack);
+
+		if header_size < 12 {
+			Err("Fat method header is too small.")?;
+		}
+		if local_var_sig_tok == 0 && flags & METHOD_INIT_LOCALS != 0 {
+			debug!("Method requires zero-initialized locals, but has none.");
+		}
+
+		let code = data.get(header_size..header_size + code_size).ok_or("Method code exceeds the image.")?;
+
+		let mut clauses = Vec::new();
+		if flags & METHOD_MORE_SECTS != 0 {
+			*offset = align_up(header_size + code_size, 4);
+			loop {
+				let more = parse_section(data, offset, code_size, &mut clauses)?;
+				if !more {
+					break;
+				}
+				*offset = align_up(*offset, 4);
+			}
+		}
+
+		Ok(MethodBody {
+			max_stack,
+			init_locals: flags & METHOD_INIT_LOCALS != 0,
+			local_var_sig_tok,
+			code,
+			clauses: clauses.into_boxed_slice(),
+		})
+	}
+
+	/// Writes the body with the smallest header and clause format that fit.
+	pub fn write(&self, out: &mut Vec<u8>) {
+		let is_tiny = self.code.len() <= TINY_MAX_SIZE
+			&& self.max_stack <= TINY_MAX_STACK
+			&& self.local_var_sig_tok == 0
+			&& self.clauses.is_empty();
+		if is_tiny {
+			out.push((self.code.len() as u8) << 2 | METHOD_TINY_FORMAT);
+			out.extend_from_slice(self.code);
+			return;
+		}
+
+		let mut flags = METHOD_FAT_FORMAT;
+		if self.init_locals {
+			flags |= METHOD_INIT_LOCALS;
+		}
+		if !self.clauses.is_empty() {
+			flags |= METHOD_MORE_SECTS;
+		}
+
+		let start = out.len();
+		out.write((FAT_HEADER_SIZE / 4) << 12 | flags);
+		out.write(self.max_stack);
+		out.write(self.code.len() as u32);
+		out.write(self.local_var_sig_tok);
+		out.extend_from_slice(self.code);
+
+		if self.clauses.is_empty() {
+			return;
+		}
+
+		// Offsets are relative to the body, which is 4-byte aligned itself.
+		out.resize(start + align_up(out.len() - start, 4), 0);
+
+		let is_small = self.clauses.len() * SMALL_CLAUSE_SIZE + 4 <= 0xFF
+			&& self.clauses.iter().all(|c| {
+				c.try_offset <= 0xFFFF && c.try_length <= 0xFF
+					&& c.handler_offset <= 0xFFFF && c.handler_length <= 0xFF
+			});
+		if is_small {
+			out.write(SECTION_EH_TABLE);
+			out.write((self.clauses.len() * SMALL_CLAUSE_SIZE + 4) as u8);
+			out.write(0u16);
+		} else {
+			let size = self.clauses.len() * FAT_CLAUSE_SIZE + 4;
+			out.write(SECTION_EH_TABLE | SECTION_FAT_FORMAT);
+			out.write(size as u16);
+			out.write((size >> 16) as u8);
+		}
+
+		for c in self.clauses.iter() {
+			let (flags, class_or_filter) = match c.handler {
+				Handler::Catch(t)  => (CLAUSE_EXCEPTION, t.value()),
+				Handler::Filter(o) => (CLAUSE_FILTER, o),
+				Handler::Finally   => (CLAUSE_FINALLY, 0),
+				Handler::Fault     => (CLAUSE_FAULT, 0),
+			};
+			if is_small {
+				out.write(flags as u16);
+				out.write(c.try_offset as u16);
+				out.write(c.try_length as u8);
+				out.write(c.handler_offset as u16);
+				out.write(c.handler_length as u8);
+			} else {
+				out.write(flags);
+				out.write(c.try_offset);
+				out.write(c.try_length);
+				out.write(c.handler_offset);
+				out.write(c.handler_length);
+			}
+			out.write(class_or_filter);
+		}
+	}
+}
+
+/// Parses a data section, II.25.4.5. Returns whether more sections follow.
+fn parse_section(data: &[u8], offset: &mut usize, code_size: usize, clauses: &mut Vec<ExceptionClause>) -> Result<bool> {
+	let kind: u8 = data.read(offset)?;
+
+	let is_fat = kind & SECTION_FAT_FORMAT != 0;
+	let size = if is_fat {
+		let lo: u16 = data.read(offset)?;
+		let hi: u8 = data.read(offset)?;
+		(hi as usize) << 16 | lo as usize
+	} else {
+		let size: u8 = data.read(offset)?;
+		// Reserved.
+		*offset += 2;
+		size as usize
+	};
+
+	if size < 4 {
+		Err("Method data section is too small.")?;
+	}
+
+	if kind & SECTION_OPT_IL_TABLE != 0 {
+		Err("Method has an OptIL section.")?;
+	}
+	if kind & SECTION_EH_TABLE == 0 {
+		// Unknown sections are skipped.
+		*offset += size - 4;
+		return Ok(kind & SECTION_MORE_SECTS != 0);
+	}
+
+	let clause_size = if is_fat { FAT_CLAUSE_SIZE } else { SMALL_CLAUSE_SIZE };
+	let n = (size - 4) / clause_size;
+	for _ in 0..n {
+		let (flags, try_offset, try_length, handler_offset, handler_length) = if is_fat {
+			(
+				data.read::<u32>(offset)?,
+				data.read::<u32>(offset)?,
+				data.read::<u32>(offset)?,
+				data.read::<u32>(offset)?,
+				data.read::<u32>(offset)?,
+			)
+		} else {
+			(
+				data.read::<u16>(offset)? as u32,
+				data.read::<u16>(offset)? as u32,
+				data.read::<u8>(offset)? as u32,
+				data.read::<u16>(offset)? as u32,
+				data.read::<u8>(offset)? as u32,
+			)
+		};
+		let class_or_filter: u32 = data.read(offset)?;
+
+		let handler = match flags {
+			CLAUSE_EXCEPTION => Handler::Catch(MetadataToken::try_from(class_or_filter)?),
+			CLAUSE_FILTER    => Handler::Filter(class_or_filter),
+			CLAUSE_FINALLY   => Handler::Finally,
+			CLAUSE_FAULT     => Handler::Fault,
+			_ => Err("Unknown exception clause kind.")?,
+		};
+
+		let within = |start: u32, length: u32| start.checked_add(length).is_some_and(|end| end as usize <= code_size);
+		if !within(try_offset, try_length) {
+			Err("Protected block of an exception clause exceeds the code.")?;
+		}
+		if !within(handler_offset, handler_length) {
+			Err("Handler of an exception clause exceeds the code.")?;
+		}
+
+		clauses.push(ExceptionClause { handler, try_offset, try_length, handler_offset, handler_length });
+	}
+
+	Ok(kind & SECTION_MORE_SECTS != 0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A fat body of a single `ret` with one fat finally clause.
+	fn body(try_offset: u32, try_length: u32, handler_offset: u32, handler_length: u32) -> Vec<u8> {
+		let mut data = Vec::new();
+		data.write((FAT_HEADER_SIZE / 4) << 12 | METHOD_FAT_FORMAT | METHOD_MORE_SECTS);
+		data.write(8u16);
+		data.write(1u32);
+		data.write(0u32);
+		data.extend_from_slice(&[0x2A, 0, 0, 0]);
+		data.write(SECTION_EH_TABLE | SECTION_FAT_FORMAT);
+		data.write((FAT_CLAUSE_SIZE + 4) as u16);
+		data.write(0u8);
+		for x in [CLAUSE_FINALLY, try_offset, try_length, handler_offset, handler_length, 0] {
+			data.write(x);
+		}
+		data
+	}
+
+	#[test]
+	fn clauses_within_code() {
+		let body = body(0, 1, 0, 1);
+		let body = MethodBody::parse(&body).expect("Failed to parse.");
+		assert_eq!(body.clauses[0].try_end(), 1);
+		assert_eq!(body.clauses[0].handler_end(), 1);
+	}
+
+	#[test]
+	fn clauses_exceeding_code() {
+		for (try_offset, try_length, handler_offset, handler_length) in [
+			(0, u32::MAX, 0, 1),
+			(u32::MAX, 1, 0, 1),
+			(0, 2, 0, 1),
+			(0, 1, 1, u32::MAX),
+			(0, 1, 1, 1),
+		] {
+			let body = body(try_offset, try_length, handler_offset, handler_length);
+			assert!(MethodBody::parse(&body).is_err(), "{:?}", (try_offset, try_length, handler_offset, handler_length));
+		}
+	}
+}
@@ -1,6 +1,9 @@
 mod constants;
 pub use self::constants::*;
 
+mod flags;
+pub use self::flags::*;
+
 mod header;
 pub use self::header::*;
 
@@ -21,3 +24,27 @@ pub use self::token::*;
 
 mod il;
 pub use self::il::*;
+
+mod validation;
+pub use self::validation::*;
+
+mod signatures;
+pub use self::signatures::*;
+
+mod attributes;
+pub use self::attributes::*;
+
+mod values;
+pub use self::values::*;
+
+mod marshal;
+pub use self::marshal::*;
+
+mod security;
+pub use self::security::*;
+
+mod instruction;
+pub use self::instruction::*;
+
+mod method_body;
+pub use self::method_body::*;
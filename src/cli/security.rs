@@ -0,0 +1,112 @@
+use crate::Result;
+use crate::error::Error;
+use crate::cli::flags::*;
+use crate::cli::{
+	TableRows, DeclSecutity, HasDeclSecurity, AttributeDecoder, NamedArg,
+	blob_at, read_compressed_u32, read_ser_string,
+};
+
+/// A decoded permission set of a DeclSecurity row.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PermissionSet {
+	/// Legacy form: a serialized XML document.
+	Xml(String),
+	/// Binary form, which ILAsm emits for `.permissionset` with attributes.
+	Attributes(Box<[SecurityAttribute]>),
+}
+
+/// A security attribute of the binary permission set.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SecurityAttribute {
+	/// Serialized (reflection) name of the attribute type.
+	pub ty: String,
+	pub named: Box<[NamedArg]>,
+}
+
+/// Name of the action as ILAsm writes it in `.permissionset`.
+pub fn security_action_name(action: u16) -> &'static str {
+	match action {
+		SECURITY_ACTION_REQUEST             => "request",
+		SECURITY_ACTION_DEMAND              => "demand",
+		SECURITY_ACTION_ASSERT              => "assert",
+		SECURITY_ACTION_DENY                => "deny",
+		SECURITY_ACTION_PERMIT_ONLY         => "permitonly",
+		SECURITY_ACTION_LINK_DEMAND         => "linkcheck",
+		SECURITY_ACTION_INHERITANCE_DEMAND  => "inheritcheck",
+		SECURITY_ACTION_REQUEST_MINIMUM     => "reqmin",
+		SECURITY_ACTION_REQUEST_OPTIONAL    => "reqopt",
+		SECURITY_ACTION_REQUEST_REFUSE      => "reqrefuse",
+		SECURITY_ACTION_PREJIT_GRANT        => "prejitgrant",
+		SECURITY_ACTION_PREJIT_DENIED       => "prejitdeny",
+		SECURITY_ACTION_NON_CAS_DEMAND      => "noncasdemand",
+		SECURITY_ACTION_NON_CAS_LINK_DEMAND => "noncaslinkdemand",
+		SECURITY_ACTION_NON_CAS_INHERITANCE => "noncasinheritance",
+		_                                   => "unknown",
+	}
+}
+
+impl TableRows {
+	/// All security declarations attached to the `parent`.
+	pub fn security_of(&self, parent: HasDeclSecurity) -> impl Iterator<Item = &DeclSecutity> {
+		self.security_attributes.iter().filter(move |s| s.parent == parent)
+	}
+
+	pub fn permission_set(&self, strings: &[u8], blobs: &[u8], decl: &DeclSecutity) -> Result<PermissionSet> {
+		let data = blob_at(blobs, decl.permission_set.into_index())?;
+
+		match data.first() {
+			Some(b'.') => {
+				let decoder = AttributeDecoder::new(self, strings, blobs);
+				Ok(PermissionSet::Attributes(parse_attributes(&decoder, data)?))
+			},
+			Some(_) => Ok(PermissionSet::Xml(parse_xml(data)?)),
+			None => Err("Permission set is empty.")?,
+		}
+	}
+}
+
+/// The binary form is `.` followed by the number of attributes, each being
+/// the type name, the size of its arguments and the named arguments.
+fn parse_attributes(decoder: &AttributeDecoder, data: &[u8]) -> Result<Box<[SecurityAttribute]>> {
+	let offset = &mut 1usize;
+
+	let n = read_compressed_u32(data, offset)?;
+	// Counts come from the blob and are not trusted for preallocation.
+	let mut attributes = Vec::new();
+	for _ in 0..n {
+		let ty = read_ser_string(data, offset)?.ok_or("Security attribute has null type name.")?;
+
+		let size = read_compressed_u32(data, offset)? as usize;
+		let end = *offset + size;
+		if end > data.len() {
+			Err("Security attribute exceeds the blob.")?;
+		}
+
+		let n_named = read_compressed_u32(data, offset)? as usize;
+		let named = decoder.named_args(data, offset, n_named)?;
+		if *offset != end {
+			Err("Security attribute size does not match its arguments.")?;
+		}
+
+		attributes.push(SecurityAttribute { ty, named });
+	}
+
+	Ok(attributes.into_boxed_slice())
+}
+
+/// The XML form is stored as UTF-16, although some compilers emitted it
+/// as a plain 8-bit string.
+fn parse_xml(data: &[u8]) -> Result<String> {
+	let is_wide = data.len() & 1 == 0 && data.get(1) == Some(&0);
+	if is_wide {
+		let wide: Vec<u16> = data.chunks_exact(2)
+			.map(|c| u16::from_le_bytes([c[0], c[1]]))
+			.collect();
+		String::from_utf16(&wide)
+			.map_err(|_| Error::General("Permission set is not a valid utf-16 string."))
+	} else {
+		std::str::from_utf8(data)
+			.map(|s| s.to_owned())
+			.map_err(|_| Error::General("Permission set is not a valid utf-8 string."))
+	}
+}
@@ -0,0 +1,908 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::Result;
+use crate::buf::Reading;
+use crate::cli::constants::*;
+use crate::cli::{MetadataToken, MethodDefOrRef, TableRows, TypeDefOrRef, blob_at, read_compressed_u32, read_compressed_i32, compress_u32, compress_i32};
+
+// Real signatures nest a few types deep, this only stops a malformed one
+// from exhausting the host stack.
+const MAX_SIG_NESTING: u32 = 64;
+
+/// A type as it is encoded in signatures, II.23.2.12.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeSig {
+	Void,
+	Boolean,
+	Char,
+	I1,
+	U1,
+	I2,
+	U2,
+	I4,
+	U4,
+	I8,
+	U8,
+	R4,
+	R8,
+	String,
+	Object,
+	I,
+	U,
+	TypedByRef,
+	Class(TypeDefOrRef),
+	ValueType(TypeDefOrRef),
+	/// Single-dimensional, zero-based array.
+	SzArray(Box<TypeSig>),
+	/// General array, possibly multi-dimensional and with bounds.
+	Array(Box<TypeSig>, ArrayShape),
+	Ptr(Box<TypeSig>),
+	ByRef(Box<TypeSig>),
+	FnPtr(Box<MethodSig>),
+	GenericInst {
+		is_value_type: bool,
+		ty: TypeDefOrRef,
+		args: Box<[TypeSig]>,
+	},
+	/// Generic parameter of the enclosing type.
+	Var(u32),
+	/// Generic parameter of the enclosing method.
+	MVar(u32),
+	/// CMOD_REQD and CMOD_OPT, which precede the type they modify.
+	CustomMod {
+		required: bool,
+		modifier: TypeDefOrRef,
+		ty: Box<TypeSig>,
+	},
+	/// Only valid for local variables.
+	Pinned(Box<TypeSig>),
+}
+
+/// II.23.2.13
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArrayShape {
+	pub rank: u32,
+	/// Sizes of the first dimensions, the rest are unspecified.
+	pub sizes: Box<[u32]>,
+	/// Lower bounds of the first dimensions, the rest are unspecified.
+	pub lo_bounds: Box<[i32]>,
+}
+
+impl ArrayShape {
+	fn parse(data: &[u8], offset: &mut usize) -> Result<ArrayShape> {
+		let rank = read_compressed_u32(data, offset)?;
+		if rank == 0 {
+			Err("Array shape has zero rank.")?;
+		}
+
+		let n = read_compressed_u32(data, offset)?;
+		if n > rank {
+			Err("Array shape has more sizes than its rank.")?;
+		}
+		let sizes = (0..n)
+			.map(|_| read_compressed_u32(data, offset))
+			.collect::<Result<Vec<_>>>()?;
+
+		let n = read_compressed_u32(data, offset)?;
+		if n > rank {
+			Err("Array shape has more lower bounds than its rank.")?;
+		}
+		let lo_bounds = (0..n)
+			.map(|_| read_compressed_i32(data, offset))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(ArrayShape {
+			rank,
+			sizes: sizes.into_boxed_slice(),
+			lo_bounds: lo_bounds.into_boxed_slice(),
+		})
+	}
+
+	fn write(&self, out: &mut Vec<u8>) {
+		compress_u32(self.rank, out);
+		compress_u32(self.sizes.len() as u32, out);
+		for size in self.sizes.iter() {
+			compress_u32(*size, out);
+		}
+		compress_u32(self.lo_bounds.len() as u32, out);
+		for lo in self.lo_bounds.iter() {
+			compress_i32(*lo, out);
+		}
+	}
+}
+
+/// Displays bounds as ILAsm does, e.g. `[0...3,,5...]`.
+impl fmt::Display for ArrayShape {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "[")?;
+		for i in 0..self.rank as usize {
+			if i != 0 {
+				write!(f, ",")?;
+			}
+			let size = self.sizes.get(i);
+			let lo = self.lo_bounds.get(i);
+			match (lo, size) {
+				(Some(lo), Some(size)) => write!(f, "{}...{}", lo, *lo as i64 + *size as i64 - 1)?,
+				(Some(lo), None)       => write!(f, "{}...", lo)?,
+				(None, Some(size))     => write!(f, "{}", size)?,
+				(None, None) if self.rank == 1 => write!(f, "...")?,
+				(None, None)           => {},
+			}
+		}
+		write!(f, "]")
+	}
+}
+
+impl TypeSig {
+	/// Parses FieldSig, II.23.2.4.
+	pub fn parse_field_sig(data: &[u8]) -> Result<TypeSig> {
+		let offset = &mut 0usize;
+		let b: u8 = data.read(offset)?;
+		if b != SIG_FIELD {
+			Err("Field signature has wrong prolog.")?;
+		}
+		TypeSig::parse(data, offset)
+	}
+
+	/// Parses a TypeSpec blob, II.23.2.14.
+	pub fn parse_type_spec(data: &[u8]) -> Result<TypeSig> {
+		TypeSig::parse(data, &mut 0)
+	}
+
+	pub fn parse(data: &[u8], offset: &mut usize) -> Result<TypeSig> {
+		TypeSig::parse_nested(data, offset, 0)
+	}
+
+	fn parse_nested(data: &[u8], offset: &mut usize, depth: u32) -> Result<TypeSig> {
+		if depth > MAX_SIG_NESTING {
+			Err("Signature nests types too deeply.")?;
+		}
+		let element_type: u8 = data.read(offset)?;
+
+		let ty = match element_type {
+			ELEMENT_TYPE_VOID       => TypeSig::Void,
+			ELEMENT_TYPE_BOOLEAN    => TypeSig::Boolean,
+			ELEMENT_TYPE_CHAR       => TypeSig::Char,
+			ELEMENT_TYPE_I1         => TypeSig::I1,
+			ELEMENT_TYPE_U1         => TypeSig::U1,
+			ELEMENT_TYPE_I2         => TypeSig::I2,
+			ELEMENT_TYPE_U2         => TypeSig::U2,
+			ELEMENT_TYPE_I4         => TypeSig::I4,
+			ELEMENT_TYPE_U4         => TypeSig::U4,
+			ELEMENT_TYPE_I8         => TypeSig::I8,
+			ELEMENT_TYPE_U8         => TypeSig::U8,
+			ELEMENT_TYPE_R4         => TypeSig::R4,
+			ELEMENT_TYPE_R8         => TypeSig::R8,
+			ELEMENT_TYPE_STRING     => TypeSig::String,
+			ELEMENT_TYPE_OBJECT     => TypeSig::Object,
+			ELEMENT_TYPE_I          => TypeSig::I,
+			ELEMENT_TYPE_U          => TypeSig::U,
+			ELEMENT_TYPE_TYPEDBYREF => TypeSig::TypedByRef,
+			ELEMENT_TYPE_CLASS      => TypeSig::Class(parse_type_def_or_ref(data, offset)?),
+			ELEMENT_TYPE_VALUETYPE  => TypeSig::ValueType(parse_type_def_or_ref(data, offset)?),
+			ELEMENT_TYPE_SZARRAY    => TypeSig::SzArray(Box::new(TypeSig::parse_nested(data, offset, depth + 1)?)),
+			ELEMENT_TYPE_ARRAY      => {
+				let ty = TypeSig::parse_nested(data, offset, depth + 1)?;
+				TypeSig::Array(Box::new(ty), ArrayShape::parse(data, offset)?)
+			},
+			ELEMENT_TYPE_PTR        => TypeSig::Ptr(Box::new(TypeSig::parse_nested(data, offset, depth + 1)?)),
+			ELEMENT_TYPE_BYREF      => TypeSig::ByRef(Box::new(TypeSig::parse_nested(data, offset, depth + 1)?)),
+			ELEMENT_TYPE_FNPTR      => TypeSig::FnPtr(Box::new(MethodSig::parse_at(data, offset, depth + 1)?)),
+			ELEMENT_TYPE_GENERICINST => {
+				let is_value_type = match data.read::<u8>(offset)? {
+					ELEMENT_TYPE_CLASS     => false,
+					ELEMENT_TYPE_VALUETYPE => true,
+					_ => Err("Generic instantiation is neither class nor value type.")?,
+				};
+				let ty = parse_type_def_or_ref(data, offset)?;
+				let n = read_compressed_u32(data, offset)?;
+				if n == 0 {
+					Err("Generic instantiation has no arguments.")?;
+				}
+				let args = (0..n)
+					.map(|_| TypeSig::parse_nested(data, offset, depth + 1))
+					.collect::<Result<Vec<_>>>()?;
+				TypeSig::GenericInst { is_value_type, ty, args: args.into_boxed_slice() }
+			},
+			ELEMENT_TYPE_VAR        => TypeSig::Var(read_compressed_u32(data, offset)?),
+			ELEMENT_TYPE_MVAR       => TypeSig::MVar(read_compressed_u32(data, offset)?),
+			ELEMENT_TYPE_CMOD_REQD | ELEMENT_TYPE_CMOD_OPT => {
+				let modifier = parse_type_def_or_ref(data, offset)?;
+				TypeSig::CustomMod {
+					required: element_type == ELEMENT_TYPE_CMOD_REQD,
+					modifier,
+					ty: Box::new(TypeSig::parse_nested(data, offset, depth + 1)?),
+				}
+			},
+			ELEMENT_TYPE_PINNED     => TypeSig::Pinned(Box::new(TypeSig::parse_nested(data, offset, depth + 1)?)),
+			_ => Err("Unknown element type in signature.")?,
+		};
+
+		Ok(ty)
+	}
+}
+
+impl TypeSig {
+	/// Calls the function on the type and on every type it is built from.
+	pub fn visit(&self, f: &mut impl FnMut(&TypeSig)) {
+		f(self);
+		match self {
+			TypeSig::SzArray(ty) | TypeSig::Array(ty, _) | TypeSig::Ptr(ty) | TypeSig::ByRef(ty) |
+			TypeSig::Pinned(ty) | TypeSig::CustomMod { ty, .. } => ty.visit(f),
+			TypeSig::FnPtr(sig) => sig.visit(f),
+			TypeSig::GenericInst { args, .. } => args.iter().for_each(|a| a.visit(f)),
+			_ => {},
+		}
+	}
+}
+
+impl MethodSig {
+	pub fn visit(&self, f: &mut impl FnMut(&TypeSig)) {
+		self.ret.visit(f);
+		self.params.iter().for_each(|p| p.visit(f));
+	}
+}
+
+impl TypeSig {
+	/// Writes FieldSig, II.23.2.4.
+	pub fn write_field_sig(&self, out: &mut Vec<u8>) {
+		out.push(SIG_FIELD);
+		self.write(out);
+	}
+
+	pub fn write(&self, out: &mut Vec<u8>) {
+		match self {
+			TypeSig::Void         => out.push(ELEMENT_TYPE_VOID),
+			TypeSig::Boolean      => out.push(ELEMENT_TYPE_BOOLEAN),
+			TypeSig::Char         => out.push(ELEMENT_TYPE_CHAR),
+			TypeSig::I1           => out.push(ELEMENT_TYPE_I1),
+			TypeSig::U1           => out.push(ELEMENT_TYPE_U1),
+			TypeSig::I2           => out.push(ELEMENT_TYPE_I2),
+			TypeSig::U2           => out.push(ELEMENT_TYPE_U2),
+			TypeSig::I4           => out.push(ELEMENT_TYPE_I4),
+			TypeSig::U4           => out.push(ELEMENT_TYPE_U4),
+			TypeSig::I8           => out.push(ELEMENT_TYPE_I8),
+			TypeSig::U8           => out.push(ELEMENT_TYPE_U8),
+			TypeSig::R4           => out.push(ELEMENT_TYPE_R4),
+			TypeSig::R8           => out.push(ELEMENT_TYPE_R8),
+			TypeSig::String       => out.push(ELEMENT_TYPE_STRING),
+			TypeSig::Object       => out.push(ELEMENT_TYPE_OBJECT),
+			TypeSig::I            => out.push(ELEMENT_TYPE_I),
+			TypeSig::U            => out.push(ELEMENT_TYPE_U),
+			TypeSig::TypedByRef   => out.push(ELEMENT_TYPE_TYPEDBYREF),
+			TypeSig::Class(t)     => {
+				out.push(ELEMENT_TYPE_CLASS);
+				compress_u32(t.encode(), out);
+			},
+			TypeSig::ValueType(t) => {
+				out.push(ELEMENT_TYPE_VALUETYPE);
+				compress_u32(t.encode(), out);
+			},
+			TypeSig::SzArray(t)   => {
+				out.push(ELEMENT_TYPE_SZARRAY);
+				t.write(out);
+			},
+			TypeSig::Array(t, shape) => {
+				out.push(ELEMENT_TYPE_ARRAY);
+				t.write(out);
+				shape.write(out);
+			},
+			TypeSig::Ptr(t)       => {
+				out.push(ELEMENT_TYPE_PTR);
+				t.write(out);
+			},
+			TypeSig::ByRef(t)     => {
+				out.push(ELEMENT_TYPE_BYREF);
+				t.write(out);
+			},
+			TypeSig::FnPtr(sig)   => {
+				out.push(ELEMENT_TYPE_FNPTR);
+				sig.write(out);
+			},
+			TypeSig::GenericInst { is_value_type, ty, args } => {
+				out.push(ELEMENT_TYPE_GENERICINST);
+				out.push(if *is_value_type { ELEMENT_TYPE_VALUETYPE } else { ELEMENT_TYPE_CLASS });
+				compress_u32(ty.encode(), out);
+				compress_u32(args.len() as u32, out);
+				for arg in args.iter() {
+					arg.write(out);
+				}
+			},
+			TypeSig::Var(n)       => {
+				out.push(ELEMENT_TYPE_VAR);
+				compress_u32(*n, out);
+			},
+			TypeSig::MVar(n)      => {
+				out.push(ELEMENT_TYPE_MVAR);
+				compress_u32(*n, out);
+			},
+			TypeSig::CustomMod { required, modifier, ty } => {
+				out.push(if *required { ELEMENT_TYPE_CMOD_REQD } else { ELEMENT_TYPE_CMOD_OPT });
+				compress_u32(modifier.encode(), out);
+				ty.write(out);
+			},
+			TypeSig::Pinned(t)    => {
+				out.push(ELEMENT_TYPE_PINNED);
+				t.write(out);
+			},
+		}
+	}
+}
+
+/// Reads TypeDefOrRefOrSpecEncoded, II.23.2.8.
+fn parse_type_def_or_ref(data: &[u8], offset: &mut usize) -> Result<TypeDefOrRef> {
+	TypeDefOrRef::decode(read_compressed_u32(data, offset)?)
+}
+
+/// Resolves type references met in signatures into names.
+pub trait TypeNames {
+	fn write_type(&self, f: &mut fmt::Formatter, ty: TypeDefOrRef) -> fmt::Result;
+}
+
+/// Writes type references as tokens in comments, e.g. `/*01000005*/`.
+pub struct TokenNames;
+
+impl TypeNames for TokenNames {
+	fn write_type(&self, f: &mut fmt::Formatter, ty: TypeDefOrRef) -> fmt::Result {
+		write!(f, "/*{:08x}*/", ty.token())
+	}
+}
+
+/// Displays signature parts with type references resolved by `names`.
+pub struct WithNames<'a, T> {
+	value: &'a T,
+	names: &'a dyn TypeNames,
+}
+
+impl TypeSig {
+	pub fn display<'a>(&'a self, names: &'a dyn TypeNames) -> WithNames<'a, TypeSig> {
+		WithNames { value: self, names }
+	}
+
+	fn fmt_with(&self, f: &mut fmt::Formatter, names: &dyn TypeNames) -> fmt::Result {
+		match self {
+			TypeSig::Void         => write!(f, "void"),
+			TypeSig::Boolean      => write!(f, "bool"),
+			TypeSig::Char         => write!(f, "char"),
+			TypeSig::I1           => write!(f, "int8"),
+			TypeSig::U1           => write!(f, "uint8"),
+			TypeSig::I2           => write!(f, "int16"),
+			TypeSig::U2           => write!(f, "uint16"),
+			TypeSig::I4           => write!(f, "int32"),
+			TypeSig::U4           => write!(f, "uint32"),
+			TypeSig::I8           => write!(f, "int64"),
+			TypeSig::U8           => write!(f, "uint64"),
+			TypeSig::R4           => write!(f, "float32"),
+			TypeSig::R8           => write!(f, "float64"),
+			TypeSig::String       => write!(f, "string"),
+			TypeSig::Object       => write!(f, "object"),
+			TypeSig::I            => write!(f, "native int"),
+			TypeSig::U            => write!(f, "native uint"),
+			TypeSig::TypedByRef   => write!(f, "typedref"),
+			TypeSig::Class(t)     => {
+				write!(f, "class ")?;
+				names.write_type(f, *t)
+			},
+			TypeSig::ValueType(t) => {
+				write!(f, "valuetype ")?;
+				names.write_type(f, *t)
+			},
+			TypeSig::SzArray(t)   => {
+				t.fmt_with(f, names)?;
+				write!(f, "[]")
+			},
+			TypeSig::Array(t, s)  => {
+				t.fmt_with(f, names)?;
+				write!(f, "{}", s)
+			},
+			TypeSig::Ptr(t)       => {
+				t.fmt_with(f, names)?;
+				write!(f, "*")
+			},
+			TypeSig::ByRef(t)     => {
+				t.fmt_with(f, names)?;
+				write!(f, "&")
+			},
+			TypeSig::FnPtr(sig)   => {
+				write!(f, "method ")?;
+				sig.fmt_named(f, "*", names)
+			},
+			TypeSig::GenericInst { is_value_type, ty, args } => {
+				let kind = if *is_value_type { "valuetype" } else { "class" };
+				write!(f, "{} ", kind)?;
+				names.write_type(f, *ty)?;
+				write!(f, "<")?;
+				for (i, arg) in args.iter().enumerate() {
+					if i != 0 {
+						write!(f, ",")?;
+					}
+					arg.fmt_with(f, names)?;
+				}
+				write!(f, ">")
+			},
+			TypeSig::Var(n)       => write!(f, "!{}", n),
+			TypeSig::MVar(n)      => write!(f, "!!{}", n),
+			TypeSig::CustomMod { required, modifier, ty } => {
+				let kind = if *required { "modreq" } else { "modopt" };
+				ty.fmt_with(f, names)?;
+				write!(f, " {}(", kind)?;
+				names.write_type(f, *modifier)?;
+				write!(f, ")")
+			},
+			TypeSig::Pinned(t)    => {
+				t.fmt_with(f, names)?;
+				write!(f, " pinned")
+			},
+		}
+	}
+}
+
+impl fmt::Display for TypeSig {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.fmt_with(f, &TokenNames)
+	}
+}
+
+impl fmt::Display for WithNames<'_, TypeSig> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.value.fmt_with(f, self.names)
+	}
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CallingConvention {
+	Default,
+	VarArg,
+	/// Carries the number of generic parameters.
+	Generic(u32),
+	/// Unmanaged calling conventions are valid only for calli stand-alone
+	/// signatures, II.23.2.3.
+	C,
+	StdCall,
+	ThisCall,
+	FastCall,
+}
+
+/// MethodDefSig and MethodRefSig, II.23.2.1 and II.23.2.2.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MethodSig {
+	pub has_this: bool,
+	pub explicit_this: bool,
+	pub call_conv: CallingConvention,
+	pub ret: TypeSig,
+	pub params: Box<[TypeSig]>,
+	/// Index of the first parameter following the sentinel, only vararg
+	/// call sites have one.
+	pub sentinel: Option<usize>,
+}
+
+impl MethodSig {
+	pub fn parse(data: &[u8]) -> Result<MethodSig> {
+		MethodSig::parse_at(data, &mut 0, 0)
+	}
+
+	fn parse_at(data: &[u8], offset: &mut usize, depth: u32) -> Result<MethodSig> {
+		let b: u8 = data.read(offset)?;
+
+		let has_this = b & SIG_HAS_THIS != 0;
+		let explicit_this = b & SIG_EXPLICIT_THIS != 0;
+		if explicit_this && !has_this {
+			Err("Method signature has EXPLICITTHIS without HASTHIS.")?;
+		}
+
+		let call_conv = if b & SIG_GENERIC != 0 {
+			let n = read_compressed_u32(data, offset)?;
+			if n == 0 {
+				Err("Generic method signature has no generic parameters.")?;
+			}
+			CallingConvention::Generic(n)
+		} else {
+			match b & SIG_KIND_MASK {
+				SIG_DEFAULT  => CallingConvention::Default,
+				SIG_VARARG   => CallingConvention::VarArg,
+				SIG_C        => CallingConvention::C,
+				SIG_STDCALL  => CallingConvention::StdCall,
+				SIG_THISCALL => CallingConvention::ThisCall,
+				SIG_FASTCALL => CallingConvention::FastCall,
+				_ => Err("Unsupported method calling convention.")?,
+			}
+		};
+
+		let n = read_compressed_u32(data, offset)? as usize;
+		let ret = TypeSig::parse_nested(data, offset, depth)?;
+
+		// The count comes from the blob, each parameter takes a byte at least.
+		let mut params = Vec::with_capacity(n.min(data.len().saturating_sub(*offset)));
+		let mut sentinel = None;
+		while params.len() < n {
+			let b: u8 = data.read_at(*offset)?;
+			if b == ELEMENT_TYPE_SENTINEL {
+				if call_conv != CallingConvention::VarArg || sentinel.is_some() {
+					Err("Unexpected sentinel in method signature.")?;
+				}
+				*offset += 1;
+				sentinel = Some(params.len());
+				continue;
+			}
+
+			let param = TypeSig::parse_nested(data, offset, depth)?;
+			if param == TypeSig::Void {
+				Err("Method parameter cannot be void.")?;
+			}
+			params.push(param);
+		}
+
+		Ok(MethodSig {
+			has_this,
+			explicit_this,
+			call_conv,
+			ret,
+			params: params.into_boxed_slice(),
+			sentinel,
+		})
+	}
+
+	pub fn write(&self, out: &mut Vec<u8>) {
+		let mut b = match self.call_conv {
+			CallingConvention::Default    => SIG_DEFAULT,
+			CallingConvention::VarArg     => SIG_VARARG,
+			CallingConvention::Generic(_) => SIG_GENERIC,
+			CallingConvention::C          => SIG_C,
+			CallingConvention::StdCall    => SIG_STDCALL,
+			CallingConvention::ThisCall   => SIG_THISCALL,
+			CallingConvention::FastCall   => SIG_FASTCALL,
+		};
+		if self.has_this {
+			b |= SIG_HAS_THIS;
+		}
+		if self.explicit_this {
+			b |= SIG_EXPLICIT_THIS;
+		}
+		out.push(b);
+		if let CallingConvention::Generic(n) = self.call_conv {
+			compress_u32(n, out);
+		}
+
+		compress_u32(self.params.len() as u32, out);
+		self.ret.write(out);
+		for (i, param) in self.params.iter().enumerate() {
+			if self.sentinel == Some(i) {
+				out.push(ELEMENT_TYPE_SENTINEL);
+			}
+			param.write(out);
+		}
+		if self.sentinel.is_some() && self.sentinel == Some(self.params.len()) {
+			out.push(ELEMENT_TYPE_SENTINEL);
+		}
+	}
+
+	/// Displays the signature as ILDasm does at call sites, e.g.
+	/// `instance void Foo(int32, string)`.
+	pub fn named<'a>(&'a self, name: &'a str) -> NamedMethodSig<'a> {
+		NamedMethodSig { sig: self, name, names: &TokenNames }
+	}
+
+	/// Same as `named`, but with type references resolved by `names`.
+	pub fn named_with<'a>(&'a self, name: &'a str, names: &'a dyn TypeNames) -> NamedMethodSig<'a> {
+		NamedMethodSig { sig: self, name, names }
+	}
+
+	pub fn display<'a>(&'a self, names: &'a dyn TypeNames) -> WithNames<'a, MethodSig> {
+		WithNames { value: self, names }
+	}
+
+	/// Calling convention keywords as ILAsm writes them before the return
+	/// type, each followed by a space.
+	pub fn call_conv_keywords(&self) -> String {
+		let mut s = String::new();
+		if self.has_this {
+			s.push_str("instance ");
+		}
+		if self.explicit_this {
+			s.push_str("explicit ");
+		}
+		match self.call_conv {
+			CallingConvention::VarArg   => s.push_str("vararg "),
+			CallingConvention::C        => s.push_str("unmanaged cdecl "),
+			CallingConvention::StdCall  => s.push_str("unmanaged stdcall "),
+			CallingConvention::ThisCall => s.push_str("unmanaged thiscall "),
+			CallingConvention::FastCall => s.push_str("unmanaged fastcall "),
+			_ => {},
+		}
+		s
+	}
+
+	fn fmt_named(&self, f: &mut fmt::Formatter, name: &str, names: &dyn TypeNames) -> fmt::Result {
+		write!(f, "{}", self.call_conv_keywords())?;
+		self.ret.fmt_with(f, names)?;
+		write!(f, " {}", name)?;
+		if let CallingConvention::Generic(n) = self.call_conv {
+			write!(f, "<[{}]>", n)?;
+		}
+
+		write!(f, "(")?;
+		for (i, param) in self.params.iter().enumerate() {
+			if i != 0 {
+				write!(f, ", ")?;
+			}
+			if self.sentinel == Some(i) {
+				write!(f, "..., ")?;
+			}
+			param.fmt_with(f, names)?;
+		}
+		if self.sentinel.is_some() && self.sentinel == Some(self.params.len()) {
+			if !self.params.is_empty() {
+				write!(f, ", ")?;
+			}
+			write!(f, "...")?;
+		}
+		write!(f, ")")
+	}
+}
+
+/// Displays a method signature without a name the way function pointers are
+/// written, e.g. `instance void *(int32)`.
+impl fmt::Display for MethodSig {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.fmt_named(f, "*", &TokenNames)
+	}
+}
+
+impl fmt::Display for WithNames<'_, MethodSig> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.value.fmt_named(f, "*", self.names)
+	}
+}
+
+pub struct NamedMethodSig<'a> {
+	sig: &'a MethodSig,
+	name: &'a str,
+	names: &'a dyn TypeNames,
+}
+
+impl fmt::Display for NamedMethodSig<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.sig.fmt_named(f, self.name, self.names)
+	}
+}
+
+/// A local variable slot of LocalVarSig, II.23.2.6.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LocalVar {
+	/// Type of the slot with custom modifiers, but without PINNED and
+	/// BYREF markers.
+	pub ty: TypeSig,
+	/// Object referenced by the local shall not be moved by the GC.
+	pub pinned: bool,
+	pub by_ref: bool,
+}
+
+impl LocalVar {
+	pub fn display<'a>(&'a self, names: &'a dyn TypeNames) -> WithNames<'a, LocalVar> {
+		WithNames { value: self, names }
+	}
+
+	fn fmt_with(&self, f: &mut fmt::Formatter, names: &dyn TypeNames) -> fmt::Result {
+		self.ty.fmt_with(f, names)?;
+		if self.by_ref {
+			write!(f, "&")?;
+		}
+		if self.pinned {
+			write!(f, " pinned")?;
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Display for LocalVar {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.fmt_with(f, &TokenNames)
+	}
+}
+
+impl fmt::Display for WithNames<'_, LocalVar> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.value.fmt_with(f, self.names)
+	}
+}
+
+/// LocalVarSig, II.23.2.6.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LocalVarSig {
+	pub locals: Box<[LocalVar]>,
+}
+
+impl LocalVarSig {
+	pub fn parse(data: &[u8]) -> Result<LocalVarSig> {
+		let offset = &mut 0usize;
+
+		let b: u8 = data.read(offset)?;
+		if b != SIG_LOCAL {
+			Err("Local variables signature has wrong prolog.")?;
+		}
+
+		let n = read_compressed_u32(data, offset)?;
+		if n == 0 || n > 0xFFFE {
+			Err("Local variables signature has invalid count.")?;
+		}
+
+		let locals = (0..n)
+			.map(|_| LocalVarSig::parse_local(data, offset))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(LocalVarSig { locals: locals.into_boxed_slice() })
+	}
+
+	pub fn write(&self, out: &mut Vec<u8>) {
+		out.push(SIG_LOCAL);
+		compress_u32(self.locals.len() as u32, out);
+		for local in self.locals.iter() {
+			// Custom modifiers precede the constraint and BYREF.
+			let mut ty = &local.ty;
+			while let TypeSig::CustomMod { required, modifier, ty: inner } = ty {
+				out.push(if *required { ELEMENT_TYPE_CMOD_REQD } else { ELEMENT_TYPE_CMOD_OPT });
+				compress_u32(modifier.encode(), out);
+				ty = inner;
+			}
+			if local.pinned {
+				out.push(ELEMENT_TYPE_PINNED);
+			}
+			if local.by_ref {
+				out.push(ELEMENT_TYPE_BYREF);
+			}
+			ty.write(out);
+		}
+	}
+
+	fn parse_local(data: &[u8], offset: &mut usize) -> Result<LocalVar> {
+		let mut modifiers = Vec::new();
+		let mut pinned = false;
+		let mut by_ref = false;
+
+		loop {
+			match data.read_at::<u8>(*offset)? {
+				b @ (ELEMENT_TYPE_CMOD_REQD | ELEMENT_TYPE_CMOD_OPT) => {
+					*offset += 1;
+					let modifier = parse_type_def_or_ref(data, offset)?;
+					modifiers.push((b == ELEMENT_TYPE_CMOD_REQD, modifier));
+				},
+				ELEMENT_TYPE_PINNED => {
+					*offset += 1;
+					pinned = true;
+				},
+				ELEMENT_TYPE_BYREF => {
+					*offset += 1;
+					by_ref = true;
+					break;
+				},
+				_ => break,
+			}
+		}
+
+		let mut ty = TypeSig::parse(data, offset)?;
+		if ty == TypeSig::Void {
+			Err("Local variable cannot be void.")?;
+		}
+		for (required, modifier) in modifiers.into_iter().rev() {
+			ty = TypeSig::CustomMod { required, modifier, ty: Box::new(ty) };
+		}
+
+		Ok(LocalVar { ty, pinned, by_ref })
+	}
+}
+
+impl TableRows {
+	/// Decodes locals referenced by LocalVarSigTok of a fat method header.
+	/// Zero token stands for a method without locals.
+	pub fn local_var_sig(&self, blobs: &[u8], token: u32) -> Result<LocalVarSig> {
+		if token == 0 {
+			return Ok(LocalVarSig::default());
+		}
+		LocalVarSig::parse(self.standalone_sig_blob(blobs, token)?)
+	}
+
+	/// Decodes the call-site signature of a calli instruction.
+	pub fn standalone_method_sig(&self, blobs: &[u8], token: u32) -> Result<MethodSig> {
+		let blob = self.standalone_sig_blob(blobs, token)?;
+		if blob.first() == Some(&SIG_LOCAL) {
+			Err("Stand-alone signature describes local variables, not a method.")?;
+		}
+		MethodSig::parse(blob)
+	}
+
+	/// Decodes the signature of a method referenced by a call instruction:
+	/// MethodDef, MemberRef or MethodSpec of either. Signatures of vararg
+	/// call sites include the extra arguments.
+	pub fn method_sig(&self, blobs: &[u8], token: MetadataToken) -> Result<MethodSig> {
+		let row = token.row_index();
+		let sig = match token.table_index() {
+			METADATA_METHOD_DEF => {
+				self.method_defs.get(row).ok_or("MethodDef row is out of the table bounds.")?.sig
+			},
+			METADATA_MEMBER_REF => {
+				self.member_refs.get(row).ok_or("MemberRef row is out of the table bounds.")?.sig
+			},
+			METADATA_METHOD_SPEC => {
+				let spec = self.method_specs.get(row).ok_or("MethodSpec row is out of the table bounds.")?;
+				let row = spec.method.into_index().wrapping_sub(1);
+				match spec.method {
+					MethodDefOrRef::MethodDef(_) => {
+						self.method_defs.get(row).ok_or("MethodDef row is out of the table bounds.")?.sig
+					},
+					MethodDefOrRef::MemberRef(_) => {
+						self.member_refs.get(row).ok_or("MemberRef row is out of the table bounds.")?.sig
+					},
+				}
+			},
+			_ => Err("Token does not reference a method.")?,
+		};
+
+		let blob = blob_at(blobs, sig.into_index())?;
+		if blob.first() == Some(&SIG_FIELD) {
+			Err("Member reference describes a field, not a method.")?;
+		}
+		MethodSig::parse(blob)
+	}
+
+	/// Decodes the type of a field referenced by a Field or MemberRef token.
+	pub fn field_sig(&self, blobs: &[u8], token: MetadataToken) -> Result<TypeSig> {
+		let row = token.row_index();
+		let sig = match token.table_index() {
+			METADATA_FIELD => self.fields.get(row).ok_or("Field row is out of the table bounds.")?.sig,
+			METADATA_MEMBER_REF => {
+				self.member_refs.get(row).ok_or("MemberRef row is out of the table bounds.")?.sig
+			},
+			_ => Err("Token does not reference a field.")?,
+		};
+		TypeSig::parse_field_sig(blob_at(blobs, sig.into_index())?)
+	}
+
+	/// TypeRef rows signatures tag as value types, II.23.2.12. Nothing else
+	/// tells referenced value types from classes without loading the
+	/// assemblies defining them. Signatures failing to parse are skipped.
+	pub fn value_type_refs(&self, blobs: &[u8]) -> HashSet<u32> {
+		let mut refs = HashSet::new();
+		let mut add = |ty: &TypeSig| match ty {
+			TypeSig::ValueType(TypeDefOrRef::TypeRef(r)) |
+			TypeSig::GenericInst { is_value_type: true, ty: TypeDefOrRef::TypeRef(r), .. } => {
+				refs.insert(*r);
+			},
+			_ => {},
+		};
+
+		let sigs = self.method_defs.iter().map(|m| m.sig)
+			.chain(self.fields.iter().map(|f| f.sig))
+			.chain(self.member_refs.iter().map(|m| m.sig))
+			.chain(self.standalone_signatures.iter().map(|s| s.sig));
+		for sig in sigs {
+			let blob = match blob_at(blobs, sig.into_index()) {
+				Ok(blob) => blob,
+				Err(_) => continue,
+			};
+			match blob.first() {
+				Some(&SIG_FIELD) => if let Ok(ty) = TypeSig::parse_field_sig(blob) { ty.visit(&mut add) },
+				Some(&SIG_LOCAL) => if let Ok(locals) = LocalVarSig::parse(blob) {
+					locals.locals.iter().for_each(|l| l.ty.visit(&mut add));
+				},
+				_ => if let Ok(sig) = MethodSig::parse(blob) { sig.visit(&mut add) },
+			}
+		}
+		for spec in self.type_specs.iter() {
+			if let Ok(ty) = blob_at(blobs, spec.sig.into_index()).and_then(TypeSig::parse_type_spec) {
+				ty.visit(&mut add);
+			}
+		}
+		refs
+	}
+
+	fn standalone_sig_blob<'a>(&self, blobs: &'a [u8], token: u32) -> Result<&'a [u8]> {
+		if (token >> 24) as usize != METADATA_STANDALONE_SIG {
+			Err("Token does not reference the StandAloneSig table.")?;
+		}
+		let row = (token & 0xFF_FFFF) as usize;
+		let sig = self.standalone_signatures.get(row.wrapping_sub(1))
+			.ok_or("StandAloneSig row is out of the table bounds.")?;
+		blob_at(blobs, sig.sig.into_index())
+	}
+}
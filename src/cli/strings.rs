@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use crate::Result;
 use crate::error::Error;
 use crate::buf::Reading;
+use crate::cli::StringIndex;
+use crate::utils::align_up;
 
 pub fn parse_strings(data: & [u8]) -> Result<Box<[&str]>> {
-	if data.len() < 1 || data[0] != 0 {
+	if data.is_empty() || data[0] != 0 {
 		Err("Strings heap is invalid.")?;
 	}
 
@@ -17,3 +21,60 @@ pub fn parse_strings(data: & [u8]) -> Result<Box<[&str]>> {
 
 	Ok(strings.into_boxed_slice())
 }
+
+/// Reads a string starting at `index` byte offset in the #Strings heap.
+pub fn string_at(data: &[u8], index: usize) -> Result<&str> {
+	if index >= data.len() {
+		Err("String index is out of the heap bounds.")?;
+	}
+
+	let s = &data[index..];
+	let len = s.iter().position(|c| *c == 0).ok_or("String is not null-terminated.")?;
+
+	std::str::from_utf8(&s[..len])
+		.map_err(|_| Error::General("Found a string that is not a valid utf-8 string."))
+}
+
+/// Builds a #Strings heap, identical strings are stored only once.
+#[derive(Debug, Clone)]
+pub struct StringsBuilder {
+	data: Vec<u8>,
+	known: HashMap<String, u32>,
+}
+
+impl Default for StringsBuilder {
+	fn default() -> Self {
+		// The first entry is always the empty string.
+		StringsBuilder { data: vec![0], known: HashMap::new() }
+	}
+}
+
+impl StringsBuilder {
+	pub fn add(&mut self, s: &str) -> StringIndex {
+		if s.is_empty() {
+			return StringIndex(0);
+		}
+		if let Some(i) = self.known.get(s) {
+			return StringIndex(*i);
+		}
+
+		let i = self.data.len() as u32;
+		self.data.extend_from_slice(s.as_bytes());
+		self.data.push(0);
+		self.known.insert(s.to_owned(), i);
+		StringIndex(i)
+	}
+
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.data.len() == 1
+	}
+
+	pub fn finish(mut self) -> Vec<u8> {
+		self.data.resize(align_up(self.data.len(), 4), 0);
+		self.data
+	}
+}
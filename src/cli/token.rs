@@ -13,7 +13,7 @@ impl TryFrom<u32> for MetadataToken {
 
 	fn try_from(x: u32) -> Result<Self> {
 		let idx = table_index(x);
-		if idx >= METADATA_MODULE && idx <= METADATA_GENERIC_PARAM_CONSTRAINT {
+		if (METADATA_MODULE..=METADATA_GENERIC_PARAM_CONSTRAINT).contains(&idx) {
 			Ok(MetadataToken(x))
 		} else {
 			Err(Error::General("Unknown metadata table in possible token."))
@@ -29,6 +29,11 @@ impl MetadataToken {
 	pub fn row_index(&self) -> usize {
 		((self.0 & 0xFFFFFF) - 1) as usize
 	}
+
+	/// The token as it is stored in IL and headers.
+	pub fn value(&self) -> u32 {
+		self.0
+	}
 }
 
 fn table_index(x: u32) -> usize {
@@ -0,0 +1,615 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::cli::constants::*;
+use crate::cli::flags::*;
+use crate::cli::{
+	Metadata,
+	Tables,
+	TableRows,
+	StringIndex,
+	BlobIndex,
+	GuidIndex,
+	string_at,
+	blob_at,
+};
+
+// II.22 lists a lot of rules valid metadata shall obey. `TableRows::parse`
+// only rejects what makes the rest of parsing impossible, everything else is
+// checked here in a separate pass, which reports every finding instead of
+// bailing out on the first one.
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Severity {
+	/// Violation of a rule the spec marks as [WARNING] or [CLS].
+	Warning,
+	/// Violation of a rule the spec marks as [ERROR].
+	Error,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error   => write!(f, "error"),
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Finding {
+	pub severity: Severity,
+	pub table: usize,
+	/// 0-based row index within the table.
+	pub row: usize,
+	pub message: &'static str,
+}
+
+impl fmt::Display for Finding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: [{} {:#010x}] {}",
+			self.severity,
+			table_name(self.table),
+			(self.table as u32) << 24 | (self.row as u32 + 1),
+			self.message)
+	}
+}
+
+pub fn validate(header: &Tables, rows: &TableRows, metadata: &Metadata) -> Box<[Finding]> {
+	let mut v = Validator {
+		header,
+		strings: metadata.strings.unwrap_or(&[]),
+		blobs: metadata.blobs.unwrap_or(&[]),
+		n_guids: metadata.guids.map_or(0, |g| g.len() >> 4),
+		findings: Vec::new(),
+	};
+
+	v.modules(rows);
+	v.type_refs(rows);
+	v.type_defs(rows);
+	v.fields(rows);
+	v.method_defs(rows);
+	v.params(rows);
+	v.members(rows);
+	v.attributes(rows);
+	v.layouts(rows);
+	v.events_and_properties(rows);
+	v.assemblies(rows);
+	v.generics(rows);
+
+	v.findings.into_boxed_slice()
+}
+
+struct Validator<'a> {
+	header: &'a Tables,
+	strings: &'a [u8],
+	blobs: &'a [u8],
+	n_guids: usize,
+	findings: Vec<Finding>,
+}
+
+impl<'a> Validator<'a> {
+	fn report(&mut self, severity: Severity, table: usize, row: usize, message: &'static str) {
+		self.findings.push(Finding { severity, table, row, message });
+	}
+
+	fn error(&mut self, table: usize, row: usize, message: &'static str) {
+		self.report(Severity::Error, table, row, message);
+	}
+
+	fn warning(&mut self, table: usize, row: usize, message: &'static str) {
+		self.report(Severity::Warning, table, row, message);
+	}
+
+	fn string(&self, i: StringIndex) -> Option<&'a str> {
+		string_at(self.strings, i.into_index()).ok()
+	}
+
+	fn check_string(&mut self, table: usize, row: usize, i: StringIndex) {
+		if string_at(self.strings, i.into_index()).is_err() {
+			self.error(table, row, "String index is out of the #Strings heap bounds.");
+		}
+	}
+
+	fn check_name(&mut self, table: usize, row: usize, i: StringIndex) {
+		match string_at(self.strings, i.into_index()) {
+			Ok("") => self.error(table, row, "Name shall not be empty."),
+			Ok(_)  => {},
+			Err(_) => self.error(table, row, "Name index is out of the #Strings heap bounds."),
+		}
+	}
+
+	fn check_blob(&mut self, table: usize, row: usize, i: BlobIndex) {
+		if blob_at(self.blobs, i.into_index()).is_err() {
+			self.error(table, row, "Blob index is out of the #Blob heap bounds.");
+		}
+	}
+
+	fn check_sig(&mut self, table: usize, row: usize, i: BlobIndex) {
+		match blob_at(self.blobs, i.into_index()) {
+			Ok([]) => self.error(table, row, "Signature shall not be empty."),
+			Ok(_)  => {},
+			Err(_) => self.error(table, row, "Signature index is out of the #Blob heap bounds."),
+		}
+	}
+
+	fn check_guid(&mut self, table: usize, row: usize, i: GuidIndex) {
+		if i.into_index() > self.n_guids {
+			self.error(table, row, "Guid index is out of the #GUID heap bounds.");
+		}
+	}
+
+	/// Checks a simple or coded index, `target` is a 1-based row number.
+	fn check_ref(&mut self, table: usize, row: usize, target_table: usize, target: usize, nullable: bool) {
+		if target == 0 {
+			if !nullable {
+				self.error(table, row, "Required reference is null.");
+			}
+		} else if target > self.header.lens[target_table] as usize {
+			self.error(table, row, "Reference is out of the target table bounds.");
+		}
+	}
+
+	/// Checks the "list" columns, which mark the first of a contiguous run
+	/// of rows owned by a row in another table.
+	fn check_runs(&mut self, table: usize, target_table: usize, starts: impl Iterator<Item = usize>) {
+		let n = self.header.lens[target_table] as usize;
+		let mut prev = 1;
+		for (row, start) in starts.enumerate() {
+			if start == 0 || start > n + 1 {
+				self.error(table, row, "List start is out of the owned table bounds.");
+			} else if start < prev {
+				self.error(table, row, "Owned runs are not monotonic.");
+			} else {
+				prev = start;
+			}
+		}
+	}
+
+	/// Returns the owned run for every owner row as [start, end) 0-based
+	/// row range.
+	fn runs(&self, target_table: usize, starts: &[usize]) -> Vec<(usize, usize)> {
+		let n = self.header.lens[target_table] as usize;
+		let mut runs = Vec::with_capacity(starts.len());
+		for (i, start) in starts.iter().enumerate() {
+			let end = starts.get(i + 1).copied().unwrap_or(n + 1);
+			let start = start.saturating_sub(1).min(n);
+			let end = end.saturating_sub(1).min(n).max(start);
+			runs.push((start, end));
+		}
+		runs
+	}
+
+	fn check_sorted<T, K: PartialOrd>(&mut self, table: usize, items: &[T], key: impl Fn(&T) -> K) {
+		for (i, w) in items.windows(2).enumerate() {
+			if key(&w[0]) > key(&w[1]) {
+				self.error(table, i + 1, "Table is not sorted by its primary key.");
+			}
+		}
+	}
+
+	fn check_single(&mut self, table: usize, required: bool) {
+		let n = self.header.lens[table];
+		if n > 1 {
+			self.error(table, 1, "Table shall contain at most one row.");
+		}
+		if required && n == 0 {
+			self.error(table, 0, "Table shall contain exactly one row.");
+		}
+	}
+
+	fn modules(&mut self, rows: &TableRows) {
+		const T: usize = METADATA_MODULE;
+		self.check_single(T, true);
+		for (i, r) in rows.modules.iter().enumerate() {
+			self.check_name(T, i, r.name);
+			self.check_guid(T, i, r.mvid);
+			if r.mvid.into_index() == 0 {
+				self.error(T, i, "Module shall have a Mvid.");
+			}
+			if r.generation != 0 {
+				self.error(T, i, "Module.Generation shall be zero.");
+			}
+			self.check_guid(T, i, r.enc_id);
+			self.check_guid(T, i, r.enc_base_id);
+			if r.enc_id.into_index() != 0 || r.enc_base_id.into_index() != 0 {
+				self.error(T, i, "Module.EncId and EncBaseId shall be zero.");
+			}
+		}
+
+		const R: usize = METADATA_MODULE_REF;
+		for (i, r) in rows.module_refs.iter().enumerate() {
+			self.check_name(R, i, r.name);
+		}
+	}
+
+	fn type_refs(&mut self, rows: &TableRows) {
+		const T: usize = METADATA_TYPE_REF;
+		for (i, r) in rows.type_refs.iter().enumerate() {
+			self.check_ref(T, i, r.scope.table_index(), r.scope.into_index(), true);
+			if r.scope.into_index() == 0 {
+				self.warning(T, i, "Resolution scope is null, the type shall be found in ExportedType.");
+			}
+			self.check_name(T, i, r.name);
+			self.check_string(T, i, r.namespace);
+		}
+
+		const S: usize = METADATA_TYPE_SPEC;
+		for (i, r) in rows.type_specs.iter().enumerate() {
+			self.check_sig(S, i, r.sig);
+		}
+	}
+
+	fn type_defs(&mut self, rows: &TableRows) {
+		const T: usize = METADATA_TYPE_DEF;
+
+		let mut enclosing = vec![0; rows.type_defs.len()];
+		for n in rows.nested_classes.iter() {
+			if let Some(e) = enclosing.get_mut(n.nested.into_index().wrapping_sub(1)) {
+				*e = n.enclosing.into_index();
+			}
+		}
+
+		let mut names = HashSet::new();
+
+		for (i, r) in rows.type_defs.iter().enumerate() {
+			self.check_name(T, i, r.name);
+			self.check_string(T, i, r.namespace);
+			self.check_ref(T, i, r.extends.table_index(), r.extends.into_index(), true);
+
+			let is_nested = enclosing[i] != 0;
+			let visibility = r.flags & TYPE_VISIBILITY_MASK;
+			if is_nested && visibility <= TYPE_PUBLIC {
+				self.error(T, i, "Nested type shall have nested visibility.");
+			}
+			if !is_nested && visibility > TYPE_PUBLIC {
+				self.error(T, i, "Type with nested visibility is not nested.");
+			}
+			if r.flags & TYPE_LAYOUT_MASK == TYPE_LAYOUT_MASK {
+				self.error(T, i, "Type layout is invalid.");
+			}
+			if r.flags & TYPE_CLASS_SEMANTICS_MASK == TYPE_INTERFACE {
+				if r.flags & TYPE_ABSTRACT == 0 {
+					self.error(T, i, "Interface shall be abstract.");
+				}
+				if r.flags & TYPE_SEALED != 0 {
+					self.error(T, i, "Interface shall not be sealed.");
+				}
+				if r.extends.into_index() != 0 {
+					self.error(T, i, "Interface shall not extend a type.");
+				}
+			}
+			if r.flags & TYPE_RT_SPECIAL_NAME != 0 && r.flags & TYPE_SPECIAL_NAME == 0 {
+				self.error(T, i, "RTSpecialName is set without SpecialName.");
+			}
+
+			if let (Some(namespace), Some(name)) = (self.string(r.namespace), self.string(r.name)) {
+				if !names.insert((enclosing[i], namespace, name)) {
+					self.error(T, i, "Duplicate type definition.");
+				}
+			}
+		}
+
+		let fields = rows.type_defs.iter().map(|t| t.field_list.into_index());
+		self.check_runs(T, METADATA_FIELD, fields);
+		let methods = rows.type_defs.iter().map(|t| t.method_list.into_index());
+		self.check_runs(T, METADATA_METHOD_DEF, methods);
+
+		const N: usize = METADATA_NESTED_CLASS;
+		for (i, r) in rows.nested_classes.iter().enumerate() {
+			self.check_ref(N, i, METADATA_TYPE_DEF, r.nested.into_index(), false);
+			self.check_ref(N, i, METADATA_TYPE_DEF, r.enclosing.into_index(), false);
+			if r.nested == r.enclosing {
+				self.error(N, i, "Type is nested into itself.");
+			}
+		}
+		self.check_sorted(N, &rows.nested_classes, |r| r.nested.into_index());
+
+		const I: usize = METADATA_INTERFACE_IMPL;
+		for (i, r) in rows.interface_impls.iter().enumerate() {
+			self.check_ref(I, i, METADATA_TYPE_DEF, r.class.into_index(), false);
+			self.check_ref(I, i, r.iface.table_index(), r.iface.into_index(), false);
+		}
+		self.check_sorted(I, &rows.interface_impls, |r| (r.class.into_index(), r.iface.encode()));
+	}
+
+	fn fields(&mut self, rows: &TableRows) {
+		const T: usize = METADATA_FIELD;
+
+		let with_rva: HashSet<usize> = rows.field_rvas.iter().map(|r| r.field.into_index()).collect();
+		let (with_default, with_marshal) = constants_and_marshals(rows, T);
+
+		for (i, r) in rows.fields.iter().enumerate() {
+			self.check_name(T, i, r.name);
+			self.check_sig(T, i, r.sig);
+
+			if r.flags & FIELD_ACCESS_MASK == FIELD_ACCESS_MASK {
+				self.error(T, i, "Field access is invalid.");
+			}
+			if r.flags & FIELD_LITERAL != 0 {
+				if r.flags & FIELD_STATIC == 0 {
+					self.error(T, i, "Literal field shall be static.");
+				}
+				if r.flags & FIELD_INIT_ONLY != 0 {
+					self.error(T, i, "Literal field shall not be InitOnly.");
+				}
+				if r.flags & FIELD_HAS_DEFAULT == 0 {
+					self.error(T, i, "Literal field shall have a default value.");
+				}
+			}
+			if r.flags & FIELD_RT_SPECIAL_NAME != 0 && r.flags & FIELD_SPECIAL_NAME == 0 {
+				self.error(T, i, "RTSpecialName is set without SpecialName.");
+			}
+			if (r.flags & FIELD_HAS_FIELD_RVA != 0) != with_rva.contains(&(i + 1)) {
+				self.error(T, i, "HasFieldRVA does not match the FieldRVA table.");
+			}
+			if (r.flags & FIELD_HAS_DEFAULT != 0) != with_default.contains(&(i + 1)) {
+				self.error(T, i, "HasDefault does not match the Constant table.");
+			}
+			if (r.flags & FIELD_HAS_FIELD_MARSHAL != 0) != with_marshal.contains(&(i + 1)) {
+				self.error(T, i, "HasFieldMarshal does not match the FieldMarshal table.");
+			}
+		}
+
+		const R: usize = METADATA_FIELD_RVA;
+		for (i, r) in rows.field_rvas.iter().enumerate() {
+			self.check_ref(R, i, T, r.field.into_index(), false);
+			if r.rva == 0 {
+				self.error(R, i, "Field RVA shall be non-zero.");
+			}
+		}
+		self.check_sorted(R, &rows.field_rvas, |r| r.field.into_index());
+	}
+
+	fn method_defs(&mut self, rows: &TableRows) {
+		const T: usize = METADATA_METHOD_DEF;
+		for (i, r) in rows.method_defs.iter().enumerate() {
+			self.check_name(T, i, r.name);
+			self.check_sig(T, i, r.sig);
+
+			let f = r.flags;
+			if f & METHOD_MEMBER_ACCESS_MASK == METHOD_MEMBER_ACCESS_MASK {
+				self.error(T, i, "Method access is invalid.");
+			}
+			if f & METHOD_STATIC != 0 && f & (METHOD_FINAL | METHOD_VIRTUAL | METHOD_NEW_SLOT) != 0 {
+				self.error(T, i, "Static method shall not be Final, Virtual or NewSlot.");
+			}
+			if f & (METHOD_FINAL | METHOD_NEW_SLOT | METHOD_STRICT | METHOD_ABSTRACT) != 0 && f & METHOD_VIRTUAL == 0 {
+				self.error(T, i, "Final, NewSlot, Strict or Abstract method shall be Virtual.");
+			}
+			if f & METHOD_ABSTRACT != 0 && f & METHOD_PINVOKE_IMPL != 0 {
+				self.error(T, i, "Method shall not be both Abstract and PinvokeImpl.");
+			}
+			if f & METHOD_RT_SPECIAL_NAME != 0 && f & METHOD_SPECIAL_NAME == 0 {
+				self.error(T, i, "RTSpecialName is set without SpecialName.");
+			}
+
+			let no_body = f & (METHOD_ABSTRACT | METHOD_PINVOKE_IMPL) != 0
+				|| r.impl_flags & METHOD_IMPL_INTERNAL_CALL != 0
+				|| r.impl_flags & METHOD_IMPL_CODE_TYPE_MASK == METHOD_IMPL_RUNTIME;
+			if no_body && r.rva != 0 {
+				self.error(T, i, "Method without a body shall have zero RVA.");
+			}
+			if !no_body && r.rva == 0 {
+				self.error(T, i, "Method with a body shall have non-zero RVA.");
+			}
+		}
+
+		let params = rows.method_defs.iter().map(|m| m.param_list.into_index());
+		self.check_runs(T, METADATA_PARAM, params);
+
+		const M: usize = METADATA_IMPL_MAP;
+		for (i, r) in rows.impl_maps.iter().enumerate() {
+			self.check_ref(M, i, r.member_fwd.table_index(), r.member_fwd.into_index(), false);
+			self.check_name(M, i, r.name);
+			self.check_ref(M, i, METADATA_MODULE_REF, r.scope.into_index(), false);
+		}
+		self.check_sorted(M, &rows.impl_maps, |r| r.member_fwd.encode());
+
+		const S: usize = METADATA_STANDALONE_SIG;
+		for (i, r) in rows.standalone_signatures.iter().enumerate() {
+			self.check_sig(S, i, r.sig);
+		}
+	}
+
+	fn params(&mut self, rows: &TableRows) {
+		const T: usize = METADATA_PARAM;
+
+		let (with_default, with_marshal) = constants_and_marshals(rows, T);
+		for (i, r) in rows.params.iter().enumerate() {
+			self.check_string(T, i, r.name);
+			if r.flags & PARAM_UNUSED != 0 {
+				self.warning(T, i, "Reserved param flags are set.");
+			}
+
+			if (r.flags & PARAM_HAS_DEFAULT != 0) != with_default.contains(&(i + 1)) {
+				self.error(T, i, "HasDefault does not match the Constant table.");
+			}
+			if (r.flags & PARAM_HAS_FIELD_MARSHAL != 0) != with_marshal.contains(&(i + 1)) {
+				self.error(T, i, "HasFieldMarshal does not match the FieldMarshal table.");
+			}
+		}
+
+		let starts: Vec<usize> = rows.method_defs.iter().map(|m| m.param_list.into_index()).collect();
+		for (start, end) in self.runs(T, &starts) {
+			for i in start + 1..end {
+				if rows.params[i - 1].seq >= rows.params[i].seq {
+					self.error(T, i, "Params of a method are not ordered by their sequence.");
+				}
+			}
+		}
+	}
+
+	fn members(&mut self, rows: &TableRows) {
+		const R: usize = METADATA_MEMBER_REF;
+		for (i, r) in rows.member_refs.iter().enumerate() {
+			self.check_ref(R, i, r.class.table_index(), r.class.into_index(), false);
+			self.check_name(R, i, r.name);
+			self.check_sig(R, i, r.sig);
+		}
+
+		const I: usize = METADATA_METHOD_IMPL;
+		for (i, r) in rows.method_impls.iter().enumerate() {
+			self.check_ref(I, i, METADATA_TYPE_DEF, r.class.into_index(), false);
+			self.check_ref(I, i, r.body.table_index(), r.body.into_index(), false);
+			self.check_ref(I, i, r.decl.table_index(), r.decl.into_index(), false);
+		}
+		self.check_sorted(I, &rows.method_impls, |r| r.class.into_index());
+
+		const S: usize = METADATA_METHOD_SPEC;
+		for (i, r) in rows.method_specs.iter().enumerate() {
+			self.check_ref(S, i, r.method.table_index(), r.method.into_index(), false);
+			self.check_sig(S, i, r.inst);
+		}
+	}
+
+	fn attributes(&mut self, rows: &TableRows) {
+		const C: usize = METADATA_CONSTANT;
+		for (i, r) in rows.constants.iter().enumerate() {
+			self.check_ref(C, i, r.parent.table_index(), r.parent.into_index(), false);
+			self.check_blob(C, i, r.value);
+		}
+		self.check_sorted(C, &rows.constants, |r| r.parent.encode());
+
+		const A: usize = METADATA_CUSTOM_ATTRIBUTE;
+		for (i, r) in rows.custom_attributes.iter().enumerate() {
+			self.check_ref(A, i, r.parent.table_index(), r.parent.into_index(), false);
+			self.check_ref(A, i, r.ty.table_index(), r.ty.into_index(), false);
+			self.check_blob(A, i, r.value);
+		}
+		self.check_sorted(A, &rows.custom_attributes, |r| r.parent.encode());
+
+		const M: usize = METADATA_FIELD_MARSHAL;
+		for (i, r) in rows.field_marshals.iter().enumerate() {
+			self.check_ref(M, i, r.parent.table_index(), r.parent.into_index(), false);
+			self.check_sig(M, i, r.native_ty);
+		}
+		self.check_sorted(M, &rows.field_marshals, |r| r.parent.encode());
+
+		const S: usize = METADATA_DECL_SECURITY;
+		for (i, r) in rows.security_attributes.iter().enumerate() {
+			self.check_ref(S, i, r.parent.table_index(), r.parent.into_index(), false);
+			self.check_sig(S, i, r.permission_set);
+		}
+		self.check_sorted(S, &rows.security_attributes, |r| r.parent.encode());
+	}
+
+	fn layouts(&mut self, rows: &TableRows) {
+		const C: usize = METADATA_CLASS_LAYOUT;
+		for (i, r) in rows.class_layouts.iter().enumerate() {
+			self.check_ref(C, i, METADATA_TYPE_DEF, r.parent.into_index(), false);
+			if r.packing_size > 128 || !(r.packing_size == 0 || r.packing_size.is_power_of_two()) {
+				self.error(C, i, "Packing size shall be one of 0, 1, 2, 4, 8, 16, 32, 64 or 128.");
+			}
+		}
+		self.check_sorted(C, &rows.class_layouts, |r| r.parent.into_index());
+
+		const F: usize = METADATA_FIELD_LAYOUT;
+		for (i, r) in rows.field_layouts.iter().enumerate() {
+			self.check_ref(F, i, METADATA_FIELD, r.field.into_index(), false);
+		}
+		self.check_sorted(F, &rows.field_layouts, |r| r.field.into_index());
+	}
+
+	fn events_and_properties(&mut self, rows: &TableRows) {
+		const EM: usize = METADATA_EVENT_MAP;
+		for (i, r) in rows.event_maps.iter().enumerate() {
+			self.check_ref(EM, i, METADATA_TYPE_DEF, r.parent.into_index(), false);
+		}
+		let events = rows.event_maps.iter().map(|m| m.event_list.into_index());
+		self.check_runs(EM, METADATA_EVENT, events);
+
+		const E: usize = METADATA_EVENT;
+		for (i, r) in rows.events.iter().enumerate() {
+			self.check_name(E, i, r.name);
+			self.check_ref(E, i, r.ty.table_index(), r.ty.into_index(), true);
+		}
+
+		const PM: usize = METADATA_PROPERTY_MAP;
+		for (i, r) in rows.property_maps.iter().enumerate() {
+			self.check_ref(PM, i, METADATA_TYPE_DEF, r.parent.into_index(), false);
+		}
+		let properties = rows.property_maps.iter().map(|m| m.property_list.into_index());
+		self.check_runs(PM, METADATA_PROPERTY, properties);
+
+		const P: usize = METADATA_PROPERTY;
+		for (i, r) in rows.properties.iter().enumerate() {
+			self.check_name(P, i, r.name);
+			self.check_sig(P, i, r.ty);
+		}
+
+		const S: usize = METADATA_METHOD_SEMANTICS;
+		for (i, r) in rows.method_semantics.iter().enumerate() {
+			self.check_ref(S, i, METADATA_METHOD_DEF, r.method.into_index(), false);
+			self.check_ref(S, i, r.assoc.table_index(), r.assoc.into_index(), false);
+		}
+		self.check_sorted(S, &rows.method_semantics, |r| r.assoc.encode());
+	}
+
+	fn assemblies(&mut self, rows: &TableRows) {
+		const A: usize = METADATA_ASSEMBLY;
+		self.check_single(A, false);
+		for (i, r) in rows.assemblies.iter().enumerate() {
+			self.check_name(A, i, r.name);
+			self.check_string(A, i, r.culture);
+			self.check_blob(A, i, r.pub_key);
+		}
+
+		const R: usize = METADATA_ASSEMBLY_REF;
+		for (i, r) in rows.assembly_refs.iter().enumerate() {
+			self.check_name(R, i, r.name);
+			self.check_string(R, i, r.culture);
+			self.check_blob(R, i, r.pub_key_or_token);
+			self.check_blob(R, i, r.hash);
+		}
+
+		const F: usize = METADATA_FILE;
+		for (i, r) in rows.files.iter().enumerate() {
+			self.check_name(F, i, r.name);
+			self.check_sig(F, i, r.hash);
+		}
+
+		const E: usize = METADATA_EXPORTED_TYPE;
+		for (i, r) in rows.exported_types.iter().enumerate() {
+			self.check_name(E, i, r.name);
+			self.check_string(E, i, r.namespace);
+			self.check_ref(E, i, r.implementation.table_index(), r.implementation.into_index(), false);
+		}
+
+		const M: usize = METADATA_MANIFEST_RESOURCE;
+		for (i, r) in rows.manifest_resources.iter().enumerate() {
+			self.check_name(M, i, r.name);
+			self.check_ref(M, i, r.implementation.table_index(), r.implementation.into_index(), true);
+		}
+	}
+
+	fn generics(&mut self, rows: &TableRows) {
+		const P: usize = METADATA_GENERIC_PARAM;
+		for (i, r) in rows.generic_params.iter().enumerate() {
+			self.check_ref(P, i, r.owner.table_index(), r.owner.into_index(), false);
+			self.check_name(P, i, r.name);
+		}
+		self.check_sorted(P, &rows.generic_params, |r| (r.owner.encode(), r.number));
+
+		const C: usize = METADATA_GENERIC_PARAM_CONSTRAINT;
+		for (i, r) in rows.generic_param_constraints.iter().enumerate() {
+			self.check_ref(C, i, P, r.owner.into_index(), false);
+			self.check_ref(C, i, r.constraint.table_index(), r.constraint.into_index(), false);
+		}
+		self.check_sorted(C, &rows.generic_param_constraints, |r| r.owner.into_index());
+	}
+}
+
+/// 1-based rows of the table, fields or params, that have constants and
+/// marshalling descriptors.
+fn constants_and_marshals(rows: &TableRows, table: usize) -> (HashSet<usize>, HashSet<usize>) {
+	let constants = rows.constants.iter()
+		.filter(|c| c.parent.table_index() == table)
+		.map(|c| c.parent.into_index())
+		.collect();
+	let marshals = rows.field_marshals.iter()
+		.filter(|m| m.parent.table_index() == table)
+		.map(|m| m.parent.into_index())
+		.collect();
+	(constants, marshals)
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+use crate::Result;
+use crate::error::Error;
+use crate::buf::{Reading, Writing};
+use crate::cli::constants::*;
+use crate::cli::{TableRows, HasConstant, TypeDefOrRef, TypeSig, blob_at};
+use crate::pe;
+
+/// A compile-time constant of a field, parameter or property, II.22.9.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConstantValue {
+	Bool(bool),
+	/// UTF-16 code unit.
+	Char(u16),
+	I1(i8),
+	U1(u8),
+	I2(i16),
+	U2(u16),
+	I4(i32),
+	U4(u32),
+	I8(i64),
+	U8(u64),
+	R4(f32),
+	R8(f64),
+	String(String),
+	/// Null object reference.
+	Null,
+}
+
+impl ConstantValue {
+	/// Interprets the value blob according to the element type stored in
+	/// the Constant row.
+	pub fn parse(ty: u8, data: &[u8]) -> Result<ConstantValue> {
+		let offset = &mut 0usize;
+
+		let value = match ty {
+			ELEMENT_TYPE_BOOLEAN => ConstantValue::Bool(data.read::<u8>(offset)? != 0),
+			ELEMENT_TYPE_CHAR    => ConstantValue::Char(data.read(offset)?),
+			ELEMENT_TYPE_I1      => ConstantValue::I1(data.read(offset)?),
+			ELEMENT_TYPE_U1      => ConstantValue::U1(data.read(offset)?),
+			ELEMENT_TYPE_I2      => ConstantValue::I2(data.read(offset)?),
+			ELEMENT_TYPE_U2      => ConstantValue::U2(data.read(offset)?),
+			ELEMENT_TYPE_I4      => ConstantValue::I4(data.read(offset)?),
+			ELEMENT_TYPE_U4      => ConstantValue::U4(data.read(offset)?),
+			ELEMENT_TYPE_I8      => ConstantValue::I8(data.read(offset)?),
+			ELEMENT_TYPE_U8      => ConstantValue::U8(data.read(offset)?),
+			ELEMENT_TYPE_R4      => ConstantValue::R4(data.read(offset)?),
+			ELEMENT_TYPE_R8      => ConstantValue::R8(data.read(offset)?),
+			ELEMENT_TYPE_STRING  => {
+				// Unlike #US, there is no terminal byte here.
+				if data.len() & 1 != 0 {
+					Err("String constant has odd length.")?;
+				}
+				let wide: Vec<u16> = data.chunks_exact(2)
+					.map(|c| u16::from_le_bytes([c[0], c[1]]))
+					.collect();
+				let s = String::from_utf16(&wide)
+					.map_err(|_| Error::General("String constant is not a valid utf-16 string."))?;
+				*offset = data.len();
+				ConstantValue::String(s)
+			},
+			ELEMENT_TYPE_CLASS   => {
+				if data.read::<u32>(offset)? != 0 {
+					Err("Class constant shall be null.")?;
+				}
+				ConstantValue::Null
+			},
+			_ => Err("Unsupported constant type.")?,
+		};
+
+		if *offset != data.len() {
+			Err("Constant value size does not match its type.")?;
+		}
+
+		Ok(value)
+	}
+
+	/// Element type stored in the Constant row.
+	pub fn element_type(&self) -> u8 {
+		match self {
+			ConstantValue::Bool(_)   => ELEMENT_TYPE_BOOLEAN,
+			ConstantValue::Char(_)   => ELEMENT_TYPE_CHAR,
+			ConstantValue::I1(_)     => ELEMENT_TYPE_I1,
+			ConstantValue::U1(_)     => ELEMENT_TYPE_U1,
+			ConstantValue::I2(_)     => ELEMENT_TYPE_I2,
+			ConstantValue::U2(_)     => ELEMENT_TYPE_U2,
+			ConstantValue::I4(_)     => ELEMENT_TYPE_I4,
+			ConstantValue::U4(_)     => ELEMENT_TYPE_U4,
+			ConstantValue::I8(_)     => ELEMENT_TYPE_I8,
+			ConstantValue::U8(_)     => ELEMENT_TYPE_U8,
+			ConstantValue::R4(_)     => ELEMENT_TYPE_R4,
+			ConstantValue::R8(_)     => ELEMENT_TYPE_R8,
+			ConstantValue::String(_) => ELEMENT_TYPE_STRING,
+			ConstantValue::Null      => ELEMENT_TYPE_CLASS,
+		}
+	}
+
+	/// Writes the value blob.
+	pub fn write(&self, out: &mut Vec<u8>) {
+		match self {
+			ConstantValue::Bool(x)   => out.write(*x as u8),
+			ConstantValue::Char(x)   => out.write(*x),
+			ConstantValue::I1(x)     => out.write(*x),
+			ConstantValue::U1(x)     => out.write(*x),
+			ConstantValue::I2(x)     => out.write(*x),
+			ConstantValue::U2(x)     => out.write(*x),
+			ConstantValue::I4(x)     => out.write(*x),
+			ConstantValue::U4(x)     => out.write(*x),
+			ConstantValue::I8(x)     => out.write(*x),
+			ConstantValue::U8(x)     => out.write(*x),
+			ConstantValue::R4(x)     => out.write(*x),
+			ConstantValue::R8(x)     => out.write(*x),
+			ConstantValue::String(s) => {
+				for c in s.encode_utf16() {
+					out.write(c);
+				}
+			},
+			ConstantValue::Null      => out.write(0u32),
+		}
+	}
+}
+
+/// Displays the value as ILAsm writes field and parameter initializers,
+/// e.g. `int32(42)`.
+impl fmt::Display for ConstantValue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ConstantValue::Bool(x)   => write!(f, "bool({})", x),
+			ConstantValue::Char(x)   => write!(f, "char(0x{:04X})", x),
+			// Integers are printed as their bit patterns, as ILDasm does.
+			ConstantValue::I1(x)     => write!(f, "int8(0x{:02X})", *x as u8),
+			ConstantValue::U1(x)     => write!(f, "uint8(0x{:02X})", x),
+			ConstantValue::I2(x)     => write!(f, "int16(0x{:04X})", *x as u16),
+			ConstantValue::U2(x)     => write!(f, "uint16(0x{:04X})", x),
+			ConstantValue::I4(x)     => write!(f, "int32(0x{:08X})", *x as u32),
+			ConstantValue::U4(x)     => write!(f, "uint32(0x{:08X})", x),
+			ConstantValue::I8(x)     => write!(f, "int64(0x{:016X})", *x as u64),
+			ConstantValue::U8(x)     => write!(f, "uint64(0x{:016X})", x),
+			ConstantValue::R4(x)     => write!(f, "float32({:?})", x),
+			ConstantValue::R8(x)     => write!(f, "float64({:?})", x),
+			ConstantValue::String(s) => write!(f, "{:?}", s),
+			ConstantValue::Null      => write!(f, "nullref"),
+		}
+	}
+}
+
+impl TableRows {
+	/// Returns the constant of a field, parameter or property if it has one.
+	pub fn constant_of(&self, blobs: &[u8], parent: HasConstant) -> Result<Option<ConstantValue>> {
+		match self.constants.iter().find(|c| c.parent == parent) {
+			Some(c) => {
+				let data = blob_at(blobs, c.value.into_index())?;
+				Ok(Some(ConstantValue::parse(c.ty, data)?))
+			},
+			None => Ok(None),
+		}
+	}
+
+	/// Returns the initial data of the 0-based field row backed by FieldRVA,
+	/// sized by the field type. Compilers type such fields with value types
+	/// of explicit ClassLayout size, e.g. `__StaticArrayInitTypeSize=N`.
+	pub fn field_data<'a>(&self, image: &'a [u8], pe: &pe::Header, blobs: &[u8], field: usize) -> Result<&'a [u8]> {
+		let f = self.fields.get(field).ok_or("Field row is out of the table bounds.")?;
+		let rva = self.field_rvas.iter()
+			.find(|r| r.field.into_index() == field + 1)
+			.ok_or("Field has no RVA.")?
+			.rva;
+
+		let size = match TypeSig::parse_field_sig(blob_at(blobs, f.sig.into_index())?)? {
+			TypeSig::Boolean | TypeSig::I1 | TypeSig::U1 => 1,
+			TypeSig::Char | TypeSig::I2 | TypeSig::U2    => 2,
+			TypeSig::I4 | TypeSig::U4 | TypeSig::R4      => 4,
+			TypeSig::I8 | TypeSig::U8 | TypeSig::R8      => 8,
+			TypeSig::ValueType(TypeDefOrRef::TypeDef(t)) => {
+				self.class_layout_of((t as usize).wrapping_sub(1))
+					.ok_or("Field value type has no explicit size.")?
+					.class_size as usize
+			},
+			_ => Err("Unsupported type of field with RVA.")?,
+		};
+
+		let offset = pe.rva2offset(rva as usize).ok_or("Failed to convert field RVA.")?;
+		let data = image.get(offset..offset + size).ok_or("Field data exceeds the image.")?;
+		Ok(data)
+	}
+}
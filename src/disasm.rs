@@ -0,0 +1,1068 @@
+use std::fmt;
+
+use crate::Result;
+use crate::assembly::Assembly;
+use crate::buf::Reading;
+use crate::cli::*;
+
+const INDENT: &str = "  ";
+
+/// Produces an ILDasm-like listing of the whole assembly, which ILAsm is
+/// expected to accept back.
+pub fn disassemble(asm: &Assembly) -> Result<String> {
+	let mut d = Disassembler {
+		asm,
+		rows: &asm.rows,
+		strings: asm.strings(),
+		blobs: asm.blobs(),
+		out: String::new(),
+		indent: 0,
+		declared: vec![false; asm.rows.type_defs.len()],
+	};
+
+	d.manifest()?;
+
+	let data = d.field_data();
+
+	// Global members belong to the `<Module>` type, which is always first.
+	if !d.rows.type_defs.is_empty() {
+		d.members(0)?;
+	}
+	for t in 1..d.rows.type_defs.len() {
+		if d.enclosing_of(t).is_none() {
+			d.class(t)?;
+		}
+	}
+
+	for (rva, bytes) in data {
+		match bytes {
+			Ok(bytes) => d.line(format!(".data cil I_{:08X} = bytearray ({})", rva, hex(bytes))),
+			Err(e) => d.line(format!("// Failed to read the data at I_{:08X}: {}", rva, e)),
+		}
+	}
+
+	Ok(d.out)
+}
+
+struct Disassembler<'a, 'b> {
+	asm: &'b Assembly<'a>,
+	rows: &'b TableRows,
+	strings: &'a [u8],
+	blobs: &'a [u8],
+	out: String,
+	indent: usize,
+	/// Types already listed, nested ones may claim more than one enclosing
+	/// type or even enclose themselves.
+	declared: Vec<bool>,
+}
+
+/// A region of a method body wrapped into braces.
+struct Block {
+	start: u32,
+	end: u32,
+	/// Line preceding the opening brace, if any.
+	header: Option<String>,
+	footer: &'static str,
+	/// Blocks not starting at an instruction boundary are never opened.
+	opened: bool,
+}
+
+impl TypeNames for Disassembler<'_, '_> {
+	fn write_type(&self, f: &mut fmt::Formatter, ty: TypeDefOrRef) -> fmt::Result {
+		write!(f, "{}", self.type_name(ty))
+	}
+}
+
+impl<'a> Disassembler<'a, '_> {
+	/// Writes the text indented, continuation lines included.
+	fn line(&mut self, s: impl AsRef<str>) {
+		for s in s.as_ref().split('\n') {
+			for _ in 0..if s.is_empty() { 0 } else { self.indent } {
+				self.out.push_str(INDENT);
+			}
+			self.out.push_str(s);
+			self.out.push('\n');
+		}
+	}
+
+	fn open(&mut self) {
+		self.line("{");
+		self.indent += 1;
+	}
+
+	fn close(&mut self, comment: impl AsRef<str>) {
+		self.indent -= 1;
+		let comment = comment.as_ref();
+		if comment.is_empty() {
+			self.line("}");
+		} else {
+			self.line(format!("}} // {}", comment));
+		}
+	}
+
+	fn string(&self, i: StringIndex) -> &'a str {
+		string_at(self.strings, i.into_index()).unwrap_or("")
+	}
+
+	fn blob(&self, i: BlobIndex) -> &'a [u8] {
+		blob_at(self.blobs, i.into_index()).unwrap_or(&[])
+	}
+
+	// Names.
+
+	/// Full name of a type reference, e.g. `[mscorlib]System.Object`.
+	fn type_name(&self, ty: TypeDefOrRef) -> String {
+		self.nested_type_name(ty, 0).unwrap_or_else(|| format!("/*{:08x}*/", ty.token()))
+	}
+
+	/// Enclosing types are named first. A chain of them longer than their
+	/// table can only be a cycle, which leaves the type with no name.
+	fn nested_type_name(&self, ty: TypeDefOrRef, depth: usize) -> Option<String> {
+		let row = ty.into_index().wrapping_sub(1);
+		let name = match ty {
+			TypeDefOrRef::TypeDef(_) => match self.rows.type_defs.get(row) {
+				Some(_) if depth > self.rows.type_defs.len() => return None,
+				Some(t) => {
+					let prefix = match self.enclosing_of(row) {
+						Some(e) => self.nested_type_name(TypeDefOrRef::TypeDef(e as u32 + 1), depth + 1)? + "/",
+						None => String::new(),
+					};
+					prefix + &dotted(self.string(t.namespace), self.string(t.name))
+				},
+				None => format!("/*{:08x}*/", ty.token()),
+			},
+			TypeDefOrRef::TypeRef(_) => match self.rows.type_refs.get(row) {
+				Some(_) if depth > self.rows.type_refs.len() => return None,
+				Some(t) => {
+					let prefix = match t.scope {
+						ResolutionScope::AssemblyRef(r) => match self.rows.assembly_refs.get((r as usize).wrapping_sub(1)) {
+							Some(a) => format!("[{}]", quote(self.string(a.name))),
+							None => String::new(),
+						},
+						ResolutionScope::ModuleRef(r) => match self.rows.module_refs.get((r as usize).wrapping_sub(1)) {
+							Some(m) => format!("[.module {}]", quote(self.string(m.name))),
+							None => String::new(),
+						},
+						ResolutionScope::TypeRef(r) => self.nested_type_name(TypeDefOrRef::TypeRef(r), depth + 1)? + "/",
+						ResolutionScope::Module(_) => String::new(),
+					};
+					prefix + &dotted(self.string(t.namespace), self.string(t.name))
+				},
+				None => format!("/*{:08x}*/", ty.token()),
+			},
+			TypeDefOrRef::TypeSpec(_) => {
+				let sig = self.rows.type_specs.get(row)
+					.map(|s| TypeSig::parse_type_spec(self.blob(s.sig)));
+				match sig {
+					Some(Ok(sig)) => sig.display(self).to_string(),
+					_ => format!("/*{:08x}*/", ty.token()),
+				}
+			},
+		};
+		Some(name)
+	}
+
+	fn enclosing_of(&self, type_def: usize) -> Option<usize> {
+		self.rows.nested_classes.iter()
+			.find(|n| n.nested.into_index() == type_def + 1)
+			.map(|n| n.enclosing.into_index().wrapping_sub(1))
+	}
+
+	/// Type-qualified name of a method or a field owned by the type row.
+	fn member_name(&self, owner: Option<usize>, name: &str) -> String {
+		match owner {
+			// Global members are not qualified.
+			Some(0) | None => quote(name),
+			Some(t) => format!("{}::{}", self.type_name(TypeDefOrRef::TypeDef(t as u32 + 1)), quote(name)),
+		}
+	}
+
+	fn generic_params(&self, owner: TypeOrMethodDef) -> String {
+		let mut params: Vec<_> = self.rows.generic_params.iter()
+			.filter(|p| p.owner == owner)
+			.collect();
+		if params.is_empty() {
+			return String::new();
+		}
+		params.sort_by_key(|p| p.number);
+		let names: Vec<_> = params.iter().map(|p| quote(self.string(p.name))).collect();
+		format!("<{}>", names.join(", "))
+	}
+
+	// Tokens.
+
+	fn method_ref(&self, method: usize) -> String {
+		let m = match self.rows.method_defs.get(method) {
+			Some(m) => m,
+			None => return format!("/*{:08x}*/", (METADATA_METHOD_DEF as u32) << 24 | (method as u32 + 1)),
+		};
+		let name = self.member_name(self.rows.method_owner(method), self.string(m.name));
+		match MethodSig::parse(self.blob(m.sig)) {
+			Ok(sig) => sig.named_with(&name, self).to_string(),
+			Err(_) => name,
+		}
+	}
+
+	fn field_ref(&self, field: usize) -> String {
+		let f = match self.rows.fields.get(field) {
+			Some(f) => f,
+			None => return format!("/*{:08x}*/", (METADATA_FIELD as u32) << 24 | (field as u32 + 1)),
+		};
+		let name = self.member_name(self.rows.field_owner(field), self.string(f.name));
+		match TypeSig::parse_field_sig(self.blob(f.sig)) {
+			Ok(ty) => format!("{} {}", ty.display(self), name),
+			Err(_) => name,
+		}
+	}
+
+	fn member_ref_parent(&self, parent: MemberRefParent) -> String {
+		let row = parent.into_index() as u32;
+		match parent {
+			MemberRefParent::TypeDef(_)  => self.type_name(TypeDefOrRef::TypeDef(row)),
+			MemberRefParent::TypeRef(_)  => self.type_name(TypeDefOrRef::TypeRef(row)),
+			MemberRefParent::TypeSpec(_) => self.type_name(TypeDefOrRef::TypeSpec(row)),
+			MemberRefParent::ModuleRef(_) | MemberRefParent::MethodDef(_) => String::new(),
+		}
+	}
+
+	/// Returns the reference and whether it is a field one.
+	fn member_ref(&self, member: usize) -> (String, bool) {
+		let m = match self.rows.member_refs.get(member) {
+			Some(m) => m,
+			None => return (format!("/*{:08x}*/", (METADATA_MEMBER_REF as u32) << 24 | (member as u32 + 1)), false),
+		};
+
+		// Vararg call sites reference the method definition directly.
+		if let MemberRefParent::MethodDef(d) = m.class {
+			return (self.method_ref((d as usize).wrapping_sub(1)), false);
+		}
+
+		let parent = self.member_ref_parent(m.class);
+		let name = if parent.is_empty() {
+			quote(self.string(m.name))
+		} else {
+			format!("{}::{}", parent, quote(self.string(m.name)))
+		};
+
+		let sig = self.blob(m.sig);
+		if sig.first().is_some_and(|b| b & SIG_KIND_MASK == SIG_FIELD) {
+			match TypeSig::parse_field_sig(sig) {
+				Ok(ty) => (format!("{} {}", ty.display(self), name), true),
+				Err(_) => (name, true),
+			}
+		} else {
+			match MethodSig::parse(sig) {
+				Ok(sig) => (sig.named_with(&name, self).to_string(), false),
+				Err(_) => (name, false),
+			}
+		}
+	}
+
+	fn method_spec(&self, spec: usize) -> String {
+		let s = match self.rows.method_specs.get(spec) {
+			Some(s) => s,
+			None => return format!("/*{:08x}*/", (METADATA_METHOD_SPEC as u32) << 24 | (spec as u32 + 1)),
+		};
+
+		let (owner, name, sig) = match s.method {
+			MethodDefOrRef::MethodDef(m) => {
+				let m = (m as usize).wrapping_sub(1);
+				match self.rows.method_defs.get(m) {
+					Some(d) => (
+						self.member_name(self.rows.method_owner(m), self.string(d.name)),
+						String::new(),
+						self.blob(d.sig)),
+					None => return format!("/*{:08x}*/", s.method.token()),
+				}
+			},
+			MethodDefOrRef::MemberRef(m) => match self.rows.member_refs.get((m as usize).wrapping_sub(1)) {
+				Some(r) => (self.member_ref_parent(r.class), quote(self.string(r.name)), self.blob(r.sig)),
+				None => return format!("/*{:08x}*/", s.method.token()),
+			},
+		};
+		let name = if name.is_empty() { owner } else { format!("{}::{}", owner, name) };
+
+		let args = match generic_inst(self.blob(s.inst)) {
+			Ok(args) => args.iter().map(|a| a.display(self).to_string()).collect::<Vec<_>>().join(","),
+			Err(_) => return name,
+		};
+		let name = format!("{}<{}>", name, args);
+
+		match MethodSig::parse(sig) {
+			Ok(mut sig) => {
+				// Arity is already evident from the instantiation.
+				sig.call_conv = CallingConvention::Default;
+				sig.named_with(&name, self).to_string()
+			},
+			Err(_) => name,
+		}
+	}
+
+	fn token(&self, token: MetadataToken) -> String {
+		let row = token.row_index();
+		match token.table_index() {
+			METADATA_TYPE_DEF   => self.type_name(TypeDefOrRef::TypeDef(row as u32 + 1)),
+			METADATA_TYPE_REF   => self.type_name(TypeDefOrRef::TypeRef(row as u32 + 1)),
+			METADATA_TYPE_SPEC  => self.type_name(TypeDefOrRef::TypeSpec(row as u32 + 1)),
+			METADATA_FIELD      => self.field_ref(row),
+			METADATA_METHOD_DEF => self.method_ref(row),
+			METADATA_MEMBER_REF => self.member_ref(row).0,
+			METADATA_METHOD_SPEC => self.method_spec(row),
+			_ => format!("/*{:08x}*/", token.value()),
+		}
+	}
+
+	/// `ldtoken` tells members from types by a keyword.
+	fn ldtoken(&self, token: MetadataToken) -> String {
+		match token.table_index() {
+			METADATA_FIELD => format!("field {}", self.token(token)),
+			METADATA_METHOD_DEF | METADATA_METHOD_SPEC => format!("method {}", self.token(token)),
+			METADATA_MEMBER_REF => match self.member_ref(token.row_index()) {
+				(s, true) => format!("field {}", s),
+				(s, false) => format!("method {}", s),
+			},
+			_ => self.token(token),
+		}
+	}
+
+	fn custom_attributes(&mut self, parent: HasCustomAttribute) {
+		let attributes: Vec<_> = self.rows.attributes_of(parent).cloned().collect();
+		for a in attributes {
+			let ctor = match a.ty {
+				CustomAttributeType::MethodDef(m) => self.method_ref((m as usize).wrapping_sub(1)),
+				CustomAttributeType::MemberRef(m) => self.member_ref((m as usize).wrapping_sub(1)).0,
+			};
+			let value = self.blob(a.value);
+			self.line(format!(".custom {} = ({})", ctor, hex(value)));
+		}
+	}
+
+	fn security(&mut self, parent: HasDeclSecurity) {
+		let decls: Vec<_> = self.rows.security_of(parent).cloned().collect();
+		for decl in decls {
+			let set = self.blob(decl.permission_set);
+			self.line(format!(".permissionset {} = ({})", security_action_name(decl.action), hex(set)));
+		}
+	}
+
+	// Manifest.
+
+	fn manifest(&mut self) -> Result<()> {
+		for r in self.rows.assembly_refs.iter() {
+			self.line(format!(".assembly extern {}", quote_dotted(self.string(r.name))));
+			self.open();
+			let key = self.blob(r.pub_key_or_token);
+			if !key.is_empty() {
+				// PublicKey flag tells the full key from its token.
+				let kind = if r.flags & 0x1 != 0 { "publickey" } else { "publickeytoken" };
+				self.line(format!(".{} = ({})", kind, hex(key)));
+			}
+			self.line(format!(".ver {}:{}:{}:{}", r.major_version, r.minor_version, r.build_number, r.revision_number));
+			self.close("");
+		}
+
+		if let Some(a) = self.rows.assemblies.first() {
+			self.line(format!(".assembly {}", quote_dotted(self.string(a.name))));
+			self.open();
+			self.custom_attributes(HasCustomAttribute::Assembly(1));
+			self.security(HasDeclSecurity::Assembly(1));
+			let key = self.blob(a.pub_key);
+			if !key.is_empty() {
+				self.line(format!(".publickey = ({})", hex(key)));
+			}
+			let algo = match a.hash_algo {
+				HashAlgo::MD5  => 0x8003,
+				HashAlgo::SHA1 => 0x8004,
+			};
+			self.line(format!(".hash algorithm 0x{:08x}", algo));
+			let culture = self.string(a.culture);
+			if !culture.is_empty() {
+				self.line(format!(".culture {:?}", culture));
+			}
+			self.line(format!(".ver {}:{}:{}:{}", a.major_version, a.minor_version, a.build_number, a.revision_number));
+			self.close("");
+		}
+
+		if let Some(m) = self.rows.modules.first() {
+			self.line(format!(".module {}", quote_dotted(self.string(m.name))));
+			let guids = parse_guids(self.asm.guids())?;
+			if let Some(mvid) = guids.get(m.mvid.into_index().wrapping_sub(1)) {
+				self.line(format!("// MVID: {}", mvid));
+			}
+			self.custom_attributes(HasCustomAttribute::Module(1));
+		}
+		self.line("");
+
+		Ok(())
+	}
+
+	/// Data of fields with RVA, sorted by their RVA.
+	fn field_data(&self) -> Vec<(u32, Result<&'a [u8]>)> {
+		let mut data: Vec<_> = self.rows.field_rvas.iter()
+			.map(|r| {
+				let field = r.field.into_index().wrapping_sub(1);
+				(r.rva, self.rows.field_data(self.asm.image, &self.asm.pe, self.blobs, field))
+			})
+			.collect();
+		data.sort_by_key(|d| d.0);
+		data.dedup_by_key(|d| d.0);
+		data
+	}
+
+	// Types.
+
+	fn class(&mut self, t: usize) -> Result<()> {
+		match self.declared.get_mut(t) {
+			Some(declared) if !*declared => *declared = true,
+			_ => return Ok(()),
+		}
+		let def = self.rows.type_defs[t].clone();
+		let flags = def.flags;
+
+		let mut decl = String::from(".class ");
+		if flags & TYPE_CLASS_SEMANTICS_MASK == TYPE_INTERFACE {
+			decl.push_str("interface ");
+		}
+		decl.push_str(match flags & TYPE_VISIBILITY_MASK {
+			TYPE_NOT_PUBLIC            => "private ",
+			TYPE_PUBLIC                => "public ",
+			TYPE_NESTED_PUBLIC         => "nested public ",
+			TYPE_NESTED_PRIVATE        => "nested private ",
+			TYPE_NESTED_FAMILY         => "nested family ",
+			TYPE_NESTED_ASSEMBLY       => "nested assembly ",
+			TYPE_NESTED_FAM_AND_ASSEM  => "nested famandassem ",
+			_                          => "nested famorassem ",
+		});
+		if flags & TYPE_ABSTRACT != 0 {
+			decl.push_str("abstract ");
+		}
+		decl.push_str(match flags & TYPE_LAYOUT_MASK {
+			TYPE_SEQUENTIAL_LAYOUT => "sequential ",
+			TYPE_EXPLICIT_LAYOUT   => "explicit ",
+			_                      => "auto ",
+		});
+		decl.push_str(match flags & TYPE_STRING_FORMAT_MASK {
+			TYPE_UNICODE_CLASS => "unicode ",
+			TYPE_AUTO_CLASS    => "autochar ",
+			TYPE_ANSI_CLASS    => "ansi ",
+			_                  => "",
+		});
+		let keywords = [
+			(TYPE_IMPORT, "import "),
+			(TYPE_SERIALIZABLE, "serializable "),
+			(TYPE_SEALED, "sealed "),
+			(TYPE_SPECIAL_NAME, "specialname "),
+			(TYPE_RT_SPECIAL_NAME, "rtspecialname "),
+			(TYPE_BEFORE_FIELD_INIT, "beforefieldinit "),
+		];
+		for (flag, keyword) in keywords.iter() {
+			if flags & flag != 0 {
+				decl.push_str(keyword);
+			}
+		}
+		// Nested types are declared by their own name only.
+		decl.push_str(&dotted(self.string(def.namespace), self.string(def.name)));
+		decl.push_str(&self.generic_params(TypeOrMethodDef::TypeDef(t as u32 + 1)));
+		self.line(decl);
+
+		if def.extends.into_index() != 0 {
+			self.line(format!("       extends {}", self.type_name(def.extends)));
+		}
+		let interfaces: Vec<_> = self.rows.interface_impls.iter()
+			.filter(|i| i.class.into_index() == t + 1)
+			.map(|i| self.type_name(i.iface))
+			.collect();
+		if !interfaces.is_empty() {
+			self.line(format!("       implements {}", interfaces.join(",\n                  ")));
+		}
+
+		self.open();
+		self.custom_attributes(HasCustomAttribute::TypeDef(t as u32 + 1));
+		self.security(HasDeclSecurity::TypeDef(t as u32 + 1));
+		if let Some(layout) = self.rows.class_layout_of(t) {
+			let (pack, size) = (layout.packing_size, layout.class_size);
+			self.line(format!(".pack {}", pack));
+			self.line(format!(".size {}", size));
+		}
+
+		let nested: Vec<_> = self.rows.nested_classes.iter()
+			.filter(|n| n.enclosing.into_index() == t + 1)
+			.map(|n| n.nested.into_index().wrapping_sub(1))
+			.collect();
+		for n in nested {
+			self.class(n)?;
+		}
+
+		self.members(t)?;
+
+		let name = self.type_name(TypeDefOrRef::TypeDef(t as u32 + 1));
+		self.close(format!("end of class {}", name));
+		self.line("");
+
+		Ok(())
+	}
+
+	fn members(&mut self, t: usize) -> Result<()> {
+		for field in self.rows.fields_of(t) {
+			self.field(field)?;
+		}
+		for method in self.rows.methods_of(t) {
+			self.method(method)?;
+		}
+		Ok(())
+	}
+
+	fn field(&mut self, field: usize) -> Result<()> {
+		let f = self.rows.fields[field].clone();
+		let flags = f.flags;
+
+		let mut decl = String::from(".field ");
+		if let Some(layout) = self.rows.field_layouts.iter().find(|l| l.field.into_index() == field + 1) {
+			decl.push_str(&format!("[{}] ", layout.offset));
+		}
+		decl.push_str(match flags & FIELD_ACCESS_MASK {
+			FIELD_PRIVATE       => "private ",
+			FIELD_FAM_AND_ASSEM => "famandassem ",
+			FIELD_ASSEMBLY      => "assembly ",
+			FIELD_FAMILY        => "family ",
+			FIELD_FAM_OR_ASSEM  => "famorassem ",
+			FIELD_PUBLIC        => "public ",
+			_                   => "privatescope ",
+		});
+		let keywords = [
+			(FIELD_STATIC, "static "),
+			(FIELD_INIT_ONLY, "initonly "),
+			(FIELD_LITERAL, "literal "),
+			(FIELD_NOT_SERIALIZED, "notserialized "),
+			(FIELD_SPECIAL_NAME, "specialname "),
+			(FIELD_RT_SPECIAL_NAME, "rtspecialname "),
+		];
+		for (flag, keyword) in keywords.iter() {
+			if flags & flag != 0 {
+				decl.push_str(keyword);
+			}
+		}
+		match self.rows.marshal_of(self.blobs, HasFieldMarshall::Field(field as u32 + 1)) {
+			Ok(Some(marshal)) => decl.push_str(&format!("marshal({}) ", marshal)),
+			Ok(None) => {},
+			Err(e) => self.line(format!("// Failed to parse the marshalling descriptor: {}", e)),
+		}
+		let ty = TypeSig::parse_field_sig(self.blob(f.sig))?;
+		decl.push_str(&format!("{} {}", ty.display(self), quote(self.string(f.name))));
+
+		if let Some(r) = self.rows.field_rvas.iter().find(|r| r.field.into_index() == field + 1) {
+			decl.push_str(&format!(" at I_{:08X}", r.rva));
+		}
+		match self.rows.constant_of(self.blobs, HasConstant::Field(field as u32 + 1)) {
+			Ok(Some(value)) => decl.push_str(&format!(" = {}", value)),
+			Ok(None) => {},
+			Err(e) => self.line(format!("// Failed to parse the constant: {}", e)),
+		}
+		self.line(decl);
+		self.custom_attributes(HasCustomAttribute::Field(field as u32 + 1));
+
+		Ok(())
+	}
+
+	fn method(&mut self, method: usize) -> Result<()> {
+		let m = self.rows.method_defs[method].clone();
+		let flags = m.flags;
+		let sig = MethodSig::parse(self.blob(m.sig))?;
+
+		let mut decl = String::from(".method ");
+		decl.push_str(match flags & METHOD_MEMBER_ACCESS_MASK {
+			METHOD_PRIVATE       => "private ",
+			METHOD_FAM_AND_ASSEM => "famandassem ",
+			METHOD_ASSEM         => "assembly ",
+			METHOD_FAMILY        => "family ",
+			METHOD_FAM_OR_ASSEM  => "famorassem ",
+			METHOD_PUBLIC        => "public ",
+			_                    => "privatescope ",
+		});
+		let keywords = [
+			(METHOD_FINAL, "final "),
+			(METHOD_HIDE_BY_SIG, "hidebysig "),
+			(METHOD_NEW_SLOT, "newslot "),
+			(METHOD_STRICT, "strict "),
+			(METHOD_SPECIAL_NAME, "specialname "),
+			(METHOD_RT_SPECIAL_NAME, "rtspecialname "),
+			(METHOD_ABSTRACT, "abstract "),
+			(METHOD_VIRTUAL, "virtual "),
+			(METHOD_STATIC, "static "),
+		];
+		for (flag, keyword) in keywords.iter() {
+			if flags & flag != 0 {
+				decl.push_str(keyword);
+			}
+		}
+		if flags & METHOD_PINVOKE_IMPL != 0 {
+			let import = self.rows.impl_maps.iter()
+				.find(|i| i.member_fwd == MemberForwarded::MethodDef(method as u32 + 1));
+			if let Some(import) = import {
+				let module = self.rows.module_refs.get(import.scope.into_index().wrapping_sub(1))
+					.map_or("", |m| self.string(m.name));
+				decl.push_str(&format!("pinvokeimpl({:?} as {:?}) ", module, self.string(import.name)));
+			}
+		}
+
+		decl.push_str(&sig.call_conv_keywords());
+		decl.push_str(&sig.ret.display(self).to_string());
+		decl.push(' ');
+		decl.push_str(&quote(self.string(m.name)));
+		decl.push_str(&self.generic_params(TypeOrMethodDef::MethodDef(method as u32 + 1)));
+
+		let params: Vec<_> = self.rows.params_of(method)
+			.map(|p| (p, self.rows.params[p].clone()))
+			.collect();
+		let mut args = Vec::with_capacity(sig.params.len());
+		for (i, ty) in sig.params.iter().enumerate() {
+			let mut arg = String::new();
+			if let Some((p, param)) = params.iter().find(|(_, p)| p.seq as usize == i + 1) {
+				if param.flags & PARAM_IN != 0 {
+					arg.push_str("[in] ");
+				}
+				if param.flags & PARAM_OUT != 0 {
+					arg.push_str("[out] ");
+				}
+				if param.flags & PARAM_OPTIONAL != 0 {
+					arg.push_str("[opt] ");
+				}
+				arg.push_str(&ty.display(self).to_string());
+				match self.rows.marshal_of(self.blobs, HasFieldMarshall::Param(*p as u32 + 1)) {
+					Ok(Some(marshal)) => arg.push_str(&format!(" marshal({})", marshal)),
+					Ok(None) => {},
+					Err(e) => self.line(format!("// Failed to parse the marshalling descriptor of parameter {}: {}", i + 1, e)),
+				}
+				let name = self.string(param.name);
+				if !name.is_empty() {
+					arg.push(' ');
+					arg.push_str(&quote(name));
+				}
+			} else {
+				arg.push_str(&ty.display(self).to_string());
+			}
+			args.push(arg);
+		}
+		decl.push_str(&format!("({})", args.join(", ")));
+
+		let impl_flags = m.impl_flags;
+		decl.push_str(match impl_flags & METHOD_IMPL_CODE_TYPE_MASK {
+			METHOD_IMPL_NATIVE  => " native",
+			METHOD_IMPL_OPTIL   => " optil",
+			METHOD_IMPL_RUNTIME => " runtime",
+			_                   => " cil",
+		});
+		decl.push_str(if impl_flags & METHOD_IMPL_MANAGED_MASK == METHOD_IMPL_UNMANAGED { " unmanaged" } else { " managed" });
+		let keywords = [
+			(METHOD_IMPL_FORWARD_REF, " forwardref"),
+			(METHOD_IMPL_PRESERVE_SIG, " preservesig"),
+			(METHOD_IMPL_INTERNAL_CALL, " internalcall"),
+			(METHOD_IMPL_SYNCHRONIZED, " synchronized"),
+			(METHOD_IMPL_NO_INLINING, " noinlining"),
+			(METHOD_IMPL_NO_OPTIMIZATION, " nooptimization"),
+		];
+		for (flag, keyword) in keywords.iter() {
+			if impl_flags & flag != 0 {
+				decl.push_str(keyword);
+			}
+		}
+		self.line(decl);
+
+		self.open();
+		let token = (METADATA_METHOD_DEF as u32) << 24 | (method as u32 + 1);
+		if self.asm.cli.ep_token == token {
+			self.line(".entrypoint");
+		}
+		self.custom_attributes(HasCustomAttribute::MethodDef(method as u32 + 1));
+		self.security(HasDeclSecurity::MethodDef(method as u32 + 1));
+		for (p, param) in params.iter() {
+			let value = match self.rows.constant_of(self.blobs, HasConstant::Param(*p as u32 + 1)) {
+				Ok(value) => value,
+				Err(e) => {
+					self.line(format!("// Failed to parse the constant of parameter {}: {}", param.seq, e));
+					None
+				},
+			};
+			let has_attributes = self.rows.attributes_of(HasCustomAttribute::Param(*p as u32 + 1)).next().is_some();
+			if value.is_none() && !has_attributes {
+				continue;
+			}
+			match value {
+				Some(value) => self.line(format!(".param [{}] = {}", param.seq, value)),
+				None => self.line(format!(".param [{}]", param.seq)),
+			}
+			self.custom_attributes(HasCustomAttribute::Param(*p as u32 + 1));
+		}
+
+		match self.asm.method_body(method) {
+			Ok(Some(body)) => {
+				// Arguments are numbered from zero, including `this`.
+				let first = if sig.has_this && !sig.explicit_this { 1 } else { 0 };
+				let arg_names: Vec<_> = (0..sig.params.len() + first)
+					.map(|i| {
+						params.iter()
+							.find(|(_, p)| i >= first && p.seq as usize == i - first + 1)
+							.map(|(_, p)| self.string(p.name))
+							.filter(|n| !n.is_empty())
+							.map_or_else(|| i.to_string(), quote)
+					})
+					.collect();
+				self.body(&body, &arg_names)?
+			},
+			Ok(None) => {},
+			Err(e) => self.line(format!("// Failed to parse the method body: {}", e)),
+		}
+
+		let name = self.member_name(self.rows.method_owner(method), self.string(m.name));
+		self.close(format!("end of method {}", name));
+		self.line("");
+
+		Ok(())
+	}
+
+	// Code.
+
+	fn body(&mut self, body: &MethodBody, arg_names: &[String]) -> Result<()> {
+		self.line(format!("// Code size {} (0x{:x})", body.code.len(), body.code.len()));
+		self.line(format!(".maxstack {}", body.max_stack));
+
+		let locals = self.rows.local_var_sig(self.blobs, body.local_var_sig_tok)?;
+		if !locals.locals.is_empty() {
+			let vars: Vec<_> = locals.locals.iter()
+				.enumerate()
+				.map(|(i, l)| format!("{} V_{}", l.display(self), i))
+				.collect();
+			let init = if body.init_locals { "init " } else { "" };
+			self.line(format!(".locals {}({})", init, vars.join(", ")));
+		}
+
+		let mut blocks = self.blocks(body);
+		for ins in Instructions::new(body.code) {
+			let ins = match ins {
+				Ok(ins) => ins,
+				Err(e) => {
+					self.line(format!("// Failed to decode an instruction: {}", e));
+					break;
+				},
+			};
+			self.blocks_at(&mut blocks, ins.offset);
+
+			let mut offset = ins.offset;
+			for prefix in ins.prefixes.iter() {
+				let (s, size) = match prefix {
+					Prefix::Unaligned(a)   => (format!("unaligned. {}", a), 3),
+					Prefix::Volatile       => ("volatile.".to_owned(), 2),
+					Prefix::Tail           => ("tail.".to_owned(), 2),
+					Prefix::Constrained(t) => (format!("constrained. {}", self.token(*t)), 6),
+					Prefix::No(mask)       => (format!("no. {}", mask), 3),
+					Prefix::ReadOnly       => ("readonly.".to_owned(), 2),
+				};
+				self.line(format!("IL_{:04x}:  {}", offset, s));
+				offset += size;
+			}
+
+			let name = dump_opcode(ins.opcode);
+			let operand = self.operand(&ins, arg_names);
+			if operand.is_empty() {
+				self.line(format!("IL_{:04x}:  {}", offset, name));
+			} else {
+				self.line(format!("IL_{:04x}:  {:<10} {}", offset, name, operand));
+			}
+		}
+		self.blocks_at(&mut blocks, body.code.len() as u32);
+
+		Ok(())
+	}
+
+	fn operand(&self, ins: &Instruction, arg_names: &[String]) -> String {
+		let is_arg = matches!(ins.opcode, LDARG_S | LDARGA_S | STARG_S | LDARG | LDARGA | STARG);
+		let var = |n: usize| if is_arg {
+			arg_names.get(n).cloned().unwrap_or_else(|| n.to_string())
+		} else {
+			format!("V_{}", n)
+		};
+
+		match &ins.operand {
+			Operand::None      => String::new(),
+			Operand::U8(x)     => var(*x as usize),
+			Operand::U16(x)    => var(*x as usize),
+			Operand::I8(x)     => x.to_string(),
+			Operand::I32(x)    => x.to_string(),
+			Operand::I64(x)    => x.to_string(),
+			Operand::F32(x) if x.is_finite() => format!("{:?}", x),
+			Operand::F32(x)    => format!("({})", hex(&x.to_le_bytes())),
+			Operand::F64(x) if x.is_finite() => format!("{:?}", x),
+			Operand::F64(x)    => format!("({})", hex(&x.to_le_bytes())),
+			Operand::Branch(t) => format!("IL_{:04x}", t),
+			// One target per line, aligned past the opening parenthesis.
+			Operand::Switch(targets) => {
+				let labels: Vec<_> = targets.iter().map(|t| format!("{:22}IL_{:04x}", "", t)).collect();
+				format!("( \n{})", labels.join(",\n"))
+			},
+			Operand::Token(t) if ins.opcode == LDTOKEN => self.ldtoken(*t),
+			Operand::Token(t) if ins.opcode == CALLI => {
+				match self.rows.standalone_method_sig(self.blobs, t.value()) {
+					Ok(sig) => sig.named_with("", self).to_string(),
+					Err(_) => format!("/*{:08x}*/", t.value()),
+				}
+			},
+			Operand::Token(t)  => self.token(*t),
+			Operand::String(offset) => {
+				match user_string_at(self.asm.user_strings(), *offset as usize) {
+					Ok(s) => string_literal(&s),
+					Err(_) => format!("/*{:08x}*/", 0x7000_0000 | offset),
+				}
+			},
+		}
+	}
+
+	/// Turns exception clauses into nested blocks. Clauses sharing the same
+	/// protected region share a single `.try` block.
+	fn blocks(&self, body: &MethodBody) -> Vec<Block> {
+		let mut blocks: Vec<Block> = Vec::new();
+		for c in body.clauses.iter() {
+			let shared = blocks.iter().any(|b| b.start == c.try_offset && b.end == c.try_end() && b.footer == "end .try");
+			if !shared {
+				blocks.push(Block { start: c.try_offset, end: c.try_end(), header: Some(".try".to_owned()), footer: "end .try", opened: false });
+			}
+
+			let header = match c.handler {
+				Handler::Catch(t) => format!("catch {}", self.token(t)),
+				Handler::Finally  => "finally".to_owned(),
+				Handler::Fault    => "fault".to_owned(),
+				Handler::Filter(start) => {
+					blocks.push(Block { start, end: c.handler_offset, header: Some("filter".to_owned()), footer: "end filter", opened: false });
+					blocks.push(Block { start: c.handler_offset, end: c.handler_end(), header: None, footer: "end handler", opened: false });
+					continue;
+				},
+			};
+			blocks.push(Block { start: c.handler_offset, end: c.handler_end(), header: Some(header), footer: "end handler", opened: false });
+		}
+		blocks
+	}
+
+	/// Closes blocks ending at the offset, innermost first, then opens the
+	/// ones starting there, outermost first.
+	fn blocks_at(&mut self, blocks: &mut Vec<Block>, offset: u32) {
+		let mut ending: Vec<_> = blocks.iter().filter(|b| b.end == offset && b.opened).collect();
+		ending.sort_by_key(|b| std::cmp::Reverse(b.start));
+		let footers: Vec<_> = ending.iter().map(|b| b.footer).collect();
+		for footer in footers {
+			self.close(footer);
+		}
+
+		blocks.retain(|b| b.end != offset);
+
+		let mut starting: Vec<_> = blocks.iter_mut().filter(|b| b.start == offset).collect();
+		starting.sort_by_key(|b| std::cmp::Reverse(b.end));
+		let mut headers = Vec::with_capacity(starting.len());
+		for b in starting {
+			b.opened = true;
+			headers.push(b.header.clone());
+		}
+		for header in headers {
+			if let Some(header) = header {
+				self.line(header);
+			}
+			self.open();
+		}
+	}
+}
+
+/// Reads the instantiation blob of a MethodSpec, II.23.2.15.
+fn generic_inst(data: &[u8]) -> Result<Box<[TypeSig]>> {
+	let offset = &mut 0usize;
+	if data.read::<u8>(offset)? != SIG_GENERICINST {
+		Err("MethodSpec instantiation has wrong prolog.")?;
+	}
+	let n = read_compressed_u32(data, offset)?;
+	let args = (0..n)
+		.map(|_| TypeSig::parse(data, offset))
+		.collect::<Result<Vec<_>>>()?;
+	Ok(args.into_boxed_slice())
+}
+
+fn hex(data: &[u8]) -> String {
+	let bytes: Vec<_> = data.iter().map(|b| format!("{:02X}", b)).collect();
+	bytes.join(" ")
+}
+
+/// Writes the string as an ILAsm literal. Strings which cannot be written
+/// as a literal are written as UTF-16 bytes.
+fn string_literal(s: &str) -> String {
+	let mut literal = String::with_capacity(s.len() + 2);
+	literal.push('"');
+	for c in s.chars() {
+		match c {
+			'"'  => literal.push_str("\\\""),
+			'\\' => literal.push_str("\\\\"),
+			'\n' => literal.push_str("\\n"),
+			'\r' => literal.push_str("\\r"),
+			'\t' => literal.push_str("\\t"),
+			' '..='~' => literal.push(c),
+			_ => {
+				let wide: Vec<u8> = s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+				return format!("bytearray ({})", hex(&wide));
+			},
+		}
+	}
+	literal.push('"');
+	literal
+}
+
+/// Keywords, which cannot be used as identifiers without quotes.
+const KEYWORDS: &[&str] = &[
+	"abstract", "add", "and", "as", "assembly", "at", "bool", "box", "break", "call", "char", "cil",
+	"class", "default", "div", "dup", "explicit", "extends", "family", "field", "final", "float32",
+	"float64", "implements", "in", "init", "instance", "int", "int16", "int32", "int64", "int8",
+	"interface", "literal", "managed", "marshal", "method", "mul", "native", "neg", "nop", "not",
+	"object", "opt", "or", "out", "pop", "private", "public", "rem", "ret", "sealed", "shl", "shr",
+	"static", "string", "sub", "value", "valuetype", "vararg", "virtual", "void", "xor",
+];
+
+/// Quotes the identifier if ILAsm would not accept it as is.
+fn quote(name: &str) -> String {
+	let is_start = |c: char| c.is_ascii_alphabetic() || "_$@?`".contains(c);
+	let is_id = |c: char| is_start(c) || c.is_ascii_digit();
+
+	let valid = match name {
+		".ctor" | ".cctor" => true,
+		_ => {
+			let mut chars = name.chars();
+			chars.next().is_some_and(is_start) && chars.all(is_id) && !KEYWORDS.contains(&name)
+		},
+	};
+
+	if valid {
+		name.to_owned()
+	} else {
+		format!("'{}'", name.replace('\\', "\\\\").replace('\'', "\\'"))
+	}
+}
+
+/// Quotes each part of a dotted name separately.
+fn quote_dotted(name: &str) -> String {
+	let parts: Vec<_> = name.split('.').map(quote).collect();
+	parts.join(".")
+}
+
+/// Joins the namespace and the name, the latter may contain dots itself.
+fn dotted(namespace: &str, name: &str) -> String {
+	if namespace.is_empty() {
+		quote(name)
+	} else {
+		format!("{}.{}", quote_dotted(namespace), quote(name))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ilasm::assemble;
+
+	const SOURCE: &str = r#"
+.assembly extern mscorlib { .publickeytoken = (B7 7A 5C 56 19 34 E0 89 ) .ver 4:0:0:0 }
+.assembly test { .ver 1:2:3:4 }
+.module test.exe
+.data Table = bytearray (01 02 03 04)
+.method public static void Global() cil managed
+{
+	ldtoken Test/Inner
+	pop
+	ret
+}
+.class public auto ansi sealed Test extends [mscorlib]System.Object
+{
+	.custom instance void [mscorlib]System.ObsoleteAttribute::.ctor() = (01 00 00 00)
+	.field public static literal int32 Answer = int32(42)
+	.field public static literal int8 Low = int8(-1)
+	.field public static literal int64 Lower = int64(-2)
+	.field public static int32 Data at Table
+	.field private string name
+	.method public static void Main() cil managed
+	{
+		.entrypoint
+		.maxstack 2
+		.locals init (int32 x, string s)
+		ldstr "Hello"
+		stloc.1
+		.try
+		{
+			ldloc.1
+			call void [mscorlib]System.Console::WriteLine(string)
+			leave.s Done
+		}
+		catch [mscorlib]System.Exception
+		{
+			pop
+			leave.s Done
+		}
+	Done:
+		ldc.i4.1
+		switch (One, Two)
+	One:
+		ldc.i4.2
+		stloc.0
+	Two:
+		ldloc.0
+		ldc.i4.3
+		bge.s One
+		ret
+	}
+	.method public static int32 Pick(int32 a, [opt] int32 b) cil managed
+	{
+		.param [2] = int32(7)
+		.maxstack 2
+		ldarg.0
+		ldarg.1
+		add
+		ret
+	}
+	.class nested private auto ansi Inner extends [mscorlib]System.Object
+	{
+		.method public instance int64 Twice(int64 x) cil managed
+		{
+			.maxstack 2
+			ldarg.1
+			ldarg.1
+			add
+			ret
+		}
+	}
+}
+"#;
+
+	fn listing(source: &str) -> String {
+		let image = assemble(source).expect("Failed to assemble.");
+		let asm = Assembly::parse(&image).expect("Failed to parse.");
+		disassemble(&asm).expect("Failed to disassemble.")
+	}
+
+	#[test]
+	fn listing_reassembles() {
+		let first = listing(SOURCE);
+		assert!(first.contains("// MVID: {00000000-0000-0000-0000-000000000000}\n"), "{}", first);
+		assert!(first.contains("literal int32 Answer = int32(0x0000002A)\n"), "{}", first);
+		assert!(first.contains("literal int8 Low = int8(0xFF)\n"), "{}", first);
+		assert!(first.contains("literal int64 Lower = int64(0xFFFFFFFFFFFFFFFE)\n"), "{}", first);
+		assert!(first.contains(concat!(
+			"    IL_0012:  switch     ( \n",
+			"                          IL_001f,\n",
+			"                          IL_0021)\n",
+		)), "{}", first);
+		assert_eq!(listing(&first), first);
+	}
+
+	#[test]
+	fn nested_class_cycle() {
+		let image = assemble(SOURCE).expect("Failed to assemble.");
+		let mut asm = Assembly::parse(&image).expect("Failed to parse.");
+		// `Test` and `Inner`, rows 2 and 3, enclose each other.
+		asm.rows.nested_classes = vec![
+			NestedClass { nested: TypeDefIndex(3), enclosing: TypeDefIndex(2) },
+			NestedClass { nested: TypeDefIndex(2), enclosing: TypeDefIndex(3) },
+		].into_boxed_slice();
+		let listing = disassemble(&asm).expect("Failed to disassemble.");
+		assert!(listing.contains("ldtoken    /*02000003*/\n"), "{}", listing);
+	}
+}
@@ -8,6 +8,17 @@ use crate::buf;
 pub enum Error {
 	Unknown,
 	General(&'static str),
+	/// Wrong command-line arguments.
+	Usage(&'static str),
+	/// An error in IL source text at the line.
+	Syntax(usize, &'static str),
+	/// Invalid or unverifiable IL at the offset in the method body.
+	Verification(u32, &'static str),
+	/// An instruction at the offset the interpreter cannot execute yet,
+	/// named by its opcode.
+	Unsupported(u32, &'static str),
+	/// A managed exception of the type no handler caught.
+	Exception(&'static str),
 	IO(io::Error),
 	Parse(buf::Error),
 }
@@ -17,6 +28,11 @@ impl fmt::Display for Error {
 		match *self {
 			Error::Unknown        => write!(fmt, "Unknown error"),
 			Error::General(ref s) => write!(fmt, "{}", s),
+			Error::Usage(ref s)   => write!(fmt, "{}", s),
+			Error::Syntax(line, s) => write!(fmt, "Line {}: {}", line, s),
+			Error::Verification(offset, s) => write!(fmt, "IL_{:04x}: {}", offset, s),
+			Error::Unsupported(offset, s) => write!(fmt, "IL_{:04x}: `{}` is not supported yet.", offset, s),
+			Error::Exception(s)   => write!(fmt, "Unhandled exception. {}", s),
 			Error::IO(ref e)      => write!(fmt, "IO error: {}", e),
 			Error::Parse(ref e)   => write!(fmt, "Parsing error: {}", e),
 		}
@@ -28,12 +44,32 @@ impl error::Error for Error {
 		match *self {
 			Error::Unknown      => None,
 			Error::General(_)   => None,
+			Error::Usage(_)     => None,
+			Error::Syntax(..)   => None,
+			Error::Verification(..) => None,
+			Error::Unsupported(..) => None,
+			Error::Exception(_) => None,
 			Error::IO(ref e)    => Some(e),
 			Error::Parse(ref e) => Some(e),
 		}
 	}
 }
 
+impl Error {
+	/// Process exit code, following sysexits.h.
+	pub fn exit_code(&self) -> i32 {
+		match *self {
+			Error::Usage(_) => 64,
+			Error::General(_) | Error::Syntax(..) | Error::Verification(..) | Error::Parse(_) => 65,
+			Error::Unsupported(..) => 69,
+			Error::Exception(_) => 70,
+			Error::IO(ref e) if e.kind() == io::ErrorKind::NotFound => 66,
+			Error::IO(_) => 74,
+			Error::Unknown => 70,
+		}
+	}
+}
+
 impl From<&'static str> for Error {
 	fn from(s: &'static str) -> Self {
 		Error::General(s)
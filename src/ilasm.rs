@@ -0,0 +1,2011 @@
+use std::collections::HashMap;
+
+use crate::Result;
+use crate::error::Error;
+use crate::cli::*;
+use crate::image::ImageBuilder;
+
+// Builds an executable out of a subset of ILAsm (II.5 - II.15):
+//
+// - `.assembly` and `.assembly extern` with `.ver`, `.publickey`,
+//   `.publickeytoken`, `.hash algorithm` and `.culture`;
+// - `.module`, `.data` with `bytearray`;
+// - `.class` with flags, `extends`, `implements`, `.pack` and `.size`,
+//   nested classes;
+// - `.field` with flags, explicit offsets, `at` data labels and constants;
+// - `.method` with flags, parameters, `.entrypoint`, `.maxstack`,
+//   `.locals`, `.param`, labels, block-style and label-style `.try`;
+// - `.custom` with raw attribute blobs;
+// - every opcode of the `cli::il` tables.
+//
+// Generics, properties, events and P/Invoke are not supported. Classes
+// without `extends` have no base type.
+
+/// Assembles the source into a PE image.
+pub fn assemble(source: &str) -> Result<Vec<u8>> {
+	let tokens = lex(source)?;
+	let program = Parser { source, tokens, pos: 0 }.program()?;
+	Emitter::default().emit(&program)
+}
+
+// Lexer.
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+	Ident(String),
+	/// Identifier in single quotes, never a keyword.
+	Quoted(String),
+	Str(String),
+	Int(i64),
+	Float(f64),
+	Punct(&'static str),
+}
+
+#[derive(Debug, Clone)]
+struct Lexeme {
+	token: Token,
+	line: usize,
+	/// Byte offset in the source.
+	pos: usize,
+}
+
+const PUNCTS: [&str; 16] = ["::", "!!", ":", "{", "}", "(", ")", "[", "]", "<", ">", ",", "=", "*", "&", "!"];
+
+fn is_ident_start(c: char) -> bool {
+	c.is_ascii_alphabetic() || "_$@?`.".contains(c)
+}
+
+fn is_ident(c: char) -> bool {
+	is_ident_start(c) || c.is_ascii_digit()
+}
+
+fn lex(source: &str) -> Result<Vec<Lexeme>> {
+	let bytes = source.as_bytes();
+	let mut tokens = Vec::new();
+	let mut line = 1;
+	let mut i = 0;
+
+	while i < bytes.len() {
+		let c = bytes[i] as char;
+		let start = i;
+
+		if c == '\n' {
+			line += 1;
+			i += 1;
+			continue;
+		}
+		if c.is_ascii_whitespace() {
+			i += 1;
+			continue;
+		}
+		if source[i..].starts_with("//") {
+			while i < bytes.len() && bytes[i] != b'\n' {
+				i += 1;
+			}
+			continue;
+		}
+		if source[i..].starts_with("/*") {
+			let end = source[i + 2..].find("*/").ok_or(Error::Syntax(line, "Unterminated comment."))?;
+			line += source[i..i + 2 + end].matches('\n').count();
+			i += end + 4;
+			continue;
+		}
+
+		let token = if c == '"' || c == '\'' {
+			let (s, len) = quoted(&source[i..], line)?;
+			i += len;
+			if c == '"' { Token::Str(s) } else { Token::Quoted(s) }
+		} else if c.is_ascii_digit() || (c == '-' && bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit())) {
+			let (number, len) = number(&source[i..], line)?;
+			i += len;
+			number
+		} else if is_ident_start(c) {
+			while i < bytes.len() && is_ident(bytes[i] as char) {
+				i += 1;
+			}
+			Token::Ident(source[start..i].to_owned())
+		} else if c == '/' {
+			// Separates nested type names.
+			i += 1;
+			Token::Punct("/")
+		} else {
+			let p = PUNCTS.iter()
+				.find(|p| source[i..].starts_with(**p))
+				.ok_or(Error::Syntax(line, "Unexpected character."))?;
+			i += p.len();
+			Token::Punct(p)
+		};
+
+		tokens.push(Lexeme { token, line, pos: start });
+	}
+
+	Ok(tokens)
+}
+
+/// Reads a string in double or single quotes, returning it unescaped along
+/// with its length in the source.
+fn quoted(s: &str, line: usize) -> Result<(String, usize)> {
+	let mut chars = s.char_indices();
+	let (_, q) = chars.next().ok_or(Error::Syntax(line, "Expected a string."))?;
+
+	let mut out = String::new();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'\\' => {
+				let (_, e) = chars.next().ok_or(Error::Syntax(line, "Unterminated string."))?;
+				out.push(match e {
+					'n' => '\n',
+					'r' => '\r',
+					't' => '\t',
+					'0' => '\0',
+					_ => e,
+				});
+			},
+			'\n' => Err(Error::Syntax(line, "Unterminated string."))?,
+			_ if c == q => return Ok((out, i + 1)),
+			_ => out.push(c),
+		}
+	}
+
+	Err(Error::Syntax(line, "Unterminated string."))
+}
+
+fn number(s: &str, line: usize) -> Result<(Token, usize)> {
+	let bytes = s.as_bytes();
+	let negative = bytes[0] == b'-';
+	let digits = if negative { 1 } else { 0 };
+
+	if s[digits..].starts_with("0x") || s[digits..].starts_with("0X") {
+		let end = digits + 2 + bytes[digits + 2..].iter().take_while(|b| b.is_ascii_hexdigit()).count();
+		let x = u64::from_str_radix(&s[digits + 2..end], 16)
+			.map_err(|_| Error::Syntax(line, "Invalid hexadecimal number."))? as i64;
+		return Ok((Token::Int(if negative { x.wrapping_neg() } else { x }), end));
+	}
+
+	let mut end = digits + bytes[digits..].iter().take_while(|b| b.is_ascii_digit()).count();
+	let mut is_float = false;
+	if bytes.get(end) == Some(&b'.') && bytes.get(end + 1).is_some_and(|b| b.is_ascii_digit()) {
+		is_float = true;
+		end += 1 + bytes[end + 1..].iter().take_while(|b| b.is_ascii_digit()).count();
+	}
+	if matches!(bytes.get(end), Some(b'e') | Some(b'E')) {
+		let sign = matches!(bytes.get(end + 1), Some(b'-') | Some(b'+')) as usize;
+		if bytes.get(end + 1 + sign).is_some_and(|b| b.is_ascii_digit()) {
+			is_float = true;
+			end += 1 + sign + bytes[end + 1 + sign..].iter().take_while(|b| b.is_ascii_digit()).count();
+		}
+	}
+
+	let token = if is_float {
+		Token::Float(s[..end].parse().map_err(|_| Error::Syntax(line, "Invalid number."))?)
+	} else {
+		let x = s[..end].parse::<i64>()
+			.or_else(|_| s[..end].parse::<u64>().map(|x| x as i64))
+			.map_err(|_| Error::Syntax(line, "Invalid number."))?;
+		Token::Int(x)
+	};
+	Ok((token, end))
+}
+
+// Syntax tree.
+
+/// A reference to a type by name, e.g. `[mscorlib]System.Object` or
+/// `Outer/Inner`.
+#[derive(Debug, PartialEq, Clone)]
+struct TypeName {
+	scope: Option<String>,
+	/// The outermost type's full name followed by names of nested ones.
+	path: Vec<String>,
+	line: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Type {
+	/// Types without references: primitives and generic parameters.
+	Plain(TypeSig),
+	Class(TypeName),
+	Value(TypeName),
+	SzArray(Box<Type>),
+	Ptr(Box<Type>),
+	ByRef(Box<Type>),
+	Pinned(Box<Type>),
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+	has_this: bool,
+	explicit_this: bool,
+	vararg: bool,
+	ret: Type,
+	params: Vec<Type>,
+}
+
+#[derive(Debug, Clone)]
+struct MemberRefDecl {
+	sig: Signature,
+	/// None for global members.
+	owner: Option<Type>,
+	name: String,
+	line: usize,
+}
+
+#[derive(Debug, Clone)]
+struct FieldRefDecl {
+	ty: Type,
+	owner: Option<Type>,
+	name: String,
+	line: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CustomDecl {
+	ctor: MemberRefDecl,
+	value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct AssemblyDecl {
+	name: String,
+	version: [u16; 4],
+	public_key: Vec<u8>,
+	culture: String,
+	hash_algo: Option<u32>,
+	customs: Vec<CustomDecl>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ClassDecl {
+	flags: u32,
+	/// Full name, including the namespace.
+	name: String,
+	extends: Option<TypeName>,
+	implements: Vec<TypeName>,
+	layout: Option<(u16, u32)>,
+	fields: Vec<FieldDecl>,
+	methods: Vec<MethodDecl>,
+	nested: Vec<ClassDecl>,
+	customs: Vec<CustomDecl>,
+}
+
+#[derive(Debug, Clone)]
+struct FieldDecl {
+	flags: u16,
+	offset: Option<u32>,
+	ty: Type,
+	name: String,
+	data: Option<(String, usize)>,
+	value: Option<ConstantValue>,
+	customs: Vec<CustomDecl>,
+}
+
+#[derive(Debug, Clone)]
+struct ParamDecl {
+	flags: u16,
+	ty: Type,
+	name: String,
+}
+
+#[derive(Debug, Clone)]
+struct MethodDecl {
+	flags: u16,
+	impl_flags: u16,
+	vararg: bool,
+	ret: Type,
+	name: String,
+	params: Vec<ParamDecl>,
+	body: BodyDecl,
+	line: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct BodyDecl {
+	is_entry_point: bool,
+	max_stack: Option<u16>,
+	init_locals: bool,
+	locals: Vec<(Type, String)>,
+	items: Vec<Item>,
+	clauses: Vec<ClauseDecl>,
+	/// Constants of parameters, by their sequence number.
+	param_values: Vec<(u16, ConstantValue)>,
+	customs: Vec<CustomDecl>,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+	Label(String, usize),
+	Instruction(u16, OperandDecl, usize),
+}
+
+#[derive(Debug, Clone)]
+enum OperandDecl {
+	None,
+	Int(i64),
+	Float(f64),
+	/// Argument or local variable referenced by its name.
+	Var(String),
+	Label(String),
+	Switch(Vec<String>),
+	String(String),
+	Method(MemberRefDecl),
+	Field(FieldRefDecl),
+	Type(Type),
+	Sig(Signature),
+}
+
+#[derive(Debug, Clone)]
+enum HandlerDecl {
+	Catch(Type),
+	/// Label of the filter code.
+	Filter(String),
+	Finally,
+	Fault,
+}
+
+/// Bounds of protected and handler blocks as labels.
+#[derive(Debug, Clone)]
+struct ClauseDecl {
+	handler: HandlerDecl,
+	try_start: String,
+	try_end: String,
+	handler_start: String,
+	handler_end: String,
+	line: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Program {
+	assembly: Option<AssemblyDecl>,
+	externs: Vec<AssemblyDecl>,
+	module: Option<String>,
+	/// Members of `<Module>`.
+	globals: ClassDecl,
+	classes: Vec<ClassDecl>,
+	data: Vec<(String, Vec<u8>)>,
+}
+
+// Parser.
+
+const TYPE_FLAGS: [(&str, u32); 16] = [
+	("private",         TYPE_NOT_PUBLIC),
+	("public",          TYPE_PUBLIC),
+	("interface",       TYPE_INTERFACE | TYPE_ABSTRACT),
+	("abstract",        TYPE_ABSTRACT),
+	("sealed",          TYPE_SEALED),
+	("auto",            TYPE_AUTO_LAYOUT),
+	("sequential",      TYPE_SEQUENTIAL_LAYOUT),
+	("explicit",        TYPE_EXPLICIT_LAYOUT),
+	("ansi",            TYPE_ANSI_CLASS),
+	("unicode",         TYPE_UNICODE_CLASS),
+	("autochar",        TYPE_AUTO_CLASS),
+	("import",          TYPE_IMPORT),
+	("serializable",    TYPE_SERIALIZABLE),
+	("specialname",     TYPE_SPECIAL_NAME),
+	("rtspecialname",   TYPE_RT_SPECIAL_NAME),
+	("beforefieldinit", TYPE_BEFORE_FIELD_INIT),
+];
+
+const NESTED_VISIBILITY: [(&str, u32); 6] = [
+	("public",      TYPE_NESTED_PUBLIC),
+	("private",     TYPE_NESTED_PRIVATE),
+	("family",      TYPE_NESTED_FAMILY),
+	("assembly",    TYPE_NESTED_ASSEMBLY),
+	("famandassem", TYPE_NESTED_FAM_AND_ASSEM),
+	("famorassem",  TYPE_NESTED_FAM_OR_ASSEM),
+];
+
+const FIELD_FLAGS: [(&str, u16); 13] = [
+	("privatescope",  FIELD_COMPILER_CONTROLLED),
+	("private",       FIELD_PRIVATE),
+	("famandassem",   FIELD_FAM_AND_ASSEM),
+	("assembly",      FIELD_ASSEMBLY),
+	("family",        FIELD_FAMILY),
+	("famorassem",    FIELD_FAM_OR_ASSEM),
+	("public",        FIELD_PUBLIC),
+	("static",        FIELD_STATIC),
+	("initonly",      FIELD_INIT_ONLY),
+	("literal",       FIELD_LITERAL),
+	("notserialized", FIELD_NOT_SERIALIZED),
+	("specialname",   FIELD_SPECIAL_NAME),
+	("rtspecialname", FIELD_RT_SPECIAL_NAME),
+];
+
+const METHOD_FLAGS: [(&str, u16); 16] = [
+	("privatescope",  METHOD_COMPILER_CONTROLLED),
+	("private",       METHOD_PRIVATE),
+	("famandassem",   METHOD_FAM_AND_ASSEM),
+	("assembly",      METHOD_ASSEM),
+	("family",        METHOD_FAMILY),
+	("famorassem",    METHOD_FAM_OR_ASSEM),
+	("public",        METHOD_PUBLIC),
+	("static",        METHOD_STATIC),
+	("final",         METHOD_FINAL),
+	("virtual",       METHOD_VIRTUAL),
+	("hidebysig",     METHOD_HIDE_BY_SIG),
+	("newslot",       METHOD_NEW_SLOT),
+	("strict",        METHOD_STRICT),
+	("abstract",      METHOD_ABSTRACT),
+	("specialname",   METHOD_SPECIAL_NAME),
+	("rtspecialname", METHOD_RT_SPECIAL_NAME),
+];
+
+const METHOD_IMPL_FLAGS: [(&str, u16); 12] = [
+	("cil",            METHOD_IMPL_IL),
+	("native",         METHOD_IMPL_NATIVE),
+	("optil",          METHOD_IMPL_OPTIL),
+	("runtime",        METHOD_IMPL_RUNTIME),
+	("managed",        METHOD_IMPL_MANAGED),
+	("unmanaged",      METHOD_IMPL_UNMANAGED),
+	("forwardref",     METHOD_IMPL_FORWARD_REF),
+	("preservesig",    METHOD_IMPL_PRESERVE_SIG),
+	("internalcall",   METHOD_IMPL_INTERNAL_CALL),
+	("synchronized",   METHOD_IMPL_SYNCHRONIZED),
+	("noinlining",     METHOD_IMPL_NO_INLINING),
+	("nooptimization", METHOD_IMPL_NO_OPTIMIZATION),
+];
+
+struct Parser<'a> {
+	source: &'a str,
+	tokens: Vec<Lexeme>,
+	pos: usize,
+}
+
+impl Parser<'_> {
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.pos).map(|l| &l.token)
+	}
+
+	fn peek_at(&self, n: usize) -> Option<&Token> {
+		self.tokens.get(self.pos + n).map(|l| &l.token)
+	}
+
+	fn line(&self) -> usize {
+		self.tokens.get(self.pos)
+			.or_else(|| self.tokens.last())
+			.map_or(1, |l| l.line)
+	}
+
+	fn error(&self, message: &'static str) -> Error {
+		Error::Syntax(self.line(), message)
+	}
+
+	fn is(&self, keyword: &str) -> bool {
+		matches!(self.peek(), Some(Token::Ident(s)) if s == keyword)
+	}
+
+	fn is_punct(&self, p: &str) -> bool {
+		matches!(self.peek(), Some(Token::Punct(s)) if *s == p)
+	}
+
+	fn eat(&mut self, keyword: &str) -> bool {
+		let is = self.is(keyword);
+		if is {
+			self.pos += 1;
+		}
+		is
+	}
+
+	fn eat_punct(&mut self, p: &str) -> bool {
+		let is = self.is_punct(p);
+		if is {
+			self.pos += 1;
+		}
+		is
+	}
+
+	fn expect(&mut self, keyword: &str, message: &'static str) -> Result<()> {
+		if !self.eat(keyword) {
+			Err(self.error(message))?;
+		}
+		Ok(())
+	}
+
+	fn expect_punct(&mut self, p: &str, message: &'static str) -> Result<()> {
+		if !self.eat_punct(p) {
+			Err(self.error(message))?;
+		}
+		Ok(())
+	}
+
+	/// Reads a keyword standing for a flag, if the next token is one.
+	fn flag<T: Copy>(&mut self, flags: &[(&str, T)]) -> Option<T> {
+		let flag = match self.peek() {
+			Some(Token::Ident(s)) => flags.iter().find(|(name, _)| name == s).map(|(_, flag)| *flag)?,
+			_ => return None,
+		};
+		self.pos += 1;
+		Some(flag)
+	}
+
+	/// Reads an identifier, possibly dotted and made of quoted parts.
+	fn name(&mut self) -> Result<String> {
+		let mut name = String::new();
+		loop {
+			match self.peek() {
+				Some(Token::Ident(s)) | Some(Token::Quoted(s)) => name.push_str(s),
+				_ => Err(self.error("Expected a name."))?,
+			}
+			self.pos += 1;
+
+			// `A.'b'` is lexed as `A.` followed by a quoted part.
+			let joins = name.ends_with('.') && matches!(self.peek(), Some(Token::Quoted(_)))
+				|| matches!(self.peek(), Some(Token::Ident(s)) if s.starts_with('.') && !name.is_empty()
+					&& matches!(self.tokens[self.pos - 1].token, Token::Quoted(_)));
+			if !joins {
+				return Ok(name);
+			}
+		}
+	}
+
+	fn int(&mut self) -> Result<i64> {
+		match self.peek() {
+			Some(Token::Int(x)) => {
+				let x = *x;
+				self.pos += 1;
+				Ok(x)
+			},
+			_ => Err(self.error("Expected an integer.")),
+		}
+	}
+
+	fn float(&mut self) -> Result<f64> {
+		match self.peek() {
+			Some(Token::Float(x)) => {
+				let x = *x;
+				self.pos += 1;
+				Ok(x)
+			},
+			Some(Token::Int(x)) => {
+				let x = *x as f64;
+				self.pos += 1;
+				Ok(x)
+			},
+			_ => Err(self.error("Expected a number.")),
+		}
+	}
+
+	fn string(&mut self) -> Result<String> {
+		match self.peek() {
+			Some(Token::Str(s)) => {
+				let s = s.clone();
+				self.pos += 1;
+				Ok(s)
+			},
+			_ => Err(self.error("Expected a string.")),
+		}
+	}
+
+	/// Reads `( XX XX ... )`. Hex bytes do not form valid tokens, so they
+	/// are read from the source directly.
+	fn bytes(&mut self) -> Result<Vec<u8>> {
+		let line = self.line();
+		if !self.is_punct("(") {
+			Err(self.error("Expected a byte array."))?;
+		}
+		let start = self.tokens[self.pos].pos + 1;
+		let len = self.source[start..].find(')').ok_or(Error::Syntax(line, "Unterminated byte array."))?;
+		let end = start + len;
+
+		let mut bytes = Vec::new();
+		for part in self.source[start..end].split_whitespace() {
+			let b = u8::from_str_radix(part, 16).map_err(|_| Error::Syntax(line, "Invalid byte in byte array."))?;
+			bytes.push(b);
+		}
+
+		while self.tokens.get(self.pos).is_some_and(|l| l.pos <= end) {
+			self.pos += 1;
+		}
+		Ok(bytes)
+	}
+
+	fn program(mut self) -> Result<Program> {
+		let mut program = Program::default();
+
+		while let Some(token) = self.peek() {
+			let directive = match token {
+				Token::Ident(s) => s.clone(),
+				_ => Err(self.error("Expected a directive."))?,
+			};
+			self.pos += 1;
+
+			match directive.as_str() {
+				".assembly" => {
+					if self.eat("extern") {
+						program.externs.push(self.assembly()?);
+					} else {
+						if program.assembly.is_some() {
+							Err(self.error("Assembly is declared twice."))?;
+						}
+						program.assembly = Some(self.assembly()?);
+					}
+				},
+				".module" => program.module = Some(self.name()?),
+				".class"  => program.classes.push(self.class()?),
+				".field"  => program.globals.fields.push(self.field()?),
+				".method" => program.globals.methods.push(self.method()?),
+				".custom" => program.globals.customs.push(self.custom()?),
+				".data"   => {
+					self.eat("cil");
+					let label = self.name()?;
+					self.expect_punct("=", "Expected `=`.")?;
+					self.expect("bytearray", "Expected `bytearray`.")?;
+					program.data.push((label, self.bytes()?));
+				},
+				_ => Err(Error::Syntax(self.line(), "Unsupported directive."))?,
+			}
+		}
+
+		Ok(program)
+	}
+
+	fn assembly(&mut self) -> Result<AssemblyDecl> {
+		let mut decl = AssemblyDecl { name: self.name()?, ..Default::default() };
+		self.expect_punct("{", "Expected `{`.")?;
+
+		while !self.eat_punct("}") {
+			if self.eat(".ver") {
+				for i in 0..4 {
+					if i != 0 {
+						self.expect_punct(":", "Expected `:`.")?;
+					}
+					decl.version[i] = self.int()? as u16;
+				}
+			} else if self.eat(".publickey") || self.eat(".publickeytoken") {
+				self.expect_punct("=", "Expected `=`.")?;
+				decl.public_key = self.bytes()?;
+			} else if self.eat(".hash") {
+				if self.eat("algorithm") {
+					decl.hash_algo = Some(self.int()? as u32);
+				} else {
+					// Hash of a referenced assembly is ignored.
+					self.expect_punct("=", "Expected `=`.")?;
+					self.bytes()?;
+				}
+			} else if self.eat(".culture") || self.eat(".locale") {
+				decl.culture = self.string()?;
+			} else if self.eat(".custom") {
+				decl.customs.push(self.custom()?);
+			} else {
+				Err(self.error("Unsupported assembly declaration."))?;
+			}
+		}
+
+		Ok(decl)
+	}
+
+	fn custom(&mut self) -> Result<CustomDecl> {
+		let ctor = self.method_ref()?;
+		self.expect_punct("=", "Expected `=`.")?;
+		let value = self.bytes()?;
+		Ok(CustomDecl { ctor, value })
+	}
+
+	fn class(&mut self) -> Result<ClassDecl> {
+		let mut decl = ClassDecl::default();
+
+		loop {
+			if self.eat("nested") {
+				let visibility = self.flag(&NESTED_VISIBILITY).ok_or(self.error("Unknown nested type visibility."))?;
+				decl.flags = decl.flags & !TYPE_VISIBILITY_MASK | visibility;
+			} else if let Some(flag) = self.flag(&TYPE_FLAGS) {
+				decl.flags |= flag;
+			} else {
+				break;
+			}
+		}
+
+		decl.name = self.name()?;
+		if self.is_punct("<") {
+			Err(self.error("Generic types are not supported."))?;
+		}
+		if self.eat("extends") {
+			decl.extends = Some(self.class_name()?);
+		}
+		if self.eat("implements") {
+			loop {
+				decl.implements.push(self.class_name()?);
+				if !self.eat_punct(",") {
+					break;
+				}
+			}
+		}
+
+		self.expect_punct("{", "Expected `{`.")?;
+		while !self.eat_punct("}") {
+			if self.eat(".field") {
+				decl.fields.push(self.field()?);
+			} else if self.eat(".method") {
+				decl.methods.push(self.method()?);
+			} else if self.eat(".class") {
+				decl.nested.push(self.class()?);
+			} else if self.eat(".custom") {
+				decl.customs.push(self.custom()?);
+			} else if self.eat(".pack") {
+				let pack = self.int()? as u16;
+				let size = decl.layout.map_or(0, |l| l.1);
+				decl.layout = Some((pack, size));
+			} else if self.eat(".size") {
+				let size = self.int()? as u32;
+				let pack = decl.layout.map_or(0, |l| l.0);
+				decl.layout = Some((pack, size));
+			} else {
+				Err(self.error("Unsupported class member."))?;
+			}
+		}
+
+		Ok(decl)
+	}
+
+	/// Reads a type name, which may be preceded by `class` or `valuetype`.
+	fn class_name(&mut self) -> Result<TypeName> {
+		if !self.eat("class") {
+			self.eat("valuetype");
+		}
+		self.type_name()
+	}
+
+	fn type_name(&mut self) -> Result<TypeName> {
+		let line = self.line();
+		let scope = if self.eat_punct("[") {
+			if self.is(".module") {
+				Err(self.error("Module-scoped types are not supported."))?;
+			}
+			let scope = self.name()?;
+			self.expect_punct("]", "Expected `]`.")?;
+			Some(scope)
+		} else {
+			None
+		};
+
+		let mut path = vec![self.name()?];
+		while self.eat_punct("/") {
+			path.push(self.name()?);
+		}
+
+		Ok(TypeName { scope, path, line })
+	}
+
+	fn ty(&mut self) -> Result<Type> {
+		let keyword = match self.peek() {
+			Some(Token::Ident(s)) => s.clone(),
+			Some(Token::Punct("!")) | Some(Token::Punct("!!")) => {
+				let is_method = self.is_punct("!!");
+				self.pos += 1;
+				let n = self.int()? as u32;
+				let var = if is_method { TypeSig::MVar(n) } else { TypeSig::Var(n) };
+				return self.type_suffix(Type::Plain(var));
+			},
+			_ => Err(self.error("Expected a type."))?,
+		};
+		self.pos += 1;
+
+		let plain = |sig| Type::Plain(sig);
+		let ty = match keyword.as_str() {
+			"void"      => plain(TypeSig::Void),
+			"bool"      => plain(TypeSig::Boolean),
+			"char"      => plain(TypeSig::Char),
+			"int8"      => plain(TypeSig::I1),
+			"int16"     => plain(TypeSig::I2),
+			"int32"     => plain(TypeSig::I4),
+			"int64"     => plain(TypeSig::I8),
+			"uint8"     => plain(TypeSig::U1),
+			"uint16"    => plain(TypeSig::U2),
+			"uint32"    => plain(TypeSig::U4),
+			"uint64"    => plain(TypeSig::U8),
+			"float32"   => plain(TypeSig::R4),
+			"float64"   => plain(TypeSig::R8),
+			"string"    => plain(TypeSig::String),
+			"object"    => plain(TypeSig::Object),
+			"typedref"  => plain(TypeSig::TypedByRef),
+			"unsigned"  => match self.peek() {
+				Some(Token::Ident(s)) => {
+					let sig = match s.as_str() {
+						"int8"  => TypeSig::U1,
+						"int16" => TypeSig::U2,
+						"int32" => TypeSig::U4,
+						"int64" => TypeSig::U8,
+						_ => Err(self.error("Expected an integer type."))?,
+					};
+					self.pos += 1;
+					plain(sig)
+				},
+				_ => Err(self.error("Expected an integer type."))?,
+			},
+			"native"    => {
+				let unsigned = self.eat("unsigned") || self.eat("uint");
+				if !unsigned {
+					self.expect("int", "Expected `int`.")?;
+				} else {
+					self.eat("int");
+				}
+				plain(if unsigned { TypeSig::U } else { TypeSig::I })
+			},
+			"class"     => Type::Class(self.type_name()?),
+			"valuetype" => Type::Value(self.type_name()?),
+			_ => Err(Error::Syntax(self.line(), "Unknown type."))?,
+		};
+
+		self.type_suffix(ty)
+	}
+
+	/// Reads array, pointer and reference modifiers. A bracket followed by
+	/// a name starts the resolution scope of what follows instead.
+	fn type_suffix(&mut self, mut ty: Type) -> Result<Type> {
+		loop {
+			ty = if self.is_punct("[") && matches!(self.peek_at(1), Some(Token::Punct("]"))) {
+				self.pos += 2;
+				Type::SzArray(Box::new(ty))
+			} else if self.eat_punct("*") {
+				Type::Ptr(Box::new(ty))
+			} else if self.eat_punct("&") {
+				Type::ByRef(Box::new(ty))
+			} else if self.eat("pinned") {
+				Type::Pinned(Box::new(ty))
+			} else if self.is_punct("[") && matches!(self.peek_at(1), Some(Token::Punct(",")) | Some(Token::Int(_))) {
+				Err(self.error("Multi-dimensional arrays are not supported."))?
+			} else {
+				return Ok(ty);
+			};
+		}
+	}
+
+	/// Reads a type used as a token, where a bare name stands for a class.
+	fn type_token(&mut self) -> Result<Type> {
+		match self.peek() {
+			Some(Token::Ident(s)) if !is_type_keyword(s) => Ok(Type::Class(self.type_name()?)),
+			Some(Token::Quoted(_)) | Some(Token::Punct("[")) => Ok(Type::Class(self.type_name()?)),
+			_ => self.ty(),
+		}
+	}
+
+	fn call_conv(&mut self) -> (bool, bool, bool) {
+		let mut has_this = false;
+		let mut explicit_this = false;
+		let mut vararg = false;
+		loop {
+			if self.eat("instance") {
+				has_this = true;
+			} else if self.eat("explicit") {
+				explicit_this = true;
+			} else if self.eat("vararg") {
+				vararg = true;
+			} else if !self.eat("default") {
+				return (has_this, explicit_this, vararg);
+			}
+		}
+	}
+
+	/// Reads `owner::name` or just `name` of a global member.
+	fn member_name(&mut self) -> Result<(Option<Type>, String)> {
+		let is_global = matches!(self.peek(), Some(Token::Ident(_)) | Some(Token::Quoted(_)))
+			&& matches!(self.peek_at(1), Some(Token::Punct("(")));
+		if is_global {
+			return Ok((None, self.name()?));
+		}
+
+		let owner = self.type_token()?;
+		self.expect_punct("::", "Expected `::`.")?;
+		Ok((Some(owner), self.name()?))
+	}
+
+	fn method_ref(&mut self) -> Result<MemberRefDecl> {
+		let line = self.line();
+		let (has_this, explicit_this, vararg) = self.call_conv();
+		let ret = self.ty()?;
+		let (owner, name) = self.member_name()?;
+		if self.is_punct("<") {
+			Err(self.error("Generic methods are not supported."))?;
+		}
+		let params = self.type_list()?;
+		let sig = Signature { has_this, explicit_this, vararg, ret, params };
+		Ok(MemberRefDecl { sig, owner, name, line })
+	}
+
+	fn field_ref(&mut self) -> Result<FieldRefDecl> {
+		let line = self.line();
+		let ty = self.ty()?;
+		let (owner, name) = self.member_name()?;
+		Ok(FieldRefDecl { ty, owner, name, line })
+	}
+
+	/// Reads `(type, type, ...)` of call sites.
+	fn type_list(&mut self) -> Result<Vec<Type>> {
+		self.expect_punct("(", "Expected `(`.")?;
+		let mut types = Vec::new();
+		while !self.eat_punct(")") {
+			if !types.is_empty() {
+				self.expect_punct(",", "Expected `,`.")?;
+			}
+			types.push(self.ty()?);
+		}
+		Ok(types)
+	}
+
+	fn constant(&mut self) -> Result<ConstantValue> {
+		if let Some(Token::Str(_)) = self.peek() {
+			return Ok(ConstantValue::String(self.string()?));
+		}
+		if self.eat("nullref") {
+			return Ok(ConstantValue::Null);
+		}
+
+		let keyword = match self.peek() {
+			Some(Token::Ident(s)) => s.clone(),
+			_ => Err(self.error("Expected a constant."))?,
+		};
+		self.pos += 1;
+		self.expect_punct("(", "Expected `(`.")?;
+
+		let value = match keyword.as_str() {
+			"bool" => {
+				let value = if self.eat("true") {
+					true
+				} else {
+					self.expect("false", "Expected `true` or `false`.")?;
+					false
+				};
+				ConstantValue::Bool(value)
+			},
+			"char"    => ConstantValue::Char(self.int()? as u16),
+			"int8"    => ConstantValue::I1(self.int()? as i8),
+			"uint8"   => ConstantValue::U1(self.int()? as u8),
+			"int16"   => ConstantValue::I2(self.int()? as i16),
+			"uint16"  => ConstantValue::U2(self.int()? as u16),
+			"int32"   => ConstantValue::I4(self.int()? as i32),
+			"uint32"  => ConstantValue::U4(self.int()? as u32),
+			"int64"   => ConstantValue::I8(self.int()?),
+			"uint64"  => ConstantValue::U8(self.int()? as u64),
+			// Integers stand for the bit pattern of the value.
+			"float32" => match self.peek() {
+				Some(Token::Int(x)) => {
+					let x = *x;
+					self.pos += 1;
+					ConstantValue::R4(f32::from_bits(x as u32))
+				},
+				_ => ConstantValue::R4(self.float()? as f32),
+			},
+			"float64" => match self.peek() {
+				Some(Token::Int(x)) => {
+					let x = *x;
+					self.pos += 1;
+					ConstantValue::R8(f64::from_bits(x as u64))
+				},
+				_ => ConstantValue::R8(self.float()?),
+			},
+			_ => Err(Error::Syntax(self.line(), "Unknown constant type."))?,
+		};
+
+		self.expect_punct(")", "Expected `)`.")?;
+		Ok(value)
+	}
+
+	fn field(&mut self) -> Result<FieldDecl> {
+		let offset = if self.eat_punct("[") {
+			let offset = self.int()? as u32;
+			self.expect_punct("]", "Expected `]`.")?;
+			Some(offset)
+		} else {
+			None
+		};
+
+		let mut flags = 0;
+		while let Some(flag) = self.flag(&FIELD_FLAGS) {
+			flags |= flag;
+		}
+
+		let ty = self.ty()?;
+		let name = self.name()?;
+
+		let data = if self.eat("at") {
+			let line = self.line();
+			Some((self.name()?, line))
+		} else {
+			None
+		};
+		let value = if self.eat_punct("=") {
+			flags |= FIELD_HAS_DEFAULT;
+			Some(self.constant()?)
+		} else {
+			None
+		};
+		if data.is_some() {
+			flags |= FIELD_HAS_FIELD_RVA;
+		}
+
+		Ok(FieldDecl { flags, offset, ty, name, data, value, customs: Vec::new() })
+	}
+
+	fn method(&mut self) -> Result<MethodDecl> {
+		let line = self.line();
+
+		let mut flags = 0;
+		while let Some(flag) = self.flag(&METHOD_FLAGS) {
+			flags |= flag;
+		}
+		if self.is("pinvokeimpl") {
+			Err(self.error("P/Invoke is not supported."))?;
+		}
+
+		// Non-static methods always have `this`, whether it is spelled or not.
+		let (_, _, vararg) = self.call_conv();
+		let ret = self.ty()?;
+		let name = self.name()?;
+		if self.is_punct("<") {
+			Err(self.error("Generic methods are not supported."))?;
+		}
+
+		self.expect_punct("(", "Expected `(`.")?;
+		let mut params = Vec::new();
+		while !self.eat_punct(")") {
+			if !params.is_empty() {
+				self.expect_punct(",", "Expected `,`.")?;
+			}
+			let mut flags = 0;
+			while self.eat_punct("[") {
+				flags |= match self.peek() {
+					Some(Token::Ident(s)) if s == "in"  => PARAM_IN,
+					Some(Token::Ident(s)) if s == "out" => PARAM_OUT,
+					Some(Token::Ident(s)) if s == "opt" => PARAM_OPTIONAL,
+					_ => Err(self.error("Unknown parameter attribute."))?,
+				};
+				self.pos += 1;
+				self.expect_punct("]", "Expected `]`.")?;
+			}
+			let ty = self.ty()?;
+			let name = match self.peek() {
+				Some(Token::Ident(_)) | Some(Token::Quoted(_)) => self.name()?,
+				_ => String::new(),
+			};
+			params.push(ParamDecl { flags, ty, name });
+		}
+
+		let mut impl_flags = 0;
+		while let Some(flag) = self.flag(&METHOD_IMPL_FLAGS) {
+			impl_flags |= flag;
+		}
+
+		self.expect_punct("{", "Expected `{`.")?;
+		let mut body = BodyDecl::default();
+		let mut n_labels = 0;
+		self.body(&mut body, &mut n_labels)?;
+
+		Ok(MethodDecl { flags, impl_flags, vararg, ret, name, params, body, line })
+	}
+
+	/// Reads body items up to and including the closing brace.
+	fn body(&mut self, body: &mut BodyDecl, n_labels: &mut usize) -> Result<()> {
+		loop {
+			let line = self.line();
+			let word = match self.peek() {
+				Some(Token::Punct("}")) => {
+					self.pos += 1;
+					return Ok(());
+				},
+				Some(Token::Ident(s)) => s.clone(),
+				None => Err(self.error("Unexpected end of method body."))?,
+				_ => Err(self.error("Expected an instruction."))?,
+			};
+			self.pos += 1;
+
+			match word.as_str() {
+				".entrypoint" => body.is_entry_point = true,
+				".maxstack"   => body.max_stack = Some(self.int()? as u16),
+				".locals"     => {
+					if self.eat("init") {
+						body.init_locals = true;
+					}
+					self.expect_punct("(", "Expected `(`.")?;
+					let mut first = true;
+					while !self.eat_punct(")") {
+						if !first {
+							self.expect_punct(",", "Expected `,`.")?;
+						}
+						first = false;
+						let ty = self.ty()?;
+						let name = match self.peek() {
+							Some(Token::Ident(_)) | Some(Token::Quoted(_)) => self.name()?,
+							_ => String::new(),
+						};
+						body.locals.push((ty, name));
+					}
+				},
+				".param" => {
+					self.expect_punct("[", "Expected `[`.")?;
+					let seq = self.int()? as u16;
+					self.expect_punct("]", "Expected `]`.")?;
+					if self.eat_punct("=") {
+						body.param_values.push((seq, self.constant()?));
+					}
+				},
+				".custom" => body.customs.push(self.custom()?),
+				".try"    => self.try_block(body, n_labels, line)?,
+				_ if matches!(self.peek(), Some(Token::Punct(":"))) => {
+					self.pos += 1;
+					body.items.push(Item::Label(word, line));
+				},
+				_ => {
+					let opcode = opcode_by_name(&word).ok_or(Error::Syntax(line, "Unknown opcode."))?;
+					let operand = self.operand(opcode)?;
+					body.items.push(Item::Instruction(opcode, operand, line));
+				},
+			}
+		}
+	}
+
+	fn synthetic_label(body: &mut BodyDecl, n_labels: &mut usize, line: usize) -> String {
+		// Spaces cannot appear in source labels.
+		let label = format!("try {}", n_labels);
+		*n_labels += 1;
+		body.items.push(Item::Label(label.clone(), line));
+		label
+	}
+
+	fn try_block(&mut self, body: &mut BodyDecl, n_labels: &mut usize, line: usize) -> Result<()> {
+		// `.try L1 to L2 catch T handler L3 to L4` references labels.
+		if !self.is_punct("{") {
+			let try_start = self.name()?;
+			self.expect("to", "Expected `to`.")?;
+			let try_end = self.name()?;
+			let handler = self.handler_kind()?;
+			let handler = match handler {
+				Some(HandlerDecl::Filter(_)) => {
+					let filter = self.name()?;
+					HandlerDecl::Filter(filter)
+				},
+				Some(h) => h,
+				None => Err(self.error("Expected a handler."))?,
+			};
+			self.expect("handler", "Expected `handler`.")?;
+			let handler_start = self.name()?;
+			self.expect("to", "Expected `to`.")?;
+			let handler_end = self.name()?;
+			body.clauses.push(ClauseDecl { handler, try_start, try_end, handler_start, handler_end, line });
+			return Ok(());
+		}
+
+		self.pos += 1;
+		let try_start = Parser::synthetic_label(body, n_labels, line);
+		self.body(body, n_labels)?;
+		let try_end = Parser::synthetic_label(body, n_labels, line);
+
+		let mut n_handlers = 0;
+		while let Some(handler) = self.handler_kind()? {
+			let line = self.line();
+			let handler = match handler {
+				HandlerDecl::Filter(_) => {
+					self.expect_punct("{", "Expected `{`.")?;
+					let filter = Parser::synthetic_label(body, n_labels, line);
+					self.body(body, n_labels)?;
+					HandlerDecl::Filter(filter)
+				},
+				h => h,
+			};
+			self.expect_punct("{", "Expected `{`.")?;
+			let handler_start = Parser::synthetic_label(body, n_labels, line);
+			self.body(body, n_labels)?;
+			let handler_end = Parser::synthetic_label(body, n_labels, line);
+
+			body.clauses.push(ClauseDecl {
+				handler,
+				try_start: try_start.clone(),
+				try_end: try_end.clone(),
+				handler_start,
+				handler_end,
+				line,
+			});
+			n_handlers += 1;
+		}
+
+		if n_handlers == 0 {
+			Err(Error::Syntax(line, "Protected block has no handlers."))?;
+		}
+		Ok(())
+	}
+
+	/// Reads `catch T`, `finally`, `fault` or `filter`, if any. The filter
+	/// label is filled in later.
+	fn handler_kind(&mut self) -> Result<Option<HandlerDecl>> {
+		let handler = if self.eat("catch") {
+			HandlerDecl::Catch(self.type_token()?)
+		} else if self.eat("finally") {
+			HandlerDecl::Finally
+		} else if self.eat("fault") {
+			HandlerDecl::Fault
+		} else if self.eat("filter") {
+			HandlerDecl::Filter(String::new())
+		} else {
+			return Ok(None);
+		};
+		Ok(Some(handler))
+	}
+
+	fn operand(&mut self, opcode: u16) -> Result<OperandDecl> {
+		let operand = match operand_type(opcode)? {
+			OperandType::None => OperandDecl::None,
+			OperandType::U8 | OperandType::U16 => match self.peek() {
+				Some(Token::Int(_)) => OperandDecl::Int(self.int()?),
+				_ => OperandDecl::Var(self.name()?),
+			},
+			OperandType::I8 | OperandType::I32 | OperandType::I64 => OperandDecl::Int(self.int()?),
+			OperandType::F32 | OperandType::F64 => {
+				if self.is_punct("(") {
+					// Bit pattern, e.g. of NaN.
+					let bytes = self.bytes()?;
+					let mut raw = [0u8; 8];
+					if bytes.len() > 8 {
+						Err(self.error("Too many bytes in a float."))?;
+					}
+					raw[..bytes.len()].copy_from_slice(&bytes);
+					if bytes.len() == 4 {
+						OperandDecl::Float(f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64)
+					} else {
+						OperandDecl::Float(f64::from_le_bytes(raw))
+					}
+				} else {
+					OperandDecl::Float(self.float()?)
+				}
+			},
+			OperandType::ShortBranchTarget | OperandType::BranchTarget => OperandDecl::Label(self.name()?),
+			OperandType::Switch => {
+				self.expect_punct("(", "Expected `(`.")?;
+				let mut labels = Vec::new();
+				while !self.eat_punct(")") {
+					if !labels.is_empty() {
+						self.expect_punct(",", "Expected `,`.")?;
+					}
+					labels.push(self.name()?);
+				}
+				OperandDecl::Switch(labels)
+			},
+			OperandType::String => {
+				if self.eat("bytearray") {
+					let bytes = self.bytes()?;
+					let wide: Vec<u16> = bytes.chunks(2)
+						.map(|c| u16::from_le_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+						.collect();
+					OperandDecl::String(String::from_utf16_lossy(&wide))
+				} else {
+					OperandDecl::String(self.string()?)
+				}
+			},
+			OperandType::Token => match opcode {
+				CALL | CALLVIRT | NEWOBJ | JMP | LDFTN | LDVIRTFTN => OperandDecl::Method(self.method_ref()?),
+				LDFLD | LDFLDA | STFLD | LDSFLD | LDSFLDA | STSFLD => OperandDecl::Field(self.field_ref()?),
+				CALLI => {
+					let (has_this, explicit_this, vararg) = self.call_conv();
+					let ret = self.ty()?;
+					let params = self.type_list()?;
+					OperandDecl::Sig(Signature { has_this, explicit_this, vararg, ret, params })
+				},
+				LDTOKEN if self.eat("method") => OperandDecl::Method(self.method_ref()?),
+				LDTOKEN if self.eat("field") => OperandDecl::Field(self.field_ref()?),
+				_ => OperandDecl::Type(self.type_token()?),
+			},
+		};
+		Ok(operand)
+	}
+}
+
+fn is_type_keyword(s: &str) -> bool {
+	matches!(s,
+		"void" | "bool" | "char" | "int8" | "int16" | "int32" | "int64" | "uint8" | "uint16" |
+		"uint32" | "uint64" | "float32" | "float64" | "string" | "object" | "typedref" |
+		"unsigned" | "native" | "class" | "valuetype")
+}
+
+// Emitter.
+
+#[derive(Default)]
+struct Emitter {
+	strings: StringsBuilder,
+	blobs: BlobsBuilder,
+	user_strings: UserStringsBuilder,
+	guids: GuidsBuilder,
+	image: ImageBuilder,
+	rows: Rows,
+
+	/// 1-based TypeDef rows by full path, e.g. `Ns.Outer/Inner`.
+	type_defs: HashMap<String, u32>,
+	/// Keyed by encoded scopes.
+	type_refs: HashMap<(u32, String), u32>,
+	assembly_refs: HashMap<String, u32>,
+	/// Keyed by encoded parents.
+	member_refs: HashMap<(u32, String, Vec<u8>), u32>,
+	type_specs: HashMap<Vec<u8>, u32>,
+	standalone_sigs: HashMap<Vec<u8>, u32>,
+	/// 1-based MethodDef rows by owner, name and signature.
+	methods: HashMap<(u32, String, Vec<u8>), u32>,
+	/// 1-based Field rows by owner and name.
+	fields: HashMap<(u32, String), u32>,
+	data: HashMap<String, u32>,
+	customs: Vec<(HasCustomAttribute, CustomDecl)>,
+}
+
+/// Tables being built, converted into TableRows once complete.
+#[derive(Default)]
+struct Rows {
+	modules: Vec<Module>,
+	type_refs: Vec<TypeRef>,
+	type_defs: Vec<TypeDef>,
+	fields: Vec<Field>,
+	method_defs: Vec<MethodDef>,
+	params: Vec<Param>,
+	interface_impls: Vec<InterfaceImpl>,
+	member_refs: Vec<MemberRef>,
+	constants: Vec<Constant>,
+	custom_attributes: Vec<CustomAttribute>,
+	class_layouts: Vec<ClassLayout>,
+	field_layouts: Vec<FieldLayout>,
+	standalone_signatures: Vec<StandAloneSig>,
+	type_specs: Vec<TypeSpec>,
+	field_rvas: Vec<FieldRVA>,
+	assemblies: Vec<Assembly>,
+	assembly_refs: Vec<AssemblyRef>,
+	nested_classes: Vec<NestedClass>,
+}
+
+/// A method waiting for its body to be encoded.
+struct PendingBody<'p> {
+	row: u32,
+	decl: &'p MethodDecl,
+}
+
+impl Emitter {
+	fn emit(mut self, program: &Program) -> Result<Vec<u8>> {
+		for (label, bytes) in program.data.iter() {
+			let rva = self.image.add_data(bytes);
+			self.data.insert(label.clone(), rva);
+		}
+
+		for decl in program.externs.iter() {
+			let row = self.assembly_ref(&decl.name);
+			let r = &mut self.rows.assembly_refs[row as usize - 1];
+			r.major_version = decl.version[0];
+			r.minor_version = decl.version[1];
+			r.build_number = decl.version[2];
+			r.revision_number = decl.version[3];
+			// Exactly 8 bytes make a token, anything else is a full key.
+			r.flags = if decl.public_key.is_empty() || decl.public_key.len() == 8 { 0 } else { 0x1 };
+			r.pub_key_or_token = self.blobs.add(&decl.public_key);
+			r.culture = self.strings.add(&decl.culture);
+		}
+
+		// `<Module>` comes first, then classes in the order of declaration,
+		// each followed by its nested classes.
+		let globals = ClassDecl { name: "<Module>".to_owned(), ..program.globals.clone() };
+		let mut classes = vec![(&globals, None, String::new())];
+		for class in program.classes.iter() {
+			flatten(class, None, "", &mut classes);
+		}
+		for (i, (_, _, path)) in classes.iter().enumerate().skip(1) {
+			if self.type_defs.insert(path.clone(), i as u32 + 1).is_some() {
+				Err("Class is declared twice.")?;
+			}
+		}
+
+		let mut bodies = Vec::new();
+		for (i, (class, enclosing, _)) in classes.iter().enumerate() {
+			self.class(i as u32 + 1, class, *enclosing, &mut bodies)?;
+		}
+
+		let mut ep_token = 0;
+		for body in bodies {
+			if body.decl.body.is_entry_point {
+				if ep_token != 0 {
+					Err(Error::Syntax(body.decl.line, "Entry point is declared twice."))?;
+				}
+				ep_token = (METADATA_METHOD_DEF as u32) << 24 | body.row;
+			}
+			let rva = self.body(&body)?;
+			self.rows.method_defs[body.row as usize - 1].rva = rva;
+		}
+
+		let customs = std::mem::take(&mut self.customs);
+		for (parent, decl) in customs {
+			let ty = match self.method_token(&decl.ctor)? {
+				MethodDefOrRef::MethodDef(m) => CustomAttributeType::MethodDef(m),
+				MethodDefOrRef::MemberRef(m) => CustomAttributeType::MemberRef(m),
+			};
+			let value = self.blobs.add(&decl.value);
+			self.rows.custom_attributes.push(CustomAttribute { parent, ty, value });
+		}
+
+		let module_name = match (&program.module, &program.assembly) {
+			(Some(m), _)    => m.clone(),
+			(None, Some(a)) => format!("{}.exe", a.name),
+			(None, None)    => "module.exe".to_owned(),
+		};
+		let name = self.strings.add(&module_name);
+		let mvid = self.guids.add(Guid::default());
+		self.rows.modules.push(Module { generation: 0, name, mvid, enc_id: GuidIndex(0), enc_base_id: GuidIndex(0) });
+
+		if let Some(decl) = &program.assembly {
+			let hash_algo = match decl.hash_algo {
+				Some(0x8003) => HashAlgo::MD5,
+				Some(0x8004) | None => HashAlgo::SHA1,
+				_ => Err("Unsupported hash algorithm.")?,
+			};
+			let assembly = Assembly {
+				hash_algo,
+				major_version: decl.version[0],
+				minor_version: decl.version[1],
+				build_number: decl.version[2],
+				revision_number: decl.version[3],
+				flags: if decl.public_key.is_empty() { 0 } else { 0x1 },
+				pub_key: self.blobs.add(&decl.public_key),
+				name: self.strings.add(&decl.name),
+				culture: self.strings.add(&decl.culture),
+			};
+			self.rows.assemblies.push(assembly);
+			for custom in decl.customs.iter() {
+				let ctor = self.method_token(&custom.ctor)?;
+				let ty = match ctor {
+					MethodDefOrRef::MethodDef(m) => CustomAttributeType::MethodDef(m),
+					MethodDefOrRef::MemberRef(m) => CustomAttributeType::MemberRef(m),
+				};
+				let value = self.blobs.add(&custom.value);
+				self.rows.custom_attributes.push(CustomAttribute { parent: HasCustomAttribute::Assembly(1), ty, value });
+			}
+		}
+
+		self.finish(ep_token)
+	}
+
+	fn finish(self, ep_token: u32) -> Result<Vec<u8>> {
+		let mut rows = self.rows;
+		// Tables with a primary key shall be sorted by it, II.22.
+		rows.constants.sort_by_key(|c| c.parent.encode());
+		rows.custom_attributes.sort_by_key(|c| c.parent.encode());
+		rows.field_rvas.sort_by_key(|r| r.field.into_index());
+
+		let rows = TableRows {
+			modules: rows.modules.into_boxed_slice(),
+			type_refs: rows.type_refs.into_boxed_slice(),
+			type_defs: rows.type_defs.into_boxed_slice(),
+			fields: rows.fields.into_boxed_slice(),
+			method_defs: rows.method_defs.into_boxed_slice(),
+			params: rows.params.into_boxed_slice(),
+			interface_impls: rows.interface_impls.into_boxed_slice(),
+			member_refs: rows.member_refs.into_boxed_slice(),
+			constants: rows.constants.into_boxed_slice(),
+			custom_attributes: rows.custom_attributes.into_boxed_slice(),
+			class_layouts: rows.class_layouts.into_boxed_slice(),
+			field_layouts: rows.field_layouts.into_boxed_slice(),
+			standalone_signatures: rows.standalone_signatures.into_boxed_slice(),
+			type_specs: rows.type_specs.into_boxed_slice(),
+			field_rvas: rows.field_rvas.into_boxed_slice(),
+			assemblies: rows.assemblies.into_boxed_slice(),
+			assembly_refs: rows.assembly_refs.into_boxed_slice(),
+			nested_classes: rows.nested_classes.into_boxed_slice(),
+			..Default::default()
+		};
+
+		let tables = rows.write_stream(self.strings.len(), 16, self.blobs.len());
+		let strings = self.strings.finish();
+		let user_strings = self.user_strings.finish();
+		let blobs = self.blobs.finish();
+		let guids = self.guids.finish();
+
+		let metadata = Metadata {
+			version: "v4.0.30319",
+			logical_tables: Some(&tables),
+			strings: Some(&strings),
+			user_strings: Some(&user_strings),
+			blobs: Some(&blobs),
+			guids: Some(&guids),
+			..Metadata::default()
+		};
+		Ok(self.image.build(&metadata, ep_token))
+	}
+
+	fn class<'p>(&mut self, row: u32, class: &'p ClassDecl, enclosing: Option<u32>, bodies: &mut Vec<PendingBody<'p>>) -> Result<()> {
+		let (namespace, name) = split_name(&class.name);
+		let extends = match &class.extends {
+			Some(name) => self.type_def_or_ref(name)?,
+			None => TypeDefOrRef::TypeDef(0),
+		};
+		let def = TypeDef {
+			flags: class.flags,
+			name: self.strings.add(name),
+			namespace: self.strings.add(namespace),
+			extends,
+			field_list: FieldIndex(self.rows.fields.len() as u32 + 1),
+			method_list: MethodDefIndex(self.rows.method_defs.len() as u32 + 1),
+		};
+		self.rows.type_defs.push(def);
+
+		if let Some(enclosing) = enclosing {
+			self.rows.nested_classes.push(NestedClass { nested: TypeDefIndex(row), enclosing: TypeDefIndex(enclosing) });
+		}
+		for iface in class.implements.iter() {
+			let iface = self.type_def_or_ref(iface)?;
+			self.rows.interface_impls.push(InterfaceImpl { class: TypeDefIndex(row), iface });
+		}
+		if let Some((packing_size, class_size)) = class.layout {
+			self.rows.class_layouts.push(ClassLayout { packing_size, class_size, parent: TypeDefIndex(row) });
+		}
+		for custom in class.customs.iter() {
+			self.customs.push((HasCustomAttribute::TypeDef(row), custom.clone()));
+		}
+
+		for field in class.fields.iter() {
+			self.field(row, field)?;
+		}
+		for method in class.methods.iter() {
+			let method_row = self.method(row, method)?;
+			bodies.push(PendingBody { row: method_row, decl: method });
+		}
+
+		Ok(())
+	}
+
+	fn field(&mut self, owner: u32, decl: &FieldDecl) -> Result<()> {
+		let row = self.rows.fields.len() as u32 + 1;
+		let ty = self.sig(&decl.ty)?;
+		let mut sig = Vec::new();
+		ty.write_field_sig(&mut sig);
+
+		self.rows.fields.push(Field {
+			flags: decl.flags,
+			name: self.strings.add(&decl.name),
+			sig: self.blobs.add(&sig),
+		});
+		if self.fields.insert((owner, decl.name.clone()), row).is_some() {
+			Err("Field is declared twice.")?;
+		}
+
+		if let Some(offset) = decl.offset {
+			self.rows.field_layouts.push(FieldLayout { offset, field: FieldIndex(row) });
+		}
+		if let Some((label, line)) = &decl.data {
+			let rva = *self.data.get(label).ok_or(Error::Syntax(*line, "Unknown data label."))?;
+			self.rows.field_rvas.push(FieldRVA { rva, field: FieldIndex(row) });
+		}
+		if let Some(value) = &decl.value {
+			self.constant(HasConstant::Field(row), value);
+		}
+		for custom in decl.customs.iter() {
+			self.customs.push((HasCustomAttribute::Field(row), custom.clone()));
+		}
+
+		Ok(())
+	}
+
+	fn constant(&mut self, parent: HasConstant, value: &ConstantValue) {
+		let mut blob = Vec::new();
+		value.write(&mut blob);
+		let value_index = self.blobs.add(&blob);
+		self.rows.constants.push(Constant { ty: value.element_type(), parent, value: value_index });
+	}
+
+	fn method(&mut self, owner: u32, decl: &MethodDecl) -> Result<u32> {
+		let row = self.rows.method_defs.len() as u32 + 1;
+		let has_this = decl.flags & METHOD_STATIC == 0;
+		let sig = MethodSig {
+			has_this,
+			explicit_this: false,
+			call_conv: if decl.vararg { CallingConvention::VarArg } else { CallingConvention::Default },
+			ret: self.sig(&decl.ret)?,
+			params: decl.params.iter().map(|p| self.sig(&p.ty)).collect::<Result<Vec<_>>>()?.into_boxed_slice(),
+			sentinel: None,
+		};
+		let mut blob = Vec::new();
+		sig.write(&mut blob);
+
+		self.rows.method_defs.push(MethodDef {
+			rva: 0,
+			impl_flags: decl.impl_flags,
+			flags: decl.flags,
+			name: self.strings.add(&decl.name),
+			sig: self.blobs.add(&blob),
+			param_list: ParamIndex(self.rows.params.len() as u32 + 1),
+		});
+		if self.methods.insert((owner, decl.name.clone(), blob), row).is_some() {
+			Err(Error::Syntax(decl.line, "Method is declared twice."))?;
+		}
+
+		for (i, p) in decl.params.iter().enumerate() {
+			let seq = i as u16 + 1;
+			let value = decl.body.param_values.iter().find(|(s, _)| *s == seq).map(|(_, v)| v);
+			let mut flags = p.flags;
+			if value.is_some() {
+				flags |= PARAM_HAS_DEFAULT;
+			}
+			if p.name.is_empty() && flags == 0 {
+				continue;
+			}
+			self.rows.params.push(Param { flags, seq, name: self.strings.add(&p.name) });
+			if let Some(value) = value {
+				let param = self.rows.params.len() as u32;
+				self.constant(HasConstant::Param(param), value);
+			}
+		}
+		for custom in decl.body.customs.iter() {
+			self.customs.push((HasCustomAttribute::MethodDef(row), custom.clone()));
+		}
+
+		Ok(row)
+	}
+
+	// References.
+
+	fn assembly_ref(&mut self, name: &str) -> u32 {
+		if let Some(row) = self.assembly_refs.get(name) {
+			return *row;
+		}
+		let r = AssemblyRef {
+			major_version: 0,
+			minor_version: 0,
+			build_number: 0,
+			revision_number: 0,
+			flags: 0,
+			pub_key_or_token: BlobIndex(0),
+			name: self.strings.add(name),
+			culture: StringIndex(0),
+			hash: BlobIndex(0),
+		};
+		self.rows.assembly_refs.push(r);
+		let row = self.rows.assembly_refs.len() as u32;
+		self.assembly_refs.insert(name.to_owned(), row);
+		row
+	}
+
+	fn type_def_or_ref(&mut self, name: &TypeName) -> Result<TypeDefOrRef> {
+		let scope = match &name.scope {
+			None => {
+				let path = name.path.join("/");
+				let row = self.type_defs.get(&path).ok_or(Error::Syntax(name.line, "Unknown type."))?;
+				return Ok(TypeDefOrRef::TypeDef(*row));
+			},
+			// Referenced assemblies need not be declared.
+			Some(assembly) => ResolutionScope::AssemblyRef(self.assembly_ref(assembly)),
+		};
+
+		let mut scope = scope;
+		let mut row = 0;
+		for part in name.path.iter() {
+			row = match self.type_refs.get(&(scope.encode(), part.clone())) {
+				Some(row) => *row,
+				None => {
+					let (namespace, type_name) = split_name(part);
+					let r = TypeRef {
+						scope,
+						name: self.strings.add(type_name),
+						namespace: self.strings.add(namespace),
+					};
+					self.rows.type_refs.push(r);
+					let row = self.rows.type_refs.len() as u32;
+					self.type_refs.insert((scope.encode(), part.clone()), row);
+					row
+				},
+			};
+			scope = ResolutionScope::TypeRef(row);
+		}
+
+		Ok(TypeDefOrRef::TypeRef(row))
+	}
+
+	fn sig(&mut self, ty: &Type) -> Result<TypeSig> {
+		let sig = match ty {
+			Type::Plain(sig)       => sig.clone(),
+			Type::Class(name)      => TypeSig::Class(self.type_def_or_ref(name)?),
+			Type::Value(name)  => TypeSig::ValueType(self.type_def_or_ref(name)?),
+			Type::SzArray(t)       => TypeSig::SzArray(Box::new(self.sig(t)?)),
+			Type::Ptr(t)           => TypeSig::Ptr(Box::new(self.sig(t)?)),
+			Type::ByRef(t)         => TypeSig::ByRef(Box::new(self.sig(t)?)),
+			Type::Pinned(t)        => TypeSig::Pinned(Box::new(self.sig(t)?)),
+		};
+		Ok(sig)
+	}
+
+	fn method_sig(&mut self, sig: &Signature) -> Result<MethodSig> {
+		Ok(MethodSig {
+			has_this: sig.has_this,
+			explicit_this: sig.explicit_this,
+			call_conv: if sig.vararg { CallingConvention::VarArg } else { CallingConvention::Default },
+			ret: self.sig(&sig.ret)?,
+			params: sig.params.iter().map(|p| self.sig(p)).collect::<Result<Vec<_>>>()?.into_boxed_slice(),
+			sentinel: None,
+		})
+	}
+
+	/// Token of a type used as an instruction operand.
+	fn type_token(&mut self, ty: &Type) -> Result<TypeDefOrRef> {
+		match ty {
+			Type::Class(name) | Type::Value(name) => self.type_def_or_ref(name),
+			_ => {
+				let mut blob = Vec::new();
+				self.sig(ty)?.write(&mut blob);
+				if let Some(row) = self.type_specs.get(&blob) {
+					return Ok(TypeDefOrRef::TypeSpec(*row));
+				}
+				let sig = self.blobs.add(&blob);
+				self.rows.type_specs.push(TypeSpec { sig });
+				let row = self.rows.type_specs.len() as u32;
+				self.type_specs.insert(blob, row);
+				Ok(TypeDefOrRef::TypeSpec(row))
+			},
+		}
+	}
+
+	/// Resolves the owner of a member into a TypeDef row, if it is defined
+	/// here, or into a MemberRef parent otherwise.
+	fn member_owner(&mut self, owner: &Option<Type>) -> Result<std::result::Result<u32, MemberRefParent>> {
+		let owner = match owner {
+			// Global members belong to `<Module>`.
+			None => return Ok(Ok(1)),
+			Some(owner) => self.type_token(owner)?,
+		};
+		let parent = match owner {
+			TypeDefOrRef::TypeDef(row)  => return Ok(Ok(row)),
+			TypeDefOrRef::TypeRef(row)  => MemberRefParent::TypeRef(row),
+			TypeDefOrRef::TypeSpec(row) => MemberRefParent::TypeSpec(row),
+		};
+		Ok(Err(parent))
+	}
+
+	fn member_ref(&mut self, parent: MemberRefParent, name: &str, sig: Vec<u8>) -> u32 {
+		let key = (parent.encode(), name.to_owned(), sig);
+		if let Some(row) = self.member_refs.get(&key) {
+			return *row;
+		}
+		let r = MemberRef {
+			class: parent,
+			name: self.strings.add(name),
+			sig: self.blobs.add(&key.2),
+		};
+		self.rows.member_refs.push(r);
+		let row = self.rows.member_refs.len() as u32;
+		self.member_refs.insert(key, row);
+		row
+	}
+
+	fn method_token(&mut self, decl: &MemberRefDecl) -> Result<MethodDefOrRef> {
+		let sig = self.method_sig(&decl.sig)?;
+		let mut blob = Vec::new();
+		sig.write(&mut blob);
+
+		match self.member_owner(&decl.owner)? {
+			Ok(owner) => {
+				let row = self.methods.get(&(owner, decl.name.clone(), blob))
+					.ok_or(Error::Syntax(decl.line, "Unknown method."))?;
+				Ok(MethodDefOrRef::MethodDef(*row))
+			},
+			Err(parent) => Ok(MethodDefOrRef::MemberRef(self.member_ref(parent, &decl.name, blob))),
+		}
+	}
+
+	fn field_token(&mut self, decl: &FieldRefDecl) -> Result<u32> {
+		match self.member_owner(&decl.owner)? {
+			Ok(owner) => {
+				let row = self.fields.get(&(owner, decl.name.clone()))
+					.ok_or(Error::Syntax(decl.line, "Unknown field."))?;
+				Ok((METADATA_FIELD as u32) << 24 | row)
+			},
+			Err(parent) => {
+				let mut blob = Vec::new();
+				self.sig(&decl.ty)?.write_field_sig(&mut blob);
+				let row = self.member_ref(parent, &decl.name, blob);
+				Ok((METADATA_MEMBER_REF as u32) << 24 | row)
+			},
+		}
+	}
+
+	fn standalone_sig(&mut self, blob: Vec<u8>) -> u32 {
+		let row = match self.standalone_sigs.get(&blob) {
+			Some(row) => *row,
+			None => {
+				let sig = self.blobs.add(&blob);
+				self.rows.standalone_signatures.push(StandAloneSig { sig });
+				let row = self.rows.standalone_signatures.len() as u32;
+				self.standalone_sigs.insert(blob, row);
+				row
+			},
+		};
+		(METADATA_STANDALONE_SIG as u32) << 24 | row
+	}
+
+	// Code.
+
+	fn body(&mut self, pending: &PendingBody) -> Result<u32> {
+		let decl = pending.decl;
+		let body = &decl.body;
+		if decl.flags & METHOD_ABSTRACT != 0 || decl.impl_flags & METHOD_IMPL_CODE_TYPE_MASK == METHOD_IMPL_RUNTIME {
+			if !body.items.is_empty() {
+				Err(Error::Syntax(decl.line, "Method without a body has instructions."))?;
+			}
+			return Ok(0);
+		}
+
+		// Arguments are numbered from zero, including `this`.
+		let first_arg = if decl.flags & METHOD_STATIC == 0 { 1 } else { 0 };
+		let args: HashMap<&str, usize> = decl.params.iter()
+			.enumerate()
+			.filter(|(_, p)| !p.name.is_empty())
+			.map(|(i, p)| (p.name.as_str(), i + first_arg))
+			.collect();
+		let locals: HashMap<&str, usize> = body.locals.iter()
+			.enumerate()
+			.filter(|(_, l)| !l.1.is_empty())
+			.map(|(i, l)| (l.1.as_str(), i))
+			.collect();
+
+		// Labels are resolved once sizes of all instructions are known.
+		let mut labels = HashMap::new();
+		let mut offset = 0u32;
+		for item in body.items.iter() {
+			match item {
+				Item::Label(name, line) => {
+					if labels.insert(name.as_str(), offset).is_some() {
+						Err(Error::Syntax(*line, "Label is declared twice."))?;
+					}
+				},
+				Item::Instruction(opcode, operand, _) => {
+					offset += ins_size(*opcode)? as u32;
+					if let OperandDecl::Switch(targets) = operand {
+						offset += targets.len() as u32 * 4;
+					}
+				},
+			}
+		}
+		let label = |name: &str, line: usize| -> Result<u32> {
+			Ok(*labels.get(name).ok_or(Error::Syntax(line, "Unknown label."))?)
+		};
+
+		let mut code = Vec::with_capacity(offset as usize);
+		for item in body.items.iter() {
+			let (opcode, operand, line) = match item {
+				Item::Instruction(opcode, operand, line) => (*opcode, operand, *line),
+				Item::Label(..) => continue,
+			};
+
+			if opcode > 0xFF {
+				code.push((opcode >> 8) as u8);
+			}
+			code.push(opcode as u8);
+
+			let next = code.len() as u32 + operand_type(opcode)?.measure() as u32;
+			match (operand_type(opcode)?, operand) {
+				(OperandType::None, OperandDecl::None) => {},
+				(OperandType::U8, OperandDecl::Int(x))  => code.push(*x as u8),
+				(OperandType::U16, OperandDecl::Int(x)) => code.extend_from_slice(&(*x as u16).to_le_bytes()),
+				(t @ (OperandType::U8 | OperandType::U16), OperandDecl::Var(name)) => {
+					let is_arg = matches!(opcode, LDARG_S | LDARGA_S | STARG_S | LDARG | LDARGA | STARG);
+					let vars = if is_arg { &args } else { &locals };
+					let i = *vars.get(name.as_str()).ok_or(Error::Syntax(line, "Unknown variable."))?;
+					if t == OperandType::U8 {
+						code.push(i as u8);
+					} else {
+						code.extend_from_slice(&(i as u16).to_le_bytes());
+					}
+				},
+				(OperandType::I8, OperandDecl::Int(x))  => code.push(*x as i8 as u8),
+				(OperandType::I32, OperandDecl::Int(x)) => code.extend_from_slice(&(*x as i32).to_le_bytes()),
+				(OperandType::I64, OperandDecl::Int(x)) => code.extend_from_slice(&x.to_le_bytes()),
+				(OperandType::F32, OperandDecl::Float(x)) => code.extend_from_slice(&(*x as f32).to_le_bytes()),
+				(OperandType::F64, OperandDecl::Float(x)) => code.extend_from_slice(&x.to_le_bytes()),
+				(OperandType::ShortBranchTarget, OperandDecl::Label(name)) => {
+					let delta = label(name, line)? as i64 - next as i64;
+					if delta < i8::MIN as i64 || delta > i8::MAX as i64 {
+						Err(Error::Syntax(line, "Short branch target is too far."))?;
+					}
+					code.push(delta as i8 as u8);
+				},
+				(OperandType::BranchTarget, OperandDecl::Label(name)) => {
+					let delta = label(name, line)? as i64 - next as i64;
+					code.extend_from_slice(&(delta as i32).to_le_bytes());
+				},
+				(OperandType::Switch, OperandDecl::Switch(targets)) => {
+					code.extend_from_slice(&(targets.len() as u32).to_le_bytes());
+					// Targets are relative to the end of the whole table.
+					let next = next + targets.len() as u32 * 4;
+					for name in targets.iter() {
+						let delta = label(name, line)? as i64 - next as i64;
+						code.extend_from_slice(&(delta as i32).to_le_bytes());
+					}
+				},
+				(OperandType::String, OperandDecl::String(s)) => {
+					let offset = self.user_strings.add(s);
+					code.extend_from_slice(&(0x7000_0000 | offset).to_le_bytes());
+				},
+				(OperandType::Token, operand) => {
+					let token = match operand {
+						OperandDecl::Method(m) => self.method_token(m)?.token(),
+						OperandDecl::Field(f)  => self.field_token(f)?,
+						OperandDecl::Type(t)   => self.type_token(t)?.token(),
+						OperandDecl::Sig(sig)  => {
+							let mut blob = Vec::new();
+							self.method_sig(sig)?.write(&mut blob);
+							self.standalone_sig(blob)
+						},
+						_ => Err(Error::Syntax(line, "Invalid operand."))?,
+					};
+					code.extend_from_slice(&token.to_le_bytes());
+				},
+				_ => Err(Error::Syntax(line, "Invalid operand."))?,
+			}
+		}
+
+		let mut clauses = Vec::with_capacity(body.clauses.len());
+		for c in body.clauses.iter() {
+			let try_offset = label(&c.try_start, c.line)?;
+			let handler_offset = label(&c.handler_start, c.line)?;
+			let handler = match &c.handler {
+				HandlerDecl::Catch(t) => {
+					let token = self.type_token(t)?.token();
+					Handler::Catch(std::convert::TryFrom::try_from(token)?)
+				},
+				HandlerDecl::Filter(l) => Handler::Filter(label(l, c.line)?),
+				HandlerDecl::Finally   => Handler::Finally,
+				HandlerDecl::Fault     => Handler::Fault,
+			};
+			let try_length = match label(&c.try_end, c.line)?.checked_sub(try_offset) {
+				Some(0) => Err(Error::Syntax(c.line, "Protected block is empty."))?,
+				Some(n) => n,
+				None => Err(Error::Syntax(c.line, "Protected block ends before it starts."))?,
+			};
+			let handler_length = match label(&c.handler_end, c.line)?.checked_sub(handler_offset) {
+				Some(0) => Err(Error::Syntax(c.line, "Handler block is empty."))?,
+				Some(n) => n,
+				None => Err(Error::Syntax(c.line, "Handler block ends before it starts."))?,
+			};
+			clauses.push(ExceptionClause {
+				handler,
+				try_offset,
+				try_length,
+				handler_offset,
+				handler_length,
+			});
+		}
+
+		let local_var_sig_tok = if body.locals.is_empty() {
+			0
+		} else {
+			let locals = body.locals.iter()
+				.map(|(ty, _)| {
+					let (ty, pinned) = match ty {
+						Type::Pinned(t) => (&**t, true),
+						t => (t, false),
+					};
+					let (ty, by_ref) = match ty {
+						Type::ByRef(t) => (&**t, true),
+						t => (t, false),
+					};
+					Ok(LocalVar { ty: self.sig(ty)?, pinned, by_ref })
+				})
+				.collect::<Result<Vec<_>>>()?;
+			let mut blob = Vec::new();
+			LocalVarSig { locals: locals.into_boxed_slice() }.write(&mut blob);
+			self.standalone_sig(blob)
+		};
+
+		let body = MethodBody {
+			max_stack: body.max_stack.unwrap_or(8),
+			init_locals: body.init_locals,
+			local_var_sig_tok,
+			code: &code,
+			clauses: clauses.into_boxed_slice(),
+		};
+		let mut out = Vec::new();
+		body.write(&mut out);
+		Ok(self.image.add_method_body(&out))
+	}
+}
+
+/// Lists the class and its nested classes with their enclosing rows and
+/// full paths, rows being 1-based positions in the list.
+fn flatten<'p>(class: &'p ClassDecl, enclosing: Option<u32>, prefix: &str, out: &mut Vec<(&'p ClassDecl, Option<u32>, String)>) {
+	let path = if prefix.is_empty() { class.name.clone() } else { format!("{}/{}", prefix, class.name) };
+	out.push((class, enclosing, path.clone()));
+	let row = out.len() as u32;
+	for nested in class.nested.iter() {
+		flatten(nested, Some(row), &path, out);
+	}
+}
+
+/// Splits a full type name into the namespace and the name.
+fn split_name(full: &str) -> (&str, &str) {
+	match full.rfind('.') {
+		Some(i) if i > 0 => (&full[..i], &full[i + 1..]),
+		_ => ("", full),
+	}
+}
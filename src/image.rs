@@ -0,0 +1,286 @@
+use log::{debug};
+
+use crate::buf::Writing;
+use crate::cli;
+use crate::pe::*;
+use crate::utils::align_up;
+
+// Taken from ECMA II.25.2.1: the only DOS header (and stub) a CLI image is
+// expected to have.
+const DOS_HEADER: [u8; 128] = [
+	0x4D, 0x5A, 0x90, 0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00,
+	0xB8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00,
+	0x0E, 0x1F, 0xBA, 0x0E, 0x00, 0xB4, 0x09, 0xCD, 0x21, 0xB8, 0x01, 0x4C, 0xCD, 0x21, 0x54, 0x68,
+	0x69, 0x73, 0x20, 0x70, 0x72, 0x6F, 0x67, 0x72, 0x61, 0x6D, 0x20, 0x63, 0x61, 0x6E, 0x6E, 0x6F,
+	0x74, 0x20, 0x62, 0x65, 0x20, 0x72, 0x75, 0x6E, 0x20, 0x69, 0x6E, 0x20, 0x44, 0x4F, 0x53, 0x20,
+	0x6D, 0x6F, 0x64, 0x65, 0x2E, 0x0D, 0x0D, 0x0A, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+const IMAGE_BASE:        u32   = 0x0040_0000;
+const SECTION_ALIGNMENT: usize = 0x2000;
+const FILE_ALIGNMENT:    usize = 0x200;
+
+const COFF_HEADER_SIZE:    usize = 20;
+const OPT_HEADER_SIZE:     usize = 224;
+const SECTION_HEADER_SIZE: usize = 40;
+const N_SECTIONS:          usize = 2;
+
+const DATA_DIR_INDEX_IMPORT:     usize = 1;
+const DATA_DIR_INDEX_BASE_RELOC: usize = 5;
+const DATA_DIR_INDEX_IAT:        usize = 12;
+
+const IMAGE_SCN_CNT_CODE:             u32 = 0x0000_0020;
+const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+const IMAGE_SCN_MEM_DISCARDABLE:      u32 = 0x0200_0000;
+const IMAGE_SCN_MEM_EXECUTE:          u32 = 0x2000_0000;
+const IMAGE_SCN_MEM_READ:             u32 = 0x4000_0000;
+
+const IMAGE_SUBSYSTEM_WINDOWS_CUI: u16 = 3;
+// DYNAMIC_BASE | NX_COMPAT | NO_SEH | TERMINAL_SERVER_AWARE.
+const DLL_CHARACTERISTICS: u16 = 0x8540;
+
+const IMAGE_REL_BASED_HIGHLOW: u16 = 3;
+
+/// The .text section always starts with the import address table (just one
+/// entry and a terminator) and the CLI header.
+const TEXT_RVA: usize = SECTION_ALIGNMENT;
+const IAT_SIZE: usize = 8;
+const CODE_RVA: usize = TEXT_RVA + IAT_SIZE + cli::CLI_HEADER_SIZE;
+
+const ENTRY_POINT_NAME: &[u8] = b"_CorExeMain\0";
+const ENTRY_POINT_DLL:  &[u8] = b"mscoree.dll\0";
+
+/// Lays out an IL-only PE32 executable (II.25) as follows:
+///
+/// - headers: DOS header with stub, PE signature, COFF and optional
+///   headers, section headers;
+/// - .text: import address table, CLI header, method bodies and field
+///   data, metadata, import table and the native entry point stub, which
+///   jumps into `mscoree.dll!_CorExeMain`;
+/// - .reloc: a single fixup of the entry point stub.
+///
+/// Method bodies and data are placed before the metadata, so their RVAs are
+/// known while tables referencing them are still being built.
+#[derive(Debug, Clone, Default)]
+pub struct ImageBuilder {
+	code: Vec<u8>,
+}
+
+impl ImageBuilder {
+	/// Adds an encoded method body (header, code and extra data sections),
+	/// returning its RVA for the MethodDef row.
+	pub fn add_method_body(&mut self, body: &[u8]) -> u32 {
+		// II.25.4.5: Fat headers shall be 4-byte aligned, while tiny ones
+		// have no alignment requirements.
+		if body.first().is_some_and(|b| b & 0b11 == 0x3) {
+			self.align(4);
+		}
+		self.append(body)
+	}
+
+	/// Adds the initial data of a field, returning its RVA for the FieldRVA
+	/// row.
+	pub fn add_data(&mut self, data: &[u8]) -> u32 {
+		self.align(8);
+		self.append(data)
+	}
+
+	fn align(&mut self, n: usize) {
+		let len = align_up(CODE_RVA + self.code.len(), n) - CODE_RVA;
+		self.code.resize(len, 0);
+	}
+
+	fn append(&mut self, data: &[u8]) -> u32 {
+		let rva = (CODE_RVA + self.code.len()) as u32;
+		self.code.extend_from_slice(data);
+		rva
+	}
+
+	pub fn build(self, metadata: &cli::Metadata, ep_token: u32) -> Vec<u8> {
+		let mut text = Vec::new();
+		let rva = |text: &Vec<u8>| (TEXT_RVA + text.len()) as u32;
+
+		let iat_rva = rva(&text);
+		text.resize(IAT_SIZE, 0);
+
+		let cli_rva = rva(&text);
+		text.resize(text.len() + cli::CLI_HEADER_SIZE, 0);
+
+		text.extend_from_slice(&self.code);
+		text.resize(align_up(text.len(), 4), 0);
+
+		let metadata_rva = rva(&text);
+		metadata.write(&mut text);
+		let metadata_size = rva(&text) - metadata_rva;
+
+		let mut cli_header = Vec::with_capacity(cli::CLI_HEADER_SIZE);
+		cli::Header { ep_token, metadata_rva, metadata_size }.write(&mut cli_header);
+		let cli_offset = cli_rva as usize - TEXT_RVA;
+		text[cli_offset..cli_offset + cli::CLI_HEADER_SIZE].copy_from_slice(&cli_header);
+
+		// Import table: a directory with a single entry and a terminator,
+		// lookup table, hint/name table and the dll name.
+		text.resize(align_up(text.len(), 4), 0);
+		let import_rva = rva(&text);
+		let lookup_rva = import_rva + 40;
+		let hint_name_rva = lookup_rva + 8;
+		let dll_name_rva = hint_name_rva + 2 + ENTRY_POINT_NAME.len() as u32;
+
+		text.write(lookup_rva);
+		// Time stamp and forwarder chain.
+		text.write(0u32);
+		text.write(0u32);
+		text.write(dll_name_rva);
+		text.write(iat_rva);
+		text.resize(text.len() + 20, 0);
+
+		text.write(hint_name_rva);
+		text.write(0u32);
+
+		// Hint.
+		text.write(0u16);
+		text.extend_from_slice(ENTRY_POINT_NAME);
+		text.extend_from_slice(ENTRY_POINT_DLL);
+		let import_size = rva(&text) - import_rva;
+
+		let iat_offset = iat_rva as usize - TEXT_RVA;
+		text[iat_offset..iat_offset + 4].copy_from_slice(&hint_name_rva.to_le_bytes());
+
+		// Entry point stub is `jmp dword ptr [iat]`, its operand gets
+		// relocated and so is kept 4-byte aligned.
+		while (rva(&text) + 2) & 3 != 0 {
+			text.push(0);
+		}
+		let ep_rva = rva(&text);
+		text.extend_from_slice(&[0xFF, 0x25]);
+		text.write(IMAGE_BASE + iat_rva);
+		let fixup_rva = ep_rva + 2;
+
+		let reloc_rva = align_up(TEXT_RVA + text.len(), SECTION_ALIGNMENT) as u32;
+		let mut reloc = Vec::new();
+		reloc.write(fixup_rva & !0xFFF);
+		// Block size.
+		reloc.write(12u32);
+		reloc.write(IMAGE_REL_BASED_HIGHLOW << 12 | (fixup_rva & 0xFFF) as u16);
+		// Padding.
+		reloc.write(0u16);
+
+		let image_size = align_up(reloc_rva as usize + reloc.len(), SECTION_ALIGNMENT) as u32;
+
+		let headers_size = align_up(
+			DOS_HEADER.len() + 4 + COFF_HEADER_SIZE + OPT_HEADER_SIZE + N_SECTIONS * SECTION_HEADER_SIZE,
+			FILE_ALIGNMENT);
+		let text_raw_size = align_up(text.len(), FILE_ALIGNMENT);
+		let reloc_raw_size = align_up(reloc.len(), FILE_ALIGNMENT);
+
+		debug!("Image: .text at {:#0x} ({:#0x} bytes), .reloc at {:#0x}, entry point at {:#0x}.",
+			TEXT_RVA, text.len(), reloc_rva, ep_rva);
+
+		let mut out = Vec::with_capacity(headers_size + text_raw_size + reloc_raw_size);
+		out.extend_from_slice(&DOS_HEADER);
+		out.write(PE_MAGIC);
+
+		// COFF header.
+		out.write(IMAGE_FILE_MACHINE_I386);
+		out.write(N_SECTIONS as u16);
+		// Time stamp, symbol table pointer and number of symbols.
+		out.write(0u32);
+		out.write(0u32);
+		out.write(0u32);
+		out.write(OPT_HEADER_SIZE as u16);
+		out.write(IMAGE_FILE_EXECUTABLE_IMAGE);
+
+		// Optional header, standard fields.
+		out.write(OPT_MAGIC_PE32);
+		// Linker version.
+		out.write(8u8);
+		out.write(0u8);
+		out.write(text_raw_size as u32);
+		out.write(reloc_raw_size as u32);
+		// Size of uninitialized data.
+		out.write(0u32);
+		out.write(ep_rva);
+		// Base of code and data.
+		out.write(TEXT_RVA as u32);
+		out.write(reloc_rva);
+
+		// NT-specific fields.
+		out.write(IMAGE_BASE);
+		out.write(SECTION_ALIGNMENT as u32);
+		out.write(FILE_ALIGNMENT as u32);
+		// OS, image and subsystem versions.
+		out.write(4u16);
+		out.write(0u16);
+		out.write(0u16);
+		out.write(0u16);
+		out.write(4u16);
+		out.write(0u16);
+		// Reserved.
+		out.write(0u32);
+		out.write(image_size);
+		out.write(headers_size as u32);
+		// File checksum.
+		out.write(0u32);
+		out.write(IMAGE_SUBSYSTEM_WINDOWS_CUI);
+		out.write(DLL_CHARACTERISTICS);
+		// Stack reserve and commit, heap reserve and commit.
+		out.write(0x0010_0000u32);
+		out.write(0x0000_1000u32);
+		out.write(0x0010_0000u32);
+		out.write(0x0000_1000u32);
+		// Loader flags.
+		out.write(0u32);
+		out.write(DATA_DIRS_COUNT as u32);
+
+		let mut dirs = [(0u32, 0u32); DATA_DIRS_COUNT];
+		dirs[DATA_DIR_INDEX_IMPORT]     = (import_rva, import_size);
+		dirs[DATA_DIR_INDEX_BASE_RELOC] = (reloc_rva, reloc.len() as u32);
+		dirs[DATA_DIR_INDEX_IAT]        = (iat_rva, IAT_SIZE as u32);
+		dirs[DATA_DIR_INDEX_CLI_HEADER] = (cli_rva, cli::CLI_HEADER_SIZE as u32);
+		for (dir_rva, dir_size) in &dirs {
+			out.write(*dir_rva);
+			out.write(*dir_size);
+		}
+
+		let text_raw = headers_size;
+		let reloc_raw = text_raw + text_raw_size;
+		write_section_header(&mut out, b".text\0\0\0",
+			text.len(), TEXT_RVA as u32, text_raw_size, text_raw,
+			IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ);
+		write_section_header(&mut out, b".reloc\0\0",
+			reloc.len(), reloc_rva, reloc_raw_size, reloc_raw,
+			IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_DISCARDABLE | IMAGE_SCN_MEM_READ);
+		out.resize(headers_size, 0);
+
+		out.extend_from_slice(&text);
+		out.resize(reloc_raw, 0);
+		out.extend_from_slice(&reloc);
+		out.resize(reloc_raw + reloc_raw_size, 0);
+
+		out
+	}
+}
+
+fn write_section_header(
+	out: &mut Vec<u8>,
+	name: &[u8; 8],
+	virtual_size: usize,
+	virtual_address: u32,
+	raw_size: usize,
+	raw_address: usize,
+	characteristics: u32)
+{
+	out.extend_from_slice(name);
+	out.write(virtual_size as u32);
+	out.write(virtual_address);
+	out.write(raw_size as u32);
+	out.write(raw_address as u32);
+	// Relocations and line numbers pointers and counts.
+	out.write(0u32);
+	out.write(0u32);
+	out.write(0u16);
+	out.write(0u16);
+	out.write(characteristics);
+}
@@ -1,26 +1,159 @@
-use log::{LevelFilter, Metadata, Record};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug)]
-struct SimpleLogger;
+use log::{Level, LevelFilter, Metadata, Record};
 
-impl log::Log for SimpleLogger {
-	fn enabled(&self, _metadata: &Metadata) -> bool {
-		true
+use crate::Result;
+
+/// Environment variables, overridden by the matching command-line flags.
+pub const LOG_ENV: &str = "APS_LOG";
+pub const LOG_FILE_ENV: &str = "APS_LOG_FILE";
+pub const LOG_TIME_ENV: &str = "APS_LOG_TIME";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Config {
+	/// Level of modules without a filter of their own.
+	pub level: LevelFilter,
+	/// Module paths with their levels, e.g. `aps::cli` or just `cli`. The
+	/// longest matching path wins.
+	pub modules: Vec<(String, LevelFilter)>,
+	/// Log file, stderr if none.
+	pub file: Option<PathBuf>,
+	/// Prefix records with UTC time of day.
+	pub timestamps: bool,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		// Stays quiet unless something goes wrong, so stdout belongs to the
+		// managed program.
+		Config { level: LevelFilter::Warn, modules: Vec::new(), file: None, timestamps: false }
+	}
+}
+
+impl Config {
+	/// Reads the configuration from `APS_LOG`, `APS_LOG_FILE` and
+	/// `APS_LOG_TIME`.
+	pub fn from_env() -> Result<Config> {
+		let mut config = Config::default();
+		if let Ok(filters) = std::env::var(LOG_ENV) {
+			config.set_filters(&filters)?;
+		}
+		if let Ok(file) = std::env::var(LOG_FILE_ENV) {
+			config.file = Some(PathBuf::from(file));
+		}
+		if let Ok(time) = std::env::var(LOG_TIME_ENV) {
+			config.timestamps = !matches!(time.as_str(), "" | "0" | "false");
+		}
+		Ok(config)
+	}
+
+	/// Applies filters like `info,cli=trace,aps::disasm=off`: a bare level
+	/// sets the default one, `module=level` sets that of the module.
+	pub fn set_filters(&mut self, filters: &str) -> Result<()> {
+		for filter in filters.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+			match filter.split_once('=') {
+				Some((module, level)) => {
+					let level = parse_level(level)?;
+					let module = module.trim().to_owned();
+					self.modules.retain(|(m, _)| *m != module);
+					self.modules.push((module, level));
+				},
+				None => self.level = parse_level(filter)?,
+			}
+		}
+		Ok(())
+	}
+
+	/// Replaces the filters with ones given on the command line, dropping
+	/// any taken from `APS_LOG` before.
+	pub fn override_filters(&mut self, filters: &str) -> Result<()> {
+		let default = Config::default();
+		self.level = default.level;
+		self.modules = default.modules;
+		self.set_filters(filters)
+	}
+
+	fn level_of(&self, target: &str) -> LevelFilter {
+		// Paths are accepted both with and without the crate name.
+		let local = target.strip_prefix("aps::").unwrap_or(target);
+		self.modules.iter()
+			.filter(|(m, _)| matches_module(target, m) || matches_module(local, m))
+			.max_by_key(|(m, _)| m.len())
+			.map_or(self.level, |(_, level)| *level)
+	}
+}
+
+fn parse_level(s: &str) -> Result<LevelFilter> {
+	s.trim().parse().map_err(|_| "Unknown log level.".into())
+}
+
+fn matches_module(target: &str, module: &str) -> bool {
+	target == module || target.starts_with(module) && target[module.len()..].starts_with("::")
+}
+
+struct Logger {
+	config: Config,
+	out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl log::Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= self.config.level_of(metadata.target())
 	}
 
 	fn log(&self, record: &Record) {
-		if self.enabled(record.metadata()) {
-			println!("{}: {}", record.level(), record.args());
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+
+		let mut line = String::new();
+		if self.config.timestamps {
+			line.push_str(&time_of_day());
+			line.push(' ');
+		}
+		line.push_str(&format!("{}: ", record.level()));
+		if record.level() >= Level::Debug {
+			line.push_str(&format!("[{}] ", record.target()));
+		}
+		line.push_str(&format!("{}\n", record.args()));
+
+		if let Ok(mut out) = self.out.lock() {
+			// Losing a log line is better than failing the program.
+			let _ = out.write_all(line.as_bytes());
 		}
 	}
 
-	fn flush(&self) {}
+	fn flush(&self) {
+		if let Ok(mut out) = self.out.lock() {
+			let _ = out.flush();
+		}
+	}
+}
+
+/// UTC time as `hh:mm:ss.mmm`.
+fn time_of_day() -> String {
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+	let ms = now.as_millis() % (24 * 60 * 60 * 1000);
+	format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
 }
 
-static LOGGER: SimpleLogger = SimpleLogger;
+pub fn init(config: Config) -> Result<()> {
+	let out: Box<dyn Write + Send> = match &config.file {
+		Some(path) => Box::new(File::create(path)?),
+		None => Box::new(io::stderr()),
+	};
+
+	let max_level = config.modules.iter()
+		.map(|(_, level)| *level)
+		.fold(config.level, |max, level| max.max(level));
 
-pub fn init() {
-	log::set_logger(&LOGGER)
-		.map(|()| log::set_max_level(LevelFilter::Trace))
-		.expect("Failed to set a logger.");
+	// The logger lives as long as the process.
+	let logger = Box::leak(Box::new(Logger { config, out: Mutex::new(out) }));
+	log::set_logger(logger).map_err(|_| "Logger is already set.")?;
+	log::set_max_level(max_level);
+	Ok(())
 }
@@ -6,100 +6,278 @@
 
 extern crate log;
 
+mod analysis;
+mod assembly;
 mod buf;
 mod cli;
+mod disasm;
 mod error;
+mod ilasm;
+mod image;
 mod logging;
 mod pe;
 mod utils;
+mod vm;
 
 use std::path::Path;
 use std::convert::TryFrom;
 
-use log::{trace, debug, info};
+use log::{trace, debug, info, warn, error};
 
 use buf::Reading;
-use error::Result;
+use error::{Error, Result};
 use pe::Header;
-use utils::{read_whole_file, dump, align_up, os_is_64};
+use utils::{read_whole_file, align_up, os_is_64};
 
-const SUBJECT: &str = "subject\\bin\\Debug\\netcoreapp3.1\\subject.dll";
+const USAGE: &str = "\
+Usage: aps [options] <command> <assembly> [args...]
 
-fn main() -> Result<()> {	
-	logging::init();
-	
-	info!("Hello, sailor!");
-	let path = std::env::current_dir()?;
-	info!("The current directory is `{}`.", path.display());
-	info!("Subject: `{}`.", SUBJECT);
+Options:
+    --log <filters>           Log filters like `info,cli=trace`, overrides APS_LOG. Defaults to `warn`.
+    --log-file <path>         Writes the log to the file instead of stderr, overrides APS_LOG_FILE.
+    --log-time                Prefixes log records with UTC time, as does APS_LOG_TIME=1.
 
-	let data = &*read_whole_file(Path::new(SUBJECT))?;
-	info!("Subject size: {} bytes.", data.len());
+Commands:
+    run <assembly> [args...]  Runs the entry point, passing the rest as its `string[] args`.
+    dump <assembly>           Prints metadata and the entry point body.
+    disasm <assembly>         Prints the ILAsm listing.
+    verify <assembly>         Verifies bodies of all methods.
+    help                      Prints this message.
 
-	let pe_header = Header::parse(data)?;
+An <assembly> is either a PE image or ILAsm source, assembled first if its
+name ends with `.il`.";
 
-	let cli_offset = pe_header.rva2offset(pe_header.cli_rva as usize).ok_or("Failed to convert CLI header RVA.")?;
-	if cli_offset >= data.len() {
-		Err("CLI header RVA is wrong.")?;
+enum Command {
+	Run(String, Vec<String>),
+	Dump(String),
+	Disasm(String),
+	Verify(String),
+	Help,
+}
+
+/// Parses the command line, applying options before the command to the log
+/// configuration.
+fn parse_args(mut args: impl Iterator<Item = String>, log: &mut logging::Config) -> Result<Command> {
+	let command = loop {
+		let arg = args.next().ok_or(Error::Usage("No command given."))?;
+		match arg.as_str() {
+			"--log" => log.override_filters(&args.next().ok_or(Error::Usage("No log filters given."))?)?,
+			"--log-file" => log.file = Some(args.next().ok_or(Error::Usage("No log file given."))?.into()),
+			"--log-time" => log.timestamps = true,
+			_ if arg.starts_with("--") && arg.len() > 2 && arg != "--help" => Err(Error::Usage("Unknown option."))?,
+			_ => break arg,
+		}
+	};
+	let mut path = || args.next().ok_or(Error::Usage("No assembly given."));
+
+	let command = match command.as_str() {
+		"run"    => {
+			let path = path()?;
+			Command::Run(path, args.collect())
+		},
+		"dump"   => Command::Dump(path()?),
+		"disasm" => Command::Disasm(path()?),
+		"verify" => Command::Verify(path()?),
+		"help" | "-h" | "--help" => Command::Help,
+		_ => Err(Error::Usage("Unknown command."))?,
+	};
+	Ok(command)
+}
+
+fn main() {
+	let result = logging::Config::from_env().and_then(|mut log| {
+		let command = parse_args(std::env::args().skip(1), &mut log)?;
+		logging::init(log)?;
+		Ok(command)
+	});
+
+	let result = result.and_then(|command| match command {
+		Command::Run(path, args) => run(&path, &args),
+		Command::Dump(path)      => dump(&path),
+		Command::Disasm(path)    => disasm(&path),
+		Command::Verify(path)    => verify(&path),
+		Command::Help => {
+			println!("{}", USAGE);
+			Ok(0)
+		},
+	});
+
+	let code = match result {
+		Ok(code) => code,
+		Err(e) => {
+			eprintln!("aps: {}", e);
+			if let Error::Usage(_) = e {
+				eprintln!("\n{}", USAGE);
+			}
+			e.exit_code()
+		},
+	};
+	std::process::exit(code);
+}
+
+/// 0-based MethodDef row of the entry point.
+fn entry_point(asm: &assembly::Assembly) -> Result<usize> {
+	if asm.cli.ep_token == 0 {
+		Err("Assembly has no entry point.")?;
+	}
+	let ep = cli::MetadataToken::try_from(asm.cli.ep_token)?;
+	if ep.table_index() != cli::METADATA_METHOD_DEF {
+		Err("Unsupported entry-point type (non-method).")?;
 	}
+	if ep.row_index() >= asm.rows.method_defs.len() {
+		Err("Entry point is out of the MethodDef table bounds.")?;
+	}
+	Ok(ep.row_index())
+}
 
-	let cli = &data[cli_offset..cli_offset + pe_header.cli_size as usize];
-	let cli_header = cli::Header::parse(cli, &pe_header)?;
+/// Name of the 0-based method as `Namespace.Type::Method`.
+fn method_name(asm: &assembly::Assembly, method: usize) -> Result<String> {
+	let m = &asm.rows.method_defs[method];
+	let name = cli::string_at(asm.strings(), m.name.into_index())?;
+	match asm.rows.method_owner(method) {
+		Some(owner) => {
+			let owner = asm.rows.type_name(asm.strings(), cli::TypeDefOrRef::TypeDef(owner as u32 + 1))?;
+			Ok(format!("{}::{}", owner, name))
+		},
+		None => Ok(name.to_owned()),
+	}
+}
 
-	let metadata_offset = pe_header.rva2offset(cli_header.metadata_rva as usize).ok_or("Failed to convert CLI metadata RVA.")?;
-	if metadata_offset >= data.len() {
-		Err("CLI metadata RVA is wrong.")?;
+/// Reads the image, assembling it first if the path names ILAsm source.
+fn read_image(path: &str) -> Result<Box<[u8]>> {
+	let path = Path::new(path);
+	let data = read_whole_file(path)?;
+	if path.extension().is_some_and(|e| e == "il") {
+		let source = std::str::from_utf8(&data).map_err(|_| "Source is not valid UTF-8.")?;
+		debug!("Assembling {}.", path.display());
+		return Ok(ilasm::assemble(source)?.into_boxed_slice());
 	}
+	Ok(data)
+}
 
-	let metadata = &data[metadata_offset..metadata_offset + cli_header.metadata_size as usize];
-	let cli_metadata = cli::Metadata::parse(metadata)?;
+fn run(path: &str, args: &[String]) -> Result<i32> {
+	let data = read_image(path)?;
+	let asm = assembly::Assembly::parse(&data)?;
+	asm.validate()?;
+	let ep = entry_point(&asm)?;
+	info!("Running {} with {} argument(s).", method_name(&asm, ep)?, args.len());
 
-	let guids = cli::parse_guids(cli_metadata.guids.unwrap_or(&[0]));
-	let strings = cli::parse_strings(cli_metadata.strings.unwrap_or(&[0]));
-	let user_strings = cli::parse_user_strings(cli_metadata.user_strings.unwrap_or(&[0]));
-	let blobs = cli::parse_blobs(cli_metadata.blobs.unwrap_or(&[0]));
+	let code = vm::Interpreter::new(&asm).run_entry_point(ep, args)?;
+	info!("Exited with code {}.", code);
+	Ok(code)
+}
 
-	if let Some(logical_tables) = cli_metadata.logical_tables {
-		trace!("Parsing logical tables...");
-		let header = cli::Tables::parse(logical_tables)?;
-		let rows = &logical_tables[header.size..];
-		let rows = cli::TableRows::parse(&header, rows)?;
+fn disasm(path: &str) -> Result<i32> {
+	let data = read_image(path)?;
+	let asm = assembly::Assembly::parse(&data)?;
+	asm.validate()?;
+	print!("{}", disasm::disassemble(&asm)?);
+	Ok(0)
+}
+
+fn verify(path: &str) -> Result<i32> {
+	let data = read_image(path)?;
+	let asm = assembly::Assembly::parse(&data)?;
+	asm.validate()?;
 
-		let ep = cli::MetadataToken::try_from(cli_header.ep_token)?;
-		debug!("Entry point: {:?}:{:?}", ep.table_index(), ep.row_index());
-		if ep.table_index() != cli::METADATA_METHOD_DEF {
-			Err("Unsupported entry-point type (non-method).")?;
+	let mut code = 0;
+	let mut failed = 0;
+	for method in 0..asm.rows.method_defs.len() {
+		if let Err(e) = analysis::verify(&asm, method) {
+			println!("{}: {}", method_name(&asm, method)?, e);
+			code = e.exit_code();
+			failed += 1;
 		}
+	}
+	println!("{} of {} method(s) failed verification.", failed, asm.rows.method_defs.len());
+	Ok(code)
+}
+
+fn dump(path: &str) -> Result<i32> {
+	let data = &*read_image(path)?;
+	println!("Size: {} bytes.", data.len());
 
-		let main = &rows.method_defs[ep.row_index()];
-		debug!("main: {:?}", main);
-		let main_offset = pe_header.rva2offset(main.rva as usize).ok_or("Failed to convert main RVA.")?;
-
-		let method_data = &data[main_offset..];
-
-		let mut offset = &mut 0usize;
-		let b: u8 = method_data.read(offset)?;
-		match b & 0b11 {
-			0x2 => {
-				let byte_size = (b >> 2) as usize;
-				debug!("Method is CorILMethod_TinyFormat: {} byte(s).", byte_size);
-				let il = &method_data[1..1 + byte_size];
-				dump(il, il.len());
-
-				*offset = 0;
-				while *offset < il.len() {
-					let op: u8 = il.read(offset)?;
-					debug!("{:#04x} | {}", op, cli::dump_opcode(op));
-					*offset += cli::ins_size(op)? - 1;
-				}
-			},
-			0x3 => {
-				debug!("Method is CorILMethod_FatFormat.");
-				unimplemented!();
-			},
-			_ => Err("Invalid method header.")?,
+	let asm = assembly::Assembly::parse(data)?;
+	let rows = &asm.rows;
+	let strings = asm.strings();
+	let blobs = asm.blobs();
+
+	// Findings are logged, dumping goes on to show what it can.
+	if asm.validate().is_err() {
+		warn!("Dumping invalid metadata.");
+	}
+
+	let logical_tables = asm.metadata.logical_tables.unwrap_or(&[]);
+	let rewritten = rows.write_stream(strings.len(), asm.guids().len(), blobs.len());
+	if rewritten[..] == logical_tables[..] {
+		debug!("Logical tables round-trip.");
+	} else {
+		warn!("Logical tables do not round-trip.");
+	}
+
+	for attribute in rows.attributes_of(cli::HasCustomAttribute::Assembly(1)) {
+		let name = rows.attribute_name(strings, attribute)?;
+		match rows.attribute_args(strings, blobs, attribute) {
+			Ok(args) => println!("Assembly attribute {}: {:?}", name, args),
+			Err(e)   => warn!("Assembly attribute {} is not decoded: {}", name, e),
+		}
+	}
+
+	for decl in rows.security_attributes.iter() {
+		let action = cli::security_action_name(decl.action);
+		match rows.permission_set(strings, blobs, decl) {
+			Ok(set) => println!("Security {} of {:?}: {:?}", action, decl.parent, set),
+			Err(e)  => warn!("Security {} of {:?} is not decoded: {}", action, decl.parent, e),
+		}
+	}
+
+	for constant in rows.constants.iter() {
+		let value = cli::blob_at(blobs, constant.value.into_index())
+			.and_then(|data| cli::ConstantValue::parse(constant.ty, data));
+		match value {
+			Ok(value) => println!("Constant of {:?}: {}", constant.parent, value),
+			Err(e)    => warn!("Constant of {:?} is not decoded: {}", constant.parent, e),
 		}
 	}
 
-	Ok(())
+	let ep = match entry_point(&asm) {
+		Ok(ep) => ep,
+		Err(e) => {
+			println!("{}", e);
+			return Ok(0);
+		},
+	};
+
+	let main = &rows.method_defs[ep];
+	let main_name = cli::string_at(strings, main.name.into_index())?;
+	let main_sig = cli::blob_at(blobs, main.sig.into_index())?;
+	let main_sig = cli::MethodSig::parse(main_sig)?;
+	println!("Entry point: {}", main_sig.named(main_name));
+	let body = asm.method_body(ep)?.ok_or("Entry point has no body.")?;
+	utils::dump(body.code, body.code.len());
+
+	let locals = rows.local_var_sig(blobs, body.local_var_sig_tok)?;
+	for (i, local) in locals.locals.iter().enumerate() {
+		println!("Local {}: {}", i, local);
+	}
+
+	let cfg = analysis::Cfg::build(&body)?;
+	let depths = analysis::stack_depths(&asm, ep, &body, &cfg)?;
+	println!("Max stack depth: {} of {}.", depths.max, body.max_stack);
+	match analysis::verify(&asm, ep) {
+		Ok(())  => println!("Verified."),
+		Err(e)  => println!("Unverifiable: {}", e),
+	}
+	for (i, block) in cfg.blocks.iter().enumerate() {
+		println!("Block {} [{:#06x}, {:#06x}), successors: {:?}", i, block.start, block.end, block.successors);
+		for ins in cfg.block_instructions(i) {
+			println!("{:#06x} | {} {:?}", ins.offset, cli::dump_opcode(ins.opcode), ins.operand);
+		}
+	}
+
+	for clause in body.clauses.iter() {
+		println!("{:?}", clause);
+	}
+
+	Ok(0)
 }
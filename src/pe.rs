@@ -4,28 +4,28 @@ use crate::Result;
 use crate::buf::Reading;
 
 /// Dos header magic: MZ (little-endian).
-const DOS_MAGIC: u16 = 0x5a4d;
-const PE_OFFSET: usize = 0x3c;
+pub const DOS_MAGIC: u16 = 0x5a4d;
+pub const PE_OFFSET: usize = 0x3c;
 
 /// PE header magic: PE (little-endian).
-const PE_MAGIC: u32 = 0x0000_4550;
+pub const PE_MAGIC: u32 = 0x0000_4550;
 
-const IMAGE_FILE_MACHINE_I386: u16 = 0x14c;
+pub const IMAGE_FILE_MACHINE_I386: u16 = 0x14c;
 
 // Shall be zero.
-const IMAGE_FILE_RELOCS_STRIPPED:  u16 = 0x0001;
+pub const IMAGE_FILE_RELOCS_STRIPPED:  u16 = 0x0001;
 // Shall be one.
-const IMAGE_FILE_EXECUTABLE_IMAGE: u16 = 0x0002;
+pub const IMAGE_FILE_EXECUTABLE_IMAGE: u16 = 0x0002;
 // Shall be one if and only if COMIMAGE_FLAGS_32BITREQUIRED is one.
-const IMAGE_FILE_32BIT_MACHINE:    u16 = 0x0100;
+pub const IMAGE_FILE_32BIT_MACHINE:    u16 = 0x0100;
 // A CIL-only DLL sets flag to one, while a CIL-only .exe has flag set to zero.
-const IMAGE_FILE_DLL:              u16 = 0x2000;
+pub const IMAGE_FILE_DLL:              u16 = 0x2000;
 
 /// Optional header magic.
-const OPT_MAGIC_PE32: u16 = 0x10b;
+pub const OPT_MAGIC_PE32: u16 = 0x10b;
 
-const DATA_DIRS_COUNT: usize = 16;
-const DATA_DIR_INDEX_CLI_HEADER: usize = 14;
+pub const DATA_DIRS_COUNT: usize = 16;
+pub const DATA_DIR_INDEX_CLI_HEADER: usize = 14;
 
 #[derive(Debug, PartialEq, Clone, Default)]
 pub struct Header {
@@ -42,7 +42,7 @@ pub struct Section {
 }
 
 impl Header {
-	pub fn rva2offset(self: &Self, rva: usize) -> Option<usize> {
+	pub fn rva2offset(&self, rva: usize) -> Option<usize> {
 		for s in &self.sections {
 			// TODO(dmi): @incomplete That should handle virtual vs raw size
 			// and alignments, etc.
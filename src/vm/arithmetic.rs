@@ -0,0 +1,146 @@
+use crate::Result;
+use crate::error::Error;
+use crate::cli::*;
+use crate::vm::{ARITHMETIC, DIVIDE_BY_ZERO, INVALID_PROGRAM, OVERFLOW, Value};
+
+/// Operands of a binary instruction brought to a common type, III.1.5
+/// table 2.
+enum Operands {
+	Int32(i32, i32),
+	Int64(i64, i64),
+	NativeInt(isize, isize),
+	Float(f64, f64),
+}
+
+/// Unsigned instructions widen int32 to native int with zero extension, the
+/// rest with sign extension.
+fn operands(op: u16, a: Value, b: Value) -> Result<Operands> {
+	let unsigned = matches!(op, DIV_UN | REM_UN | ADD_OVF_UN | SUB_OVF_UN | MUL_OVF_UN);
+	let widen = |x: i32| if unsigned { x as u32 as isize } else { x as isize };
+
+	let operands = match (a, b) {
+		(Value::Int32(a), Value::Int32(b)) => Operands::Int32(a, b),
+		(Value::Int64(a), Value::Int64(b)) => Operands::Int64(a, b),
+		(Value::NativeInt(a), Value::NativeInt(b)) => Operands::NativeInt(a, b),
+		(Value::Int32(a), Value::NativeInt(b)) => Operands::NativeInt(widen(a), b),
+		(Value::NativeInt(a), Value::Int32(b)) => Operands::NativeInt(a, widen(b)),
+		(Value::Float(a), Value::Float(b)) => Operands::Float(a, b),
+		(Value::ManagedPtr(_), _) | (_, Value::ManagedPtr(_)) => {
+			Err("Managed pointer arithmetic is not supported yet.")?
+		},
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(operands)
+}
+
+macro_rules! gen_integer_op {
+	($name:ident, $signed:ty, $unsigned:ty) => {
+		/// Integer arithmetic wrapping around unless the opcode checks for
+		/// overflow.
+		fn $name(op: u16, a: $signed, b: $signed) -> Result<$signed> {
+			let (ua, ub) = (a as $unsigned, b as $unsigned);
+			let overflow = || Error::Exception(OVERFLOW);
+
+			if matches!(op, DIV | DIV_UN | REM | REM_UN) && b == 0 {
+				Err(Error::Exception(DIVIDE_BY_ZERO))?;
+			}
+			// The quotient is not representable, III.3.31 and III.3.55.
+			if matches!(op, DIV | REM) && a == <$signed>::MIN && b == -1 {
+				Err(Error::Exception(ARITHMETIC))?;
+			}
+
+			let x = match op {
+				ADD => a.wrapping_add(b),
+				SUB => a.wrapping_sub(b),
+				MUL => a.wrapping_mul(b),
+				DIV => a / b,
+				DIV_UN => (ua / ub) as $signed,
+				REM => a % b,
+				REM_UN => (ua % ub) as $signed,
+				AND => a & b,
+				OR  => a | b,
+				XOR => a ^ b,
+				ADD_OVF => a.checked_add(b).ok_or_else(overflow)?,
+				SUB_OVF => a.checked_sub(b).ok_or_else(overflow)?,
+				MUL_OVF => a.checked_mul(b).ok_or_else(overflow)?,
+				ADD_OVF_UN => ua.checked_add(ub).ok_or_else(overflow)? as $signed,
+				SUB_OVF_UN => ua.checked_sub(ub).ok_or_else(overflow)? as $signed,
+				MUL_OVF_UN => ua.checked_mul(ub).ok_or_else(overflow)? as $signed,
+				_ => Err(Error::Exception(INVALID_PROGRAM))?,
+			};
+			Ok(x)
+		}
+	};
+}
+
+gen_integer_op!(int32_op, i32, u32);
+gen_integer_op!(int64_op, i64, u64);
+gen_integer_op!(native_int_op, isize, usize);
+
+/// Floats follow IEC 60559:1989, no exceptions are thrown. Bitwise and
+/// checked instructions do not take them, III.1.5 tables 5 and 7.
+fn float_op(op: u16, a: f64, b: f64) -> Result<f64> {
+	let x = match op {
+		ADD => a + b,
+		SUB => a - b,
+		MUL => a * b,
+		DIV => a / b,
+		// Truncating remainder, as fmod does.
+		REM => a % b,
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(x)
+}
+
+/// Executes `add`, `sub`, `mul`, `div`, `rem`, their unsigned and checked
+/// variants and bitwise `and`, `or` and `xor`.
+pub fn binary(op: u16, a: Value, b: Value) -> Result<Value> {
+	let x = match operands(op, a, b)? {
+		Operands::Int32(a, b) => Value::Int32(int32_op(op, a, b)?),
+		Operands::Int64(a, b) => Value::Int64(int64_op(op, a, b)?),
+		Operands::NativeInt(a, b) => Value::NativeInt(native_int_op(op, a, b)?),
+		Operands::Float(a, b) => Value::Float(float_op(op, a, b)?),
+	};
+	Ok(x)
+}
+
+/// Executes `shl`, `shr` and `shr.un`, III.1.5 table 6. The result has the
+/// type of the shifted value. Amounts of the value's width or more are
+/// unspecified, they are masked the way x86 and ARM64 hardware does.
+pub fn shift(op: u16, value: Value, amount: Value) -> Result<Value> {
+	let amount = match amount {
+		Value::Int32(x) => x as u32,
+		Value::NativeInt(x) => x as u32,
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+
+	let x = match (op, value) {
+		(SHL, Value::Int32(x)) => Value::Int32(x.wrapping_shl(amount)),
+		(SHR, Value::Int32(x)) => Value::Int32(x.wrapping_shr(amount)),
+		(SHR_UN, Value::Int32(x)) => Value::Int32((x as u32).wrapping_shr(amount) as i32),
+		(SHL, Value::Int64(x)) => Value::Int64(x.wrapping_shl(amount)),
+		(SHR, Value::Int64(x)) => Value::Int64(x.wrapping_shr(amount)),
+		(SHR_UN, Value::Int64(x)) => Value::Int64((x as u64).wrapping_shr(amount) as i64),
+		(SHL, Value::NativeInt(x)) => Value::NativeInt(x.wrapping_shl(amount)),
+		(SHR, Value::NativeInt(x)) => Value::NativeInt(x.wrapping_shr(amount)),
+		(SHR_UN, Value::NativeInt(x)) => Value::NativeInt((x as usize).wrapping_shr(amount) as isize),
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(x)
+}
+
+/// Executes `neg` and `not`, III.1.5 table 3. Negating the minimal integer
+/// gives it back.
+pub fn unary(op: u16, value: Value) -> Result<Value> {
+	let x = match (op, value) {
+		(NEG, Value::Int32(x)) => Value::Int32(x.wrapping_neg()),
+		(NEG, Value::Int64(x)) => Value::Int64(x.wrapping_neg()),
+		(NEG, Value::NativeInt(x)) => Value::NativeInt(x.wrapping_neg()),
+		(NEG, Value::Float(x)) => Value::Float(-x),
+		(NOT, Value::Int32(x)) => Value::Int32(!x),
+		(NOT, Value::Int64(x)) => Value::Int64(!x),
+		(NOT, Value::NativeInt(x)) => Value::NativeInt(!x),
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(x)
+}
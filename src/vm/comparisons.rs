@@ -0,0 +1,100 @@
+use std::cmp::Ordering;
+
+use crate::Result;
+use crate::error::Error;
+use crate::cli::*;
+use crate::vm::{INVALID_PROGRAM, Pointer, Value};
+
+/// Orders two operands of a comparison, III.1.5 table 4. None means they
+/// are unordered, that is one of them is NaN. Unsigned comparisons widen
+/// int32 to native int with zero extension, the rest with sign extension.
+fn compare(a: &Value, b: &Value, unsigned: bool) -> Result<Option<Ordering>> {
+	let widen = |x: i32| if unsigned { x as u32 as isize } else { x as isize };
+	let integers = |a: isize, b: isize| if unsigned { (a as usize).cmp(&(b as usize)) } else { a.cmp(&b) };
+
+	let ordering = match (a, b) {
+		(Value::Int32(a), Value::Int32(b)) if unsigned => (*a as u32).cmp(&(*b as u32)),
+		(Value::Int32(a), Value::Int32(b)) => a.cmp(b),
+		(Value::Int64(a), Value::Int64(b)) if unsigned => (*a as u64).cmp(&(*b as u64)),
+		(Value::Int64(a), Value::Int64(b)) => a.cmp(b),
+		(Value::NativeInt(a), Value::NativeInt(b)) => integers(*a, *b),
+		(Value::Int32(a), Value::NativeInt(b)) => integers(widen(*a), *b),
+		(Value::NativeInt(a), Value::Int32(b)) => integers(*a, widen(*b)),
+		(Value::Float(a), Value::Float(b)) => return Ok(a.partial_cmp(b)),
+		// Only equality and `cgt.un`, commonly used to test against null,
+		// are allowed on references. Objects have no addresses, those
+		// allocated earlier are taken as lower.
+		(Value::Object(a), Value::Object(b)) if unsigned => a.map(|r| r.0 + 1).cmp(&b.map(|r| r.0 + 1)),
+		(Value::ManagedPtr(a), Value::ManagedPtr(b)) => pointers(a, b)?,
+		// A pointer to a location is never null.
+		(Value::ManagedPtr(_), Value::NativeInt(0)) => Ordering::Greater,
+		(Value::NativeInt(0), Value::ManagedPtr(_)) => Ordering::Less,
+		(Value::ManagedPtr(_), Value::NativeInt(_)) | (Value::NativeInt(_), Value::ManagedPtr(_)) => {
+			Err("Comparing managed pointers with addresses is not supported yet.")?
+		},
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(Some(ordering))
+}
+
+/// Pointers into the same array or frame are ordered by their indices, the
+/// layout of anything else is unknown.
+fn pointers(a: &Pointer, b: &Pointer) -> Result<Ordering> {
+	let ordering = match (a, b) {
+		_ if a == b => Ordering::Equal,
+		(Pointer::Element { array: a, index: i }, Pointer::Element { array: b, index: j }) if a == b => i.cmp(j),
+		(Pointer::Arg { frame: a, index: i }, Pointer::Arg { frame: b, index: j }) if a == b => i.cmp(j),
+		(Pointer::Local { frame: a, index: i }, Pointer::Local { frame: b, index: j }) if a == b => i.cmp(j),
+		_ => Err("Ordering managed pointers to unrelated locations is not supported yet.")?,
+	};
+	Ok(ordering)
+}
+
+/// Evaluates the condition of `ceq`, `cgt`, `clt`, their unsigned variants
+/// and the binary conditional branches, III.3.5-3.15 and III.3.21-25.
+/// Unordered floats compare false, except under the `.un` forms, which are
+/// true for them.
+pub fn condition(op: u16, a: &Value, b: &Value) -> Result<bool> {
+	let unsigned = matches!(op,
+		BNE_UN | BNE_UN_S | BGE_UN | BGE_UN_S | BGT_UN | BGT_UN_S | BLE_UN | BLE_UN_S | BLT_UN | BLT_UN_S |
+		CGT_UN | CLT_UN);
+	let equality = matches!(op, CEQ | BEQ | BEQ_S | BNE_UN | BNE_UN_S);
+	// References are only ordered by `cgt.un`.
+	if !equality && op != CGT_UN && matches!((a, b), (Value::Object(_), _) | (_, Value::Object(_))) {
+		Err(Error::Exception(INVALID_PROGRAM))?;
+	}
+	let ordering = match (a, b) {
+		(Value::Object(a), Value::Object(b)) if equality => Some(if a == b { Ordering::Equal } else { Ordering::Less }),
+		_ => compare(a, b, unsigned)?,
+	};
+
+	let x = match op {
+		CEQ | BEQ | BEQ_S => ordering == Some(Ordering::Equal),
+		BNE_UN | BNE_UN_S => ordering != Some(Ordering::Equal),
+		CGT | BGT | BGT_S => ordering == Some(Ordering::Greater),
+		CLT | BLT | BLT_S => ordering == Some(Ordering::Less),
+		BGE | BGE_S => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+		BLE | BLE_S => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+		CGT_UN | BGT_UN | BGT_UN_S => matches!(ordering, Some(Ordering::Greater) | None),
+		CLT_UN | BLT_UN | BLT_UN_S => matches!(ordering, Some(Ordering::Less) | None),
+		BGE_UN | BGE_UN_S => ordering != Some(Ordering::Less),
+		BLE_UN | BLE_UN_S => ordering != Some(Ordering::Greater),
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(x)
+}
+
+/// Whether `brtrue` branches on the value, III.3.18: it is non-zero or a
+/// non-null reference.
+pub fn is_true(value: &Value) -> Result<bool> {
+	let x = match value {
+		Value::Int32(x) => *x != 0,
+		Value::Int64(x) => *x != 0,
+		Value::NativeInt(x) => *x != 0,
+		Value::Object(r) => r.is_some(),
+		Value::ManagedPtr(_) => true,
+		// Floats are not tested this way.
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(x)
+}
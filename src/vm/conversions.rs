@@ -0,0 +1,182 @@
+use crate::Result;
+use crate::error::Error;
+use crate::cli::*;
+use crate::vm::{ARITHMETIC, INVALID_PROGRAM, OVERFLOW, Value};
+
+/// Integer type a conversion produces.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Target {
+	I1,
+	U1,
+	I2,
+	U2,
+	I4,
+	U4,
+	I8,
+	U8,
+	I,
+	U,
+}
+
+impl Target {
+	fn is_unsigned(self) -> bool {
+		matches!(self, Target::U1 | Target::U2 | Target::U4 | Target::U8 | Target::U)
+	}
+
+	fn range(self) -> (i128, i128) {
+		match self {
+			Target::I1 => (i8::MIN as i128, i8::MAX as i128),
+			Target::U1 => (0, u8::MAX as i128),
+			Target::I2 => (i16::MIN as i128, i16::MAX as i128),
+			Target::U2 => (0, u16::MAX as i128),
+			Target::I4 => (i32::MIN as i128, i32::MAX as i128),
+			Target::U4 => (0, u32::MAX as i128),
+			Target::I8 => (i64::MIN as i128, i64::MAX as i128),
+			Target::U8 => (0, u64::MAX as i128),
+			Target::I  => (isize::MIN as i128, isize::MAX as i128),
+			Target::U  => (0, usize::MAX as i128),
+		}
+	}
+
+	/// Truncates the integer to the type, then widens it to the stack type:
+	/// int32 for types up to 32 bits, sign- or zero-extending narrower ones.
+	fn narrow(self, x: i128) -> Value {
+		match self {
+			Target::I1 => Value::Int32(x as i8 as i32),
+			Target::U1 => Value::Int32(x as u8 as i32),
+			Target::I2 => Value::Int32(x as i16 as i32),
+			Target::U2 => Value::Int32(x as u16 as i32),
+			Target::I4 | Target::U4 => Value::Int32(x as i32),
+			Target::I8 | Target::U8 => Value::Int64(x as i64),
+			Target::I | Target::U => Value::NativeInt(x as isize),
+		}
+	}
+
+	/// Converts a float truncating it towards zero and saturating at the
+	/// bounds, NaN gives zero. Types narrower than 32 bits go through int32
+	/// and are truncated from it, as CoreCLR does.
+	fn saturate(self, x: f64) -> Value {
+		match self {
+			Target::I1 | Target::U1 | Target::I2 | Target::U2 | Target::I4 => self.narrow(x as i32 as i128),
+			Target::U4 => self.narrow(x as u32 as i128),
+			Target::I8 => self.narrow(x as i64 as i128),
+			Target::U8 => self.narrow(x as u64 as i128),
+			Target::I  => self.narrow(x as isize as i128),
+			Target::U  => self.narrow(x as usize as i128),
+		}
+	}
+
+	/// Converts a float truncating it towards zero, throwing if the result
+	/// does not fit.
+	fn check_float(self, x: f64) -> Result<Value> {
+		let (min, max) = self.range();
+		let x = x.trunc();
+		// Bounds of integer types are powers of two, exact as floats.
+		if x.is_nan() || x < min as f64 || x >= (max + 1) as f64 {
+			Err(Error::Exception(OVERFLOW))?;
+		}
+		Ok(self.narrow(x as i128))
+	}
+
+	fn check(self, x: i128) -> Result<Value> {
+		let (min, max) = self.range();
+		if x < min || x > max {
+			Err(Error::Exception(OVERFLOW))?;
+		}
+		Ok(self.narrow(x))
+	}
+}
+
+/// Integer operand as a signed or unsigned number.
+fn integer(value: &Value, unsigned: bool) -> Option<i128> {
+	let x = match *value {
+		Value::Int32(x) if unsigned => x as u32 as i128,
+		Value::Int32(x) => x as i128,
+		Value::Int64(x) if unsigned => x as u64 as i128,
+		Value::Int64(x) => x as i128,
+		Value::NativeInt(x) if unsigned => x as usize as i128,
+		Value::NativeInt(x) => x as i128,
+		_ => return None,
+	};
+	Some(x)
+}
+
+/// Executes the `conv.*` family, III.3.27-29. Unchecked forms truncate
+/// integers and saturate floats, checked ones throw OverflowException on
+/// values out of the target's range. `.un` forms treat integer operands as
+/// unsigned.
+pub fn convert(op: u16, value: Value) -> Result<Value> {
+	let (target, checked, unsigned_source) = match op {
+		CONV_R4 => {
+			// Integers are rounded to float32 directly, not through float64.
+			let x = match value {
+				Value::Float(x) => x as f32,
+				ref v => integer(v, false).ok_or(Error::Exception(INVALID_PROGRAM))? as f32,
+			};
+			return Ok(Value::Float(x as f64));
+		},
+		CONV_R8 | CONV_R_UN => {
+			let x = match value {
+				Value::Float(x) => x,
+				ref v => integer(v, op == CONV_R_UN).ok_or(Error::Exception(INVALID_PROGRAM))? as f64,
+			};
+			return Ok(Value::Float(x));
+		},
+
+		CONV_I1 => (Target::I1, false, false),
+		CONV_U1 => (Target::U1, false, false),
+		CONV_I2 => (Target::I2, false, false),
+		CONV_U2 => (Target::U2, false, false),
+		CONV_I4 => (Target::I4, false, false),
+		CONV_U4 => (Target::U4, false, false),
+		CONV_I8 => (Target::I8, false, false),
+		CONV_U8 => (Target::U8, false, false),
+		CONV_I  => (Target::I, false, false),
+		CONV_U  => (Target::U, false, false),
+
+		CONV_OVF_I1 => (Target::I1, true, false),
+		CONV_OVF_U1 => (Target::U1, true, false),
+		CONV_OVF_I2 => (Target::I2, true, false),
+		CONV_OVF_U2 => (Target::U2, true, false),
+		CONV_OVF_I4 => (Target::I4, true, false),
+		CONV_OVF_U4 => (Target::U4, true, false),
+		CONV_OVF_I8 => (Target::I8, true, false),
+		CONV_OVF_U8 => (Target::U8, true, false),
+		CONV_OVF_I  => (Target::I, true, false),
+		CONV_OVF_U  => (Target::U, true, false),
+
+		CONV_OVF_I1_UN => (Target::I1, true, true),
+		CONV_OVF_U1_UN => (Target::U1, true, true),
+		CONV_OVF_I2_UN => (Target::I2, true, true),
+		CONV_OVF_U2_UN => (Target::U2, true, true),
+		CONV_OVF_I4_UN => (Target::I4, true, true),
+		CONV_OVF_U4_UN => (Target::U4, true, true),
+		CONV_OVF_I8_UN => (Target::I8, true, true),
+		CONV_OVF_U8_UN => (Target::U8, true, true),
+		CONV_OVF_I_UN  => (Target::I, true, true),
+		CONV_OVF_U_UN  => (Target::U, true, true),
+
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+
+	match value {
+		Value::Float(x) if checked => target.check_float(x),
+		Value::Float(x) => Ok(target.saturate(x)),
+		ref v => {
+			// Unchecked conversions zero-extend into unsigned types and
+			// sign-extend into signed ones.
+			let unsigned = if checked { unsigned_source } else { target.is_unsigned() };
+			let x = integer(v, unsigned).ok_or(Error::Exception(INVALID_PROGRAM))?;
+			if checked { target.check(x) } else { Ok(target.narrow(x)) }
+		},
+	}
+}
+
+/// Executes `ckfinite`, III.3.21, which leaves the value on the stack.
+pub fn check_finite(value: Value) -> Result<Value> {
+	match value {
+		Value::Float(x) if x.is_finite() => Ok(value),
+		Value::Float(_) => Err(Error::Exception(ARITHMETIC)),
+		_ => Err(Error::Exception(INVALID_PROGRAM)),
+	}
+}
@@ -0,0 +1,11 @@
+// Exceptions the execution engine throws itself, by their type names.
+
+/// Thrown instead of executing IL that violates the stack rules of III.1.7
+/// or uses operands of wrong types.
+pub const INVALID_PROGRAM: &str    = "System.InvalidProgramException";
+pub const NULL_REFERENCE: &str     = "System.NullReferenceException";
+pub const INDEX_OUT_OF_RANGE: &str = "System.IndexOutOfRangeException";
+pub const STACK_OVERFLOW: &str     = "System.StackOverflowException";
+pub const ARITHMETIC: &str         = "System.ArithmeticException";
+pub const DIVIDE_BY_ZERO: &str     = "System.DivideByZeroException";
+pub const OVERFLOW: &str           = "System.OverflowException";
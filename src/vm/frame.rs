@@ -0,0 +1,139 @@
+use std::rc::Rc;
+
+use crate::Result;
+use crate::error::Error;
+use crate::assembly::Assembly;
+use crate::analysis::{Cfg, is_value_type, stack_depths};
+use crate::cli::{CallingConvention, Instruction, Instructions, MethodSig, TypeDefOrRef, TypeSig, blob_at, string_at};
+use crate::vm::{INVALID_PROGRAM, Value};
+
+/// A method ready to run: its body decoded and its signature resolved.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Method {
+	/// 0-based MethodDef row.
+	pub def: usize,
+	/// As `Type::Method`, for logs.
+	pub name: String,
+	pub ret: TypeSig,
+	/// Types of arguments, `this` comes first for instance methods.
+	pub args: Box<[TypeSig]>,
+	/// Types of locals, byref ones wrapped in `TypeSig::ByRef`.
+	pub locals: Box<[TypeSig]>,
+	pub max_stack: u16,
+	pub instructions: Box<[Instruction]>,
+}
+
+impl Method {
+	/// Prepares the 0-based method row, which shall have IL body.
+	pub fn load(asm: &Assembly, def: usize) -> Result<Method> {
+		let m = asm.rows.method_defs.get(def).ok_or("Method row is out of the table bounds.")?;
+		let owner = asm.rows.method_owner(def).ok_or("Method has no owner type.")?;
+		let owner = TypeDefOrRef::TypeDef(owner as u32 + 1);
+		let name = format!("{}::{}", asm.rows.type_name(asm.strings(), owner)?, string_at(asm.strings(), m.name.into_index())?);
+
+		let body = asm.method_body(def)?.ok_or("Method has no IL body.")?;
+		let sig = MethodSig::parse(blob_at(asm.blobs(), m.sig.into_index())?)?;
+		if sig.call_conv != CallingConvention::Default {
+			Err("Only methods of the default calling convention are supported.")?;
+		}
+
+		let mut args = Vec::with_capacity(sig.params.len() + 1);
+		if sig.has_this && !sig.explicit_this {
+			args.push(if is_value_type(asm, owner)? {
+				TypeSig::ByRef(Box::new(TypeSig::ValueType(owner)))
+			} else {
+				TypeSig::Class(owner)
+			});
+		}
+		args.extend(sig.params.iter().cloned());
+
+		let locals = asm.rows.local_var_sig(asm.blobs(), body.local_var_sig_tok)?;
+		let locals = locals.locals.iter()
+			.map(|l| if l.by_ref { TypeSig::ByRef(Box::new(l.ty.clone())) } else { l.ty.clone() })
+			.collect();
+
+		let instructions = Instructions::new(body.code).collect::<Result<Vec<_>>>()?;
+
+		// The stack is checked against MaxStack on every push as well, this
+		// rejects bodies that could overflow or unbalance it before any of
+		// their code runs, as the JIT compiler does.
+		let cfg = Cfg::build(&body)?;
+		stack_depths(asm, def, &body, &cfg).map_err(|e| match e {
+			Error::Verification(..) => Error::Exception(INVALID_PROGRAM),
+			e => e,
+		})?;
+
+		Ok(Method {
+			def,
+			name,
+			ret: sig.ret,
+			args: args.into_boxed_slice(),
+			locals,
+			max_stack: body.max_stack,
+			instructions: instructions.into_boxed_slice(),
+		})
+	}
+
+	/// Index of the instruction starting at the offset.
+	pub fn index_of(&self, offset: u32) -> Result<usize> {
+		self.instructions.binary_search_by_key(&offset, |i| i.offset)
+			.map_err(|_| Error::Exception(INVALID_PROGRAM))
+	}
+}
+
+/// Activation record of a running method, III.1.7.
+#[derive(Debug, Clone)]
+pub struct Frame {
+	/// Unique within the interpreter and growing towards the top of the
+	/// call stack, ids of returned frames are not reused.
+	pub id: u64,
+	pub method: Rc<Method>,
+	pub args: Vec<Value>,
+	pub locals: Vec<Value>,
+	pub stack: Vec<Value>,
+	/// Index of the instruction to execute next.
+	pub pc: usize,
+}
+
+impl Frame {
+	/// Enters the method with arguments already coerced to their types.
+	pub fn new(asm: &Assembly, id: u64, method: Rc<Method>, args: Vec<Value>) -> Result<Frame> {
+		// Locals are zeroed whether the method asks for it or not, reading
+		// garbage would not be any more correct.
+		let locals = method.locals.iter()
+			.map(|l| match l {
+				// Byref locals hold no target until assigned one.
+				TypeSig::ByRef(_) => Ok(Value::NativeInt(0)),
+				l => Value::zero(asm, l),
+			})
+			.collect::<Result<Vec<_>>>()?;
+		let stack = Vec::with_capacity(method.max_stack as usize);
+		Ok(Frame { id, method, args, locals, stack, pc: 0 })
+	}
+
+	pub fn push(&mut self, value: Value) -> Result<()> {
+		if self.stack.len() >= self.method.max_stack as usize {
+			Err(Error::Exception(INVALID_PROGRAM))?;
+		}
+		self.stack.push(value);
+		Ok(())
+	}
+
+	pub fn pop(&mut self) -> Result<Value> {
+		self.stack.pop().ok_or(Error::Exception(INVALID_PROGRAM))
+	}
+
+	/// Pops the top `n` values, the deepest one first.
+	pub fn pop_n(&mut self, n: usize) -> Result<Vec<Value>> {
+		if n > self.stack.len() {
+			Err(Error::Exception(INVALID_PROGRAM))?;
+		}
+		Ok(self.stack.split_off(self.stack.len() - n))
+	}
+
+	/// Continues with the instruction at the offset.
+	pub fn jump(&mut self, offset: u32) -> Result<()> {
+		self.pc = self.method.index_of(offset)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,51 @@
+use crate::Result;
+use crate::error::Error;
+use crate::cli::TypeSig;
+use crate::vm::{INVALID_PROGRAM, ObjectRef, Value};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Object {
+	String(String),
+	/// Single-dimensional, zero-based array.
+	Array {
+		element: TypeSig,
+		elements: Vec<Value>,
+	},
+}
+
+/// Objects allocated by the program. Nothing is collected yet, objects live
+/// until the interpreter is dropped.
+#[derive(Debug, Default)]
+pub struct Heap {
+	objects: Vec<Object>,
+}
+
+impl Heap {
+	pub fn alloc(&mut self, object: Object) -> ObjectRef {
+		self.objects.push(object);
+		ObjectRef(self.objects.len() - 1)
+	}
+
+	pub fn get(&self, r: ObjectRef) -> &Object {
+		&self.objects[r.0]
+	}
+
+	pub fn get_mut(&mut self, r: ObjectRef) -> &mut Object {
+		&mut self.objects[r.0]
+	}
+
+	/// Elements of the array, the program is invalid if it is anything else.
+	pub fn array(&self, r: ObjectRef) -> Result<(&TypeSig, &[Value])> {
+		match self.get(r) {
+			Object::Array { element, elements } => Ok((element, elements)),
+			_ => Err(Error::Exception(INVALID_PROGRAM)),
+		}
+	}
+
+	pub fn string(&self, r: ObjectRef) -> Option<&str> {
+		match self.get(r) {
+			Object::String(s) => Some(s),
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use log::{debug, trace, warn};
+
+use crate::Result;
+use crate::error::Error;
+use crate::assembly::Assembly;
+use crate::cli::*;
+use crate::vm::{
+	Frame, Heap, INDEX_OUT_OF_RANGE, INVALID_PROGRAM, Intrinsic, Method, NULL_REFERENCE, Object, ObjectRef,
+	Pointer, STACK_OVERFLOW, Value, binary, check_finite, condition, convert, intrinsic, is_true, shift, unary,
+};
+
+// Frames live on the heap, this only stops runaway recursion before it
+// takes all the memory.
+const MAX_CALL_DEPTH: usize = 1 << 16;
+
+/// What the dispatch loop does after an instruction.
+enum Flow {
+	Next,
+	/// Leaves the current method with its return value, if any.
+	Return(Option<Value>),
+}
+
+/// The method a call instruction targets.
+enum Callee {
+	/// 0-based MethodDef row.
+	Method(usize),
+	External(String, MethodSig, Intrinsic),
+}
+
+/// Executes IL of an assembly one instruction at a time.
+pub struct Interpreter<'a, 'b> {
+	asm: &'b Assembly<'a>,
+	heap: Heap,
+	frames: Vec<Frame>,
+	/// Id of the next frame entered.
+	next_frame: u64,
+	/// Methods prepared so far by their 0-based MethodDef rows.
+	methods: HashMap<usize, Rc<Method>>,
+	/// Objects of `ldstr` literals by their #US offsets, literals are
+	/// interned.
+	literals: HashMap<u32, ObjectRef>,
+}
+
+impl<'a, 'b> Interpreter<'a, 'b> {
+	pub fn new(asm: &'b Assembly<'a>) -> Self {
+		Interpreter {
+			asm,
+			heap: Heap::default(),
+			frames: Vec::new(),
+			next_frame: 0,
+			methods: HashMap::new(),
+			literals: HashMap::new(),
+		}
+	}
+
+	/// Runs the 0-based method as the entry point, passing it the
+	/// command-line arguments, and returns the process exit code.
+	pub fn run_entry_point(&mut self, method: usize, args: &[String]) -> Result<i32> {
+		let m = self.method(method)?;
+		if !matches!(m.ret, TypeSig::Void | TypeSig::I4 | TypeSig::U4) {
+			Err("Entry point shall return void, int32 or unsigned int32.")?;
+		}
+
+		let args = match &m.args[..] {
+			[] => Vec::new(),
+			[TypeSig::SzArray(e)] if **e == TypeSig::String => {
+				let elements = args.iter()
+					.map(|a| Value::Object(Some(self.heap.alloc(Object::String(a.clone())))))
+					.collect();
+				let array = self.heap.alloc(Object::Array { element: TypeSig::String, elements });
+				vec![Value::Object(Some(array))]
+			},
+			_ => Err("Entry point shall take either no arguments or a string array.")?,
+		};
+
+		match self.invoke(m, args)? {
+			Some(Value::Int32(code)) => Ok(code),
+			_ => Ok(0),
+		}
+	}
+
+	/// Runs the method to completion with arguments of its types.
+	fn invoke(&mut self, method: Rc<Method>, args: Vec<Value>) -> Result<Option<Value>> {
+		let depth = self.frames.len();
+		self.enter(method, args)?;
+
+		loop {
+			let frame = self.frames.last_mut().ok_or(Error::Exception(INVALID_PROGRAM))?;
+			let method = Rc::clone(&frame.method);
+			// Running off the end of the code is not allowed, III.1.7.4.
+			let ins = method.instructions.get(frame.pc).ok_or(Error::Exception(INVALID_PROGRAM))?;
+			frame.pc += 1;
+			trace!("{} IL_{:04x}: {} (stack: {})", method.name, ins.offset, dump_opcode(ins.opcode), frame.stack.len());
+
+			let flow = self.execute(ins).map_err(|e| {
+				debug!("{} at IL_{:04x} of {}.", e, ins.offset, method.name);
+				e
+			})?;
+
+			if let Flow::Return(value) = flow {
+				self.frames.pop();
+				if self.frames.len() == depth {
+					return Ok(value);
+				}
+				if let Some(value) = value {
+					self.frame().push(value)?;
+				}
+			}
+		}
+	}
+
+	fn enter(&mut self, method: Rc<Method>, args: Vec<Value>) -> Result<()> {
+		if self.frames.len() == MAX_CALL_DEPTH {
+			Err(Error::Exception(STACK_OVERFLOW))?;
+		}
+		debug!("Entering {} with {} argument(s).", method.name, args.len());
+		let frame = Frame::new(self.asm, self.next_frame, method, args)?;
+		self.next_frame += 1;
+		self.frames.push(frame);
+		Ok(())
+	}
+
+	/// Prepares the 0-based method row once and shares it afterwards.
+	fn method(&mut self, def: usize) -> Result<Rc<Method>> {
+		if let Some(m) = self.methods.get(&def) {
+			return Ok(Rc::clone(m));
+		}
+		let m = Rc::new(Method::load(self.asm, def)?);
+		self.methods.insert(def, Rc::clone(&m));
+		Ok(m)
+	}
+
+	fn frame(&mut self) -> &mut Frame {
+		self.frames.last_mut().expect("Interpreter runs without a frame.")
+	}
+
+	/// Depth of the frame with the id if it has not returned yet.
+	fn depth_of(&self, id: u64) -> Option<usize> {
+		self.frames.binary_search_by_key(&id, |f| f.id).ok()
+	}
+
+	fn push(&mut self, value: Value) -> Result<()> {
+		self.frame().push(value)
+	}
+
+	fn pop(&mut self) -> Result<Value> {
+		self.frame().pop()
+	}
+
+	fn execute(&mut self, ins: &Instruction) -> Result<Flow> {
+		match ins.opcode {
+			NOP | BREAK => {},
+
+			LDARG_0..=LDARG_3 => self.load(self.arg((ins.opcode - LDARG_0) as usize)?)?,
+			LDARG_S | LDARG => self.load(self.arg(index(ins)?)?)?,
+			LDARGA_S | LDARGA => {
+				let p = self.arg(index(ins)?)?;
+				self.push(Value::ManagedPtr(p))?;
+			},
+			STARG_S | STARG => self.store(self.arg(index(ins)?)?)?,
+
+			LDLOC_0..=LDLOC_3 => self.load(self.local((ins.opcode - LDLOC_0) as usize)?)?,
+			LDLOC_S | LDLOC => self.load(self.local(index(ins)?)?)?,
+			LDLOCA_S | LDLOCA => {
+				let p = self.local(index(ins)?)?;
+				self.push(Value::ManagedPtr(p))?;
+			},
+			STLOC_0..=STLOC_3 => self.store(self.local((ins.opcode - STLOC_0) as usize)?)?,
+			STLOC_S | STLOC => self.store(self.local(index(ins)?)?)?,
+
+			LDNULL => self.push(Value::Object(None))?,
+			LDC_I4_M1..=LDC_I4_8 => self.push(Value::Int32(ins.opcode as i32 - LDC_I4_0 as i32))?,
+			LDC_I4_S | LDC_I4 | LDC_I8 | LDC_R4 | LDC_R8 => {
+				let value = match ins.operand {
+					Operand::I8(x)  => Value::Int32(x as i32),
+					Operand::I32(x) => Value::Int32(x),
+					Operand::I64(x) => Value::Int64(x),
+					Operand::F32(x) => Value::Float(x as f64),
+					Operand::F64(x) => Value::Float(x),
+					_ => Err(Error::Exception(INVALID_PROGRAM))?,
+				};
+				self.push(value)?;
+			},
+			LDSTR => {
+				let offset = match ins.operand {
+					Operand::String(offset) => offset,
+					_ => Err(Error::Exception(INVALID_PROGRAM))?,
+				};
+				let r = match self.literals.get(&offset) {
+					Some(r) => *r,
+					None => {
+						let s = user_string_at(self.asm.user_strings(), offset as usize)?;
+						let r = self.heap.alloc(Object::String(s));
+						self.literals.insert(offset, r);
+						r
+					},
+				};
+				self.push(Value::Object(Some(r)))?;
+			},
+
+			DUP => {
+				let value = self.pop()?;
+				self.push(value.clone())?;
+				self.push(value)?;
+			},
+			POP => {
+				self.pop()?;
+			},
+
+			ADD | SUB | MUL | DIV | DIV_UN | REM | REM_UN | AND | OR | XOR |
+			ADD_OVF | ADD_OVF_UN | SUB_OVF | SUB_OVF_UN | MUL_OVF | MUL_OVF_UN => {
+				let b = self.pop()?;
+				let a = self.pop()?;
+				self.push(binary(ins.opcode, a, b)?)?;
+			},
+			SHL | SHR | SHR_UN => {
+				let amount = self.pop()?;
+				let value = self.pop()?;
+				self.push(shift(ins.opcode, value, amount)?)?;
+			},
+			NEG | NOT => {
+				let value = self.pop()?;
+				self.push(unary(ins.opcode, value)?)?;
+			},
+
+			CONV_I1 | CONV_U1 | CONV_I2 | CONV_U2 | CONV_I4 | CONV_U4 | CONV_I8 | CONV_U8 | CONV_I | CONV_U |
+			CONV_R4 | CONV_R8 | CONV_R_UN |
+			CONV_OVF_I1 | CONV_OVF_U1 | CONV_OVF_I2 | CONV_OVF_U2 | CONV_OVF_I4 | CONV_OVF_U4 |
+			CONV_OVF_I8 | CONV_OVF_U8 | CONV_OVF_I | CONV_OVF_U |
+			CONV_OVF_I1_UN | CONV_OVF_U1_UN | CONV_OVF_I2_UN | CONV_OVF_U2_UN | CONV_OVF_I4_UN |
+			CONV_OVF_U4_UN | CONV_OVF_I8_UN | CONV_OVF_U8_UN | CONV_OVF_I_UN | CONV_OVF_U_UN => {
+				let value = self.pop()?;
+				self.push(convert(ins.opcode, value)?)?;
+			},
+			CKFINITE => {
+				let value = self.pop()?;
+				self.push(check_finite(value)?)?;
+			},
+
+			CEQ | CGT | CGT_UN | CLT | CLT_UN => {
+				let b = self.pop()?;
+				let a = self.pop()?;
+				self.push(Value::Int32(condition(ins.opcode, &a, &b)? as i32))?;
+			},
+
+			BR | BR_S => self.frame().jump(target(ins)?)?,
+			BRTRUE | BRTRUE_S | BRFALSE | BRFALSE_S => {
+				let value = self.pop()?;
+				if is_true(&value)? == matches!(ins.opcode, BRTRUE | BRTRUE_S) {
+					self.frame().jump(target(ins)?)?;
+				}
+			},
+			BEQ | BEQ_S | BNE_UN | BNE_UN_S | BGE | BGE_S | BGE_UN | BGE_UN_S | BGT | BGT_S | BGT_UN | BGT_UN_S |
+			BLE | BLE_S | BLE_UN | BLE_UN_S | BLT | BLT_S | BLT_UN | BLT_UN_S => {
+				let b = self.pop()?;
+				let a = self.pop()?;
+				if condition(ins.opcode, &a, &b)? {
+					self.frame().jump(target(ins)?)?;
+				}
+			},
+			SWITCH => {
+				let targets = match &ins.operand {
+					Operand::Switch(targets) => targets,
+					_ => Err(Error::Exception(INVALID_PROGRAM))?,
+				};
+				// The index is unsigned, anything out of the table falls
+				// through, III.3.66.
+				let index = match self.pop()? {
+					Value::Int32(x) => x as u32 as usize,
+					Value::NativeInt(x) => x as usize,
+					_ => Err(Error::Exception(INVALID_PROGRAM))?,
+				};
+				if let Some(&offset) = targets.get(index) {
+					self.frame().jump(offset)?;
+				}
+			},
+
+			CALL | CALLVIRT => self.call(ins)?,
+			RET => {
+				let ret = self.frame().method.ret.clone();
+				let value = match ret {
+					TypeSig::Void => None,
+					ref ty => Some(self.pop()?.coerce(ty)?),
+				};
+				if !self.frame().stack.is_empty() {
+					Err(Error::Exception(INVALID_PROGRAM))?;
+				}
+				return Ok(Flow::Return(value));
+			},
+
+			LDIND_I1 | LDIND_U1 | LDIND_I2 | LDIND_U2 | LDIND_I4 | LDIND_U4 | LDIND_I8 | LDIND_I |
+			LDIND_R4 | LDIND_R8 | LDIND_REF => {
+				let p = pointer(self.pop()?)?;
+				let value = self.read(p)?.coerce(&indirect_type(ins.opcode))?;
+				self.push(value)?;
+			},
+			STIND_I1 | STIND_I2 | STIND_I4 | STIND_I8 | STIND_I | STIND_R4 | STIND_R8 | STIND_REF => {
+				let value = self.pop()?.coerce(&indirect_type(ins.opcode))?;
+				let p = pointer(self.pop()?)?;
+				self.write(p, value)?;
+			},
+
+			LDLEN => {
+				let array = object(self.pop()?)?;
+				let len = self.heap.array(array)?.1.len();
+				self.push(Value::NativeInt(len as isize))?;
+			},
+			LDELEM_REF => {
+				let index = array_index(self.pop()?)?;
+				let array = object(self.pop()?)?;
+				let value = self.read(element(&self.heap, array, index)?)?;
+				self.push(value.coerce(&TypeSig::Object)?)?;
+			},
+
+			_ => Err(Error::Unsupported(ins.offset, dump_opcode(ins.opcode)))?,
+		}
+		Ok(Flow::Next)
+	}
+
+	/// Pointer to the argument of the current frame.
+	fn arg(&self, index: usize) -> Result<Pointer> {
+		let frame = self.frames.last().expect("Interpreter runs without a frame.");
+		if index >= frame.args.len() {
+			Err(Error::Exception(INVALID_PROGRAM))?;
+		}
+		Ok(Pointer::Arg { frame: frame.id, index })
+	}
+
+	/// Pointer to the local of the current frame.
+	fn local(&self, index: usize) -> Result<Pointer> {
+		let frame = self.frames.last().expect("Interpreter runs without a frame.");
+		if index >= frame.locals.len() {
+			Err(Error::Exception(INVALID_PROGRAM))?;
+		}
+		Ok(Pointer::Local { frame: frame.id, index })
+	}
+
+	fn load(&mut self, p: Pointer) -> Result<()> {
+		let value = self.read(p)?;
+		self.push(value)
+	}
+
+	fn store(&mut self, p: Pointer) -> Result<()> {
+		let value = self.pop()?;
+		self.write(p, value)
+	}
+
+	fn read(&self, p: Pointer) -> Result<Value> {
+		let value = match p {
+			Pointer::Arg { frame, index } => self.depth_of(frame).and_then(|d| self.frames[d].args.get(index)),
+			Pointer::Local { frame, index } => self.depth_of(frame).and_then(|d| self.frames[d].locals.get(index)),
+			Pointer::Element { array, index } => self.heap.array(array)?.1.get(index),
+		};
+		// Pointers into frames that have returned miss, as do indices of
+		// arrays that shrank, which none do yet.
+		value.cloned().ok_or(Error::Exception(INVALID_PROGRAM))
+	}
+
+	/// Stores the value converting it to the type of the location.
+	fn write(&mut self, p: Pointer, value: Value) -> Result<()> {
+		let slot = match p {
+			Pointer::Arg { frame, index } => self.depth_of(frame).and_then(|d| {
+				let f = &mut self.frames[d];
+				let ty = f.method.args.get(index)?;
+				Some((ty.clone(), f.args.get_mut(index)?))
+			}),
+			Pointer::Local { frame, index } => self.depth_of(frame).and_then(|d| {
+				let f = &mut self.frames[d];
+				let ty = f.method.locals.get(index)?;
+				Some((ty.clone(), f.locals.get_mut(index)?))
+			}),
+			Pointer::Element { array, index } => match self.heap.get_mut(array) {
+				Object::Array { element, elements } => Some((element.clone(), elements.get_mut(index).ok_or(Error::Exception(INDEX_OUT_OF_RANGE))?)),
+				_ => None,
+			},
+		};
+		let (ty, slot) = slot.ok_or(Error::Exception(INVALID_PROGRAM))?;
+		*slot = value.coerce(&ty)?;
+		Ok(())
+	}
+
+	fn call(&mut self, ins: &Instruction) -> Result<()> {
+		let token = match ins.operand {
+			Operand::Token(token) => token,
+			_ => Err(Error::Exception(INVALID_PROGRAM))?,
+		};
+
+		match self.resolve(ins, token)? {
+			Callee::Method(def) => {
+				let m = &self.asm.rows.method_defs[def];
+				if m.rva == 0 {
+					let name = string_at(self.asm.strings(), m.name.into_index())?;
+					warn!("{} has no IL body, runtime-implemented methods are not supported.", name);
+					Err(Error::Unsupported(ins.offset, dump_opcode(ins.opcode)))?;
+				}
+				let m = self.method(def)?;
+				let args = self.frame().pop_n(m.args.len())?;
+				let args = args.into_iter().zip(m.args.iter())
+					.map(|(a, ty)| a.coerce(ty))
+					.collect::<Result<Vec<_>>>()?;
+				if ins.opcode == CALLVIRT && args.first() == Some(&Value::Object(None)) {
+					Err(Error::Exception(NULL_REFERENCE))?;
+				}
+				self.enter(m, args)?;
+			},
+			Callee::External(name, sig, f) => {
+				debug!("Calling intrinsic {}.", name);
+				let args = self.frame().pop_n(sig.params.len())?;
+				let args = args.into_iter().zip(sig.params.iter())
+					.map(|(a, ty)| a.coerce(ty))
+					.collect::<Result<Vec<_>>>()?;
+				if let Some(value) = f(&mut self.heap, &sig, args)? {
+					self.push(value)?;
+				}
+			},
+		}
+		Ok(())
+	}
+
+	/// Finds the method a call token refers to: a method of this assembly or
+	/// an external one the interpreter implements.
+	fn resolve(&self, ins: &Instruction, token: MetadataToken) -> Result<Callee> {
+		let rows = &self.asm.rows;
+		let strings = self.asm.strings();
+		let blobs = self.asm.blobs();
+
+		match token.table_index() {
+			METADATA_METHOD_DEF if token.row_index() < rows.method_defs.len() => Ok(Callee::Method(token.row_index())),
+			METADATA_MEMBER_REF => {
+				let r = rows.member_refs.get(token.row_index()).ok_or("MemberRef row is out of the table bounds.")?;
+				let name = string_at(strings, r.name.into_index())?;
+				match r.class {
+					// Members of this assembly's types referenced by name.
+					MemberRefParent::TypeDef(t) => {
+						let t = (t as usize).wrapping_sub(1);
+						if t >= rows.type_defs.len() {
+							Err("TypeDef row is out of the table bounds.")?;
+						}
+						let sig = blob_at(blobs, r.sig.into_index())?;
+						for m in rows.methods_of(t) {
+							let def = &rows.method_defs[m];
+							if string_at(strings, def.name.into_index())? == name && blob_at(blobs, def.sig.into_index())? == sig {
+								return Ok(Callee::Method(m));
+							}
+						}
+						Err("Referenced method is not found.")?
+					},
+					MemberRefParent::TypeRef(t) => {
+						let name = format!("{}::{}", rows.type_name(strings, TypeDefOrRef::TypeRef(t))?, name);
+						let sig = rows.method_sig(blobs, token)?;
+						match intrinsic(&name, &sig) {
+							Some(f) => Ok(Callee::External(name, sig, f)),
+							None => {
+								warn!("{} {} is not implemented.", name, sig.named(""));
+								Err(Error::Unsupported(ins.offset, dump_opcode(ins.opcode)))
+							},
+						}
+					},
+					_ => Err("Only methods of types can be called.")?,
+				}
+			},
+			METADATA_METHOD_SPEC => Err("Generic methods are not supported yet.")?,
+			_ => Err(Error::Exception(INVALID_PROGRAM)),
+		}
+	}
+}
+
+/// Argument or local index of the instruction's operand.
+fn index(ins: &Instruction) -> Result<usize> {
+	match ins.operand {
+		Operand::U8(i) => Ok(i as usize),
+		Operand::U16(i) => Ok(i as usize),
+		_ => Err(Error::Exception(INVALID_PROGRAM)),
+	}
+}
+
+/// Offset a branch instruction transfers control to.
+fn target(ins: &Instruction) -> Result<u32> {
+	match ins.operand {
+		Operand::Branch(offset) => Ok(offset),
+		_ => Err(Error::Exception(INVALID_PROGRAM)),
+	}
+}
+
+fn pointer(value: Value) -> Result<Pointer> {
+	match value {
+		Value::ManagedPtr(p) => Ok(p),
+		_ => Err(Error::Exception(INVALID_PROGRAM)),
+	}
+}
+
+/// The object a reference points to, throwing on null.
+fn object(value: Value) -> Result<ObjectRef> {
+	match value {
+		Value::Object(Some(r)) => Ok(r),
+		Value::Object(None) => Err(Error::Exception(NULL_REFERENCE)),
+		_ => Err(Error::Exception(INVALID_PROGRAM)),
+	}
+}
+
+/// Array index operand, either int32 or native int, III.4.
+fn array_index(value: Value) -> Result<isize> {
+	match value {
+		Value::Int32(i) => Ok(i as isize),
+		Value::NativeInt(i) => Ok(i),
+		_ => Err(Error::Exception(INVALID_PROGRAM)),
+	}
+}
+
+fn element(heap: &Heap, array: ObjectRef, index: isize) -> Result<Pointer> {
+	let len = heap.array(array)?.1.len();
+	if index < 0 || index as usize >= len {
+		Err(Error::Exception(INDEX_OUT_OF_RANGE))?;
+	}
+	Ok(Pointer::Element { array, index: index as usize })
+}
+
+/// Type of the value `ldind.*` and `stind.*` access.
+fn indirect_type(op: u16) -> TypeSig {
+	match op {
+		LDIND_I1 | STIND_I1 => TypeSig::I1,
+		LDIND_U1 => TypeSig::U1,
+		LDIND_I2 | STIND_I2 => TypeSig::I2,
+		LDIND_U2 => TypeSig::U2,
+		LDIND_I4 | STIND_I4 => TypeSig::I4,
+		LDIND_U4 => TypeSig::U4,
+		LDIND_I8 | STIND_I8 => TypeSig::I8,
+		LDIND_I | STIND_I => TypeSig::I,
+		LDIND_R4 | STIND_R4 => TypeSig::R4,
+		LDIND_R8 | STIND_R8 => TypeSig::R8,
+		_ => TypeSig::Object,
+	}
+}
@@ -0,0 +1,109 @@
+use std::io::Write;
+
+use crate::Result;
+use crate::error::Error;
+use crate::cli::{MethodSig, TypeSig};
+use crate::vm::{Heap, INVALID_PROGRAM, Value};
+
+/// A method of the base class library the interpreter implements itself,
+/// taking arguments coerced to the signature's types.
+pub type Intrinsic = fn(&mut Heap, &MethodSig, Vec<Value>) -> Result<Option<Value>>;
+
+/// Looks up the implementation of an external method by its full name, e.g.
+/// `System.Console::WriteLine`, and signature.
+pub fn intrinsic(name: &str, sig: &MethodSig) -> Option<Intrinsic> {
+	let printable = match &sig.params[..] {
+		[] => true,
+		[p] => matches!(p,
+			TypeSig::Boolean | TypeSig::Char | TypeSig::I4 | TypeSig::U4 | TypeSig::I8 | TypeSig::U8 |
+			TypeSig::R4 | TypeSig::R8 | TypeSig::String),
+		_ => false,
+	};
+	if sig.has_this || sig.ret != TypeSig::Void || !printable {
+		return None;
+	}
+
+	match name {
+		"System.Console::Write" if !sig.params.is_empty() => Some(console_write),
+		"System.Console::WriteLine" => Some(console_write_line),
+		_ => None,
+	}
+}
+
+fn console_write(heap: &mut Heap, sig: &MethodSig, args: Vec<Value>) -> Result<Option<Value>> {
+	let s = format(heap, &sig.params[0], &args[0])?;
+	std::io::stdout().write_all(s.as_bytes())?;
+	Ok(None)
+}
+
+fn console_write_line(heap: &mut Heap, sig: &MethodSig, args: Vec<Value>) -> Result<Option<Value>> {
+	let mut s = match (sig.params.first(), args.first()) {
+		(Some(ty), Some(value)) => format(heap, ty, value)?,
+		_ => String::new(),
+	};
+	s.push('\n');
+	std::io::stdout().write_all(s.as_bytes())?;
+	Ok(None)
+}
+
+/// Formats the value the way its `ToString()` does under the invariant
+/// culture.
+fn format(heap: &Heap, ty: &TypeSig, value: &Value) -> Result<String> {
+	let s = match (ty, value) {
+		(TypeSig::Boolean, Value::Int32(x)) => if *x != 0 { "True" } else { "False" }.to_owned(),
+		(TypeSig::Char, Value::Int32(x)) => {
+			// Lone surrogates have no UTF-8 form.
+			char::from_u32(*x as u16 as u32).unwrap_or(char::REPLACEMENT_CHARACTER).to_string()
+		},
+		(TypeSig::I4, Value::Int32(x)) => x.to_string(),
+		(TypeSig::U4, Value::Int32(x)) => (*x as u32).to_string(),
+		(TypeSig::I8, Value::Int64(x)) => x.to_string(),
+		(TypeSig::U8, Value::Int64(x)) => (*x as u64).to_string(),
+		(TypeSig::R4, Value::Float(x)) => format_float(*x, &format!("{:e}", *x as f32), 7),
+		(TypeSig::R8, Value::Float(x)) => format_float(*x, &format!("{:e}", x), 15),
+		(TypeSig::String, Value::Object(None)) => String::new(),
+		(TypeSig::String, Value::Object(Some(r))) => heap.string(*r).ok_or(Error::Exception(INVALID_PROGRAM))?.to_owned(),
+		_ => Err(Error::Exception(INVALID_PROGRAM))?,
+	};
+	Ok(s)
+}
+
+/// Formats a float with the shortest digits that round-trip, given as
+/// `{:e}` prints them, switching to the exponent notation the way the
+/// "G" format does for the type's precision.
+pub fn format_float(x: f64, shortest: &str, precision: usize) -> String {
+	if x.is_nan() {
+		return "NaN".to_owned();
+	}
+	if x.is_infinite() {
+		return if x < 0.0 { "-Infinity" } else { "Infinity" }.to_owned();
+	}
+
+	let (mantissa, exp) = shortest.split_once('e').unwrap_or((shortest, "0"));
+	let (sign, mantissa) = match mantissa.strip_prefix('-') {
+		Some(m) => ("-", m),
+		None => ("", mantissa),
+	};
+	let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+	let digits = digits.trim_end_matches('0');
+	if digits.is_empty() {
+		return format!("{}0", sign);
+	}
+
+	// Position of the decimal point relative to the first digit.
+	let scale = exp.parse::<i32>().unwrap_or(0) + 1;
+	let n = digits.len() as i32;
+
+	if scale > n.max(precision as i32) || scale < -3 {
+		let exp = scale - 1;
+		let fraction = if n > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+		let exp_sign = if exp < 0 { '-' } else { '+' };
+		format!("{}{}{}E{}{:02}", sign, &digits[..1], fraction, exp_sign, exp.abs())
+	} else if scale <= 0 {
+		format!("{}0.{}{}", sign, "0".repeat(-scale as usize), digits)
+	} else if scale >= n {
+		format!("{}{}{}", sign, digits, "0".repeat((scale - n) as usize))
+	} else {
+		format!("{}{}.{}", sign, &digits[..scale as usize], &digits[scale as usize..])
+	}
+}
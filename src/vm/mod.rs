@@ -0,0 +1,26 @@
+mod exceptions;
+pub use self::exceptions::*;
+
+mod value;
+pub use self::value::*;
+
+mod heap;
+pub use self::heap::*;
+
+mod frame;
+pub use self::frame::*;
+
+mod arithmetic;
+pub use self::arithmetic::*;
+
+mod conversions;
+pub use self::conversions::*;
+
+mod comparisons;
+pub use self::comparisons::*;
+
+mod intrinsics;
+pub use self::intrinsics::*;
+
+mod interpreter;
+pub use self::interpreter::*;
@@ -0,0 +1,148 @@
+use std::fmt;
+
+use crate::Result;
+use crate::error::Error;
+use crate::assembly::Assembly;
+use crate::cli::{FIELD_STATIC, TypeDefOrRef, TypeSig, blob_at};
+use crate::vm::INVALID_PROGRAM;
+
+// Valid metadata has no cycles of value type fields, this only stops a
+// malformed one from exhausting the host stack.
+const MAX_VALUE_TYPE_NESTING: u32 = 64;
+
+/// An object on the heap, index in `Heap::objects`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ObjectRef(pub usize);
+
+/// Location a managed pointer refers to.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Pointer {
+	/// Argument of the frame with the id, see `Frame::id`.
+	Arg { frame: u64, index: usize },
+	Local { frame: u64, index: usize },
+	/// Element of a single-dimensional array.
+	Element { array: ObjectRef, index: usize },
+}
+
+/// A value as the evaluation stack holds it, III.1.1. Integers narrower than
+/// 32 bits are widened to int32 and float32 to F when loaded, locations keep
+/// their declared types, see `Value::coerce`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+	Int32(i32),
+	Int64(i64),
+	/// Of the host pointer size.
+	NativeInt(isize),
+	/// Type F, stored as float64 whatever the source.
+	Float(f64),
+	/// None is the null reference.
+	Object(Option<ObjectRef>),
+	ManagedPtr(Pointer),
+	/// Instance of a value type with its instance fields in declaration order.
+	Struct(TypeDefOrRef, Box<[Value]>),
+}
+
+impl Value {
+	/// The zero-initialized value of the type, II.24.4.1.
+	pub fn zero(asm: &Assembly, sig: &TypeSig) -> Result<Value> {
+		Value::zero_nested(asm, sig, 0)
+	}
+
+	fn zero_nested(asm: &Assembly, sig: &TypeSig, depth: u32) -> Result<Value> {
+		let v = match sig {
+			TypeSig::Boolean | TypeSig::Char | TypeSig::I1 | TypeSig::U1 | TypeSig::I2 | TypeSig::U2 |
+			TypeSig::I4 | TypeSig::U4 => Value::Int32(0),
+			TypeSig::I8 | TypeSig::U8 => Value::Int64(0),
+			TypeSig::I | TypeSig::U | TypeSig::Ptr(_) | TypeSig::FnPtr(_) => Value::NativeInt(0),
+			TypeSig::R4 | TypeSig::R8 => Value::Float(0.0),
+			TypeSig::String | TypeSig::Object | TypeSig::Class(_) | TypeSig::SzArray(_) | TypeSig::Array(..) |
+			TypeSig::GenericInst { is_value_type: false, .. } => Value::Object(None),
+			TypeSig::CustomMod { ty, .. } | TypeSig::Pinned(ty) => Value::zero_nested(asm, ty, depth)?,
+			TypeSig::ValueType(ty @ TypeDefOrRef::TypeDef(row)) => {
+				if depth == MAX_VALUE_TYPE_NESTING {
+					Err("Value type nesting is too deep.")?;
+				}
+				let row = (*row as usize).wrapping_sub(1);
+				if row >= asm.rows.type_defs.len() {
+					Err("TypeDef row is out of the table bounds.")?;
+				}
+				let mut fields = Vec::new();
+				for field in asm.rows.fields_of(row) {
+					let f = &asm.rows.fields[field];
+					if f.flags & FIELD_STATIC == 0 {
+						let sig = TypeSig::parse_field_sig(blob_at(asm.blobs(), f.sig.into_index())?)?;
+						fields.push(Value::zero_nested(asm, &sig, depth + 1)?);
+					}
+				}
+				Value::Struct(*ty, fields.into_boxed_slice())
+			},
+			TypeSig::ValueType(_) => Err("Value types of other assemblies are not supported yet.")?,
+			// A byref location has no default target.
+			TypeSig::ByRef(_) => Err(Error::Exception(INVALID_PROGRAM))?,
+			TypeSig::Var(_) | TypeSig::MVar(_) | TypeSig::GenericInst { .. } => {
+				Err("Generics are not supported yet.")?
+			},
+			TypeSig::Void | TypeSig::TypedByRef => Err("Type has no values.")?,
+		};
+		Ok(v)
+	}
+
+	/// Converts the value to the declared type of the location it is stored
+	/// at, truncating integers the way III.1.6 allows. Values of other stack
+	/// types make the program invalid.
+	pub fn coerce(self, sig: &TypeSig) -> Result<Value> {
+		let v = match (sig, self) {
+			(TypeSig::CustomMod { ty, .. }, v) | (TypeSig::Pinned(ty), v) => return v.coerce(ty),
+
+			(TypeSig::Boolean, Value::Int32(x)) | (TypeSig::U1, Value::Int32(x)) => Value::Int32(x as u8 as i32),
+			(TypeSig::I1, Value::Int32(x)) => Value::Int32(x as i8 as i32),
+			(TypeSig::I2, Value::Int32(x)) => Value::Int32(x as i16 as i32),
+			(TypeSig::Char, Value::Int32(x)) | (TypeSig::U2, Value::Int32(x)) => Value::Int32(x as u16 as i32),
+			(TypeSig::I4, Value::Int32(x)) | (TypeSig::U4, Value::Int32(x)) => Value::Int32(x),
+
+			(TypeSig::Boolean, Value::NativeInt(x)) | (TypeSig::U1, Value::NativeInt(x)) => Value::Int32(x as u8 as i32),
+			(TypeSig::I1, Value::NativeInt(x)) => Value::Int32(x as i8 as i32),
+			(TypeSig::I2, Value::NativeInt(x)) => Value::Int32(x as i16 as i32),
+			(TypeSig::Char, Value::NativeInt(x)) | (TypeSig::U2, Value::NativeInt(x)) => Value::Int32(x as u16 as i32),
+			(TypeSig::I4, Value::NativeInt(x)) | (TypeSig::U4, Value::NativeInt(x)) => Value::Int32(x as i32),
+
+			(TypeSig::I8, Value::Int64(x)) | (TypeSig::U8, Value::Int64(x)) => Value::Int64(x),
+
+			(TypeSig::I, Value::Int32(x)) | (TypeSig::Ptr(_), Value::Int32(x)) | (TypeSig::FnPtr(_), Value::Int32(x)) => {
+				Value::NativeInt(x as isize)
+			},
+			(TypeSig::U, Value::Int32(x)) => Value::NativeInt(x as u32 as isize),
+			(TypeSig::I, Value::NativeInt(x)) | (TypeSig::U, Value::NativeInt(x)) |
+			(TypeSig::Ptr(_), Value::NativeInt(x)) | (TypeSig::FnPtr(_), Value::NativeInt(x)) => Value::NativeInt(x),
+
+			(TypeSig::R4, Value::Float(x)) => Value::Float(x as f32 as f64),
+			(TypeSig::R8, Value::Float(x)) => Value::Float(x),
+
+			(TypeSig::String, v @ Value::Object(_)) | (TypeSig::Object, v @ Value::Object(_)) |
+			(TypeSig::Class(_), v @ Value::Object(_)) | (TypeSig::SzArray(_), v @ Value::Object(_)) |
+			(TypeSig::Array(..), v @ Value::Object(_)) => v,
+			(TypeSig::GenericInst { is_value_type: false, .. }, v @ Value::Object(_)) => v,
+
+			(TypeSig::ByRef(_), v @ Value::ManagedPtr(_)) => v,
+			(TypeSig::ValueType(_), v @ Value::Struct(..)) => v,
+
+			_ => Err(Error::Exception(INVALID_PROGRAM))?,
+		};
+		Ok(v)
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Value::Int32(x)     => write!(f, "int32({})", x),
+			Value::Int64(x)     => write!(f, "int64({})", x),
+			Value::NativeInt(x) => write!(f, "native int({})", x),
+			Value::Float(x)     => write!(f, "F({})", x),
+			Value::Object(None) => write!(f, "null"),
+			Value::Object(Some(o)) => write!(f, "object(#{})", o.0),
+			Value::ManagedPtr(p) => write!(f, "&{:?}", p),
+			Value::Struct(_, fields) => write!(f, "valuetype({} field(s))", fields.len()),
+		}
+	}
+}
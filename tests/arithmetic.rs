@@ -0,0 +1,245 @@
+// Conformance of arithmetic instructions to III.3 and the operand type
+// tables of III.1.5.
+
+mod common;
+
+use common::{expect_exception, expect_output};
+
+const INVALID_PROGRAM: &str = "System.InvalidProgramException";
+
+#[test]
+fn add() {
+	expect_output("
+		ldc.i4.2 ldc.i4.3 add call void Test::Print(int32)
+		ldc.i4 2147483647 ldc.i4.1 add call void Test::Print(int32)
+		ldc.i8 9223372036854775807 ldc.i8 1 add call void Test::Print(int64)
+		ldc.i4.5 call native int Test::Native(int32) ldc.i4.7 add call void Test::Print(native int)
+		ldc.i4.7 ldc.i4.s -10 call native int Test::Native(int32) add call void Test::Print(native int)
+		ldc.r8 0.1 ldc.r8 0.2 add call void Test::Print(float64)
+		ldc.r8 1e308 ldc.r8 1e308 add call void Test::Print(float64)
+	", &["5", "-2147483648", "-9223372036854775808", "12", "-3", "0.30000000000000004", "Infinity"]);
+	expect_exception("ldc.i4.1 ldc.i8 1 add pop", INVALID_PROGRAM);
+	expect_exception("ldc.i4.1 ldc.r8 1.0 add pop", INVALID_PROGRAM);
+	expect_exception("ldnull ldnull add pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn sub() {
+	expect_output("
+		ldc.i4.5 ldc.i4.7 sub call void Test::Print(int32)
+		ldc.i4 -2147483648 ldc.i4.1 sub call void Test::Print(int32)
+		ldc.i8 -9223372036854775808 ldc.i8 1 sub call void Test::Print(int64)
+		ldc.i4.3 call native int Test::Native(int32) ldc.i4.5 sub call void Test::Print(native int)
+		ldc.r8 1.0 ldc.r8 0.9 sub call void Test::Print(float64)
+	", &["-2", "2147483647", "9223372036854775807", "-2", "0.09999999999999998"]);
+	expect_exception("ldc.i8 1 ldc.r8 1.0 sub pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn mul() {
+	expect_output("
+		ldc.i4.6 ldc.i4.7 mul call void Test::Print(int32)
+		ldc.i4 65536 ldc.i4 65536 mul call void Test::Print(int32)
+		ldc.i8 3000000000 ldc.i8 4 mul call void Test::Print(int64)
+		ldc.i8 4294967296 ldc.i8 4294967296 mul call void Test::Print(int64)
+		ldc.i4.6 call native int Test::Native(int32) ldc.i4.s -7 mul call void Test::Print(native int)
+		ldc.r8 1.5 ldc.r8 -2.0 mul call void Test::Print(float64)
+	", &["42", "0", "12000000000", "0", "-42", "-3"]);
+	expect_exception("ldc.r8 1.0 ldc.i4.1 mul pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn div() {
+	expect_output("
+		ldc.i4.7 ldc.i4.2 div call void Test::Print(int32)
+		ldc.i4.s -7 ldc.i4.2 div call void Test::Print(int32)
+		ldc.i4.7 ldc.i4.s -2 div call void Test::Print(int32)
+		ldc.i8 -9 ldc.i8 4 div call void Test::Print(int64)
+		ldc.i4.s 9 call native int Test::Native(int32) ldc.i4.2 div call void Test::Print(native int)
+		ldc.r8 7.0 ldc.r8 2.0 div call void Test::Print(float64)
+		ldc.r8 1.0 ldc.r8 0.0 div call void Test::Print(float64)
+		ldc.r8 -1.0 ldc.r8 0.0 div call void Test::Print(float64)
+		ldc.r8 0.0 ldc.r8 0.0 div call void Test::Print(float64)
+	", &["3", "-3", "-3", "-2", "4", "3.5", "Infinity", "-Infinity", "NaN"]);
+	expect_exception("ldc.i4.1 ldc.i4.0 div pop", "System.DivideByZeroException");
+	expect_exception("ldc.i8 1 ldc.i8 0 div pop", "System.DivideByZeroException");
+	expect_exception("ldc.i4 -2147483648 ldc.i4.m1 div pop", "System.ArithmeticException");
+	expect_exception("ldc.i8 -9223372036854775808 ldc.i8 -1 div pop", "System.ArithmeticException");
+}
+
+#[test]
+fn div_un() {
+	expect_output("
+		ldc.i4.m1 ldc.i4.2 div.un call void Test::Print(int32)
+		ldc.i4.7 ldc.i4.2 div.un call void Test::Print(int32)
+		ldc.i8 -2 ldc.i8 2 div.un call void Test::Print(int64)
+		ldc.i4.s 9 call native int Test::Native(int32) ldc.i4.2 div.un call void Test::Print(native int)
+	", &["2147483647", "3", "9223372036854775807", "4"]);
+	expect_exception("ldc.i4.1 ldc.i4.0 div.un pop", "System.DivideByZeroException");
+	expect_exception("ldc.r8 1.0 ldc.r8 1.0 div.un pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn rem() {
+	expect_output("
+		ldc.i4.7 ldc.i4.3 rem call void Test::Print(int32)
+		ldc.i4.s -7 ldc.i4.3 rem call void Test::Print(int32)
+		ldc.i4.7 ldc.i4.s -3 rem call void Test::Print(int32)
+		ldc.i8 -9 ldc.i8 4 rem call void Test::Print(int64)
+		ldc.i4.s 10 call native int Test::Native(int32) ldc.i4.4 rem call void Test::Print(native int)
+		ldc.r8 5.5 ldc.r8 2.0 rem call void Test::Print(float64)
+		ldc.r8 -5.5 ldc.r8 2.0 rem call void Test::Print(float64)
+		ldc.r8 1.0 ldc.r8 0.0 rem call void Test::Print(float64)
+	", &["1", "-1", "1", "-1", "2", "1.5", "-1.5", "NaN"]);
+	expect_exception("ldc.i4.1 ldc.i4.0 rem pop", "System.DivideByZeroException");
+	expect_exception("ldc.i4 -2147483648 ldc.i4.m1 rem pop", "System.ArithmeticException");
+}
+
+#[test]
+fn rem_un() {
+	expect_output("
+		ldc.i4.m1 ldc.i4.s 10 rem.un call void Test::Print(int32)
+		ldc.i8 -1 ldc.i8 10 rem.un call void Test::Print(int64)
+	", &["5", "5"]);
+	expect_exception("ldc.i8 1 ldc.i8 0 rem.un pop", "System.DivideByZeroException");
+	expect_exception("ldc.r8 1.0 ldc.r8 1.0 rem.un pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn and() {
+	expect_output("
+		ldc.i4.s 12 ldc.i4.s 10 and call void Test::Print(int32)
+		ldc.i8 -1 ldc.i8 4294967296 and call void Test::Print(int64)
+		ldc.i4.s 12 call native int Test::Native(int32) ldc.i4.s 10 and call void Test::Print(native int)
+	", &["8", "4294967296", "8"]);
+	expect_exception("ldc.r8 1.0 ldc.r8 1.0 and pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn or() {
+	expect_output("
+		ldc.i4.s 12 ldc.i4.s 10 or call void Test::Print(int32)
+		ldc.i8 4294967296 ldc.i8 1 or call void Test::Print(int64)
+	", &["14", "4294967297"]);
+	expect_exception("ldc.r8 1.0 ldc.r8 1.0 or pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn xor() {
+	expect_output("
+		ldc.i4.s 12 ldc.i4.s 10 xor call void Test::Print(int32)
+		ldc.i8 -1 ldc.i8 1 xor call void Test::Print(int64)
+	", &["6", "-2"]);
+	expect_exception("ldc.i4.1 ldc.i8 1 xor pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn shl() {
+	expect_output("
+		ldc.i4.1 ldc.i4.s 31 shl call void Test::Print(int32)
+		ldc.i4.3 ldc.i4.2 shl call void Test::Print(int32)
+		ldc.i8 1 ldc.i4.s 40 shl call void Test::Print(int64)
+		ldc.i4.1 ldc.i4.4 call native int Test::Native(int32) shl call void Test::Print(int32)
+	", &["-2147483648", "12", "1099511627776", "16"]);
+	expect_exception("ldc.i4.1 ldc.i8 1 shl pop", INVALID_PROGRAM);
+	expect_exception("ldc.r8 1.0 ldc.i4.1 shl pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn shr() {
+	expect_output("
+		ldc.i4.s -8 ldc.i4.1 shr call void Test::Print(int32)
+		ldc.i4 -2147483648 ldc.i4.s 31 shr call void Test::Print(int32)
+		ldc.i8 -1 ldc.i4.s 63 shr call void Test::Print(int64)
+	", &["-4", "-1", "-1"]);
+}
+
+#[test]
+fn shr_un() {
+	expect_output("
+		ldc.i4.s -8 ldc.i4.1 shr.un call void Test::Print(int32)
+		ldc.i8 -1 ldc.i4.s 63 shr.un call void Test::Print(int64)
+	", &["2147483644", "1"]);
+}
+
+#[test]
+fn neg() {
+	expect_output("
+		ldc.i4.5 neg call void Test::Print(int32)
+		ldc.i4 -2147483648 neg call void Test::Print(int32)
+		ldc.i8 -9223372036854775807 neg call void Test::Print(int64)
+		ldc.i4.3 call native int Test::Native(int32) neg call void Test::Print(native int)
+		ldc.r8 0.0 neg call void Test::Print(float64)
+		ldc.r8 -2.5 neg call void Test::Print(float64)
+	", &["-5", "-2147483648", "9223372036854775807", "-3", "-0", "2.5"]);
+	expect_exception("ldnull neg pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn not() {
+	expect_output("
+		ldc.i4.0 not call void Test::Print(int32)
+		ldc.i4.5 not call void Test::Print(int32)
+		ldc.i8 0 not call void Test::Print(int64)
+	", &["-1", "-6", "-1"]);
+	expect_exception("ldc.r8 1.0 not pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn add_ovf() {
+	expect_output("
+		ldc.i4.2 ldc.i4.3 add.ovf call void Test::Print(int32)
+		ldc.i4 -2147483647 ldc.i4.m1 add.ovf call void Test::Print(int32)
+	", &["5", "-2147483648"]);
+	expect_exception("ldc.i4 2147483647 ldc.i4.1 add.ovf pop", "System.OverflowException");
+	expect_exception("ldc.i8 9223372036854775807 ldc.i8 1 add.ovf pop", "System.OverflowException");
+	expect_exception("ldc.r8 1.0 ldc.r8 1.0 add.ovf pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn add_ovf_un() {
+	expect_output("
+		ldc.i4.s -2 ldc.i4.1 add.ovf.un call void Test::Print(int32)
+		ldc.i4 2147483647 ldc.i4.1 add.ovf.un call void Test::Print(int32)
+	", &["-1", "-2147483648"]);
+	expect_exception("ldc.i4.m1 ldc.i4.1 add.ovf.un pop", "System.OverflowException");
+	expect_exception("ldc.i8 -1 ldc.i8 1 add.ovf.un pop", "System.OverflowException");
+}
+
+#[test]
+fn sub_ovf() {
+	expect_output("
+		ldc.i4.5 ldc.i4.7 sub.ovf call void Test::Print(int32)
+	", &["-2"]);
+	expect_exception("ldc.i4 -2147483648 ldc.i4.1 sub.ovf pop", "System.OverflowException");
+	expect_exception("ldc.i8 -9223372036854775808 ldc.i8 1 sub.ovf pop", "System.OverflowException");
+}
+
+#[test]
+fn sub_ovf_un() {
+	expect_output("
+		ldc.i4.7 ldc.i4.5 sub.ovf.un call void Test::Print(int32)
+		ldc.i4.m1 ldc.i4.1 sub.ovf.un call void Test::Print(int32)
+	", &["2", "-2"]);
+	expect_exception("ldc.i4.5 ldc.i4.7 sub.ovf.un pop", "System.OverflowException");
+	expect_exception("ldc.i8 0 ldc.i8 1 sub.ovf.un pop", "System.OverflowException");
+}
+
+#[test]
+fn mul_ovf() {
+	expect_output("
+		ldc.i4.6 ldc.i4.s -7 mul.ovf call void Test::Print(int32)
+		ldc.i4 -65536 ldc.i4 32768 mul.ovf call void Test::Print(int32)
+	", &["-42", "-2147483648"]);
+	expect_exception("ldc.i4 65536 ldc.i4 32768 mul.ovf pop", "System.OverflowException");
+	expect_exception("ldc.i8 4294967296 ldc.i8 4294967296 mul.ovf pop", "System.OverflowException");
+}
+
+#[test]
+fn mul_ovf_un() {
+	expect_output("
+		ldc.i4 65536 ldc.i4 65535 mul.ovf.un call void Test::Print(int32)
+	", &["-65536"]);
+	expect_exception("ldc.i4 65536 ldc.i4 65536 mul.ovf.un pop", "System.OverflowException");
+	expect_exception("ldc.i4.m1 ldc.i4.2 mul.ovf.un pop", "System.OverflowException");
+}
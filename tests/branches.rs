@@ -0,0 +1,317 @@
+// Conformance of control transfer and comparison instructions to III.3.5-18,
+// III.3.21-25, III.3.44 and III.3.66.
+
+mod common;
+
+use common::{expect_exception, expect_output};
+
+const INVALID_PROGRAM: &str = "System.InvalidProgramException";
+
+const NAN: &str = "ldc.r8 0.0 ldc.r8 0.0 div";
+
+/// Instructions printing 1 when the branch on the operands is taken, 0
+/// otherwise, for each pair of operands.
+fn branches(op: &str, cases: &[(&str, &str)]) -> String {
+	cases.iter().enumerate()
+		.map(|(i, (a, b))| format!("
+			{a} {b} {op} Taken{i}
+			ldc.i4.0 call void Test::Print(int32)
+			br.s Done{i}
+		Taken{i}:
+			ldc.i4.1 call void Test::Print(int32)
+		Done{i}:
+		"))
+		.collect()
+}
+
+/// Instructions printing the result of the comparison for each pair of
+/// operands.
+fn comparisons(op: &str, cases: &[(&str, &str)]) -> String {
+	cases.iter()
+		.map(|(a, b)| format!("{a} {b} {op} call void Test::Print(int32)\n"))
+		.collect()
+}
+
+#[test]
+fn br() {
+	expect_output("
+			br.s Forward
+			ldc.i4.0 call void Test::Print(int32)
+		Forward:
+			ldc.i4.1 call void Test::Print(int32)
+			br Long
+			ldc.i4.2 call void Test::Print(int32)
+		Long:
+			ldc.i4.3 call void Test::Print(int32)
+	", &["1", "3"]);
+}
+
+#[test]
+fn br_backward() {
+	// Counts down from 3, jumping back to the test at the loop's head.
+	expect_output("
+			ldc.i4.3
+		Loop:
+			dup call void Test::Print(int32)
+			ldc.i4.1 sub
+			dup brtrue.s Loop
+			pop
+	", &["3", "2", "1"]);
+}
+
+#[test]
+fn brtrue() {
+	expect_output(&branches("brtrue", &[
+		("", "ldc.i4.0"),
+		("", "ldc.i4.m1"),
+		("", "ldc.i8 0x100000000"),
+		("", "ldc.i4.0 call native int Test::Native(int32)"),
+		("", "ldnull"),
+		("", "ldstr \"\""),
+	]), &["0", "1", "1", "0", "0", "1"]);
+	expect_exception("ldc.r8 1.0 brtrue.s Done Done: ", INVALID_PROGRAM);
+}
+
+#[test]
+fn brfalse() {
+	expect_output(&branches("brfalse.s", &[
+		("", "ldc.i4.0"),
+		("", "ldc.i4.1"),
+		("", "ldc.i8 0"),
+		("", "ldnull"),
+		("", "ldstr \"\""),
+	]), &["1", "0", "1", "1", "0"]);
+}
+
+#[test]
+fn beq() {
+	let cases = [
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.2"),
+		("ldc.i4.m1", "ldc.i4.m1 call native int Test::Native(int32)"),
+		("ldc.i8 5", "ldc.i8 5"),
+		("ldc.r8 0.0", "ldc.r8 -0.0"),
+		(NAN, NAN),
+		("ldnull", "ldnull"),
+		("ldstr \"a\"", "ldnull"),
+	];
+	let expected = ["1", "0", "1", "1", "1", "0", "1", "0"];
+	expect_output(&branches("beq", &cases), &expected);
+	expect_output(&branches("beq.s", &cases), &expected);
+	expect_exception("ldc.i4.1 ldc.i8 1 beq.s Done Done: ", INVALID_PROGRAM);
+}
+
+#[test]
+fn bne_un() {
+	let cases = [
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.2"),
+		(NAN, NAN),
+		("ldc.r8 1.0", NAN),
+		("ldnull", "ldstr \"a\""),
+	];
+	let expected = ["0", "1", "1", "1", "1"];
+	expect_output(&branches("bne.un", &cases), &expected);
+	expect_output(&branches("bne.un.s", &cases), &expected);
+}
+
+#[test]
+fn bge() {
+	let cases = [
+		("ldc.i4.2", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i8 -1", "ldc.i8 1"),
+		("ldc.r8 1.0", NAN),
+	];
+	let expected = ["1", "1", "0", "0", "0"];
+	expect_output(&branches("bge", &cases), &expected);
+	expect_output(&branches("bge.s", &cases), &expected);
+	expect_exception("ldnull ldnull bge.s Done Done: ", INVALID_PROGRAM);
+}
+
+#[test]
+fn bge_un() {
+	let cases = [
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.m1"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.r8 1.0", NAN),
+		("ldc.r8 1.0", "ldc.r8 2.0"),
+	];
+	let expected = ["1", "0", "1", "1", "0"];
+	expect_output(&branches("bge.un", &cases), &expected);
+	expect_output(&branches("bge.un.s", &cases), &expected);
+}
+
+#[test]
+fn bgt() {
+	let cases = [
+		("ldc.i4.2", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i4.m1", "ldc.i4.2 call native int Test::Native(int32)"),
+		(NAN, "ldc.r8 1.0"),
+	];
+	let expected = ["1", "0", "0", "0", "0"];
+	expect_output(&branches("bgt", &cases), &expected);
+	expect_output(&branches("bgt.s", &cases), &expected);
+}
+
+#[test]
+fn bgt_un() {
+	let cases = [
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i8 -1", "ldc.i8 1"),
+		// Int32 is zero-extended against native int.
+		("ldc.i4.m1", "ldc.i4.1 call native int Test::Native(int32)"),
+		(NAN, "ldc.r8 1.0"),
+	];
+	let expected = ["1", "0", "1", "1", "1"];
+	expect_output(&branches("bgt.un", &cases), &expected);
+	expect_output(&branches("bgt.un.s", &cases), &expected);
+}
+
+#[test]
+fn ble() {
+	let cases = [
+		("ldc.i4.1", "ldc.i4.2"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.m1"),
+		("ldc.r8 -0.0", "ldc.r8 0.0"),
+		(NAN, NAN),
+	];
+	let expected = ["1", "1", "0", "1", "0"];
+	expect_output(&branches("ble", &cases), &expected);
+	expect_output(&branches("ble.s", &cases), &expected);
+}
+
+#[test]
+fn ble_un() {
+	let cases = [
+		("ldc.i4.1", "ldc.i4.m1"),
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.1"),
+		(NAN, NAN),
+	];
+	let expected = ["1", "0", "1", "1"];
+	expect_output(&branches("ble.un", &cases), &expected);
+	expect_output(&branches("ble.un.s", &cases), &expected);
+}
+
+#[test]
+fn blt() {
+	let cases = [
+		("ldc.i4.1", "ldc.i4.2"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i8 -2", "ldc.i8 -1"),
+		("ldc.r8 1.0", NAN),
+	];
+	let expected = ["1", "0", "1", "1", "0"];
+	expect_output(&branches("blt", &cases), &expected);
+	expect_output(&branches("blt.s", &cases), &expected);
+}
+
+#[test]
+fn blt_un() {
+	let cases = [
+		("ldc.i4.1", "ldc.i4.m1"),
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i8 1", "ldc.i8 -1"),
+		("ldc.r8 1.0", NAN),
+	];
+	let expected = ["1", "0", "1", "1"];
+	expect_output(&branches("blt.un", &cases), &expected);
+	expect_output(&branches("blt.un.s", &cases), &expected);
+}
+
+#[test]
+fn ceq() {
+	expect_output(&comparisons("ceq", &[
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.0"),
+		("ldc.i8 -1", "ldc.i8 -1"),
+		("ldc.r8 0.0", "ldc.r8 -0.0"),
+		(NAN, NAN),
+		("ldnull", "ldnull"),
+		("ldstr \"a\"", "ldstr \"a\""),
+		("ldstr \"a\"", "ldnull"),
+	]), &["1", "0", "1", "1", "0", "1", "1", "0"]);
+	expect_exception("ldc.i4.1 ldc.r8 1.0 ceq pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn cgt() {
+	expect_output(&comparisons("cgt", &[
+		("ldc.i4.2", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.m1"),
+		("ldc.i8 -1", "ldc.i8 1"),
+		("ldc.r8 2.0", "ldc.r8 1.0"),
+		(NAN, "ldc.r8 1.0"),
+	]), &["1", "0", "1", "0", "1", "0"]);
+	expect_exception("ldnull ldnull cgt pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn cgt_un() {
+	expect_output(&comparisons("cgt.un", &[
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i4.1", "ldc.i4.m1"),
+		("ldc.i8 -1", "ldc.i8 1"),
+		(NAN, "ldc.r8 1.0"),
+		("ldc.r8 1.0", "ldc.r8 2.0"),
+		("ldstr \"a\"", "ldnull"),
+		("ldnull", "ldnull"),
+	]), &["1", "0", "1", "1", "0", "1", "0"]);
+}
+
+#[test]
+fn clt() {
+	expect_output(&comparisons("clt", &[
+		("ldc.i4.1", "ldc.i4.2"),
+		("ldc.i4.1", "ldc.i4.1"),
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i4.m1 call native int Test::Native(int32)", "ldc.i4.0"),
+		("ldc.r8 1.0", NAN),
+	]), &["1", "0", "1", "1", "0"]);
+}
+
+#[test]
+fn clt_un() {
+	expect_output(&comparisons("clt.un", &[
+		("ldc.i4.1", "ldc.i4.m1"),
+		("ldc.i4.m1", "ldc.i4.1"),
+		("ldc.i8 1", "ldc.i8 -1"),
+		("ldc.r8 1.0", NAN),
+		("ldc.r8 2.0", "ldc.r8 1.0"),
+	]), &["1", "0", "1", "1", "0"]);
+	expect_exception("ldnull ldnull clt.un pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn switch() {
+	let body: String = ["ldc.i4.0", "ldc.i4.1", "ldc.i4.2", "ldc.i4.3", "ldc.i4.m1", "ldc.i4.1 call native int Test::Native(int32)"]
+		.iter().enumerate()
+		.map(|(i, index)| format!("
+			{index}
+			switch (Zero{i}, One{i}, Two{i})
+			ldc.i4.m1 call void Test::Print(int32) br.s Done{i}
+		Zero{i}:
+			ldc.i4.0 call void Test::Print(int32) br.s Done{i}
+		One{i}:
+			ldc.i4.1 call void Test::Print(int32) br.s Done{i}
+		Two{i}:
+			ldc.i4.2 call void Test::Print(int32)
+		Done{i}:
+		"))
+		.collect();
+	expect_output(&body, &["0", "1", "2", "-1", "-1", "1"]);
+	expect_output("
+			ldc.i4.0 switch ()
+			ldc.i4.1 call void Test::Print(int32)
+	", &["1"]);
+	expect_exception("ldc.i8 0 switch (Done) Done: ", INVALID_PROGRAM);
+}
@@ -0,0 +1,100 @@
+// Helpers running IL snippets through the `aps` binary.
+
+#![allow(dead_code)]
+
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static PROGRAMS: AtomicUsize = AtomicUsize::new(0);
+
+/// Wraps instructions into the entry point of a program, which may print
+/// values with `call void Test::Print(<type>)`.
+pub fn program(body: &str) -> String {
+	format!(r#"
+.assembly extern mscorlib {{ .publickeytoken = (B7 7A 5C 56 19 34 E0 89 ) .ver 4:0:0:0 }}
+.assembly test {{ }}
+.class public auto ansi abstract sealed Test extends [mscorlib]System.Object
+{{
+	.method public static void Main() cil managed
+	{{
+		.entrypoint
+		.maxstack 8
+{}
+		ret
+	}}
+	.method public static void Print(int32 x) cil managed
+	{{
+		ldarg.0
+		call void [mscorlib]System.Console::WriteLine(int32)
+		ret
+	}}
+	.method public static void Print(int64 x) cil managed
+	{{
+		ldarg.0
+		call void [mscorlib]System.Console::WriteLine(int64)
+		ret
+	}}
+	.method public static void Print(native int x) cil managed
+	{{
+		ldarg.0
+		conv.i8
+		call void [mscorlib]System.Console::WriteLine(int64)
+		ret
+	}}
+	.method public static void Print(float32 x) cil managed
+	{{
+		ldarg.0
+		call void [mscorlib]System.Console::WriteLine(float32)
+		ret
+	}}
+	.method public static void Print(float64 x) cil managed
+	{{
+		ldarg.0
+		call void [mscorlib]System.Console::WriteLine(float64)
+		ret
+	}}
+	.method public static native int Native(int32 x) cil managed
+	{{
+		ldarg.0
+		ret
+	}}
+}}
+"#, body)
+}
+
+/// Runs the `aps` command on the IL source.
+pub fn aps(command: &str, source: &str) -> Output {
+	let n = PROGRAMS.fetch_add(1, Ordering::SeqCst);
+	let path = std::env::temp_dir().join(format!("aps-test-{}-{}.il", std::process::id(), n));
+	std::fs::write(&path, source).expect("Failed to write the program.");
+
+	let output = Command::new(env!("CARGO_BIN_EXE_aps"))
+		.arg(command)
+		.arg(&path)
+		.output()
+		.expect("Failed to run aps.");
+	let _ = std::fs::remove_file(&path);
+	output
+}
+
+/// Assembles and runs the program with the body.
+pub fn run(body: &str) -> Output {
+	aps("run", &program(body))
+}
+
+/// Runs the body expecting it to print the lines.
+pub fn expect_output(body: &str, expected: &[&str]) {
+	let output = run(body);
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(output.status.success(), "Program failed: {}\n{}", stderr, body);
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert_eq!(stdout.lines().collect::<Vec<_>>(), expected, "\n{}", body);
+}
+
+/// Runs the body expecting it to end with the unhandled exception.
+pub fn expect_exception(body: &str, exception: &str) {
+	let output = run(body);
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert_eq!(output.status.code(), Some(70), "{}\n{}", stderr, body);
+	assert!(stderr.contains(&format!("Unhandled exception. {}", exception)), "{}\n{}", stderr, body);
+}
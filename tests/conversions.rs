@@ -0,0 +1,332 @@
+// Conformance of conversion instructions to III.3.19-21 and III.3.27-29.
+
+mod common;
+
+use common::{expect_exception, expect_output};
+
+const INVALID_PROGRAM: &str = "System.InvalidProgramException";
+const OVERFLOW: &str = "System.OverflowException";
+
+const NAN: &str = "ldc.r8 0.0 ldc.r8 0.0 div";
+const INFINITY: &str = "ldc.r8 1.0 ldc.r8 0.0 div";
+
+#[test]
+fn conv_i1() {
+	expect_output(&format!("
+		ldc.i4 300 conv.i1 call void Test::Print(int32)
+		ldc.i4 -129 conv.i1 call void Test::Print(int32)
+		ldc.i8 -1 conv.i1 call void Test::Print(int32)
+		ldc.r8 127.9 conv.i1 call void Test::Print(int32)
+		ldc.r8 300.0 conv.i1 call void Test::Print(int32)
+		ldc.r8 -1e10 conv.i1 call void Test::Print(int32)
+		{} conv.i1 call void Test::Print(int32)
+	", NAN), &["44", "127", "-1", "127", "44", "0", "0"]);
+	expect_exception("ldnull conv.i1 pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn conv_u1() {
+	expect_output("
+		ldc.i4.m1 conv.u1 call void Test::Print(int32)
+		ldc.i4 256 conv.u1 call void Test::Print(int32)
+		ldc.r8 -1.0 conv.u1 call void Test::Print(int32)
+	", &["255", "0", "255"]);
+}
+
+#[test]
+fn conv_i2() {
+	expect_output("
+		ldc.i4 40000 conv.i2 call void Test::Print(int32)
+		ldc.i4 -32768 conv.i2 call void Test::Print(int32)
+		ldc.r8 -32768.5 conv.i2 call void Test::Print(int32)
+	", &["-25536", "-32768", "-32768"]);
+}
+
+#[test]
+fn conv_u2() {
+	expect_output("
+		ldc.i4.m1 conv.u2 call void Test::Print(int32)
+		ldc.i4 65536 conv.u2 call void Test::Print(int32)
+		ldc.r8 65535.9 conv.u2 call void Test::Print(int32)
+	", &["65535", "0", "65535"]);
+}
+
+#[test]
+fn conv_i4() {
+	expect_output(&format!("
+		ldc.i8 4294967297 conv.i4 call void Test::Print(int32)
+		ldc.r8 2.9 conv.i4 call void Test::Print(int32)
+		ldc.r8 -2.9 conv.i4 call void Test::Print(int32)
+		ldc.r8 1e10 conv.i4 call void Test::Print(int32)
+		ldc.r8 -1e10 conv.i4 call void Test::Print(int32)
+		{} conv.i4 call void Test::Print(int32)
+		{} conv.i4 call void Test::Print(int32)
+	", NAN, INFINITY), &["1", "2", "-2", "2147483647", "-2147483648", "0", "2147483647"]);
+}
+
+#[test]
+fn conv_u4() {
+	expect_output("
+		ldc.i8 -1 conv.u4 conv.u8 call void Test::Print(int64)
+		ldc.i8 4294967296 conv.u4 call void Test::Print(int32)
+		ldc.r8 -1.0 conv.u4 call void Test::Print(int32)
+		ldc.r8 5e9 conv.u4 conv.u8 call void Test::Print(int64)
+		ldc.r8 3e9 conv.u4 conv.u8 call void Test::Print(int64)
+	", &["4294967295", "0", "0", "4294967295", "3000000000"]);
+}
+
+#[test]
+fn conv_i8() {
+	expect_output("
+		ldc.i4.m1 conv.i8 call void Test::Print(int64)
+		ldc.i4.m1 call native int Test::Native(int32) conv.i8 call void Test::Print(int64)
+		ldc.r8 1e19 conv.i8 call void Test::Print(int64)
+		ldc.r8 -1e19 conv.i8 call void Test::Print(int64)
+		ldc.r8 -2.5 conv.i8 call void Test::Print(int64)
+	", &["-1", "-1", "9223372036854775807", "-9223372036854775808", "-2"]);
+}
+
+#[test]
+fn conv_u8() {
+	expect_output("
+		ldc.i4.m1 conv.u8 call void Test::Print(int64)
+		ldc.r8 -1.0 conv.u8 call void Test::Print(int64)
+		ldc.r8 1e19 conv.u8 call void [mscorlib]System.Console::WriteLine(uint64)
+		ldc.r8 1e20 conv.u8 call void [mscorlib]System.Console::WriteLine(uint64)
+	", &["4294967295", "0", "10000000000000000000", "18446744073709551615"]);
+}
+
+#[test]
+fn conv_i() {
+	expect_output("
+		ldc.i4.m1 conv.i call void Test::Print(native int)
+		ldc.i8 -5 conv.i call void Test::Print(native int)
+		ldc.r8 -7.5 conv.i call void Test::Print(native int)
+	", &["-1", "-5", "-7"]);
+}
+
+#[test]
+fn conv_u() {
+	expect_output("
+		ldc.i4.m1 conv.u call void Test::Print(native int)
+		ldc.r8 -7.5 conv.u call void Test::Print(native int)
+	", &["4294967295", "0"]);
+}
+
+#[test]
+fn conv_r4() {
+	expect_output("
+		ldc.i4 16777217 conv.r4 call void Test::Print(float32)
+		ldc.r8 0.1 conv.r4 call void Test::Print(float64)
+		ldc.r8 0.1 conv.r4 call void Test::Print(float32)
+		ldc.r8 1e40 conv.r4 call void Test::Print(float32)
+		ldc.i8 -3 conv.r4 call void Test::Print(float32)
+	", &["16777216", "0.10000000149011612", "0.1", "Infinity", "-3"]);
+	expect_exception("ldnull conv.r4 pop", INVALID_PROGRAM);
+}
+
+#[test]
+fn conv_r8() {
+	expect_output("
+		ldc.i4.s -5 conv.r8 call void Test::Print(float64)
+		ldc.i8 9007199254740993 conv.r8 call void Test::Print(float64)
+		ldc.i4.3 call native int Test::Native(int32) conv.r8 call void Test::Print(float64)
+		ldc.r4 0.5 conv.r8 call void Test::Print(float64)
+	", &["-5", "9007199254740992", "3", "0.5"]);
+}
+
+#[test]
+fn conv_r_un() {
+	expect_output("
+		ldc.i4.m1 conv.r.un call void Test::Print(float64)
+		ldc.i8 -1 conv.r.un call void Test::Print(float64)
+		ldc.i4.7 conv.r.un call void Test::Print(float64)
+	", &["4294967295", "1.8446744073709552E+19", "7"]);
+}
+
+#[test]
+fn conv_ovf_i1() {
+	expect_output("
+		ldc.i4.s 127 conv.ovf.i1 call void Test::Print(int32)
+		ldc.i4 -128 conv.ovf.i1 call void Test::Print(int32)
+		ldc.r8 127.9 conv.ovf.i1 call void Test::Print(int32)
+		ldc.r8 -128.9 conv.ovf.i1 call void Test::Print(int32)
+	", &["127", "-128", "127", "-128"]);
+	expect_exception("ldc.i4 128 conv.ovf.i1 pop", OVERFLOW);
+	expect_exception("ldc.r8 128.0 conv.ovf.i1 pop", OVERFLOW);
+	expect_exception(&format!("{} conv.ovf.i1 pop", NAN), OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u1() {
+	expect_output("
+		ldc.i4 255 conv.ovf.u1 call void Test::Print(int32)
+		ldc.r8 -0.9 conv.ovf.u1 call void Test::Print(int32)
+	", &["255", "0"]);
+	expect_exception("ldc.i4.m1 conv.ovf.u1 pop", OVERFLOW);
+	expect_exception("ldc.i4 256 conv.ovf.u1 pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i2() {
+	expect_output("
+		ldc.i4 32767 conv.ovf.i2 call void Test::Print(int32)
+	", &["32767"]);
+	expect_exception("ldc.i4 32768 conv.ovf.i2 pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u2() {
+	expect_output("
+		ldc.i4 65535 conv.ovf.u2 call void Test::Print(int32)
+	", &["65535"]);
+	expect_exception("ldc.i4.m1 conv.ovf.u2 pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i4() {
+	expect_output("
+		ldc.i8 2147483647 conv.ovf.i4 call void Test::Print(int32)
+		ldc.r8 -2147483648.9 conv.ovf.i4 call void Test::Print(int32)
+	", &["2147483647", "-2147483648"]);
+	expect_exception("ldc.i8 2147483648 conv.ovf.i4 pop", OVERFLOW);
+	expect_exception("ldc.r8 2147483648.0 conv.ovf.i4 pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u4() {
+	expect_output("
+		ldc.i8 4294967295 conv.ovf.u4 conv.u8 call void Test::Print(int64)
+	", &["4294967295"]);
+	expect_exception("ldc.i4.m1 conv.ovf.u4 pop", OVERFLOW);
+	expect_exception("ldc.i8 4294967296 conv.ovf.u4 pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i8() {
+	expect_output("
+		ldc.i4.m1 conv.ovf.i8 call void Test::Print(int64)
+		ldc.r8 9.2e18 conv.ovf.i8 call void Test::Print(int64)
+	", &["-1", "9200000000000000000"]);
+	expect_exception("ldc.r8 9.3e18 conv.ovf.i8 pop", OVERFLOW);
+	expect_exception(&format!("{} conv.ovf.i8 pop", INFINITY), OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u8() {
+	expect_output("
+		ldc.r8 1.5e19 conv.ovf.u8 call void [mscorlib]System.Console::WriteLine(uint64)
+		ldc.i4.7 conv.ovf.u8 call void Test::Print(int64)
+	", &["15000000000000000000", "7"]);
+	expect_exception("ldc.i4.m1 conv.ovf.u8 pop", OVERFLOW);
+	expect_exception("ldc.i8 -1 conv.ovf.u8 pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i() {
+	expect_output("
+		ldc.i4.m1 conv.ovf.i call void Test::Print(native int)
+	", &["-1"]);
+	expect_exception(&format!("{} conv.ovf.i pop", NAN), OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u() {
+	expect_output("
+		ldc.i4.5 conv.ovf.u call void Test::Print(native int)
+	", &["5"]);
+	expect_exception("ldc.i4.m1 conv.ovf.u pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i1_un() {
+	expect_output("
+		ldc.i4.s 127 conv.ovf.i1.un call void Test::Print(int32)
+	", &["127"]);
+	expect_exception("ldc.i4.m1 conv.ovf.i1.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u1_un() {
+	expect_output("
+		ldc.i4 255 conv.ovf.u1.un call void Test::Print(int32)
+	", &["255"]);
+	expect_exception("ldc.i4.m1 conv.ovf.u1.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i2_un() {
+	expect_output("
+		ldc.i4 32767 conv.ovf.i2.un call void Test::Print(int32)
+	", &["32767"]);
+	expect_exception("ldc.i4.m1 conv.ovf.i2.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u2_un() {
+	expect_output("
+		ldc.i4 65535 conv.ovf.u2.un call void Test::Print(int32)
+	", &["65535"]);
+	expect_exception("ldc.i4 65536 conv.ovf.u2.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i4_un() {
+	expect_output("
+		ldc.i8 2147483647 conv.ovf.i4.un call void Test::Print(int32)
+		ldc.r8 3.7 conv.ovf.i4.un call void Test::Print(int32)
+	", &["2147483647", "3"]);
+	expect_exception("ldc.i4.m1 conv.ovf.i4.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u4_un() {
+	expect_output("
+		ldc.i4.m1 conv.ovf.u4.un conv.u8 call void Test::Print(int64)
+	", &["4294967295"]);
+	expect_exception("ldc.i8 4294967296 conv.ovf.u4.un pop", OVERFLOW);
+	expect_exception("ldc.r8 -1.0 conv.ovf.u4.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_i8_un() {
+	expect_output("
+		ldc.i4.m1 conv.ovf.i8.un call void Test::Print(int64)
+	", &["4294967295"]);
+	expect_exception("ldc.i8 -1 conv.ovf.i8.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u8_un() {
+	expect_output("
+		ldc.i8 -1 conv.ovf.u8.un call void [mscorlib]System.Console::WriteLine(uint64)
+		ldc.i4.m1 conv.ovf.u8.un call void Test::Print(int64)
+	", &["18446744073709551615", "4294967295"]);
+}
+
+#[test]
+fn conv_ovf_i_un() {
+	expect_output("
+		ldc.i4.m1 conv.ovf.i.un call void Test::Print(native int)
+	", &["4294967295"]);
+	expect_exception("ldc.i8 -1 conv.ovf.i.un pop", OVERFLOW);
+}
+
+#[test]
+fn conv_ovf_u_un() {
+	expect_output("
+		ldc.i4.m1 conv.ovf.u.un call void Test::Print(native int)
+	", &["4294967295"]);
+	expect_exception("ldc.r8 -1.0 conv.ovf.u.un pop", OVERFLOW);
+}
+
+#[test]
+fn ckfinite() {
+	expect_output("
+		ldc.r8 1.5 ckfinite call void Test::Print(float64)
+	", &["1.5"]);
+	expect_exception(&format!("{} ckfinite pop", NAN), "System.ArithmeticException");
+	expect_exception(&format!("{} ckfinite pop", INFINITY), "System.ArithmeticException");
+	expect_exception(&format!("{} neg ckfinite pop", INFINITY), "System.ArithmeticException");
+	expect_exception("ldc.i4.1 ckfinite pop", INVALID_PROGRAM);
+}
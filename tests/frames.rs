@@ -0,0 +1,61 @@
+// Managed pointers to arguments and locals of frames, III.1.1.5.2.
+
+mod common;
+
+use common::aps;
+
+/// Runs a program where `Leak` returns a pointer to its own local and
+/// `Read` dereferences the pointer it is given, with `body` in between.
+fn run_leaked(body: &str) -> std::process::Output {
+	aps("run", &format!("
+.assembly extern mscorlib {{ .publickeytoken = (B7 7A 5C 56 19 34 E0 89 ) .ver 4:0:0:0 }}
+.assembly test {{ }}
+.class public auto ansi abstract sealed Test extends [mscorlib]System.Object
+{{
+	.method public static void Main() cil managed
+	{{
+		.entrypoint
+		.maxstack 8
+		.locals init (int32& p, int32 x)
+		call int32& Test::Leak()
+		stloc.0
+		{}
+		ret
+	}}
+	.method public static int32& Leak() cil managed
+	{{
+		.maxstack 1
+		.locals init (int32 x)
+		ldc.i4.5 stloc.0
+		ldloca.s 0
+		ret
+	}}
+	.method public static void Read(int32& p) cil managed
+	{{
+		.maxstack 2
+		.locals init (int32 y)
+		ldc.i4.7 stloc.0
+		ldarg.0 ldind.i4
+		call void [mscorlib]System.Console::WriteLine(int32)
+		ret
+	}}
+}}
+", body))
+}
+
+#[test]
+fn byref_to_caller_frame() {
+	let output = run_leaked("ldc.i4.3 stloc.1 ldloca.s 1 call void Test::Read(int32&)");
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+	assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn byref_to_returned_frame() {
+	for body in ["ldloc.0 ldind.i4 pop", "ldloc.0 call void Test::Read(int32&)"] {
+		let output = run_leaked(body);
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		assert_eq!(output.status.code(), Some(70), "{}\n{}", stderr, String::from_utf8_lossy(&output.stdout));
+		assert!(stderr.contains("Unhandled exception. System.InvalidProgramException"), "{}", stderr);
+	}
+}
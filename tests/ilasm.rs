@@ -0,0 +1,44 @@
+// Errors the assembler reports on malformed sources.
+
+mod common;
+
+use common::aps;
+
+/// Source of a program whose entry point has the exception clause.
+fn with_clause(clause: &str) -> String {
+	format!("
+.assembly test {{ }}
+.class public auto ansi abstract sealed Test extends [mscorlib]System.Object
+{{
+	.method public static void Main() cil managed
+	{{
+		.entrypoint
+		.maxstack 1
+	B:	nop
+	A:	nop
+		leave.s E
+	H:	endfinally
+	E:	ret
+		{}
+	}}
+}}
+", clause)
+}
+
+fn expect_syntax_error(source: &str, message: &str) {
+	let output = aps("disasm", source);
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert_eq!(output.status.code(), Some(65), "{}", stderr);
+	assert!(stderr.contains(message), "{}", stderr);
+}
+
+#[test]
+fn try_ranges() {
+	let output = aps("disasm", &with_clause(".try A to H finally handler H to E"));
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+	expect_syntax_error(&with_clause(".try A to B finally handler H to E"), "Line 14: Protected block ends before it starts.");
+	expect_syntax_error(&with_clause(".try A to A finally handler H to E"), "Line 14: Protected block is empty.");
+	expect_syntax_error(&with_clause(".try A to H finally handler E to H"), "Line 14: Handler block ends before it starts.");
+	expect_syntax_error(&with_clause(".try A to H finally handler H to H"), "Line 14: Handler block is empty.");
+}
@@ -0,0 +1,38 @@
+// Log configuration from APS_LOG and the command line.
+
+mod common;
+
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Disassembles a program with the environment and options, returning what
+/// was logged.
+fn log(env: &str, options: &[&str]) -> String {
+	let n = RUNS.fetch_add(1, Ordering::SeqCst);
+	let path = std::env::temp_dir().join(format!("aps-test-logging-{}-{}.il", std::process::id(), n));
+	std::fs::write(&path, common::program("")).expect("Failed to write the program.");
+
+	let output = Command::new(env!("CARGO_BIN_EXE_aps"))
+		.env("APS_LOG", env)
+		.args(options)
+		.arg("disasm")
+		.arg(&path)
+		.output()
+		.expect("Failed to run aps.");
+	let _ = std::fs::remove_file(&path);
+	assert!(output.status.success());
+	String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn env_filters_apply() {
+	assert!(log("cli=debug", &[]).contains("DEBUG: [aps::cli::"));
+}
+
+#[test]
+fn command_line_overrides_env() {
+	assert_eq!(log("cli=debug", &["--log", "warn"]), "");
+	assert_eq!(log("debug", &["--log", "ilasm=warn", "--log-time"]), "");
+}
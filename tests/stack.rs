@@ -0,0 +1,80 @@
+// Evaluation stack depths against .maxstack, III.1.7.4.
+
+mod common;
+
+use common::aps;
+
+/// A program with the method besides an entry point that calls it.
+fn with_method(method: &str, call: &str) -> String {
+	format!("
+.assembly extern mscorlib {{ .publickeytoken = (B7 7A 5C 56 19 34 E0 89 ) .ver 4:0:0:0 }}
+.assembly test {{ }}
+.class public auto ansi abstract sealed Test extends [mscorlib]System.Object
+{{
+	.method public static void Main() cil managed
+	{{
+		.entrypoint
+		.maxstack 8
+		{}
+		ret
+	}}
+	{}
+}}
+", call, method)
+}
+
+#[test]
+fn arglist_counts_against_maxstack() {
+	let output = aps("verify", &with_method("
+	.method public static vararg void Args() cil managed
+	{
+		.maxstack 1
+		.locals init (int32 x)
+		arglist
+		arglist
+		pop
+		pop
+		ret
+	}", ""));
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(!output.status.success());
+	assert!(stdout.contains("Test::Args: IL_0002: Stack depth exceeds MaxStack."), "{}", stdout);
+}
+
+#[test]
+fn maxstack_is_checked_before_running() {
+	// The deeper path is never taken, the method is rejected all the same.
+	let output = aps("run", &with_method("
+	.method public static void Deep() cil managed
+	{
+		.maxstack 1
+		.locals init (int32 x)
+		ldc.i4.0
+		brtrue.s Skip
+		ldc.i4.1
+		ldc.i4.2
+		pop
+		pop
+	Skip:
+		ret
+	}", "call void Test::Deep()"));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert_eq!(output.status.code(), Some(70), "{}", stderr);
+	assert!(stderr.contains("Unhandled exception. System.InvalidProgramException"), "{}", stderr);
+}
+
+#[test]
+fn depth_beyond_largest_maxstack() {
+	let pushes = "ldnull\n".repeat(0x10000);
+	let output = aps("verify", &with_method(&format!("
+	.method public static void Deep() cil managed
+	{{
+		.maxstack 65535
+		.locals init (int32 x)
+		{}
+		ret
+	}}", pushes), ""));
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(!output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+	assert!(stdout.contains("Test::Deep: IL_ffff: Stack depth exceeds MaxStack."), "{}", stdout);
+}
@@ -0,0 +1,83 @@
+// Type checks of `aps verify`, III.1.8.
+
+mod common;
+
+use common::{aps, program};
+
+/// Verifies the source, returning whether it passed and the report.
+fn report(source: &str) -> (bool, String) {
+	let output = aps("verify", source);
+	(output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Verifies the program with the body.
+fn verify(body: &str) -> (bool, String) {
+	report(&program(body))
+}
+
+#[test]
+fn arglist_verifies_in_vararg_methods() {
+	let (ok, report) = report("
+.assembly extern mscorlib { .publickeytoken = (B7 7A 5C 56 19 34 E0 89 ) .ver 4:0:0:0 }
+.assembly test { }
+.class public auto ansi abstract sealed Test extends [mscorlib]System.Object
+{
+	.method public static vararg void Args(int32 x) cil managed
+	{
+		.maxstack 1
+		arglist
+		pop
+		ldarg.0
+		pop
+		ret
+	}
+}
+");
+	assert!(ok, "{}", report);
+	assert!(report.contains("0 of 1 method(s) failed verification."), "{}", report);
+}
+
+#[test]
+fn arglist_is_rejected_outside_vararg_methods() {
+	let (ok, report) = verify("
+		arglist
+		pop");
+	assert!(!ok);
+	assert!(report.contains("Test::Main: IL_0000: Method does not take variable arguments."), "{}", report);
+}
+
+#[test]
+fn stelem_ref_needs_reference_elements() {
+	let (ok, report) = verify("
+		ldc.i4.1
+		newarr int32
+		ldc.i4.0
+		ldc.i4.5
+		stelem.ref");
+	assert!(!ok);
+	assert!(report.contains("Test::Main: IL_0008: Array does not hold object references."), "{}", report);
+
+	let (ok, report) = verify("
+		ldc.i4.1
+		newarr string
+		ldc.i4.0
+		ldstr \"x\"
+		stelem.ref");
+	assert!(ok, "{}", report);
+}
+
+#[test]
+fn referenced_value_types() {
+	let (ok, report) = verify("
+		.locals init (valuetype [mscorlib]System.DateTime d)
+		ldloc.0
+		box [mscorlib]System.DateTime
+		unbox [mscorlib]System.DateTime
+		ldobj [mscorlib]System.DateTime
+		stloc.0
+		ldloc.0
+		box [mscorlib]System.DateTime
+		unbox.any [mscorlib]System.DateTime
+		stloc.0");
+	assert!(ok, "{}", report);
+}